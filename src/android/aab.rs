@@ -1,16 +1,26 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use colored::Colorize;
-use heck::ToUpperCamelCase;
 use thiserror::Error;
 
-use super::{config::Config, env::Env, target::Target};
+use super::{
+    config::Config,
+    env::Env,
+    gradle_output,
+    package::{PackageOptions, Packaged},
+    target::Target,
+};
 use crate::{
     bossy,
-    opts::{NoiseLevel, Profile},
+    build_events::{BuildEvent, Emitter, Phase},
+    build_failure,
+    build_manifest::{self, Artifact, ArtifactKind, Inputs},
+    opts::Profile,
     util::{
         cli::{Report, Reportable},
-        gradlew, prefix_path,
+        gradlew,
+        hook::HookError,
+        prefix_path, BuildSession, CopyArtifactsError, SESSION_VAR,
     },
 };
 
@@ -18,12 +28,15 @@ use crate::{
 pub enum AabBuildError {
     #[error("Failed to build AAB: {0}")]
     BuildFailed(bossy::Error),
+    #[error(transparent)]
+    HookFailed(HookError),
 }
 
 impl Reportable for AabBuildError {
     fn report(&self) -> Report {
         match self {
-            Self::BuildFailed(err) => Report::error("Failed to build AAB", err),
+            Self::BuildFailed(err) => build_failure::report("Failed to build AAB", err),
+            Self::HookFailed(err) => err.report(),
         }
     }
 }
@@ -32,12 +45,15 @@ impl Reportable for AabBuildError {
 pub enum AabError {
     #[error(transparent)]
     AabBuildError(AabBuildError),
+    #[error(transparent)]
+    CopyArtifactsFailed(CopyArtifactsError),
 }
 
 impl Reportable for AabError {
     fn report(&self) -> Report {
         match self {
             Self::AabBuildError(err) => err.report(),
+            Self::CopyArtifactsFailed(err) => err.report(),
         }
     }
 }
@@ -46,12 +62,32 @@ impl Reportable for AabError {
 pub fn build(
     config: &Config,
     env: &Env,
-    noise_level: NoiseLevel,
-    profile: Profile,
-    targets: Vec<&Target>,
-    split_per_abi: bool,
-) -> Result<Vec<PathBuf>, AabError> {
-    let build_ty = profile.as_str().to_upper_camel_case();
+    options: PackageOptions<'_>,
+    events: &Emitter,
+) -> Result<Packaged, AabError> {
+    events.finish(bundle(config, env, options, events))
+}
+
+fn bundle(
+    config: &Config,
+    env: &Env,
+    options: PackageOptions<'_>,
+    events: &Emitter,
+) -> Result<Packaged, AabError> {
+    let PackageOptions {
+        noise_level,
+        profile,
+        targets,
+        split_per_abi,
+        force,
+    } = options;
+    let session = BuildSession::begin(config.app(), env, noise_level, events)
+        .map_err(AabBuildError::HookFailed)
+        .map_err(AabError::AabBuildError)?;
+    let targets = Target::retain_supported(targets, config, env, events);
+    events.phase_started(Phase::Package, None);
+
+    let build_ty = profile.gradle_variant();
 
     let gradle_args = if split_per_abi {
         targets
@@ -67,29 +103,78 @@ pub fn build(
             ),
         ]
     };
-    gradlew(config, env)
-        .with_args(gradle_args)
-        .with_arg(match noise_level {
-            NoiseLevel::Polite => "--warn",
-            NoiseLevel::LoudAndProud => "--info",
-            NoiseLevel::FranklyQuitePedantic => "--debug",
-        })
-        .run_and_wait()
-        .map_err(AabBuildError::BuildFailed)
-        .map_err(AabError::AabBuildError)?;
-
     let mut outputs = Vec::new();
     if split_per_abi {
-        outputs.extend(
-            targets
-                .iter()
-                .map(|t| dunce::simplified(&aab_path(config, profile, t.arch)).to_path_buf()),
-        );
+        outputs.extend(targets.iter().map(|t| {
+            dunce::simplified(&aab_path(config, profile, flavor(t, split_per_abi))).to_path_buf()
+        }));
     } else {
-        outputs.push(dunce::simplified(&aab_path(config, profile, "universal")).to_path_buf());
+        outputs.push(dunce::simplified(&aab_path(config, profile, UNIVERSAL)).to_path_buf());
+    }
+
+    let inputs = build_manifest::fingerprint(config.app()).map(|fingerprint| Inputs {
+        request: gradle_args.clone(),
+        fingerprint,
+    });
+    if !force
+        && inputs
+            .as_ref()
+            .is_some_and(|inputs| build_manifest::is_fresh(config.app(), &outputs, inputs))
+    {
+        session
+            .finish(env, events)
+            .map_err(AabBuildError::HookFailed)
+            .map_err(AabError::AabBuildError)?;
+        return Ok(Packaged {
+            outputs,
+            skipped: true,
+        });
+    }
+    gradle_output::run(
+        gradlew(config, env)
+            .with_env_var(SESSION_VAR, "1")
+            .with_args(gradle_args),
+        noise_level,
+    )
+    .map_err(AabBuildError::BuildFailed)
+    .map_err(AabError::AabBuildError)?;
+
+    // Gradle builds the lib for each target via `cargo android build`, so
+    // there's no finer-grained progress than this to report.
+    for target in &targets {
+        events.emit(BuildEvent::TargetBuilt {
+            triple: target.triple.to_owned(),
+        });
     }
+    let artifacts = outputs
+        .iter()
+        .map(|path| Artifact::new(path, ArtifactKind::Aab, profile).with_inputs(inputs.clone()))
+        .collect::<Vec<_>>();
+    for artifact in &artifacts {
+        events.emit(BuildEvent::ArtifactProduced(artifact.clone()));
+    }
+    build_manifest::record(config.app(), artifacts);
+    session
+        .finish(env, events)
+        .map_err(AabBuildError::HookFailed)
+        .map_err(AabError::AabBuildError)?;
+
+    Ok(Packaged {
+        outputs,
+        skipped: false,
+    })
+}
+
+/// The flavor of a bundle that isn't split per ABI.
+pub const UNIVERSAL: &str = "universal";
 
-    Ok(outputs)
+/// The flavor of the bundle that `target` ends up in.
+pub fn flavor<'a>(target: &Target<'a>, split_per_abi: bool) -> &'a str {
+    if split_per_abi {
+        target.arch
+    } else {
+        UNIVERSAL
+    }
 }
 
 pub fn aab_path(config: &Config, profile: Profile, flavor: &str) -> PathBuf {
@@ -97,7 +182,7 @@ pub fn aab_path(config: &Config, profile: Profile, flavor: &str) -> PathBuf {
         config.project_dir(),
         format!(
             "app/build/outputs/{}/app-{}-{}.{}",
-            format!("bundle/{}{}", flavor, profile.as_str_pascal_case()),
+            format!("bundle/{}{}", flavor, profile.gradle_variant()),
             flavor,
             profile.as_str(),
             "aab"
@@ -110,23 +195,33 @@ pub mod cli {
     pub fn build(
         config: &Config,
         env: &Env,
-        noise_level: NoiseLevel,
-        profile: Profile,
-        targets: Vec<&Target>,
-        split_per_abi: bool,
+        options: PackageOptions<'_>,
+        out_dir: Option<&Path>,
     ) -> Result<(), AabError> {
+        let split_per_abi = options.split_per_abi;
         println!(
             "Building{} AAB{} for {} ...\n",
             if split_per_abi { "" } else { " universal" },
             if split_per_abi { "(s)" } else { "" },
-            targets
+            options
+                .targets
                 .iter()
                 .map(|t| t.triple.split("-").next().unwrap())
                 .collect::<Vec<_>>()
                 .join(", ")
         );
 
-        let outputs = super::build(config, env, noise_level, profile, targets, split_per_abi)?;
+        let Packaged {
+            mut outputs,
+            skipped,
+        } = super::build(config, env, options, &Emitter::default())?;
+        if skipped {
+            println!("Nothing changed since the last build, so skipped Gradle; pass `--force` to build anyway.");
+        }
+        if let Some(out_dir) = out_dir {
+            outputs = crate::util::copy_artifacts(out_dir, &outputs)
+                .map_err(AabError::CopyArtifactsFailed)?;
+        }
 
         println!("\nFinished building AAB(s):");
         for p in &outputs {
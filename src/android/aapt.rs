@@ -0,0 +1,176 @@
+use super::env::Env;
+use crate::{
+    bossy,
+    env::ExplicitEnv as _,
+    os::consts,
+    util::cli::{Report, Reportable},
+};
+use once_cell_regex::regex;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Failed to run `aapt2 dump badging` on {apk:?}: {cause}")]
+    DumpFailed {
+        apk: PathBuf,
+        cause: Box<bossy::Error>,
+    },
+    #[error("`aapt2 dump badging` didn't list a package name for {apk:?}.")]
+    PackageMissing { apk: PathBuf },
+    #[error("{apk:?} has no launchable activity, so there's nothing to start. Libraries and apps that only provide services can't be run directly.")]
+    NoLaunchableActivity { apk: PathBuf },
+}
+
+impl Reportable for Error {
+    fn report(&self) -> Report {
+        match self {
+            Self::DumpFailed { cause, .. } => Report::error("Failed to inspect APK", &**cause),
+            Self::PackageMissing { .. } => Report::error("Failed to inspect APK", self),
+            Self::NoLaunchableActivity { .. } => Report::action_request(
+                "APK has no launchable activity",
+                format!(
+                    "{} Pass `--activity` if you know which activity to start.",
+                    self
+                ),
+            ),
+        }
+    }
+}
+
+fn build_tools_version(name: &str) -> Vec<u32> {
+    name.split(|c: char| !c.is_ascii_digit())
+        .filter_map(|part| part.parse().ok())
+        .collect()
+}
+
+/// Finds the build tool `name` (i.e. `apksigner`) in the newest installed
+/// build-tools package, falling back to whatever's on the `PATH`.
+pub fn build_tool_path(env: &Env, name: &str) -> PathBuf {
+    let build_tools = Path::new(env.sdk_root()).join("build-tools");
+    fs::read_dir(&build_tools)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path().join(name))
+        .filter(|path| path.is_file())
+        .max_by_key(|path| {
+            path.parent()
+                .and_then(Path::file_name)
+                .map(|name| build_tools_version(&name.to_string_lossy()))
+        })
+        .unwrap_or_else(|| PathBuf::from(name))
+}
+
+pub fn aapt2_path(env: &Env) -> PathBuf {
+    build_tool_path(env, consts::AAPT2)
+}
+
+/// The output of `aapt2 dump badging`, which summarizes an APK's manifest.
+#[derive(Debug)]
+pub struct Badging(String);
+
+impl Badging {
+    pub fn dump(env: &Env, apk: impl AsRef<Path>) -> Result<Self, Error> {
+        let apk = apk.as_ref();
+        bossy::Command::impure(aapt2_path(env))
+            .with_env_vars(env.explicit_env())
+            .with_args(["dump", "badging"])
+            .with_arg(apk)
+            .run_and_wait_for_string()
+            .map(Self)
+            .map_err(|cause| Error::DumpFailed {
+                apk: apk.to_owned(),
+                cause: Box::new(cause),
+            })
+    }
+
+    fn field(&self, line: &str, key: &str) -> Option<&str> {
+        let prefix = format!("{}:", line);
+        self.0
+            .lines()
+            .filter_map(|l| l.strip_prefix(&prefix))
+            .find_map(|rest| {
+                regex!(r"(?P<key>[\w-]+)='(?P<value>[^']*)'")
+                    .captures_iter(rest)
+                    .find(|caps| &caps["key"] == key)
+                    .map(|caps| caps.name("value").unwrap().as_str())
+            })
+            .filter(|value| !value.is_empty())
+    }
+
+    pub fn package(&self) -> Option<&str> {
+        self.field("package", "name")
+    }
+
+    pub fn launchable_activity(&self) -> Option<&str> {
+        self.field("launchable-activity", "name")
+    }
+}
+
+/// The component to pass to `am start -n` to launch `apk`, i.e.
+/// `<package>/<activity>`.
+pub fn launchable_activity(env: &Env, apk: impl AsRef<Path>) -> Result<String, Error> {
+    let apk = apk.as_ref();
+    let badging = Badging::dump(env, apk)?;
+    let package = badging.package().ok_or_else(|| Error::PackageMissing {
+        apk: apk.to_owned(),
+    })?;
+    let activity = badging
+        .launchable_activity()
+        .ok_or_else(|| Error::NoLaunchableActivity {
+            apk: apk.to_owned(),
+        })?;
+    Ok(format!("{}/{}", package, activity))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    const BADGING: &str = "\
+package: name='com.example.hello_world' versionCode='1' versionName='1.0' platformBuildVersionName='12'
+sdkVersion:'24'
+targetSdkVersion:'31'
+application-label:'Hello World'
+application: label='Hello World' icon='res/mipmap-mdpi-v4/ic_launcher.png'
+launchable-activity: name='android.app.NativeActivity'  label='Hello World' icon=''
+native-code: 'arm64-v8a'
+";
+
+    #[test]
+    fn parses_badging() {
+        let badging = Badging(BADGING.to_owned());
+        assert_eq!(badging.package(), Some("com.example.hello_world"));
+        assert_eq!(
+            badging.launchable_activity(),
+            Some("android.app.NativeActivity")
+        );
+    }
+
+    #[test]
+    fn parses_badging_without_launchable_activity() {
+        let badging = Badging(
+            BADGING
+                .lines()
+                .filter(|line| !line.starts_with("launchable-activity:"))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        );
+        assert_eq!(badging.package(), Some("com.example.hello_world"));
+        assert_eq!(badging.launchable_activity(), None);
+    }
+
+    #[rstest(name, expected,
+        case("30.0.3", vec![30, 0, 3]),
+        case("33.0.0-rc1", vec![33, 0, 0, 1]),
+        case("android-4.4W", vec![4, 4])
+    )]
+    fn parses_build_tools_version(name: &str, expected: Vec<u32>) {
+        assert_eq!(build_tools_version(name), expected);
+    }
+}
@@ -1,11 +1,11 @@
 use super::{device_name, get_prop};
 use crate::{
     android::{device::Device, env::Env, target::Target},
-    bossy,
+    bossy, device,
     env::ExplicitEnv as _,
     util::cli::{Report, Reportable},
 };
-use once_cell_regex::regex_multi_line;
+use once_cell_regex::{regex, regex_multi_line};
 use std::collections::BTreeSet;
 use thiserror::Error;
 
@@ -35,19 +35,53 @@ impl Reportable for Error {
     }
 }
 
-const ADB_DEVICE_REGEX: &str = r"^([\S]{6,22})	device\b";
+// `adb devices -l` pads serial numbers with spaces, while plain `adb devices`
+// separates them with a tab.
+const ADB_DEVICE_REGEX: &str = r"^([\S]{6,22})[ \t]+device\b(.*)$";
+
+/// `adb devices -l` lists a transport ID for each device, which `adb -t`
+/// accepts in place of a serial number.
+fn transport_id(details: &str) -> Option<&str> {
+    regex!(r"\btransport_id:(\d+)")
+        .captures(details)
+        .and_then(|caps| caps.get(1))
+        .map(|id| id.as_str())
+}
 
 pub fn device_list(env: &Env) -> Result<BTreeSet<Device<'static>>, Error> {
     super::check_authorized(
         bossy::Command::pure("adb")
             .with_env_vars(env.explicit_env())
-            .with_args(&["devices"])
+            .with_args(["devices", "-l"])
             .run_and_wait_for_str(|raw_list| {
-                regex_multi_line!(ADB_DEVICE_REGEX)
+                let attached = regex_multi_line!(ADB_DEVICE_REGEX)
                     .captures_iter(raw_list)
                     .map(|caps| {
-                        assert_eq!(caps.len(), 2);
-                        let serial_no = caps.get(1).unwrap().as_str().to_owned();
+                        assert_eq!(caps.len(), 3);
+                        (caps.get(1).unwrap().as_str(), caps.get(2).unwrap().as_str())
+                    })
+                    .collect::<Vec<_>>();
+                // Every query below goes through the serial number, so
+                // devices sharing one could have their answers mixed up.
+                let duplicates = device::duplicate_ids(attached.iter().map(|(serial_no, _)| *serial_no));
+                for serial_no in &duplicates {
+                    let transport_ids = attached
+                        .iter()
+                        .filter(|(other, _)| other == serial_no)
+                        .map(|(_, details)| transport_id(details).unwrap_or("unknown"))
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    log::warn!(
+                        "multiple Android devices share the serial number {:?} (transport IDs {}), so they're being skipped, since commands could reach the wrong one; disconnect all but one, or use `adb -t <transport ID>` to reach one directly",
+                        serial_no,
+                        transport_ids
+                    );
+                }
+                attached
+                    .iter()
+                    .filter(|(serial_no, _)| !duplicates.contains(serial_no))
+                    .map(|(serial_no, _)| {
+                        let serial_no = (*serial_no).to_owned();
                         let name = device_name(env, &serial_no).map_err(Error::NameFailed)?;
                         let model = get_prop(env, &serial_no, "ro.product.model")
                             .map_err(Error::ModelFailed)?;
@@ -85,6 +119,13 @@ mod test {
             0a388e93	device usb:1-1 product:razor model:Nexus_7 device:flo\n\
             ", vec!["emulator-5556", "emulator-5554", "0a388e93"]
         ),
+        case("List of devices attached\n\
+            emulator-5554          device product:sdk_gphone64_x86_64 model:sdk_gphone64_x86_64 device:emu64xa transport_id:1\n\
+            0a388e93               device usb:1-1 product:razor model:Nexus_7 device:flo transport_id:3\n\
+            R58M12ABCDE            unauthorized usb:1-2 transport_id:4\n\
+            192.168.1.20:5555      offline transport_id:5\n\
+            ", vec!["emulator-5554", "0a388e93"]
+        ),
 
     )]
     fn test_adb_output_regex(input: &str, devices: Vec<&'static str>) {
@@ -96,4 +137,21 @@ mod test {
             .collect::<Vec<_>>();
         assert_eq!(captures, devices);
     }
+
+    #[rstest(
+        details,
+        expected,
+        case(
+            " usb:1-1 product:razor model:Nexus_7 device:flo transport_id:3",
+            Some("3")
+        ),
+        case(
+            " product:sdk_gphone64_x86_64 transport_id:12 model:sdk_gphone64",
+            Some("12")
+        ),
+        case(" usb:1-1 product:razor model:Nexus_7 device:flo", None)
+    )]
+    fn parses_transport_id(details: &str, expected: Option<&str>) {
+        assert_eq!(transport_id(details), expected);
+    }
 }
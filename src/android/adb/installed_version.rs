@@ -0,0 +1,203 @@
+use super::adb;
+use crate::{
+    android::env::Env,
+    util::cli::{Report, Reportable},
+};
+use once_cell_regex::regex;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Failed to run `adb shell dumpsys package {package}`: {source}")]
+    DumpsysFailed {
+        package: String,
+        source: Box<super::RunCheckedError>,
+    },
+    #[error(
+        "`adb shell dumpsys package {package}` listed the package, but not its `versionCode`."
+    )]
+    VersionCodeMissing { package: String },
+}
+
+impl Reportable for Error {
+    fn report(&self) -> Report {
+        match self {
+            Self::DumpsysFailed { package, source } => {
+                source.report(&format!("Failed to get installed version of {}", package))
+            }
+            Self::VersionCodeMissing { .. } => {
+                Report::error("Failed to get installed version", self)
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct VersionInfo {
+    /// Apps aren't required to set this, so it can be missing.
+    pub version_name: Option<String>,
+    pub version_code: u64,
+}
+
+fn indentation(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+/// Finds the lines describing `package` in the output of `dumpsys package`.
+///
+/// The package can be listed more than once, i.e. under "Hidden system
+/// packages" when a system app has been updated, so we only look at the
+/// first, which is the one that's actually installed.
+fn package_block<'a>(output: &'a str, package: &str) -> Option<Vec<&'a str>> {
+    let header = format!("Package [{}]", package);
+    let mut lines = output.lines();
+    let header_line = lines.find(|line| line.trim_start().starts_with(&header))?;
+    let header_indent = indentation(header_line);
+    Some(
+        lines
+            .take_while(|line| line.trim().is_empty() || indentation(line) > header_indent)
+            .collect(),
+    )
+}
+
+fn parse(output: &str, package: &str) -> Result<Option<VersionInfo>, Error> {
+    let block = match package_block(output, package) {
+        Some(block) => block,
+        None => return Ok(None),
+    };
+    let version_code = block
+        .iter()
+        .find_map(|line| {
+            regex!(r"\bversionCode=(\d+)")
+                .captures(line)
+                .and_then(|caps| caps[1].parse().ok())
+        })
+        .ok_or_else(|| Error::VersionCodeMissing {
+            package: package.to_owned(),
+        })?;
+    let version_name = block
+        .iter()
+        .find_map(|line| line.trim().strip_prefix("versionName="))
+        .map(|name| name.trim().to_owned())
+        .filter(|name| !name.is_empty() && name != "null");
+    Ok(Some(VersionInfo {
+        version_name,
+        version_code,
+    }))
+}
+
+/// The version of `package` installed on the device, or `None` if it isn't
+/// installed.
+pub fn installed_version(
+    env: &Env,
+    serial_no: &str,
+    package: &str,
+) -> Result<Option<VersionInfo>, Error> {
+    let output = super::check_authorized(
+        adb(env, serial_no)
+            .with_args(["shell", "dumpsys", "package", package])
+            .run_and_wait_for_string(),
+    )
+    .map_err(|source| Error::DumpsysFailed {
+        package: package.to_owned(),
+        source: Box::new(source),
+    })?;
+    parse(&output, package)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    const INSTALLED: &str = "\
+Activity Resolver Table:
+  Non-Data Actions:
+      android.intent.action.MAIN:
+        5d6c3a1 com.example.hello_world/android.app.NativeActivity filter 8f2e0b4
+
+Key Set Manager:
+  [com.example.hello_world]
+      Signing KeySets: 52
+
+Packages:
+  Package [com.example.hello_world] (7c1a2f9):
+    userId=10154
+    pkg=Package{3e8d1b0 com.example.hello_world}
+    codePath=/data/app/~~q1w2e3==/com.example.hello_world-a1s2d3==
+    resourcePath=/data/app/~~q1w2e3==/com.example.hello_world-a1s2d3==
+    legacyNativeLibraryDir=/data/app/~~q1w2e3==/com.example.hello_world-a1s2d3==/lib
+    primaryCpuAbi=arm64-v8a
+    secondaryCpuAbi=null
+    versionCode=42 minSdk=24 targetSdk=31
+    versionName=1.2.3
+    splits=[base]
+    apkSigningVersion=2
+    applicationInfo=ApplicationInfo{3e8d1b0 com.example.hello_world}
+    flags=[ DEBUGGABLE HAS_CODE ALLOW_CLEAR_USER_DATA ALLOW_BACKUP ]
+    timeStamp=2022-06-01 12:34:56
+    User 0: ceDataInode=131090 installed=true hidden=false suspended=false
+";
+
+    const NOT_INSTALLED: &str = "\
+Activity Resolver Table:
+  Non-Data Actions:
+      android.intent.action.MAIN:
+        1a2b3c4 com.android.settings/.Settings filter 5d6e7f8
+
+Dexopt state:
+  Unable to find package: com.example.hello_world
+";
+
+    const SYSTEM_UPDATED: &str = "\
+Packages:
+  Package [com.example.hello_world] (7c1a2f9):
+    userId=10154
+    versionCode=7 minSdk=24 targetSdk=31
+    versionName=2.0
+Hidden system packages:
+  Package [com.example.hello_world] (1f2e3d4):
+    userId=10154
+    versionCode=1 minSdk=24 targetSdk=31
+    versionName=1.0
+";
+
+    const OTHER_PACKAGE: &str = "\
+Packages:
+  Package [com.example.hello_world.debug] (7c1a2f9):
+    userId=10155
+    versionCode=3 minSdk=24 targetSdk=31
+    versionName=0.3
+";
+
+    #[rstest(output, expected,
+        case(INSTALLED, Some((42, Some("1.2.3")))),
+        case(NOT_INSTALLED, None),
+        case(SYSTEM_UPDATED, Some((7, Some("2.0")))),
+        case(OTHER_PACKAGE, None),
+        case(
+            "Packages:\n  Package [com.example.hello_world] (1):\n    versionCode=5 targetSdk=31\n    versionName=null\n",
+            Some((5, None))
+        )
+    )]
+    fn parses_dumpsys(output: &str, expected: Option<(u64, Option<&str>)>) {
+        assert_eq!(
+            parse(output, "com.example.hello_world").unwrap(),
+            expected.map(|(version_code, version_name)| VersionInfo {
+                version_name: version_name.map(ToOwned::to_owned),
+                version_code,
+            })
+        );
+    }
+
+    #[test]
+    fn version_code_missing() {
+        assert!(matches!(
+            parse(
+                "Packages:\n  Package [com.example.hello_world] (1):\n    versionName=1.0\n",
+                "com.example.hello_world"
+            ),
+            Err(Error::VersionCodeMissing { .. })
+        ));
+    }
+}
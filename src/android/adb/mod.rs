@@ -1,8 +1,14 @@
 pub mod device_list;
 pub mod device_name;
 pub mod get_prop;
+pub mod installed_version;
 
-pub use self::{device_list::device_list, device_name::device_name, get_prop::get_prop};
+pub use self::{
+    device_list::device_list,
+    device_name::device_name,
+    get_prop::get_prop,
+    installed_version::{installed_version, VersionInfo},
+};
 
 use super::env::Env;
 use crate::{bossy, env::ExplicitEnv as _, util::cli::Report};
@@ -35,7 +41,7 @@ impl RunCheckedError {
     }
 }
 
-fn check_authorized<T>(result: bossy::Result<T>) -> Result<T, RunCheckedError> {
+pub(super) fn check_authorized<T>(result: bossy::Result<T>) -> Result<T, RunCheckedError> {
     if let Err(err) = &result {
         if let Some(stderr) = err
             .stderr_str()
@@ -1,17 +1,28 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use colored::Colorize;
-use heck::ToUpperCamelCase;
 use thiserror::Error;
 
-use super::{config::Config, env::Env, jnilibs, target::Target};
+use super::{
+    config::Config,
+    env::Env,
+    gradle_output, jnilibs,
+    package::{PackageOptions, Packaged},
+    sign::{self, SignError},
+    target::Target,
+};
 use crate::{
     android::jnilibs::JniLibs,
     bossy,
-    opts::{NoiseLevel, Profile},
+    build_events::{BuildEvent, Emitter, Phase},
+    build_failure,
+    build_manifest::{self, Artifact, ArtifactKind, Inputs},
+    opts::Profile,
     util::{
         cli::{Report, Reportable},
-        gradlew, prefix_path,
+        gradlew,
+        hook::HookError,
+        prefix_path, BuildSession, CopyArtifactsError, SESSION_VAR,
     },
 };
 
@@ -21,13 +32,19 @@ pub enum ApkBuildError {
     LibSymlinkCleaningFailed(jnilibs::RemoveBrokenLinksError),
     #[error("Failed to assemble APK: {0}")]
     AssembleFailed(bossy::Error),
+    #[error(transparent)]
+    HookFailed(HookError),
+    #[error(transparent)]
+    SignFailed(SignError),
 }
 
 impl Reportable for ApkBuildError {
     fn report(&self) -> Report {
         match self {
             Self::LibSymlinkCleaningFailed(err) => err.report(),
-            Self::AssembleFailed(err) => Report::error("Failed to assemble APK", err),
+            Self::AssembleFailed(err) => build_failure::report("Failed to assemble APK", err),
+            Self::HookFailed(err) => err.report(),
+            Self::SignFailed(err) => err.report(),
         }
     }
 }
@@ -37,43 +54,76 @@ impl Reportable for ApkBuildError {
 pub enum ApkError {
     #[error(transparent)]
     ApkBuildError(ApkBuildError),
+    #[error(transparent)]
+    CopyArtifactsFailed(CopyArtifactsError),
 }
 
 impl Reportable for ApkError {
     fn report(&self) -> Report {
         match self {
             Self::ApkBuildError(err) => err.report(),
+            Self::CopyArtifactsFailed(err) => err.report(),
         }
     }
 }
 
-pub fn apk_path(config: &Config, profile: Profile, flavor: &str) -> PathBuf {
+fn output_path(config: &Config, profile: Profile, flavor: &str, suffix: &str) -> PathBuf {
     prefix_path(
         config.project_dir(),
         format!(
             "app/build/outputs/{}/app-{}-{}.{}",
             format!("apk/{}/{}", flavor, profile.as_str()),
             flavor,
-            profile.suffix(),
+            suffix,
             "apk"
         ),
     )
 }
 
+/// The APK to install for `flavor`, which is the one we signed if
+/// [`sign::signs`], and otherwise the one Gradle built.
+pub fn apk_path(config: &Config, profile: Profile, flavor: &str) -> PathBuf {
+    if sign::signs(config, profile) {
+        output_path(config, profile, flavor, profile.as_str())
+    } else {
+        output_path(config, profile, flavor, profile.suffix())
+    }
+}
+
 /// Builds APK(s) and returns the built APK(s) paths
-pub fn build<'a>(
+pub fn build(
+    config: &Config,
+    env: &Env,
+    options: PackageOptions<'_>,
+    events: &Emitter,
+) -> Result<Packaged, ApkError> {
+    events.finish(assemble(config, env, options, events))
+}
+
+fn assemble(
     config: &Config,
     env: &Env,
-    noise_level: NoiseLevel,
-    profile: Profile,
-    targets: Vec<&Target>,
-    split_per_abi: bool,
-) -> Result<Vec<PathBuf>, ApkError> {
+    options: PackageOptions<'_>,
+    events: &Emitter,
+) -> Result<Packaged, ApkError> {
+    let PackageOptions {
+        noise_level,
+        profile,
+        targets,
+        split_per_abi,
+        force,
+    } = options;
     JniLibs::remove_broken_links(config)
         .map_err(ApkBuildError::LibSymlinkCleaningFailed)
         .map_err(ApkError::ApkBuildError)?;
 
-    let build_ty = profile.as_str().to_upper_camel_case();
+    let session = BuildSession::begin(config.app(), env, noise_level, events)
+        .map_err(ApkBuildError::HookFailed)
+        .map_err(ApkError::ApkBuildError)?;
+    let targets = Target::retain_supported(targets, config, env, events);
+    events.phase_started(Phase::Package, None);
+
+    let build_ty = profile.gradle_variant();
 
     let gradle_args = if split_per_abi {
         targets
@@ -89,29 +139,79 @@ pub fn build<'a>(
             ),
         ]
     };
-    gradlew(config, env)
-        .with_args(gradle_args)
-        .with_arg(match noise_level {
-            NoiseLevel::Polite => "--warn",
-            NoiseLevel::LoudAndProud => "--info",
-            NoiseLevel::FranklyQuitePedantic => "--debug",
-        })
-        .run_and_wait()
-        .map_err(ApkBuildError::AssembleFailed)
-        .map_err(ApkError::ApkBuildError)?;
-
-    let mut outputs = Vec::new();
-    if split_per_abi {
-        outputs.extend(
-            targets
-                .iter()
-                .map(|t| dunce::simplified(&apk_path(config, profile, t.arch)).to_path_buf()),
-        );
+    let flavors = if split_per_abi {
+        targets.iter().map(|t| t.arch).collect()
     } else {
-        outputs.push(dunce::simplified(&apk_path(config, profile, "universal")).to_path_buf());
+        vec!["universal"]
+    };
+    let outputs = flavors
+        .iter()
+        .map(|flavor| dunce::simplified(&apk_path(config, profile, flavor)).to_path_buf())
+        .collect::<Vec<_>>();
+
+    let inputs = build_manifest::fingerprint(config.app()).map(|fingerprint| Inputs {
+        request: gradle_args.clone(),
+        fingerprint,
+    });
+    if !force
+        && inputs
+            .as_ref()
+            .is_some_and(|inputs| build_manifest::is_fresh(config.app(), &outputs, inputs))
+    {
+        session
+            .finish(env, events)
+            .map_err(ApkBuildError::HookFailed)
+            .map_err(ApkError::ApkBuildError)?;
+        return Ok(Packaged {
+            outputs,
+            skipped: true,
+        });
+    }
+    gradle_output::run(
+        gradlew(config, env)
+            .with_env_var(SESSION_VAR, "1")
+            .with_args(gradle_args),
+        noise_level,
+    )
+    .map_err(ApkBuildError::AssembleFailed)
+    .map_err(ApkError::ApkBuildError)?;
+    if sign::signs(config, profile) {
+        for flavor in &flavors {
+            sign::sign_apk(
+                config,
+                env,
+                &output_path(config, profile, flavor, profile.suffix()),
+                &apk_path(config, profile, flavor),
+            )
+            .map_err(ApkBuildError::SignFailed)
+            .map_err(ApkError::ApkBuildError)?;
+        }
     }
 
-    Ok(outputs)
+    // Gradle builds the lib for each target via `cargo android build`, so
+    // there's no finer-grained progress than this to report.
+    for target in &targets {
+        events.emit(BuildEvent::TargetBuilt {
+            triple: target.triple.to_owned(),
+        });
+    }
+    let artifacts = outputs
+        .iter()
+        .map(|path| Artifact::new(path, ArtifactKind::Apk, profile).with_inputs(inputs.clone()))
+        .collect::<Vec<_>>();
+    for artifact in &artifacts {
+        events.emit(BuildEvent::ArtifactProduced(artifact.clone()));
+    }
+    build_manifest::record(config.app(), artifacts);
+    session
+        .finish(env, events)
+        .map_err(ApkBuildError::HookFailed)
+        .map_err(ApkError::ApkBuildError)?;
+
+    Ok(Packaged {
+        outputs,
+        skipped: false,
+    })
 }
 
 pub mod cli {
@@ -119,23 +219,33 @@ pub mod cli {
     pub fn build(
         config: &Config,
         env: &Env,
-        noise_level: NoiseLevel,
-        profile: Profile,
-        targets: Vec<&Target>,
-        split_per_abi: bool,
+        options: PackageOptions<'_>,
+        out_dir: Option<&Path>,
     ) -> Result<(), ApkError> {
+        let split_per_abi = options.split_per_abi;
         println!(
             "Building{} APK{} for {} ...\n",
             if split_per_abi { "" } else { " universal" },
             if split_per_abi { "(s)" } else { "" },
-            targets
+            options
+                .targets
                 .iter()
                 .map(|t| t.triple.split("-").next().unwrap())
                 .collect::<Vec<_>>()
                 .join(", ")
         );
 
-        let outputs = super::build(config, env, noise_level, profile, targets, split_per_abi)?;
+        let Packaged {
+            mut outputs,
+            skipped,
+        } = super::build(config, env, options, &Emitter::default())?;
+        if skipped {
+            println!("Nothing changed since the last build, so skipped Gradle; pass `--force` to build anyway.");
+        }
+        if let Some(out_dir) = out_dir {
+            outputs = crate::util::copy_artifacts(out_dir, &outputs)
+                .map_err(ApkError::CopyArtifactsFailed)?;
+        }
 
         println!("\nFinished building APK(s):");
         for p in &outputs {
@@ -2,24 +2,32 @@ use crate::{
     android::{
         aab, adb, apk,
         config::{Config, Metadata},
-        device::{Device, RunError, StacktraceError},
+        device::{DeepLinkError, Device, RunError, StacktraceError},
+        emulator,
         env::{Env, Error as EnvError},
+        package::{self, PackageOptions},
         target::{BuildError, CompileLibError, Target},
         NAME,
     },
+    build_events::{Emitter, Phase},
+    build_log,
     config::{
+        defaults::Defaults,
         metadata::{self, Metadata as OmniMetadata},
         Config as OmniConfig, LoadOrGenError,
     },
     define_device_prompt,
     device::PromptError,
-    os,
-    target::{call_for_targets_with_fallback, TargetInvalid, TargetTrait as _},
+    init, os,
+    target::{
+        call_for_targets_in_parallel_with_fallback, call_for_targets_with_fallback, TargetInvalid,
+        TargetTrait as _,
+    },
     util::{
         cli::{
             self, Exec, GlobalFlags, Report, Reportable, TextWrapper, VERSION_LONG, VERSION_SHORT,
         },
-        prompt,
+        prompt, BuildSession,
     },
 };
 use std::path::PathBuf;
@@ -54,13 +62,23 @@ pub enum Command {
     Check {
         #[structopt(name = "targets", default_value = Target::DEFAULT_KEY, possible_values = Target::name_list())]
         targets: Vec<String>,
+        #[structopt(flatten)]
+        target_dir: cli::TargetDir,
     },
     #[structopt(name = "build", about = "Builds dynamic libraries for target(s)")]
     Build {
-        #[structopt(name = "targets", default_value = Target::DEFAULT_KEY, possible_values = Target::name_list())]
+        #[structopt(name = "targets", possible_values = Target::name_list())]
         targets: Vec<String>,
         #[structopt(flatten)]
         profile: cli::Profile,
+        #[structopt(flatten)]
+        target_dir: cli::TargetDir,
+        #[structopt(
+            short = "j",
+            long = "jobs",
+            help = "How many targets to build at once (overrides `app.build-jobs`)"
+        )]
+        jobs: Option<usize>,
     },
     #[structopt(name = "run", about = "Deploys APK to connected device")]
     Run {
@@ -70,16 +88,32 @@ pub enum Command {
         filter: cli::Filter,
         #[structopt(flatten)]
         reinstall_deps: cli::ReinstallDeps,
+        #[structopt(flatten)]
+        device: cli::DeviceId,
+        #[structopt(flatten)]
+        target_dir: cli::TargetDir,
         #[structopt(
             short = "a",
             long = "activity",
-            default_value = ".MainActivity",
-            help = "Specifies which activtiy to launch"
+            help = "Specifies which activity to launch, instead of the APK's launchable activity"
         )]
-        activity: String,
+        activity: Option<String>,
     },
     #[structopt(name = "st", about = "Displays a detailed stacktrace for a device")]
-    Stacktrace,
+    Stacktrace {
+        #[structopt(flatten)]
+        device: cli::DeviceId,
+    },
+    #[structopt(
+        name = "deep-link",
+        about = "Opens a URL in the installed app on a device, for testing deep links"
+    )]
+    DeepLink {
+        #[structopt(flatten)]
+        device: cli::DeviceId,
+        #[structopt(name = "URL", help = "The URL to open, i.e. `myapp://settings`")]
+        url: String,
+    },
     #[structopt(name = "list", about = "Lists connected devices")]
     List,
     #[structopt(name = "apk", about = "Manage and build APKs")]
@@ -92,6 +126,11 @@ pub enum Command {
         #[structopt(subcommand)]
         cmd: AabSubcommand,
     },
+    #[structopt(name = "emulator", about = "Manage Android emulators")]
+    Emulator {
+        #[structopt(subcommand)]
+        cmd: EmulatorSubcommand,
+    },
 }
 
 #[derive(StructOpt, Clone, Debug)]
@@ -105,6 +144,21 @@ pub enum ApkSubcommand {
         profile: cli::Profile,
         #[structopt(long = "split-per-abi", help = "Whether to split the APKs per ABIs.")]
         split_per_abi: bool,
+        #[structopt(
+            long = "force",
+            help = "Build even if nothing changed since the last build"
+        )]
+        force: bool,
+        #[structopt(flatten)]
+        out_dir: cli::OutDir,
+        #[structopt(flatten)]
+        target_dir: cli::TargetDir,
+    },
+    #[structopt(about = "lists the native libs packaged in an APK, by ABI")]
+    Libs {
+        #[structopt(name = "apk", parse(from_os_str))]
+        /// The APK to inspect.
+        apk: PathBuf,
     },
 }
 #[derive(StructOpt, Clone, Debug)]
@@ -118,6 +172,33 @@ pub enum AabSubcommand {
         profile: cli::Profile,
         #[structopt(long = "split-per-abi", help = "Whether to split the AABs per ABIs.")]
         split_per_abi: bool,
+        #[structopt(
+            long = "force",
+            help = "Build even if nothing changed since the last build"
+        )]
+        force: bool,
+        #[structopt(flatten)]
+        out_dir: cli::OutDir,
+        #[structopt(flatten)]
+        target_dir: cli::TargetDir,
+    },
+}
+
+#[derive(StructOpt, Clone, Debug)]
+pub enum EmulatorSubcommand {
+    #[structopt(about = "create an AVD (Android Virtual Device)")]
+    Create {
+        /// Name of the AVD to create.
+        name: String,
+        #[structopt(
+            long = "image",
+            help = "System image package path, i.e. `system-images;android-30;google_apis;x86_64`"
+        )]
+        system_image: String,
+        #[structopt(long = "device", help = "Device profile to use, i.e. `pixel_4`")]
+        device: Option<String>,
+        #[structopt(long = "force", help = "Replace an existing AVD with the same name")]
+        force: bool,
     },
 }
 
@@ -133,11 +214,15 @@ pub enum Error {
     OpenFailed(os::OpenFileError),
     CheckFailed(CompileLibError),
     BuildFailed(BuildError),
+    BuildsFailed(Vec<(String, BuildError)>),
     RunFailed(RunError),
     StacktraceFailed(StacktraceError),
+    DeepLinkFailed(DeepLinkError),
     ListFailed(adb::device_list::Error),
     ApkError(apk::ApkError),
     AabError(aab::AabError),
+    EmulatorCreateFailed(emulator::CreateError),
+    InspectLibsFailed(package::InspectLibsError),
 }
 
 impl Reportable for Error {
@@ -159,11 +244,22 @@ impl Reportable for Error {
             Self::OpenFailed(err) => Report::error("Failed to open project in Android Studio", err),
             Self::CheckFailed(err) => err.report(),
             Self::BuildFailed(err) => err.report(),
+            Self::BuildsFailed(failures) => Report::error(
+                format!("Failed to build {} of the requested targets", failures.len()),
+                failures
+                    .iter()
+                    .map(|(triple, err)| format!("{}: {}", triple, err))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            ),
             Self::RunFailed(err) => err.report(),
             Self::StacktraceFailed(err) => err.report(),
+            Self::DeepLinkFailed(err) => err.report(),
             Self::ListFailed(err) => err.report(),
             Self::ApkError(err) => err.report(),
             Self::AabError(err) => err.report(),
+            Self::EmulatorCreateFailed(err) => err.report(),
+            Self::InspectLibsFailed(err) => err.report(),
         }
     }
 }
@@ -178,20 +274,40 @@ impl Exec for Input {
     fn exec(self, wrapper: &TextWrapper) -> Result<(), Self::Report> {
         define_device_prompt!(adb::device_list, adb::device_list::Error, Android);
         fn detect_target_ok<'a>(env: &Env) -> Option<&'a Target<'a>> {
-            device_prompt(env).map(|device| device.target()).ok()
+            device_prompt(env, None, None)
+                .map(|device| device.target())
+                .ok()
         }
 
         fn with_config(
+            env: Env,
             non_interactive: bool,
             wrapper: &TextWrapper,
-            f: impl FnOnce(&Config, &Metadata) -> Result<(), Error>,
+            target_dir: Option<PathBuf>,
+            f: impl FnOnce(&Config, &Metadata, &Defaults, &Env) -> Result<(), Error>,
         ) -> Result<(), Error> {
-            let (config, _origin) = OmniConfig::load_or_gen(".", non_interactive, wrapper)
+            let (mut config, _origin) = OmniConfig::load_or_gen(".", non_interactive, wrapper)
                 .map_err(Error::ConfigFailed)?;
+            if let Some(target_dir) = target_dir {
+                config.override_target_dir(target_dir);
+            }
             let metadata =
                 OmniMetadata::load(&config.app().root_dir()).map_err(Error::MetadataFailed)?;
+            init::warn_if_outdated(config.app().root_dir(), wrapper);
+            if let Err(err) = init::fix_permissions(config.app().root_dir()) {
+                log::warn!("{}", err);
+            }
             if metadata.android().supported() {
-                f(config.android(), metadata.android())
+                let env = env.suppress_telemetry(config.telemetry().suppressions());
+                build_log::start(config.app().root_dir());
+                let result = f(
+                    config.android(),
+                    metadata.android(),
+                    config.defaults(),
+                    &env,
+                );
+                build_log::finish(result.is_ok(), config.open().build_log_on_failure());
+                result
             } else {
                 Err(Error::Unsupported)
             }
@@ -208,7 +324,15 @@ impl Exec for Input {
         }
 
         fn open_in_android_studio(config: &Config) -> Result<(), Error> {
-            os::open_file_with("Android Studio", config.project_dir()).map_err(Error::OpenFailed)
+            if !os::open_file_with("Android Studio", config.project_dir())
+                .map_err(Error::OpenFailed)?
+            {
+                println!(
+                    "Not opening Android Studio, since opening is disabled by `--no-open` or {}",
+                    os::NO_OPEN_VAR
+                );
+            }
+            Ok(())
         }
 
         fn get_targets_or_all<'a>(targets: Vec<String>) -> Result<Vec<&'a Target<'a>>, Error> {
@@ -234,120 +358,254 @@ impl Exec for Input {
                 GlobalFlags {
                     noise_level,
                     non_interactive,
+                    ..
                 },
             command,
         } = self;
         let env = Env::new().map_err(Error::EnvInitFailed)?;
         match command {
-            Command::Open => with_config(non_interactive, wrapper, |config, _| {
+            Command::Open => with_config(env, non_interactive, wrapper, None, |config, _, _, _| {
                 ensure_init(config)?;
                 open_in_android_studio(config)
             }),
-            Command::Check { targets } => {
-                with_config(non_interactive, wrapper, |config, metadata| {
+            Command::Check {
+                targets,
+                target_dir: cli::TargetDir { target_dir },
+            } => with_config(
+                env,
+                non_interactive,
+                wrapper,
+                target_dir,
+                |config, metadata, _, env| {
                     let force_color = true;
                     call_for_targets_with_fallback(
                         targets.iter(),
                         &detect_target_ok,
-                        &env,
+                        env,
                         |target: &Target| {
                             target
-                                .check(config, metadata, &env, noise_level, force_color)
+                                .check(config, metadata, env, noise_level, force_color)
                                 .map_err(Error::CheckFailed)
                         },
                     )
                     .map_err(Error::TargetInvalid)?
-                })
-            }
+                },
+            ),
             Command::Build {
                 targets,
-                profile: cli::Profile { profile },
-            } => with_config(non_interactive, wrapper, |config, metadata| {
-                ensure_init(config)?;
-                let force_color = true;
-                call_for_targets_with_fallback(
-                    targets.iter(),
-                    &detect_target_ok,
-                    &env,
-                    |target: &Target| {
-                        target
-                            .build(config, metadata, &env, noise_level, force_color, profile)
-                            .map_err(Error::BuildFailed)
-                    },
-                )
-                .map_err(Error::TargetInvalid)?
-            }),
+                profile,
+                target_dir: cli::TargetDir { target_dir },
+                jobs,
+            } => with_config(
+                env,
+                non_interactive,
+                wrapper,
+                target_dir,
+                |config, metadata, defaults, env| {
+                    ensure_init(config)?;
+                    let force_color = true;
+                    let targets = defaults.targets_or_default::<Target>(targets);
+                    let profile = defaults.profile(profile.requested());
+                    let events = Emitter::terminal();
+                    let session = BuildSession::begin(config.app(), env, noise_level, &events)
+                        .map_err(BuildError::HookFailed)
+                        .map_err(Error::BuildFailed)?;
+                    call_for_targets_in_parallel_with_fallback(
+                        targets.iter(),
+                        &detect_target_ok,
+                        env,
+                        defaults
+                            .jobs(jobs)
+                            .filter(|jobs| *jobs != 0)
+                            .unwrap_or_else(|| config.app().build_jobs()),
+                        |target: &Target, parallel| {
+                            events.phase_started(Phase::Compile, Some(target.triple));
+                            target.build(
+                                config,
+                                metadata,
+                                env,
+                                noise_level,
+                                force_color,
+                                profile,
+                                parallel,
+                            )?;
+                            events.target_built(target.triple);
+                            Ok(())
+                        },
+                    )
+                    .map_err(Error::TargetInvalid)?
+                    .map_err(|mut failures| {
+                        if failures.len() == 1 {
+                            Error::BuildFailed(failures.remove(0).1)
+                        } else {
+                            Error::BuildsFailed(
+                                failures
+                                    .into_iter()
+                                    .map(|(target, err)| (target.triple.to_owned(), err))
+                                    .collect(),
+                            )
+                        }
+                    })?;
+                    session
+                        .finish(env, &events)
+                        .map_err(BuildError::HookFailed)
+                        .map_err(Error::BuildFailed)
+                },
+            ),
             Command::Run {
-                profile: cli::Profile { profile },
+                profile,
                 filter: cli::Filter { filter },
                 reinstall_deps: cli::ReinstallDeps { reinstall_deps },
+                device: cli::DeviceId { device },
+                target_dir: cli::TargetDir { target_dir },
                 activity,
-            } => with_config(non_interactive, wrapper, |config, metadata| {
-                let build_app_bundle = metadata.asset_packs().is_some();
-                ensure_init(config)?;
-                device_prompt(&env)
+            } => with_config(
+                env,
+                non_interactive,
+                wrapper,
+                target_dir,
+                |config, metadata, defaults, env| {
+                    let build_app_bundle = metadata.asset_packs().is_some();
+                    ensure_init(config)?;
+                    device_prompt(
+                        env,
+                        defaults.device(device).as_deref(),
+                        Some(config.app().root_dir()),
+                    )
                     .map_err(Error::DevicePromptFailed)?
                     .run(
                         config,
-                        &env,
+                        env,
                         noise_level,
-                        profile,
-                        filter,
+                        defaults.profile(profile.requested()),
+                        defaults.filter(filter),
                         build_app_bundle,
                         reinstall_deps,
                         activity,
                     )
                     .and_then(|h| h.wait().map(|_| ()).map_err(RunError::LogcatFailed))
                     .map_err(Error::RunFailed)
-            }),
-            Command::Stacktrace => with_config(non_interactive, wrapper, |config, _| {
+                },
+            ),
+            Command::Stacktrace {
+                device: cli::DeviceId { device },
+            } => with_config(env, non_interactive, wrapper, None, |config, _, _, env| {
                 ensure_init(config)?;
-                device_prompt(&env)
+                device_prompt(env, device.as_deref(), Some(config.app().root_dir()))
                     .map_err(Error::DevicePromptFailed)?
-                    .stacktrace(config, &env)
+                    .stacktrace(config, env)
                     .map_err(Error::StacktraceFailed)
             }),
+            Command::DeepLink {
+                device: cli::DeviceId { device },
+                url,
+            } => with_config(env, non_interactive, wrapper, None, |config, _, _, env| {
+                device_prompt(env, device.as_deref(), Some(config.app().root_dir()))
+                    .map_err(Error::DevicePromptFailed)?
+                    .open_deep_link(config, env, &url)
+                    .map_err(Error::DeepLinkFailed)
+            }),
             Command::List => adb::device_list(&env)
                 .map_err(Error::ListFailed)
                 .map(|device_list| {
-                    prompt::list_display_only(device_list.iter(), device_list.len());
+                    prompt::list_display_only(
+                        device_list
+                            .iter()
+                            .map(|device| format!("{} [{}]", device, device.id())),
+                        device_list.len(),
+                    );
                 }),
             Command::Apk { cmd } => match cmd {
                 ApkSubcommand::Build {
                     targets,
-                    profile: cli::Profile { profile },
+                    profile,
                     split_per_abi,
-                } => with_config(non_interactive, wrapper, |config, _| {
-                    ensure_init(config)?;
+                    force,
+                    out_dir: cli::OutDir { out_dir },
+                    target_dir: cli::TargetDir { target_dir },
+                } => with_config(
+                    env,
+                    non_interactive,
+                    wrapper,
+                    target_dir,
+                    |config, _, defaults, env| {
+                        ensure_init(config)?;
 
-                    apk::cli::build(
-                        config,
-                        &env,
-                        noise_level,
-                        profile,
-                        get_targets_or_all(targets)?,
-                        split_per_abi,
-                    )
-                    .map_err(Error::ApkError)
-                }),
+                        apk::cli::build(
+                            config,
+                            env,
+                            PackageOptions {
+                                noise_level,
+                                profile: defaults.profile(profile.requested()),
+                                targets: get_targets_or_all(defaults.targets(targets))?,
+                                split_per_abi,
+                                force,
+                            },
+                            config.app().out_dir(out_dir.as_deref()).as_deref(),
+                        )
+                        .map_err(Error::ApkError)
+                    },
+                ),
+                ApkSubcommand::Libs { apk } => {
+                    let libs = package::inspect_libs(&apk).map_err(Error::InspectLibsFailed)?;
+                    if libs.is_empty() {
+                        println!("{:?} doesn't contain any native libs.", apk);
+                    }
+                    for (abi, names) in libs {
+                        println!("{}:", abi);
+                        for name in names {
+                            println!("    {}", name);
+                        }
+                    }
+                    Ok(())
+                }
             },
             Command::Aab { cmd } => match cmd {
                 AabSubcommand::Build {
                     targets,
-                    profile: cli::Profile { profile },
+                    profile,
                     split_per_abi,
-                } => with_config(non_interactive, wrapper, |config, _| {
-                    ensure_init(config)?;
-                    aab::cli::build(
-                        config,
-                        &env,
-                        noise_level,
-                        profile,
-                        get_targets_or_all(targets)?,
-                        split_per_abi,
-                    )
-                    .map_err(Error::AabError)
-                }),
+                    force,
+                    out_dir: cli::OutDir { out_dir },
+                    target_dir: cli::TargetDir { target_dir },
+                } => with_config(
+                    env,
+                    non_interactive,
+                    wrapper,
+                    target_dir,
+                    |config, _, defaults, env| {
+                        ensure_init(config)?;
+                        aab::cli::build(
+                            config,
+                            env,
+                            PackageOptions {
+                                noise_level,
+                                profile: defaults.profile(profile.requested()),
+                                targets: get_targets_or_all(defaults.targets(targets))?,
+                                split_per_abi,
+                                force,
+                            },
+                            config.app().out_dir(out_dir.as_deref()).as_deref(),
+                        )
+                        .map_err(Error::AabError)
+                    },
+                ),
+            },
+            Command::Emulator { cmd } => match cmd {
+                EmulatorSubcommand::Create {
+                    name,
+                    system_image,
+                    device,
+                    force,
+                } => emulator::create(&env, &name, &system_image, device.as_deref(), force)
+                    .map_err(Error::EmulatorCreateFailed)
+                    .map(|avd| {
+                        if avd.replaced {
+                            println!("Replaced AVD {:?} ({})", avd.name, avd.system_image);
+                        } else {
+                            println!("Created AVD {:?} ({})", avd.name, avd.system_image);
+                        }
+                    }),
             },
         }
     }
@@ -1,3 +1,4 @@
+use super::{fast_linker::FastLinker, sign::SecretString};
 use crate::{
     config::app::App,
     util::{self, cli::Report},
@@ -5,7 +6,7 @@ use crate::{
 use serde::{Deserialize, Serialize};
 use std::{
     fmt::{self, Display},
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 use thiserror::Error;
 
@@ -157,6 +158,11 @@ pub struct Raw {
     pub project_dir: Option<String>,
     pub no_default_features: Option<bool>,
     pub features: Option<Vec<String>>,
+    pub keystore_password_env: Option<String>,
+    pub keystore_password_file: Option<String>,
+    pub keystore_password: Option<SecretString>,
+    pub fast_linker: Option<FastLinker>,
+    pub copy_libs: Option<bool>,
 }
 
 #[derive(Clone, Debug, Serialize)]
@@ -167,6 +173,16 @@ pub struct Config {
     min_sdk_version: u32,
     vulkan_validation: bool,
     project_dir: PathBuf,
+    #[serde(skip)]
+    keystore_password_env: Option<String>,
+    #[serde(skip)]
+    keystore_password_file: Option<PathBuf>,
+    #[serde(skip)]
+    keystore_password: Option<SecretString>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fast_linker: Option<FastLinker>,
+    #[serde(skip)]
+    copy_libs: bool,
 }
 
 impl Config {
@@ -209,11 +225,20 @@ impl Config {
             Ok(DEFAULT_PROJECT_DIR.into())
         }?;
 
+        let keystore_password_file = raw
+            .keystore_password_file
+            .map(|path| app.prefix_path(util::expand_config_path(&path)));
+
         Ok(Self {
             app,
             min_sdk_version,
             vulkan_validation,
             project_dir,
+            keystore_password_env: raw.keystore_password_env,
+            keystore_password_file,
+            keystore_password: raw.keystore_password,
+            fast_linker: raw.fast_linker,
+            copy_libs: raw.copy_libs.unwrap_or_default(),
         })
     }
 
@@ -221,6 +246,10 @@ impl Config {
         &self.app
     }
 
+    pub(crate) fn app_mut(&mut self) -> &mut App {
+        &mut self.app
+    }
+
     pub fn so_name(&self) -> String {
         format!("lib{}.so", self.app().name_snake())
     }
@@ -238,4 +267,36 @@ impl Config {
     pub fn project_dir_exists(&self) -> bool {
         self.project_dir().is_dir()
     }
+
+    /// The env var to read the keystore password from, if any.
+    pub fn keystore_password_env(&self) -> Option<&str> {
+        self.keystore_password_env.as_deref()
+    }
+
+    /// The file to read the keystore password from, if any.
+    pub fn keystore_password_file(&self) -> Option<&Path> {
+        self.keystore_password_file.as_deref()
+    }
+
+    /// Where Android projects conventionally keep their signing config, which
+    /// may or may not exist.
+    pub fn keystore_properties_path(&self) -> PathBuf {
+        self.project_dir().join("keystore.properties")
+    }
+
+    /// The keystore password, if it was written directly into the config.
+    pub(crate) fn keystore_password(&self) -> Option<&SecretString> {
+        self.keystore_password.as_ref()
+    }
+
+    /// Which faster linker to use, if any, from `android.fast-linker`.
+    pub fn fast_linker(&self) -> Option<FastLinker> {
+        self.fast_linker
+    }
+
+    /// Whether to copy built libs into `jniLibs` rather than symlinking them,
+    /// from `android.copy-libs`.
+    pub fn copy_libs(&self) -> bool {
+        self.copy_libs
+    }
 }
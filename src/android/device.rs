@@ -1,10 +1,18 @@
-use super::{aab, adb, bundletool, config::Config, env::Env, jnilibs, target::Target};
+use super::{
+    aab, aapt, adb, bundletool,
+    config::Config,
+    env::Env,
+    jnilibs,
+    package::{self, PackageOptions},
+    target::Target,
+};
 use crate::{
     android::apk,
     bossy,
+    build_events::Emitter,
     env::ExplicitEnv as _,
     opts::{FilterLevel, NoiseLevel, Profile},
-    os::consts,
+    os::{self, consts},
     util::{
         self,
         cli::{Report, Reportable},
@@ -14,10 +22,13 @@ use crate::{
 use bossy::Handle;
 use std::{
     fmt::{self, Display},
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 use thiserror::Error;
 
+/// The activity our template generates, relative to the app's package.
+pub static DEFAULT_ACTIVITY: &str = ".MainActivity";
+
 #[derive(Debug, Error)]
 pub enum AabBuildError {
     #[error("Failed to build AAB: {0}")]
@@ -51,6 +62,15 @@ impl Reportable for ApksBuildError {
 
 #[derive(Debug, Error)]
 pub enum ApkInstallError {
+    #[error(
+        "The device supports {}, but the app was only built for {}.",
+        util::list_display(device_abis),
+        util::list_display(built_abis)
+    )]
+    AbiMismatch {
+        device_abis: Vec<String>,
+        built_abis: Vec<String>,
+    },
     #[error("Failed to install APK: {0}")]
     InstallFailed(bossy::Error),
     #[error("Failed to install APK from AAB: {0}")]
@@ -60,6 +80,13 @@ pub enum ApkInstallError {
 impl Reportable for ApkInstallError {
     fn report(&self) -> Report {
         match self {
+            Self::AbiMismatch { .. } => Report::action_request(
+                "Device architecture doesn't match any of the built ABIs",
+                format!(
+                    "{} Connect a different device, or build for one of the device's ABIs.",
+                    self
+                ),
+            ),
             Self::InstallFailed(err) => Report::error("Failed to install APK", err),
             Self::InstallFromAabFailed(err) => Report::error("Failed to install APK from AAB", err),
         }
@@ -74,6 +101,8 @@ pub enum RunError {
     AabError(aab::AabError),
     #[error(transparent)]
     ApkInstallFailed(ApkInstallError),
+    #[error(transparent)]
+    ActivityResolveFailed(aapt::Error),
     #[error("Failed to start app on device: {0}")]
     StartFailed(bossy::Error),
     #[error("Failed to wake device screen: {0}")]
@@ -94,6 +123,7 @@ impl Reportable for RunError {
             Self::ApkError(err) => err.report(),
             Self::AabError(err) => err.report(),
             Self::ApkInstallFailed(err) => err.report(),
+            Self::ActivityResolveFailed(err) => err.report(),
             Self::StartFailed(err) => Report::error("Failed to start app on device", err),
             Self::WakeScreenFailed(err) => Report::error("Failed to wake device screen", err),
             Self::LogcatFailed(err) => Report::error("Failed to log output", err),
@@ -117,6 +147,65 @@ impl Reportable for StacktraceError {
     }
 }
 
+#[derive(Debug, Error)]
+pub enum DeepLinkError {
+    #[error("{0:?} isn't a URL; deep links look like `myapp://path`.")]
+    UrlInvalid(String),
+    #[error(transparent)]
+    InstallCheckFailed(adb::installed_version::Error),
+    #[error("{package} isn't installed on the device, so there's nothing to open {url:?} in; run it first.")]
+    AppNotInstalled { package: String, url: String },
+    #[error("Failed to check which activity handles {url:?}: {cause}")]
+    ResolveFailed {
+        url: String,
+        cause: Box<adb::RunCheckedError>,
+    },
+    #[error("{package} doesn't handle {scheme}: URLs; it needs an intent filter for the scheme in its manifest.")]
+    SchemeUnhandled { package: String, scheme: String },
+    #[error("Failed to open {url:?}: {cause}")]
+    OpenFailed {
+        url: String,
+        cause: Box<adb::RunCheckedError>,
+    },
+    #[error("`am start` couldn't open {url:?}: {msg}")]
+    StartRejected { url: String, msg: String },
+}
+
+impl Reportable for DeepLinkError {
+    fn report(&self) -> Report {
+        let msg = "Failed to open deep link";
+        match self {
+            Self::InstallCheckFailed(err) => err.report(),
+            Self::ResolveFailed { cause, .. } | Self::OpenFailed { cause, .. } => cause.report(msg),
+            Self::UrlInvalid(_) | Self::AppNotInstalled { .. } | Self::SchemeUnhandled { .. } => {
+                Report::action_request(msg, self)
+            }
+            Self::StartRejected { .. } => Report::error(msg, self),
+        }
+    }
+}
+
+static VIEW_ACTION: &str = "android.intent.action.VIEW";
+
+/// We only build for the device's own target when running, so there'd be
+/// nothing to split.
+const SPLIT_AAB_PER_ABI: bool = false;
+
+fn package_name(config: &Config) -> String {
+    format!(
+        "{}.{}",
+        config.app().reverse_domain(),
+        config.app().name_snake()
+    )
+}
+
+/// Whether `resolve-activity --brief` found an activity in `package`. It
+/// prints the component on its last line, or "No activity found".
+fn resolves_to(output: &str, package: &str) -> bool {
+    let prefix = format!("{}/", package);
+    output.lines().any(|line| line.trim().starts_with(&prefix))
+}
+
 #[derive(Debug, Eq, Ord, PartialEq, PartialOrd)]
 pub struct Device<'a> {
     serial_no: String,
@@ -150,6 +239,11 @@ impl<'a> Device<'a> {
         }
     }
 
+    /// The serial number, which is how `adb` tells devices apart.
+    pub fn id(&self) -> &str {
+        &self.serial_no
+    }
+
     pub fn target(&self) -> &'a Target<'a> {
         self.target
     }
@@ -178,10 +272,46 @@ impl<'a> Device<'a> {
         noise_level: NoiseLevel,
         profile: Profile,
     ) -> Result<(), apk::ApkError> {
-        apk::build(config, env, noise_level, profile, vec![self.target()], true)?;
+        apk::build(
+            config,
+            env,
+            PackageOptions {
+                noise_level,
+                profile,
+                targets: vec![self.target()],
+                split_per_abi: true,
+                // The freshness check only covers explicit builds, since
+                // there's no `--force` to fall back on here.
+                force: true,
+            },
+            &Emitter::default(),
+        )?;
         Ok(())
     }
 
+    /// Checks that the device can run at least one of the ABIs `package` has
+    /// libs for, since otherwise the install fails with
+    /// `INSTALL_FAILED_NO_MATCHING_ABIS`, which isn't very helpful.
+    pub fn check_abi(&self, env: &Env, package: &Path) -> Result<(), ApkInstallError> {
+        // This is just a courtesy check, so we don't want to fail the
+        // install over it.
+        let built_abis = match package::packaged_abis(package) {
+            Ok(built_abis) => built_abis,
+            Err(err) => {
+                log::warn!("failed to check which ABIs were packaged: {}", err);
+                return Ok(());
+            }
+        };
+        let device_abis = match adb::get_prop(env, &self.serial_no, "ro.product.cpu.abilist") {
+            Ok(abi_list) => abi_list,
+            Err(err) => {
+                log::warn!("failed to get ABI list for device {}: {}", self, err);
+                return Ok(());
+            }
+        };
+        abis_compatible(&device_abis, built_abis)
+    }
+
     fn install_apk(
         &self,
         config: &Config,
@@ -190,6 +320,7 @@ impl<'a> Device<'a> {
     ) -> Result<(), ApkInstallError> {
         let flavor = self.target.arch;
         let apk_path = apk::apk_path(config, profile, flavor);
+        self.check_abi(env, &apk_path)?;
         self.adb(env)
             .with_arg("install")
             .with_arg(apk_path)
@@ -207,6 +338,11 @@ impl<'a> Device<'a> {
         Ok(())
     }
 
+    /// The bundle that [`Device::build_aab`] builds.
+    fn aab_path(&self, config: &Config, profile: Profile) -> PathBuf {
+        aab::aab_path(config, profile, aab::flavor(self.target, SPLIT_AAB_PER_ABI))
+    }
+
     fn build_aab(
         &self,
         config: &Config,
@@ -217,10 +353,16 @@ impl<'a> Device<'a> {
         aab::build(
             config,
             env,
-            noise_level,
-            profile,
-            vec![self.target()],
-            false,
+            PackageOptions {
+                noise_level,
+                profile,
+                targets: vec![self.target()],
+                split_per_abi: SPLIT_AAB_PER_ABI,
+                // The freshness check only covers explicit builds, since
+                // there's no `--force` to fall back on here.
+                force: true,
+            },
+            &Emitter::default(),
         )?;
         Ok(())
     }
@@ -228,7 +370,7 @@ impl<'a> Device<'a> {
     fn build_apks_from_aab(&self, config: &Config, profile: Profile) -> Result<(), ApksBuildError> {
         let flavor = self.target.arch;
         let apks_path = Self::apks_path(config, profile, flavor);
-        let aab_path = aab::aab_path(config, profile, flavor);
+        let aab_path = self.aab_path(config, profile);
         bundletool::command()
             .with_arg("build-apks")
             .with_arg(format!("--bundle={}", aab_path.to_str().unwrap()))
@@ -242,9 +384,11 @@ impl<'a> Device<'a> {
     fn install_apk_from_aab(
         &self,
         config: &Config,
+        env: &Env,
         profile: Profile,
     ) -> Result<(), ApkInstallError> {
         let flavor = self.target.arch;
+        self.check_abi(env, &self.aab_path(config, profile))?;
         let apks_path = Self::apks_path(config, profile, flavor);
         bundletool::command()
             .with_arg("install-apks")
@@ -270,7 +414,7 @@ impl<'a> Device<'a> {
         filter_level: Option<FilterLevel>,
         build_app_bundle: bool,
         reinstall_deps: bool,
-        activity: String,
+        activity: Option<String>,
     ) -> Result<Handle, RunError> {
         if build_app_bundle {
             bundletool::install(reinstall_deps).map_err(RunError::BundletoolInstallFailed)?;
@@ -280,7 +424,7 @@ impl<'a> Device<'a> {
                 .map_err(RunError::AabError)?;
             self.build_apks_from_aab(config, profile)
                 .map_err(RunError::ApksFromAabBuildFailed)?;
-            self.install_apk_from_aab(config, profile)
+            self.install_apk_from_aab(config, env, profile)
                 .map_err(RunError::ApkInstallFailed)?;
         } else {
             self.build_apk(config, env, noise_level, profile)
@@ -288,12 +432,18 @@ impl<'a> Device<'a> {
             self.install_apk(config, env, profile)
                 .map_err(RunError::ApkInstallFailed)?;
         }
-        let activity = format!(
-            "{}.{}/{}",
-            config.app().reverse_domain(),
-            config.app().name_snake(),
-            activity
-        );
+        let package = package_name(config);
+        let activity = match activity {
+            Some(activity) => format!("{}/{}", package, activity),
+            // There's no single APK to inspect when installing from an AAB,
+            // so we stick with what the template generates.
+            None if build_app_bundle => format!("{}/{}", package, DEFAULT_ACTIVITY),
+            None => {
+                aapt::launchable_activity(env, apk::apk_path(config, profile, self.target.arch))
+                    .map_err(RunError::ActivityResolveFailed)?
+            }
+        };
+        log::info!("starting activity {:?}", activity);
         self.adb(env)
             .with_args(&["shell", "am", "start", "-n", &activity])
             .run_and_wait()
@@ -316,10 +466,26 @@ impl<'a> Device<'a> {
             .map_err(RunError::LogcatFailed)
     }
 
+    /// Which Python `ndk-stack`'s wrapper runs has varied between NDK
+    /// versions, so when we can, we run the script behind it with the Python 3
+    /// that [`os::python`] picks.
+    fn stack_command(env: &Env) -> bossy::Command {
+        let script = env.ndk.stack_script();
+        if script.is_file() {
+            match os::python(env.base()) {
+                Ok(python) => return bossy::Command::pure(python).with_arg(script),
+                Err(err) => log::warn!("running `ndk-stack` as is, since {}", err),
+            }
+        } else {
+            log::info!("no `ndk-stack` script at {:?}, so running it as is", script);
+        }
+        bossy::Command::pure(env.ndk.home().join(consts::NDK_STACK))
+    }
+
     pub fn stacktrace(&self, config: &Config, env: &Env) -> Result<(), StacktraceError> {
         // -d = print and exit
         let logcat_command = adb::adb(env, &self.serial_no).with_args(&["logcat", "-d"]);
-        let stack_command = bossy::Command::pure(env.ndk.home().join(consts::NDK_STACK))
+        let stack_command = Self::stack_command(env)
             .with_env_vars(env.explicit_env())
             .with_env_var(
                 "PATH",
@@ -341,4 +507,138 @@ impl<'a> Device<'a> {
         }
         Ok(())
     }
+
+    /// Fires `url` at the app with a `VIEW` intent, the same as tapping a
+    /// link to it would. The app has to already be installed, and has to
+    /// declare an intent filter for the URL's scheme.
+    pub fn open_deep_link(
+        &self,
+        config: &Config,
+        env: &Env,
+        url: &str,
+    ) -> Result<(), DeepLinkError> {
+        let scheme = crate::device::url_scheme(url)
+            .ok_or_else(|| DeepLinkError::UrlInvalid(url.to_owned()))?;
+        let package = package_name(config);
+        if adb::installed_version(env, &self.serial_no, &package)
+            .map_err(DeepLinkError::InstallCheckFailed)?
+            .is_none()
+        {
+            return Err(DeepLinkError::AppNotInstalled {
+                package,
+                url: url.to_owned(),
+            });
+        }
+        // `adb shell` hands its args to the device's shell as one string, so
+        // the URL has to be quoted to survive any `&`s or `?`s in it.
+        let quoted = os::posix_quote(url);
+        let resolved = adb::check_authorized(
+            self.adb(env)
+                .with_args(["shell", "cmd", "package", "resolve-activity", "--brief"])
+                .with_args(["-a", VIEW_ACTION, "-d", &quoted, &package])
+                .run_and_wait_for_string(),
+        )
+        .map_err(|cause| DeepLinkError::ResolveFailed {
+            url: url.to_owned(),
+            cause: Box::new(cause),
+        })?;
+        if !resolves_to(&resolved, &package) {
+            return Err(DeepLinkError::SchemeUnhandled {
+                package,
+                scheme: scheme.to_owned(),
+            });
+        }
+        let output = adb::check_authorized(
+            self.adb(env)
+                .with_args(["shell", "am", "start", "-W"])
+                .with_args(["-a", VIEW_ACTION, "-d", &quoted, &package])
+                .run_and_wait_for_string(),
+        )
+        .map_err(|cause| DeepLinkError::OpenFailed {
+            url: url.to_owned(),
+            cause: Box::new(cause),
+        })?;
+        // `am start` exits successfully even when it couldn't start anything.
+        match output.lines().find(|line| line.starts_with("Error")) {
+            Some(line) => Err(DeepLinkError::StartRejected {
+                url: url.to_owned(),
+                msg: line.to_owned(),
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+/// `device_abis` is the device's `ro.product.cpu.abilist`. Packages without
+/// any native libs run anywhere, and older devices don't have `abilist`, so
+/// neither of those is a mismatch.
+fn abis_compatible(device_abis: &str, built_abis: Vec<String>) -> Result<(), ApkInstallError> {
+    let device_abis = device_abis
+        .split(',')
+        .map(|abi| abi.trim().to_owned())
+        .filter(|abi| !abi.is_empty())
+        .collect::<Vec<_>>();
+    if device_abis.is_empty()
+        || built_abis.is_empty()
+        || built_abis.iter().any(|built| device_abis.contains(built))
+    {
+        Ok(())
+    } else {
+        Err(ApkInstallError::AbiMismatch {
+            device_abis,
+            built_abis,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::target::TargetTrait as _;
+    use rstest::rstest;
+
+    #[rstest(
+        output,
+        expected,
+        case(
+            "priority=0 preferredOrder=0 match=0x208000 specificIndex=-1 isDefault=false\ncom.example.hello/.MainActivity\n",
+            true
+        ),
+        case("No activity found\n", false),
+        case(
+            "priority=0 preferredOrder=0 match=0x208000 specificIndex=-1 isDefault=false\ncom.example.hello.debug/.MainActivity\n",
+            false
+        )
+    )]
+    fn checks_resolved_activity(output: &str, expected: bool) {
+        assert_eq!(resolves_to(output, "com.example.hello"), expected);
+    }
+
+    #[rstest(
+        device_abis,
+        built_abis,
+        compatible,
+        case("arm64-v8a,armeabi-v7a,armeabi", &["arm64-v8a"], true),
+        case("arm64-v8a,armeabi-v7a,armeabi", &["armeabi-v7a", "x86_64"], true),
+        case("x86_64,arm64-v8a", &["armeabi-v7a", "x86"], false),
+        case("arm64-v8a", &[], true),
+        case("", &["x86_64"], true)
+    )]
+    fn checks_packaged_abis(device_abis: &str, built_abis: &[&str], compatible: bool) {
+        let result = abis_compatible(
+            device_abis,
+            built_abis.iter().map(|abi| abi.to_string()).collect(),
+        );
+        assert_eq!(result.is_ok(), compatible);
+    }
+
+    #[test]
+    fn installs_from_the_bundle_it_built() {
+        // `build_aab` makes a universal bundle, so that's what we have to
+        // check and install from, whichever target the device has.
+        for target in Target::all().values() {
+            assert_eq!(aab::flavor(target, SPLIT_AAB_PER_ABI), aab::UNIVERSAL);
+            assert_eq!(aab::flavor(target, true), target.arch);
+        }
+    }
 }
@@ -0,0 +1,337 @@
+use super::{
+    env::Env,
+    sdk::{self, SystemImage},
+};
+use crate::{
+    bossy,
+    env::ExplicitEnv as _,
+    os::consts,
+    util::cli::{Report, Reportable},
+};
+use once_cell_regex::regex_multi_line;
+use std::io::Write as _;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum CreateError {
+    #[error(transparent)]
+    SystemImagesFailed(sdk::Error),
+    #[error("System image {package:?} isn't known to `sdkmanager`.")]
+    ImageUnknown { package: String },
+    #[error("System image {package:?} isn't installed.")]
+    ImageNotInstalled { package: String },
+    #[error("An AVD named {name:?} already exists.")]
+    AlreadyExists { name: String },
+    #[error("Failed to list existing AVDs: {0}")]
+    ListFailed(#[source] bossy::Error),
+    #[error("Failed to pipe answers to `avdmanager`: {0}")]
+    PipeFailed(#[source] std::io::Error),
+    #[error("`avdmanager` doesn't recognize device profile {device:?}.")]
+    DeviceUnknown { device: String },
+    #[error("`avdmanager` rejected system image {package:?}.")]
+    ImageRejected { package: String },
+    #[error("`avdmanager create avd` failed: {message}")]
+    CreateFailed { message: String },
+    #[error("Failed to run `avdmanager create avd`: {0}")]
+    CommandFailed(#[source] bossy::Error),
+}
+
+impl Reportable for CreateError {
+    fn report(&self) -> Report {
+        let msg = "Failed to create AVD";
+        match self {
+            Self::SystemImagesFailed(err) => err.report(),
+            Self::ImageNotInstalled { package } => Report::action_request(
+                msg,
+                format!(
+                    "{} You can install it by running `sdkmanager \"{}\"`.",
+                    self, package
+                ),
+            ),
+            Self::AlreadyExists { .. } => {
+                Report::action_request(msg, format!("{} Pass `--force` to replace it.", self))
+            }
+            Self::ImageUnknown { .. } | Self::DeviceUnknown { .. } => {
+                Report::action_request(msg, self)
+            }
+            _ => Report::error(msg, self),
+        }
+    }
+}
+
+/// An AVD that was just created by [`create`].
+#[derive(Clone, Debug)]
+pub struct CreatedAvd {
+    pub name: String,
+    pub system_image: SystemImage,
+    /// Whether an existing AVD with the same name was replaced.
+    pub replaced: bool,
+}
+
+fn avdmanager(env: &Env) -> bossy::Command {
+    bossy::Command::impure(sdk::cmdline_tool_path(env, consts::AVDMANAGER))
+        .with_env_vars(env.explicit_env())
+}
+
+/// Lists the names of all existing AVDs.
+pub fn avd_names(env: &Env) -> bossy::Result<Vec<String>> {
+    avdmanager(env)
+        .with_args(["list", "avd", "-c"])
+        .run_and_wait_for_str(|output| {
+            output
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(ToOwned::to_owned)
+                .collect()
+        })
+}
+
+/// `avdmanager` reports problems as lines starting with `Error: `, which is
+/// more specific than its exit status. Returns `None` if there weren't any.
+fn parse_create_error(
+    output: &str,
+    name: &str,
+    package: &str,
+    device: Option<&str>,
+) -> Option<CreateError> {
+    let message = output
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("Error: "))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let lowercase = message.to_ascii_lowercase();
+    if message.is_empty() {
+        None
+    } else if lowercase.contains("already exists") {
+        Some(CreateError::AlreadyExists {
+            name: name.to_owned(),
+        })
+    } else if lowercase.contains("package path is not valid") {
+        Some(CreateError::ImageRejected {
+            package: package.to_owned(),
+        })
+    } else if lowercase.contains("no device found")
+        || (lowercase.contains("device") && lowercase.contains("not found"))
+    {
+        Some(CreateError::DeviceUnknown {
+            device: device.unwrap_or_default().to_owned(),
+        })
+    } else {
+        Some(CreateError::CreateFailed { message })
+    }
+}
+
+/// Creates an AVD named `name` from `system_image` (a package path like
+/// `system-images;android-30;google_apis;x86_64`), optionally using the
+/// hardware of `device_profile` (i.e. `pixel_4`).
+///
+/// This never prompts; `avdmanager` is told not to create a custom hardware
+/// profile. An existing AVD with the same name is only replaced if `force` is
+/// set.
+pub fn create(
+    env: &Env,
+    name: &str,
+    system_image: &str,
+    device_profile: Option<&str>,
+    force: bool,
+) -> Result<CreatedAvd, CreateError> {
+    let system_image = sdk::system_images(env)
+        .map_err(CreateError::SystemImagesFailed)?
+        .into_iter()
+        .find(|image| image.package_path() == system_image)
+        .ok_or_else(|| CreateError::ImageUnknown {
+            package: system_image.to_owned(),
+        })?;
+    let package = system_image.package_path();
+    if !system_image.installed {
+        return Err(CreateError::ImageNotInstalled { package });
+    }
+    let exists = avd_names(env)
+        .map_err(CreateError::ListFailed)?
+        .iter()
+        .any(|existing| existing == name);
+    if exists && !force {
+        return Err(CreateError::AlreadyExists {
+            name: name.to_owned(),
+        });
+    }
+
+    // Answer "no" to "Do you wish to create a custom hardware profile?"
+    let (stdin, mut answers) = os_pipe::pipe().map_err(CreateError::PipeFailed)?;
+    answers
+        .write_all(b"no\n")
+        .map_err(CreateError::PipeFailed)?;
+    drop(answers);
+    let mut command = avdmanager(env).with_stdin(stdin).with_args([
+        "create",
+        "avd",
+        "--name",
+        name,
+        "--package",
+        &package,
+    ]);
+    if let Some(device) = device_profile {
+        command.add_args(["--device", device]);
+    }
+    if exists {
+        command.add_arg("--force");
+    }
+    match command.run_and_wait_for_output() {
+        Ok(_) => {
+            log::info!("created AVD {:?} from {:?}", name, package);
+            Ok(CreatedAvd {
+                name: name.to_owned(),
+                system_image,
+                replaced: exists,
+            })
+        }
+        Err(err) => Err(err
+            .output()
+            .and_then(|output| {
+                let text = format!(
+                    "{}\n{}",
+                    String::from_utf8_lossy(output.stdout()),
+                    String::from_utf8_lossy(output.stderr())
+                );
+                parse_create_error(&text, name, &package, device_profile)
+            })
+            .unwrap_or(CreateError::CommandFailed(err))),
+    }
+}
+
+/// A running emulator, as listed by `adb devices`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RunningEmulator {
+    /// The serial number `adb` knows it by, i.e. `emulator-5554`.
+    pub serial_no: String,
+    /// The AVD it's running, if it would tell us.
+    pub avd_name: Option<String>,
+}
+
+/// Emulator serial numbers in `adb devices` output, whatever state they're
+/// in; ones that are still booting or have hung show up as `offline`, but
+/// they're just as in need of shutting down.
+fn parse_emulator_serials(output: &str) -> Vec<String> {
+    regex_multi_line!(r"^(emulator-\d+)\s+\S+")
+        .captures_iter(output)
+        .map(|caps| caps[1].to_owned())
+        .collect()
+}
+
+fn adb(env: &Env) -> bossy::Command {
+    bossy::Command::pure("adb").with_env_vars(env.explicit_env())
+}
+
+/// The emulators that are currently running.
+pub fn running(env: &Env) -> bossy::Result<Vec<RunningEmulator>> {
+    let serials = adb(env)
+        .with_arg("devices")
+        .run_and_wait_for_str(parse_emulator_serials)?;
+    Ok(serials
+        .into_iter()
+        .map(|serial_no| {
+            // The first line is the name, and the second is just `OK`.
+            let avd_name = adb(env)
+                .with_args(["-s", &serial_no, "emu", "avd", "name"])
+                .run_and_wait_for_str(|output| {
+                    output.lines().next().map(str::trim).map(ToOwned::to_owned)
+                })
+                .map_err(|err| log::info!("failed to get AVD name of {:?}: {}", serial_no, err))
+                .ok()
+                .flatten()
+                .filter(|name| !name.is_empty());
+            RunningEmulator {
+                serial_no,
+                avd_name,
+            }
+        })
+        .collect())
+}
+
+/// Whether `adb` failed because the emulator wasn't there to begin with.
+fn already_gone(err: &bossy::Error) -> bool {
+    err.stderr_str()
+        .and_then(Result::ok)
+        .map(|stderr| stderr.contains("not found") || stderr.contains("no emulators found"))
+        .unwrap_or(false)
+}
+
+/// Kills the emulator with `serial_no`. Returns `false` if it was already
+/// gone, which isn't an error, since that's what we wanted anyway.
+pub fn kill(env: &Env, serial_no: &str) -> bossy::Result<bool> {
+    match adb(env)
+        .with_args(["-s", serial_no, "emu", "kill"])
+        .run_and_wait_for_output()
+    {
+        Ok(_) => Ok(true),
+        Err(err) if already_gone(&err) => {
+            log::info!("emulator {:?} was already gone", serial_no);
+            Ok(false)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    #[test]
+    fn finds_emulators_in_any_state() {
+        let output = "List of devices attached\n\
+            emulator-5554\tdevice\n\
+            0a388e93\tdevice\n\
+            emulator-5556\toffline\n";
+        assert_eq!(
+            parse_emulator_serials(output),
+            ["emulator-5554", "emulator-5556"]
+        );
+    }
+
+    #[rstest(
+        output,
+        expected,
+        case(
+            "Error: Android Virtual Device 'pixel' already exists.\n\
+             Use --force if you want to replace it.\n\
+             null\n",
+            Some("An AVD named \"pixel\" already exists.")
+        ),
+        case(
+            "Error: Package path is not valid. Valid system image paths are:\n\
+             system-images;android-30;google_apis;x86_64\n\
+             null\n",
+            Some("`avdmanager` rejected system image \"system-images;android-33-ext4;google_apis;x86_64\".")
+        ),
+        case(
+            "Error: No device found matching --device pixel_9000.\n\
+             null\n",
+            Some("`avdmanager` doesn't recognize device profile \"pixel_9000\".")
+        ),
+        case(
+            "Error: Invalid --tag default for the selected package. Valid tags are:\n\
+             google_apis\n\
+             null\n",
+            Some("`avdmanager create avd` failed: Invalid --tag default for the selected package. Valid tags are:")
+        ),
+        case(
+            "Warning: Mapping new ns http://schemas.android.com/repository/android/common/02 to old ns http://schemas.android.com/repository/android/common/01\n",
+            None
+        )
+    )]
+    fn parses_avdmanager_errors(output: &str, expected: Option<&str>) {
+        assert_eq!(
+            parse_create_error(
+                output,
+                "pixel",
+                "system-images;android-33-ext4;google_apis;x86_64",
+                Some("pixel_9000"),
+            )
+            .map(|err| err.to_string())
+            .as_deref(),
+            expected
+        );
+    }
+}
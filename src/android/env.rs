@@ -3,7 +3,7 @@ use super::{
     source_props::{self, SourceProps},
 };
 use crate::{
-    env::{Error as CoreError, ExplicitEnv},
+    env::{Error as CoreError, ExplicitEnv, Suppression},
     os::Env as CoreEnv,
     util::cli::{Report, Reportable},
 };
@@ -18,7 +18,6 @@ use thiserror::Error;
 pub enum Error {
     #[error(transparent)]
     CoreEnvError(#[from] CoreError),
-    // TODO: we should be nice and provide a platform-specific suggestion
     #[error("Have you installed the Android SDK? The `ANDROID_SDK_ROOT` environment variable isn't set, and is required: {0}")]
     AndroidSdkRootNotSet(#[from] std::env::VarError),
     #[error("Have you installed the Android SDK? The `ANDROID_SDK_ROOT` environment variable is set, but doesn't point to an existing directory.")]
@@ -32,6 +31,20 @@ impl Reportable for Error {
         match self {
             Self::CoreEnvError(err) => err.report(),
             Self::NdkEnvError(err) => err.report(),
+            Self::AndroidSdkRootNotSet(_) => {
+                let shell = crate::os::detect_shell();
+                let snippet = shell.export_snippet("ANDROID_SDK_ROOT", "/path/to/android/sdk");
+                Report::action_request(
+                    "Failed to initialize Android environment",
+                    match shell.config_file() {
+                        Some(config_file) => format!(
+                            "{}\nYou can set it by adding this to `{}`:\n    {}",
+                            self, config_file, snippet
+                        ),
+                        None => format!("{}\nYou can set it by running:\n    {}", self, snippet),
+                    },
+                )
+            }
             _ => Report::error("Failed to initialize Android environment", self),
         }
     }
@@ -101,6 +114,17 @@ impl Env {
         self.base.path()
     }
 
+    /// The env this builds on, for tools that don't care about Android.
+    pub fn base(&self) -> &CoreEnv {
+        &self.base
+    }
+
+    /// See [`CoreEnv::suppress_telemetry`].
+    pub fn suppress_telemetry(mut self, suppressions: Vec<Suppression>) -> Self {
+        self.base = self.base.suppress_telemetry(suppressions);
+        self
+    }
+
     pub fn sdk_root(&self) -> &str {
         self.sdk_root.as_path().to_str().unwrap()
     }
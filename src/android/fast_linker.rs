@@ -0,0 +1,107 @@
+use super::env::Env;
+use crate::{bossy, os::consts, util};
+use serde::{Deserialize, Serialize};
+
+/// Which faster linker to use for Android builds, from
+/// `android.fast-linker`.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FastLinker {
+    /// Whichever's available, preferring `mold`.
+    Auto,
+    Lld,
+    Mold,
+}
+
+impl FastLinker {
+    fn candidates(self) -> &'static [Linker] {
+        match self {
+            Self::Auto => &[Linker::Mold, Linker::Lld],
+            Self::Lld => &[Linker::Lld],
+            Self::Mold => &[Linker::Mold],
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Linker {
+    Lld,
+    Mold,
+}
+
+impl Linker {
+    /// The name clang takes via `-fuse-ld`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Lld => "lld",
+            Self::Mold => "mold",
+        }
+    }
+
+    fn bin_name(self) -> &'static str {
+        match self {
+            Self::Lld => "ld.lld",
+            Self::Mold => "mold",
+        }
+    }
+
+    fn present(self, env: &Env) -> bool {
+        // The NDK ships its own `ld.lld` alongside clang, which is the one
+        // clang will actually pick.
+        let bundled = env
+            .ndk
+            .tool_dir()
+            .map(|dir| {
+                dir.join(format!(
+                    "{}{}",
+                    self.bin_name(),
+                    std::env::consts::EXE_SUFFIX
+                ))
+            })
+            .map(|path| path.is_file())
+            .unwrap_or_default();
+        bundled || util::command_present(self.bin_name()).unwrap_or_default()
+    }
+
+    /// Checks that the NDK's clang can actually link with this, since
+    /// `-fuse-ld` support varies between clang versions.
+    fn supported_by_clang(self, env: &Env, triple: &str, min_api: u32) -> bool {
+        let clang = match env.ndk.tool_dir() {
+            Ok(tool_dir) => tool_dir.join(consts::CLANG_BIN),
+            Err(_) => return false,
+        };
+        let supported = bossy::Command::impure(&clang)
+            .with_arg(format!("--target={}{}", triple, min_api))
+            .with_arg(format!("-fuse-ld={}", self.as_str()))
+            .with_arg("-Wl,--version")
+            .run_and_wait_for_output()
+            .is_ok();
+        if !supported {
+            log::info!("{:?} can't link with {}", clang, self.as_str());
+        }
+        supported
+    }
+
+    /// The rustflags that route linking through this linker.
+    pub fn rustflags(self) -> Vec<String> {
+        vec![format!("-Clink-arg=-fuse-ld={}", self.as_str())]
+    }
+}
+
+/// The first linker `preference` allows that's both installed and usable by
+/// the NDK's clang for `triple`. If there isn't one, we just use the NDK's
+/// default linker.
+pub fn resolve(preference: FastLinker, env: &Env, triple: &str, min_api: u32) -> Option<Linker> {
+    let linker = preference
+        .candidates()
+        .iter()
+        .copied()
+        .find(|linker| linker.present(env) && linker.supported_by_clang(env, triple, min_api));
+    if linker.is_none() {
+        log::info!(
+            "no usable linker for `android.fast-linker = {:?}`; using the NDK's default",
+            preference
+        );
+    }
+    linker
+}
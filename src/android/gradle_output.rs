@@ -0,0 +1,339 @@
+use crate::{
+    bossy, build_log,
+    opts::NoiseLevel,
+    util::cli::{self, colors},
+};
+use colored::Colorize as _;
+
+/// Tasks that finished with one of these outcomes didn't do any work.
+static QUIET_OUTCOMES: &[&str] = &["UP-TO-DATE", "NO-SOURCE", "SKIPPED", "FROM-CACHE"];
+
+/// Lines gradle prints on every build that never tell us anything useful.
+static NOISE_PREFIXES: &[&str] = &[
+    "Starting a Gradle Daemon",
+    "Deprecated Gradle features were used",
+    "You can use '--warning-mode all'",
+    "See https://docs.gradle.org",
+    "For more on this, please refer to",
+    "* Get more help at",
+];
+
+#[derive(Debug, Eq, PartialEq)]
+enum Line<'a> {
+    Configure,
+    Task {
+        name: &'a str,
+        outcome: Option<&'a str>,
+    },
+    /// The rich console's progress bar, i.e. `<===---> 42% EXECUTING [3s]`.
+    Progress,
+    ErrorStart,
+    Error,
+    Warning,
+    Summary,
+    Success,
+    Failure,
+    Noise,
+    Other,
+}
+
+impl<'a> Line<'a> {
+    fn classify(line: &'a str) -> Self {
+        let trimmed = line.trim();
+        if let Some(task) = trimmed.strip_prefix("> Task ") {
+            let mut parts = task.splitn(2, ' ');
+            let name = parts.next().unwrap_or_default();
+            let outcome = parts.next().map(str::trim);
+            Self::Task { name, outcome }
+        } else if trimmed.starts_with("> Configure project") {
+            Self::Configure
+        } else if trimmed.starts_with('<') && trimmed.contains('%') {
+            Self::Progress
+        } else if trimmed.starts_with("FAILURE:") || trimmed == "* What went wrong:" {
+            Self::ErrorStart
+        } else if trimmed.starts_with("e: ")
+            || trimmed.starts_with("error:")
+            || trimmed.contains(": error:")
+        {
+            Self::Error
+        } else if trimmed.starts_with("w: ")
+            || trimmed.starts_with("warning:")
+            || trimmed.contains(": warning:")
+        {
+            Self::Warning
+        } else if trimmed.starts_with("BUILD SUCCESSFUL") {
+            Self::Success
+        } else if trimmed.starts_with("BUILD FAILED") {
+            Self::Failure
+        } else if trimmed.ends_with("actionable tasks") || trimmed.contains(" actionable task") {
+            Self::Summary
+        } else if trimmed.is_empty()
+            || NOISE_PREFIXES
+                .iter()
+                .any(|prefix| trimmed.starts_with(prefix))
+        {
+            Self::Noise
+        } else {
+            Self::Other
+        }
+    }
+}
+
+/// Condenses gradle's output into something readable: the configuration phase
+/// is collapsed into a single line, tasks that didn't do anything are only
+/// counted, and errors and warnings are highlighted.
+#[derive(Debug)]
+pub struct GradleOutput {
+    color: bool,
+    configuring: bool,
+    in_error_block: bool,
+    in_try_section: bool,
+    skipped_tasks: usize,
+}
+
+impl GradleOutput {
+    pub fn new(color: bool) -> Self {
+        Self {
+            color,
+            configuring: false,
+            in_error_block: false,
+            in_try_section: false,
+            skipped_tasks: 0,
+        }
+    }
+
+    fn paint(&self, line: &str, color: colored::Color, bold: bool) -> String {
+        if self.color {
+            let colored = line.color(color);
+            if bold { colored.bold() } else { colored }.to_string()
+        } else {
+            line.to_owned()
+        }
+    }
+
+    fn dim(&self, line: &str) -> String {
+        if self.color {
+            line.dimmed().to_string()
+        } else {
+            line.to_owned()
+        }
+    }
+
+    /// Returns what to print in place of `line`, if anything.
+    pub fn process(&mut self, line: &str) -> Option<String> {
+        let kind = Line::classify(line);
+        if self.in_error_block {
+            // Gradle's suggestions to rerun with `--stacktrace` and friends
+            // aren't how you'd do that through us.
+            if line.starts_with("* ") {
+                self.in_try_section =
+                    line.starts_with("* Try:") || line.starts_with("* Get more help");
+            }
+            return match kind {
+                Line::Failure => {
+                    self.in_error_block = false;
+                    self.in_try_section = false;
+                    Some(self.paint(line.trim(), colors::ERROR, true))
+                }
+                _ if self.in_try_section => None,
+                Line::Noise if !line.trim().is_empty() => None,
+                _ => Some(self.paint(line, colors::ERROR, false)),
+            };
+        }
+        match kind {
+            Line::Configure => {
+                if self.configuring {
+                    None
+                } else {
+                    self.configuring = true;
+                    Some(self.dim("Configuring project..."))
+                }
+            }
+            Line::Task { name, outcome } => {
+                self.configuring = false;
+                if outcome.is_some_and(|outcome| QUIET_OUTCOMES.contains(&outcome)) {
+                    self.skipped_tasks += 1;
+                    None
+                } else {
+                    Some(format!("  {} {}", self.dim("▸"), name))
+                }
+            }
+            Line::Progress | Line::Noise => None,
+            Line::ErrorStart => {
+                self.in_error_block = true;
+                Some(self.paint(line.trim(), colors::ERROR, true))
+            }
+            Line::Error => Some(self.paint(line, colors::ERROR, false)),
+            Line::Warning => Some(self.paint(line, colors::WARNING, false)),
+            Line::Success => Some(self.paint(line.trim(), colors::VICTORY, true)),
+            Line::Failure => Some(self.paint(line.trim(), colors::ERROR, true)),
+            Line::Summary => Some(self.dim(line.trim())),
+            // Whatever the configuration phase prints is almost always noise.
+            Line::Other if self.configuring => None,
+            Line::Other => Some(line.to_owned()),
+        }
+    }
+
+    /// Returns a closing note, if there's anything worth saying.
+    pub fn finish(&mut self) -> Option<String> {
+        if self.skipped_tasks > 0 {
+            let noun = if self.skipped_tasks == 1 {
+                "task"
+            } else {
+                "tasks"
+            };
+            Some(self.dim(&format!(
+                "({} {} up-to-date or skipped)",
+                self.skipped_tasks, noun
+            )))
+        } else {
+            None
+        }
+    }
+}
+
+/// Runs a gradle command, condensing its output unless the user asked for
+/// verbose output, in which case they get gradle's raw log.
+pub fn run(command: bossy::Command, noise_level: NoiseLevel) -> bossy::Result<()> {
+    let command = command.with_arg(noise_level.gradle_flag());
+    if noise_level.polite() {
+        let mut output = GradleOutput::new(cli::should_colorize_stdout());
+        let mut command = command.with_arg("--console=plain");
+        build_log::line(&format!("$ {}", command.display()));
+        let result = command.run_streaming(|_stream, line| {
+            build_log::line(line);
+            if let Some(line) = output.process(line) {
+                println!("{}", line);
+            }
+        });
+        if let Some(line) = output.finish() {
+            println!("{}", line);
+        }
+        result.map(|_status| ())
+    } else {
+        build_log::run(command).map(|_status| ())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest(
+        line,
+        expected,
+        case("> Configure project :app", Line::Configure),
+        case(
+            "> Task :app:preBuild UP-TO-DATE",
+            Line::Task {
+                name: ":app:preBuild",
+                outcome: Some("UP-TO-DATE")
+            }
+        ),
+        case(
+            "> Task :app:compileArm64DebugKotlin",
+            Line::Task {
+                name: ":app:compileArm64DebugKotlin",
+                outcome: None
+            }
+        ),
+        case("<=====--------> 42% EXECUTING [3s]", Line::Progress),
+        case("FAILURE: Build failed with an exception.", Line::ErrorStart),
+        case("* What went wrong:", Line::ErrorStart),
+        case(
+            "e: /app/src/main/java/com/example/MainActivity.kt: (12, 5): Unresolved reference: foo",
+            Line::Error
+        ),
+        case(
+            "/app/src/main/cpp/native.c:3:10: error: 'jni.h' file not found",
+            Line::Error
+        ),
+        case(
+            "w: /app/src/main/java/com/example/MainActivity.kt: (4, 9): Variable 'x' is never used",
+            Line::Warning
+        ),
+        case("BUILD SUCCESSFUL in 14s", Line::Success),
+        case("BUILD FAILED in 3s", Line::Failure),
+        case("34 actionable tasks: 2 executed, 32 up-to-date", Line::Summary),
+        case("1 actionable task: 1 executed", Line::Summary),
+        case("Starting a Gradle Daemon (subsequent builds will be faster)", Line::Noise),
+        case("", Line::Noise),
+        case("Execution failed for task ':app:mergeDebugNativeLibs'.", Line::Other)
+    )]
+    fn classifies_gradle_lines(line: &str, expected: Line<'_>) {
+        assert_eq!(Line::classify(line), expected);
+    }
+
+    fn process(lines: &str) -> Vec<String> {
+        let mut output = GradleOutput::new(false);
+        let mut processed = lines
+            .lines()
+            .filter_map(|line| output.process(line))
+            .collect::<Vec<_>>();
+        processed.extend(output.finish());
+        processed
+    }
+
+    #[test]
+    fn condenses_successful_builds() {
+        assert_eq!(
+            process(
+                "\
+Starting a Gradle Daemon (subsequent builds will be faster)
+> Configure project :app
+WARNING: The option setting 'android.enableJetifier=true' is experimental.
+> Configure project :buildSrc
+> Task :app:preBuild UP-TO-DATE
+> Task :app:rustBuildArm64Debug
+> Task :app:mergeArm64DebugJniLibFolders UP-TO-DATE
+
+BUILD SUCCESSFUL in 14s
+34 actionable tasks: 2 executed, 32 up-to-date
+"
+            ),
+            vec![
+                "Configuring project...",
+                "  ▸ :app:rustBuildArm64Debug",
+                "BUILD SUCCESSFUL in 14s",
+                "34 actionable tasks: 2 executed, 32 up-to-date",
+                "(2 tasks up-to-date or skipped)",
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_errors_but_not_gradle_advice() {
+        assert_eq!(
+            process(
+                "\
+> Task :app:rustBuildArm64Debug FAILED
+
+FAILURE: Build failed with an exception.
+
+* What went wrong:
+Execution failed for task ':app:rustBuildArm64Debug'.
+> Process 'command 'cargo'' finished with non-zero exit value 1
+
+* Try:
+> Run with --stacktrace option to get the stack trace.
+> Run with --info or --debug option to get more log output.
+
+* Get more help at https://help.gradle.org
+
+BUILD FAILED in 3s
+"
+            ),
+            vec![
+                "  ▸ :app:rustBuildArm64Debug",
+                "FAILURE: Build failed with an exception.",
+                "",
+                "* What went wrong:",
+                "Execution failed for task ':app:rustBuildArm64Debug'.",
+                "> Process 'command 'cargo'' finished with non-zero exit value 1",
+                "",
+                "BUILD FAILED in 3s",
+            ]
+        );
+    }
+}
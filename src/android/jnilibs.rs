@@ -62,11 +62,12 @@ impl Reportable for SymlinkLibError {
     }
 }
 
+pub fn root(config: &Config) -> PathBuf {
+    prefix_path(config.project_dir(), "app/src/main/jniLibs")
+}
+
 pub fn path(config: &Config, target: Target<'_>) -> PathBuf {
-    prefix_path(
-        config.project_dir(),
-        format!("app/src/main/jniLibs/{}", &target.abi),
-    )
+    root(config).join(target.abi)
 }
 
 #[derive(Debug)]
@@ -117,12 +118,28 @@ impl JniLibs {
     }
 
     pub fn symlink_lib(&self, src: &Path) -> Result<(), SymlinkLibError> {
-        log::info!("symlinking lib {:?} in jniLibs dir {:?}", src, self.path);
+        self.symlink_lib_as(
+            src,
+            src.file_name()
+                .expect("developer error: file had no file name"),
+        )
+    }
+
+    /// Like [`JniLibs::symlink_lib`], but the symlink is named `name` rather
+    /// than after `src`.
+    pub fn symlink_lib_as(
+        &self,
+        src: &Path,
+        name: impl AsRef<std::ffi::OsStr>,
+    ) -> Result<(), SymlinkLibError> {
+        log::info!(
+            "symlinking lib {:?} in jniLibs dir {:?} as {:?}",
+            src,
+            self.path,
+            name.as_ref()
+        );
         if src.is_file() {
-            let dest = self.path.join(
-                src.file_name()
-                    .expect("developer error: file had no file name"),
-            );
+            let dest = self.path.join(name.as_ref());
             os::ln::force_symlink(src, &dest, ln::TargetStyle::File)
                 .map_err(SymlinkLibError::SymlinkFailed)?;
             Ok(())
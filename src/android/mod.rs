@@ -1,14 +1,21 @@
 pub mod aab;
+pub mod aapt;
 pub mod adb;
 pub mod apk;
 mod bundletool;
 pub mod cli;
 pub mod config;
 pub mod device;
+pub mod emulator;
 pub mod env;
+pub mod fast_linker;
+mod gradle_output;
 mod jnilibs;
 pub mod ndk;
+pub mod package;
 pub(crate) mod project;
+pub mod sdk;
+pub mod sign;
 mod source_props;
 pub mod target;
 
@@ -217,6 +217,13 @@ impl Env {
         )
     }
 
+    /// The Python script behind the `ndk-stack` wrapper, which NDK r21 and
+    /// later have.
+    pub fn stack_script(&self) -> PathBuf {
+        self.ndk_home
+            .join(format!("prebuilt/{}/bin/ndk-stack.py", host_tag()))
+    }
+
     pub fn tool_dir(&self) -> Result<PathBuf, MissingToolError> {
         MissingToolError::check_dir(self.prebuilt_dir()?.join("bin"), "tools")
     }
@@ -267,6 +274,47 @@ impl Env {
         MissingToolError::check_file(self.tool_dir()?.join(bin_path), "readelf")
     }
 
+    /// Checks that the NDK has a clang wrapper for `triple` at API level
+    /// `min_api`, and that clang actually accepts that target.
+    ///
+    /// NDKs regularly add and drop targets (i.e. r24 dropped API levels below
+    /// 19, and 32-bit support is on its way out), so this lets us skip
+    /// unsupported ABIs instead of failing halfway through a build.
+    pub fn supports_target(&self, triple: &str, min_api: u32) -> bool {
+        let compiler = match self.compiler_path(Compiler::Clang, triple, min_api) {
+            Ok(compiler) => compiler,
+            Err(err) => {
+                log::info!("NDK doesn't support target {:?}: {}", triple, err);
+                return false;
+            }
+        };
+        let clang = match self.tool_dir() {
+            Ok(tool_dir) => tool_dir.join(consts::CLANG_BIN),
+            Err(_) => compiler,
+        };
+        let supported = bossy::Command::impure(&clang)
+            .with_arg(format!("--target={}{}", triple, min_api))
+            .with_arg("-v")
+            .run_and_wait_for_output()
+            .is_ok();
+        if !supported {
+            log::info!("{:?} rejected target {:?}", clang, triple);
+        }
+        supported
+    }
+
+    /// Checks the ELF header of `elf` to see if it's a shared object, which
+    /// is the only kind of file that belongs in `jniLibs`.
+    pub fn is_shared_object(&self, elf: &Path, triple: &str) -> Result<bool, RequiredLibsError> {
+        Ok(regex_multi_line!(r"^\s*Type:\s+DYN\b").is_match(
+            bossy::Command::impure(self.readelf_path(triple)?)
+                .with_arg("-h")
+                .with_arg(dunce::simplified(elf))
+                .run_and_wait_for_output()?
+                .stdout_str()?,
+        ))
+    }
+
     pub fn required_libs(
         &self,
         elf: &Path,
@@ -291,3 +339,156 @@ impl Env {
             .collect())
     }
 }
+
+pub fn supports_target(env: &Env, triple: &str, min_api: u32) -> bool {
+    env.supports_target(triple, min_api)
+}
+
+/// Where an NDK was found.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum NdkSource {
+    NdkHome,
+    AndroidNdkHome,
+    AndroidNdkRoot,
+    /// `$ANDROID_SDK_ROOT/ndk/<version>`, where the SDK manager installs them
+    /// these days.
+    SdkSideBySide,
+    /// `$ANDROID_SDK_ROOT/ndk-bundle`, where the SDK manager used to install
+    /// them.
+    SdkBundle,
+}
+
+impl Display for NdkSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NdkHome => write!(f, "`NDK_HOME`"),
+            Self::AndroidNdkHome => write!(f, "`ANDROID_NDK_HOME`"),
+            Self::AndroidNdkRoot => write!(f, "`ANDROID_NDK_ROOT`"),
+            Self::SdkSideBySide => write!(f, "the SDK's `ndk` dir"),
+            Self::SdkBundle => write!(f, "the SDK's `ndk-bundle`"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct DiscoveredNdk {
+    pub path: PathBuf,
+    /// Everywhere this NDK was found, since i.e. `ANDROID_NDK_HOME` often
+    /// points into the SDK.
+    pub sources: Vec<NdkSource>,
+    /// `None` if `source.properties` couldn't be read.
+    pub version: Option<source_props::Revision>,
+    /// Whether this is the NDK we build with.
+    pub in_use: bool,
+}
+
+impl Display for DiscoveredNdk {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.version {
+            Some(version) => write!(f, "NDK v{}", version)?,
+            None => write!(f, "NDK of unknown version")?,
+        }
+        write!(f, " at {:?}, from ", self.path)?;
+        for (i, source) in self.sources.iter().enumerate() {
+            if i != 0 {
+                write!(f, " and ")?;
+            }
+            write!(f, "{}", source)?;
+        }
+        if self.in_use {
+            write!(f, " (in use)")?;
+        }
+        Ok(())
+    }
+}
+
+/// Every NDK we could find, and which of them we'll actually use.
+#[derive(Debug)]
+pub struct Resolution {
+    pub ndks: Vec<DiscoveredNdk>,
+}
+
+impl Resolution {
+    pub fn in_use(&self) -> Option<&DiscoveredNdk> {
+        self.ndks.iter().find(|ndk| ndk.in_use)
+    }
+
+    /// Explains which NDK wins when more than one is installed, since a
+    /// version mismatch between them is easy to miss otherwise.
+    pub fn warning(&self) -> Option<String> {
+        if self.ndks.len() < 2 {
+            return None;
+        }
+        let using = self
+            .in_use()
+            .map(|ndk| match &ndk.version {
+                Some(version) => format!("v{} at {:?}", version, ndk.path),
+                None => format!("{:?}", ndk.path),
+            })
+            .unwrap_or_else(|| "none of them".to_owned());
+        Some(format!(
+            "Found {} NDKs, and `NDK_HOME` decides which gets used ({}); set it explicitly if that isn't the one you want",
+            self.ndks.len(),
+            using,
+        ))
+    }
+}
+
+fn ndk_candidates(sdk_root: &Path) -> Vec<(NdkSource, PathBuf)> {
+    let mut candidates = [
+        (NdkSource::NdkHome, "NDK_HOME"),
+        (NdkSource::AndroidNdkHome, "ANDROID_NDK_HOME"),
+        (NdkSource::AndroidNdkRoot, "ANDROID_NDK_ROOT"),
+    ]
+    .iter()
+    .filter_map(|(source, var)| {
+        std::env::var_os(var)
+            .filter(|path| !path.is_empty())
+            .map(|path| (*source, PathBuf::from(path)))
+    })
+    .collect::<Vec<_>>();
+    if let Ok(entries) = std::fs::read_dir(sdk_root.join("ndk")) {
+        let mut side_by_side = entries
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .collect::<Vec<_>>();
+        side_by_side.sort();
+        candidates.extend(
+            side_by_side
+                .into_iter()
+                .map(|path| (NdkSource::SdkSideBySide, path)),
+        );
+    }
+    candidates.push((NdkSource::SdkBundle, sdk_root.join("ndk-bundle")));
+    candidates
+}
+
+/// Lists every NDK in the usual places, along with its version and where it
+/// was found, marking the one in `NDK_HOME` as the one in use.
+pub fn resolution_report(env: &super::env::Env) -> Resolution {
+    let in_use = dunce::canonicalize(env.ndk.home()).unwrap_or_else(|_| env.ndk.home().to_owned());
+    let mut ndks: Vec<DiscoveredNdk> = Vec::new();
+    for (source, path) in ndk_candidates(Path::new(env.sdk_root())) {
+        if !path.join("source.properties").is_file() {
+            continue;
+        }
+        let path = dunce::canonicalize(&path).unwrap_or(path);
+        if let Some(ndk) = ndks.iter_mut().find(|ndk| ndk.path == path) {
+            if !ndk.sources.contains(&source) {
+                ndk.sources.push(source);
+            }
+            continue;
+        }
+        let version = SourceProps::from_path(path.join("source.properties"))
+            .map(|props| props.pkg.revision)
+            .map_err(|err| log::warn!("failed to get version of NDK at {:?}: {}", path, err))
+            .ok();
+        ndks.push(DiscoveredNdk {
+            in_use: path == in_use,
+            path,
+            sources: vec![source],
+            version,
+        });
+    }
+    Resolution { ndks }
+}
@@ -0,0 +1,365 @@
+use super::{
+    config::Config,
+    env::Env,
+    ndk::{MissingToolError, RequiredLibsError},
+    target::Target,
+};
+use crate::{
+    config::LibNameError,
+    opts::{NoiseLevel, Profile},
+    util::cli::{Report, Reportable},
+};
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+
+/// What to have Gradle build, for [`apk::build`](super::apk::build) and
+/// [`aab::build`](super::aab::build).
+#[derive(Clone, Debug)]
+pub struct PackageOptions<'a> {
+    pub noise_level: NoiseLevel,
+    pub profile: Profile,
+    pub targets: Vec<&'a Target<'a>>,
+    /// Build a package per ABI, rather than one universal package.
+    pub split_per_abi: bool,
+    /// Build even if nothing changed since the last build.
+    pub force: bool,
+}
+
+/// What came of a Gradle build.
+#[derive(Clone, Debug)]
+pub struct Packaged {
+    pub outputs: Vec<PathBuf>,
+    /// Whether Gradle was skipped, since nothing changed since `outputs` were
+    /// last built.
+    pub skipped: bool,
+}
+
+#[derive(Debug, Error)]
+pub enum StageLibsError {
+    #[error("{0:?} isn't an Android ABI we know about.")]
+    AbiUnknown(String),
+    #[error("The lib built for {abi} is {path:?}, but nothing exists there.")]
+    SourceMissing { abi: String, path: PathBuf },
+    #[error("Failed to inspect {path:?}: {cause}")]
+    CheckFailed {
+        path: PathBuf,
+        cause: Box<RequiredLibsError>,
+    },
+    #[error("{0:?} isn't an ELF shared object, so it can't be packaged.")]
+    NotSharedObject(PathBuf),
+    #[error("Failed to locate \"libc++_shared.so\": {0}")]
+    LibcxxSharedPathFailed(MissingToolError),
+    #[error(transparent)]
+    LibNameFailed(LibNameError),
+    #[error("Failed to create directory {path:?}: {cause}")]
+    DirCreationFailed { path: PathBuf, cause: io::Error },
+    #[error("Failed to remove stale lib {path:?}: {cause}")]
+    RemoveFailed { path: PathBuf, cause: io::Error },
+    #[error("Failed to copy {src:?} to {dest:?}: {cause}")]
+    CopyFailed {
+        src: PathBuf,
+        dest: PathBuf,
+        cause: io::Error,
+    },
+}
+
+impl Reportable for StageLibsError {
+    fn report(&self) -> Report {
+        match self {
+            Self::LibNameFailed(err) => err.report(),
+            _ => Report::error("Failed to stage native libs", self),
+        }
+    }
+}
+
+fn check_lib(env: &Env, target: &Target<'_>, src: &Path) -> Result<(), StageLibsError> {
+    if !src.is_file() {
+        return Err(StageLibsError::SourceMissing {
+            abi: target.abi.to_owned(),
+            path: src.to_owned(),
+        });
+    }
+    let is_shared_object = env
+        .ndk
+        .is_shared_object(src, target.binutils_triple())
+        .map_err(|cause| StageLibsError::CheckFailed {
+            path: src.to_owned(),
+            cause: Box::new(cause),
+        })?;
+    if is_shared_object {
+        Ok(())
+    } else {
+        Err(StageLibsError::NotSharedObject(src.to_owned()))
+    }
+}
+
+fn copy_lib(src: &Path, dest_dir: &Path, name: &str) -> Result<PathBuf, StageLibsError> {
+    fs::create_dir_all(dest_dir).map_err(|cause| StageLibsError::DirCreationFailed {
+        path: dest_dir.to_owned(),
+        cause,
+    })?;
+    let dest = dest_dir.join(name);
+    // Copying through a symlink left by `symlink_libs` would overwrite the
+    // build output itself, so anything already there is removed first.
+    if fs::symlink_metadata(&dest).is_ok() {
+        fs::remove_file(&dest).map_err(|cause| StageLibsError::RemoveFailed {
+            path: dest.clone(),
+            cause,
+        })?;
+    }
+    fs::copy(src, &dest).map_err(|cause| StageLibsError::CopyFailed {
+        src: src.to_owned(),
+        dest: dest.clone(),
+        cause,
+    })?;
+    Ok(dest)
+}
+
+fn stage_lib(
+    config: &Config,
+    env: &Env,
+    target: &Target<'_>,
+    src: &Path,
+    dest_dir: &Path,
+) -> Result<Vec<PathBuf>, StageLibsError> {
+    check_lib(env, target, src)?;
+    let mut staged = vec![copy_lib(src, dest_dir, &config.so_name())?];
+    let needs_cxx_shared = env
+        .ndk
+        .required_libs(src, target.binutils_triple())
+        .map_err(|cause| StageLibsError::CheckFailed {
+            path: src.to_owned(),
+            cause: Box::new(cause),
+        })?
+        .contains("libc++_shared.so");
+    if needs_cxx_shared {
+        log::info!("lib {:?} requires \"libc++_shared.so\"", src);
+        let cxx_shared = env
+            .ndk
+            .libcxx_shared_path(*target)
+            .map_err(StageLibsError::LibcxxSharedPathFailed)?;
+        staged.push(copy_lib(&cxx_shared, dest_dir, "libc++_shared.so")?);
+    }
+    Ok(staged)
+}
+
+/// Copies each lib in `built`, keyed by ABI, into `jni_libs_dir/<abi>/`,
+/// named the way the Java side loads it, along with `libc++_shared.so` if it
+/// needs that. Unlike `symlink_libs`, the results don't depend on the build
+/// output sticking around, which is what `android.copy-libs` is for. Returns
+/// the staged paths.
+pub fn stage_libs(
+    config: &Config,
+    env: &Env,
+    built: &[(&str, PathBuf)],
+    jni_libs_dir: &Path,
+) -> Result<Vec<PathBuf>, StageLibsError> {
+    let mut staged = Vec::new();
+    for (abi, src) in built {
+        let target =
+            Target::for_abi(abi).ok_or_else(|| StageLibsError::AbiUnknown((*abi).to_owned()))?;
+        let paths = stage_lib(config, env, target, src, &jni_libs_dir.join(target.abi))?;
+        log::info!("staged {:?} for {} at {:?}", src, target.abi, paths);
+        staged.extend(paths);
+    }
+    Ok(staged)
+}
+
+#[derive(Debug, Error)]
+pub enum InspectLibsError {
+    #[error("APK {0:?} doesn't exist.")]
+    ApkMissing(PathBuf),
+    #[error("Failed to open {apk:?}: {cause}")]
+    OpenFailed { apk: PathBuf, cause: io::Error },
+    #[error("Failed to read {apk:?} as a zip archive: {cause}")]
+    ReadFailed {
+        apk: PathBuf,
+        cause: zip::result::ZipError,
+    },
+}
+
+impl Reportable for InspectLibsError {
+    fn report(&self) -> Report {
+        Report::error("Failed to inspect native libs in APK", self)
+    }
+}
+
+/// Groups the `<prefix>lib/<abi>/*.so` entries in a zip listing by ABI. APKs
+/// have no prefix, while AABs put each module's libs under `<module>/`.
+fn libs_by_abi<'a>(
+    entries: impl Iterator<Item = &'a str>,
+    prefix: &str,
+) -> Vec<(String, Vec<String>)> {
+    let mut libs = BTreeMap::<String, Vec<String>>::new();
+    for entry in entries {
+        let entry = match entry.trim().strip_prefix(prefix) {
+            Some(entry) => entry,
+            None => continue,
+        };
+        let mut parts = entry.split('/');
+        if let (Some("lib"), Some(abi), Some(name), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        {
+            if name.ends_with(".so") {
+                libs.entry(abi.to_owned())
+                    .or_default()
+                    .push(name.to_owned());
+            }
+        }
+    }
+    libs.into_iter()
+        .map(|(abi, mut names)| {
+            names.sort();
+            (abi, names)
+        })
+        .collect()
+}
+
+fn read_libs(package: &Path, prefix: &str) -> Result<Vec<(String, Vec<String>)>, InspectLibsError> {
+    if !package.is_file() {
+        return Err(InspectLibsError::ApkMissing(package.to_owned()));
+    }
+    let file = fs::File::open(package).map_err(|cause| InspectLibsError::OpenFailed {
+        apk: package.to_owned(),
+        cause,
+    })?;
+    let archive = zip::ZipArchive::new(io::BufReader::new(file)).map_err(|cause| {
+        InspectLibsError::ReadFailed {
+            apk: package.to_owned(),
+            cause,
+        }
+    })?;
+    Ok(libs_by_abi(archive.file_names(), prefix))
+}
+
+/// Lists the native libs packaged in `apk`, grouped by ABI, which is the
+/// first thing to look at when an app dies with `UnsatisfiedLinkError`. ABIs
+/// we don't know about are included as-is, since those are worth seeing too.
+pub fn inspect_libs(apk: &Path) -> Result<Vec<(String, Vec<String>)>, InspectLibsError> {
+    read_libs(apk, "")
+}
+
+/// The ABIs `package` has native libs for, which is either an APK or an AAB
+/// (judging by its extension).
+pub fn packaged_abis(package: &Path) -> Result<Vec<String>, InspectLibsError> {
+    let prefix = if package.extension().is_some_and(|ext| ext == "aab") {
+        "base/"
+    } else {
+        ""
+    };
+    read_libs(package, prefix).map(|libs| libs.into_iter().map(|(abi, _)| abi).collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn groups_libs_by_abi() {
+        let listing = "\
+AndroidManifest.xml
+classes.dex
+lib/arm64-v8a/libhello.so
+lib/arm64-v8a/libc++_shared.so
+lib/x86_64/libhello.so
+lib/armeabi-v7a/README.txt
+lib/stray.so
+res/lib/arm64-v8a/libnope.so
+resources.arsc
+";
+        assert_eq!(
+            libs_by_abi(listing.lines(), ""),
+            vec![
+                (
+                    "arm64-v8a".to_owned(),
+                    vec!["libc++_shared.so".to_owned(), "libhello.so".to_owned()]
+                ),
+                ("x86_64".to_owned(), vec!["libhello.so".to_owned()]),
+            ]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn copies_over_stale_libs() {
+        let dir = crate::util::temp_dir().join("package-stage-test");
+        let built = dir.join("target/aarch64-linux-android/debug");
+        fs::create_dir_all(&built).unwrap();
+        let src = built.join("libhello.so");
+        fs::write(&src, "new").unwrap();
+        let dest_dir = dir.join("jniLibs/arm64-v8a");
+
+        // Nothing there yet, so the dir has to be created.
+        let dest = copy_lib(&src, &dest_dir, "libapp.so").unwrap();
+        assert_eq!(dest, dest_dir.join("libapp.so"));
+        assert_eq!(fs::read_to_string(&dest).unwrap(), "new");
+
+        // A symlink from `symlink_libs` is replaced, not written through.
+        fs::remove_file(&dest).unwrap();
+        std::os::unix::fs::symlink(&src, &dest).unwrap();
+        fs::write(&src, "newer").unwrap();
+        copy_lib(&src, &dest_dir, "libapp.so").unwrap();
+        let is_symlink = fs::symlink_metadata(&dest)
+            .unwrap()
+            .file_type()
+            .is_symlink();
+        let src_contents = fs::read_to_string(&src).unwrap();
+
+        // And so is a stale copy.
+        fs::write(&src, "newest").unwrap();
+        copy_lib(&src, &dest_dir, "libapp.so").unwrap();
+        let contents = fs::read_to_string(&dest).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(!is_symlink);
+        assert_eq!(src_contents, "newer");
+        assert_eq!(contents, "newest");
+    }
+
+    fn write_zip(path: &Path, entries: &[&str]) {
+        use std::io::Write as _;
+        let mut zip = zip::ZipWriter::new(fs::File::create(path).unwrap());
+        for entry in entries {
+            zip.start_file(*entry, Default::default()).unwrap();
+            zip.write_all(b"\x7fELF").unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    #[test]
+    fn reads_abis_from_packages() {
+        let dir = crate::util::temp_dir().join("package-abis-test");
+        fs::create_dir_all(&dir).unwrap();
+        let apk = dir.join("app.apk");
+        write_zip(
+            &apk,
+            &[
+                "classes.dex",
+                "lib/arm64-v8a/libhello.so",
+                "lib/x86_64/libhello.so",
+            ],
+        );
+        let aab = dir.join("app.aab");
+        write_zip(
+            &aab,
+            &[
+                "base/dex/classes.dex",
+                "base/lib/armeabi-v7a/libhello.so",
+                "lib/x86/libnope.so",
+            ],
+        );
+        let apk_abis = packaged_abis(&apk).unwrap();
+        let aab_abis = packaged_abis(&aab).unwrap();
+        let libs = inspect_libs(&apk).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(apk_abis, vec!["arm64-v8a", "x86_64"]);
+        assert_eq!(aab_abis, vec!["armeabi-v7a"]);
+        assert_eq!(
+            libs[0],
+            ("arm64-v8a".to_owned(), vec!["libhello.so".to_owned()])
+        );
+    }
+}
@@ -13,6 +13,7 @@ use crate::{
         self,
         cli::{Report, Reportable, TextWrapper},
         ln, prefix_path,
+        toolchain::{Toolchain, ToolchainError},
     },
 };
 use path_abs::PathOps;
@@ -26,6 +27,7 @@ pub static ASSET_PACK_TEMPLATE_PACK: &str = "android-studio-asset-pack";
 
 #[derive(Debug)]
 pub enum Error {
+    ToolchainInvalid(ToolchainError),
     RustupFailed(bossy::Error),
     MissingPack(templating::LookupError),
     TemplateProcessingFailed(bicycle::ProcessingError),
@@ -54,6 +56,7 @@ pub enum Error {
 impl Reportable for Error {
     fn report(&self) -> Report {
         match self {
+            Self::ToolchainInvalid(err) => err.report(),
             Self::RustupFailed(err) => Report::error("Failed to `rustup` Android toolchains", err),
             Self::MissingPack(err) => Report::error("Failed to locate Android template pack", err),
             Self::TemplateProcessingFailed(err) => {
@@ -88,6 +91,18 @@ impl Reportable for Error {
     }
 }
 
+/// Whether `linker` is one of the NDK's clang wrappers, which is what we'd
+/// generate. This also covers NDKs other than the current one, so that
+/// switching NDKs doesn't look like a custom linker.
+fn is_ndk_linker(linker: &str, ndk: &ndk::Env) -> bool {
+    let linker = Path::new(linker);
+    linker.starts_with(ndk.home())
+        || linker
+            .to_string_lossy()
+            .replace('\\', "/")
+            .contains("/toolchains/llvm/prebuilt/")
+}
+
 pub fn gen(
     config: &Config,
     metadata: &Metadata,
@@ -98,7 +113,11 @@ pub fn gen(
     dot_cargo: &mut dot_cargo::DotCargo,
 ) -> Result<(), Error> {
     println!("Installing Android toolchains...");
-    Target::install_all().map_err(Error::RustupFailed)?;
+    let toolchain = Toolchain::detect(config.app().root_dir()).map_err(Error::ToolchainInvalid)?;
+    if let Some(toolchain) = &toolchain {
+        println!("Using pinned toolchain {}", toolchain);
+    }
+    Target::install_all(toolchain.as_ref()).map_err(Error::RustupFailed)?;
     println!("Generating Android Studio project...");
     let src = Pack::lookup_platform(TEMPLATE_PACK)
         .map_err(Error::MissingPack)?
@@ -231,12 +250,21 @@ pub fn gen(
 
     {
         for target in Target::all().values() {
-            dot_cargo.insert_target(
-                target.triple.to_owned(),
-                target
-                    .generate_cargo_config(config, &env)
-                    .map_err(Error::DotCargoGenFailed)?,
-            );
+            let mut target_config = target
+                .generate_cargo_config(config, &env)
+                .map_err(Error::DotCargoGenFailed)?;
+            if let Some(linker) = dot_cargo
+                .linker(target.triple)
+                .filter(|linker| !is_ndk_linker(linker, &env.ndk))
+            {
+                Report::action_request(
+                    format!("Keeping your linker for {}", target.triple),
+                    format!("`.cargo/config.toml` sets `target.{}.linker` to {:?}, so we're using that instead of the NDK's clang ({}). If builds fail to link, remove it and run `cargo mobile init` again.", target.triple, linker, target_config.linker.as_deref().unwrap_or_default()),
+                )
+                .print(wrapper);
+                target_config.linker = Some(linker.to_owned());
+            }
+            dot_cargo.insert_target(target.triple.to_owned(), target_config);
         }
     }
 
@@ -0,0 +1,303 @@
+use super::env::Env;
+use crate::{
+    bossy,
+    env::ExplicitEnv as _,
+    os::consts,
+    util::cli::{Report, Reportable},
+};
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display},
+    fs,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Failed to run `sdkmanager --list`: {0}")]
+    ListFailed(#[source] bossy::Error),
+}
+
+impl Reportable for Error {
+    fn report(&self) -> Report {
+        match self {
+            Self::ListFailed(err) => Report::error("Failed to list Android SDK packages", err),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub struct SystemImage {
+    pub api_level: u32,
+    /// i.e. `android-30`, or `android-33-ext4` for an extension level
+    pub platform: String,
+    /// i.e. `default`, `google_apis`, or `google_apis_playstore`
+    pub tag: String,
+    pub abi: String,
+    pub installed: bool,
+}
+
+impl Display for SystemImage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.package_path())?;
+        if self.installed {
+            write!(f, " (installed)")?;
+        }
+        Ok(())
+    }
+}
+
+impl SystemImage {
+    /// Parses an sdkmanager package path, i.e.
+    /// `system-images;android-30;google_apis;x86_64`.
+    fn from_package_path(path: &str, installed: bool) -> Option<Self> {
+        let mut parts = path.trim().split(';');
+        if parts.next()? != "system-images" {
+            return None;
+        }
+        let platform = parts.next()?.to_owned();
+        // Previews have codenames instead of numbers (which we skip), and
+        // extension levels look like `android-33-ext4`.
+        let api_level = platform
+            .strip_prefix("android-")?
+            .split('-')
+            .next()?
+            .parse()
+            .ok()?;
+        let tag = parts.next()?.to_owned();
+        let abi = parts.next()?.to_owned();
+        Some(Self {
+            api_level,
+            platform,
+            tag,
+            abi,
+            installed,
+        })
+    }
+
+    /// The path sdkmanager and avdmanager use to refer to this image.
+    pub fn package_path(&self) -> String {
+        format!("system-images;{};{};{}", self.platform, self.tag, self.abi)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum ListSection {
+    Installed,
+    Available,
+    Other,
+}
+
+fn parse_list(output: &str) -> Vec<SystemImage> {
+    let mut section = ListSection::Other;
+    let mut images = BTreeMap::new();
+    for line in output.lines() {
+        let trimmed = line.trim();
+        if trimmed.ends_with(':') && !trimmed.contains('|') {
+            section = match trimmed.to_ascii_lowercase().as_str() {
+                "installed packages:" => ListSection::Installed,
+                "available packages:" => ListSection::Available,
+                // i.e. "Available Updates:", which duplicates packages
+                // we've already seen
+                _ => ListSection::Other,
+            };
+            continue;
+        }
+        if section == ListSection::Other {
+            continue;
+        }
+        let path = match trimmed.split('|').next() {
+            Some(path) => path,
+            None => continue,
+        };
+        if let Some(image) = SystemImage::from_package_path(path, section == ListSection::Installed)
+        {
+            let key = (
+                image.api_level,
+                image.platform.clone(),
+                image.tag.clone(),
+                image.abi.clone(),
+            );
+            // Installed packages are listed before available ones, and we
+            // don't want to lose track of whether they're installed.
+            images
+                .entry(key)
+                .and_modify(|existing: &mut SystemImage| existing.installed |= image.installed)
+                .or_insert(image);
+        }
+    }
+    images.into_values().collect()
+}
+
+/// Lists installed images by walking `$ANDROID_SDK_ROOT/system-images`, which
+/// has the layout `android-<api>/<tag>/<abi>`.
+fn installed_from_dir(dir: &Path) -> Vec<SystemImage> {
+    fn subdirs(dir: &Path) -> impl Iterator<Item = (String, PathBuf)> {
+        fs::read_dir(dir)
+            .into_iter()
+            .flatten()
+            .filter_map(Result::ok)
+            .filter(|entry| entry.path().is_dir())
+            .filter_map(|entry| Some((entry.file_name().into_string().ok()?, entry.path())))
+    }
+    let mut images = Vec::new();
+    for (platform, platform_dir) in subdirs(dir) {
+        for (tag, tag_dir) in subdirs(&platform_dir) {
+            for (abi, _) in subdirs(&tag_dir) {
+                images.extend(SystemImage::from_package_path(
+                    &format!("system-images;{};{};{}", platform, tag, abi),
+                    true,
+                ));
+            }
+        }
+    }
+    images.sort();
+    images
+}
+
+/// Finds one of the command-line tools (i.e. `sdkmanager` or `avdmanager`),
+/// preferring the current `cmdline-tools` package over the deprecated `tools`
+/// one, and falling back to whatever's on the `PATH`.
+pub fn cmdline_tool_path(env: &Env, tool: &str) -> PathBuf {
+    let sdk_root = Path::new(env.sdk_root());
+    ["cmdline-tools/latest/bin", "tools/bin"]
+        .iter()
+        .map(|dir| sdk_root.join(dir).join(tool))
+        .find(|path| path.is_file())
+        .unwrap_or_else(|| PathBuf::from(tool))
+}
+
+pub fn sdkmanager_path(env: &Env) -> PathBuf {
+    cmdline_tool_path(env, consts::SDKMANAGER)
+}
+
+/// Lists the system images that are installed or available for download,
+/// sorted by API level. If `sdkmanager` can't be run, this falls back to only
+/// listing what's installed.
+pub fn system_images(env: &Env) -> Result<Vec<SystemImage>, Error> {
+    let result = bossy::Command::impure(sdkmanager_path(env))
+        .with_env_vars(env.explicit_env())
+        .with_arg("--list")
+        .run_and_wait_for_str(parse_list);
+    match result {
+        Ok(images) => Ok(images),
+        Err(err) => {
+            let dir = Path::new(env.sdk_root()).join("system-images");
+            if dir.is_dir() {
+                log::warn!(
+                    "`sdkmanager --list` failed, so only listing installed system images: {}",
+                    err
+                );
+                Ok(installed_from_dir(&dir))
+            } else {
+                Err(Error::ListFailed(err))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    const LIST_OUTPUT: &str = "\
+Loading package information...
+Loading local repository...
+[=========                              ] 25% Loading local repository...
+[=======================================] 100% Computing updates...
+Installed packages:
+  Path                                        | Version | Description                                | Location
+  -------                                     | ------- | -------                                    | -------
+  build-tools;30.0.3                          | 30.0.3  | Android SDK Build-Tools 30.0.3             | build-tools/30.0.3
+  emulator                                    | 31.3.10 | Android Emulator                           | emulator
+  platforms;android-30                        | 3       | Android SDK Platform 30                    | platforms/android-30
+  system-images;android-30;google_apis;x86_64 | 10      | Google APIs Intel x86 Atom_64 System Image | system-images/android-30/google_apis/x86_64
+
+Available Packages:
+  Path                                                          | Version      | Description
+  -------                                                       | -------      | -------
+  add-ons;addon-google_apis-google-24                           | 1            | Google APIs
+  system-images;android-29;default;x86                          | 8            | Intel x86 Atom System Image
+  system-images;android-30;google_apis;x86_64                   | 10           | Google APIs Intel x86 Atom_64 System Image
+  system-images;android-33;google_apis_playstore;arm64-v8a      | 7            | Google Play ARM 64 v8a System Image
+  system-images;android-33-ext4;google_apis_playstore;x86_64    | 1            | Google Play Intel x86_64 Atom System Image
+  system-images;android-UpsideDownCake;google_apis;x86_64       | 4            | Google APIs Intel x86_64 Atom System Image
+
+Available Updates:
+  ID                                          | Installed | Available
+  -------                                     | -------   | -------
+  system-images;android-30;google_apis;x86_64 | 9         | 10
+";
+
+    fn image(platform: &str, tag: &str, abi: &str, installed: bool) -> SystemImage {
+        SystemImage {
+            api_level: platform
+                .trim_start_matches("android-")
+                .split('-')
+                .next()
+                .unwrap()
+                .parse()
+                .unwrap(),
+            platform: platform.to_owned(),
+            tag: tag.to_owned(),
+            abi: abi.to_owned(),
+            installed,
+        }
+    }
+
+    #[test]
+    fn parses_list_output() {
+        assert_eq!(
+            parse_list(LIST_OUTPUT),
+            vec![
+                image("android-29", "default", "x86", false),
+                image("android-30", "google_apis", "x86_64", true),
+                image("android-33", "google_apis_playstore", "arm64-v8a", false),
+                image("android-33-ext4", "google_apis_playstore", "x86_64", false),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_empty_list_output() {
+        assert_eq!(
+            parse_list("Installed packages:\n  Path | Version | Description | Location\n"),
+            vec![]
+        );
+    }
+
+    #[rstest(
+        path,
+        expected,
+        case(
+            "system-images;android-30;google_apis;x86_64",
+            Some(image("android-30", "google_apis", "x86_64", true))
+        ),
+        case(
+            "  system-images;android-33-ext4;default;x86  ",
+            Some(image("android-33-ext4", "default", "x86", true))
+        ),
+        case("system-images;android-Tiramisu;google_apis;x86_64", None),
+        case("system-images;android-30;google_apis", None),
+        case("platforms;android-30", None)
+    )]
+    fn parses_package_path(path: &str, expected: Option<SystemImage>) {
+        assert_eq!(SystemImage::from_package_path(path, true), expected);
+    }
+
+    #[rstest(
+        path,
+        case("system-images;android-30;google_apis;x86_64"),
+        case("system-images;android-33-ext4;google_apis_playstore;x86_64")
+    )]
+    fn round_trips_package_paths(path: &str) {
+        assert_eq!(
+            SystemImage::from_package_path(path, false)
+                .unwrap()
+                .package_path(),
+            path
+        );
+    }
+}
@@ -0,0 +1,458 @@
+use super::{aapt, config::Config, env::Env};
+use crate::{
+    bossy,
+    env::ExplicitEnv as _,
+    opts::Profile,
+    os::{self, consts},
+    util::{
+        cli::{Report, Reportable},
+        prompt,
+    },
+};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::{
+    env,
+    fmt::{self, Debug},
+    fs, io,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+use zeroize::{Zeroize as _, Zeroizing};
+
+/// A string that's wiped from memory when dropped, and that never shows up in
+/// `Debug` output. Use [`SecretString::expose`] at the last possible moment.
+#[derive(Clone, Default)]
+pub struct SecretString(Zeroizing<String>);
+
+impl SecretString {
+    pub fn new(secret: String) -> Self {
+        Self(Zeroizing::new(secret))
+    }
+
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl Debug for SecretString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SecretString([redacted])")
+    }
+}
+
+impl<'de> Deserialize<'de> for SecretString {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Self::new)
+    }
+}
+
+// This is only so configs round-trip; nothing should be serializing these for
+// any other reason.
+impl Serialize for SecretString {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.expose())
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum KeystorePropertiesError {
+    #[error("Failed to open {path:?}: {cause}")]
+    OpenFailed { path: PathBuf, cause: io::Error },
+    #[error("Failed to parse {path:?}: {cause}")]
+    ParseFailed {
+        path: PathBuf,
+        cause: java_properties::PropertiesError,
+    },
+    #[error("{path:?} is missing {}.", keys.iter().map(|key| format!("`{}`", key)).collect::<Vec<_>>().join(", "))]
+    KeysMissing {
+        path: PathBuf,
+        keys: Vec<&'static str>,
+    },
+}
+
+impl Reportable for KeystorePropertiesError {
+    fn report(&self) -> Report {
+        Report::error("Failed to load keystore properties", self)
+    }
+}
+
+/// Signing config, as conventionally kept in a `keystore.properties` file
+/// that's left out of version control.
+#[derive(Debug)]
+pub struct KeystoreConfig {
+    pub store_file: PathBuf,
+    pub store_password: SecretString,
+    pub key_alias: String,
+    pub key_password: SecretString,
+}
+
+/// Loads signing config from the `keystore.properties` file at `path`, which
+/// needs `storeFile`, `storePassword`, `keyAlias`, and `keyPassword`. A
+/// relative `storeFile` is relative to the file's dir, which is how Gradle
+/// scripts normally resolve it.
+pub fn load_keystore_properties(
+    path: impl AsRef<Path>,
+) -> Result<KeystoreConfig, KeystorePropertiesError> {
+    let path = path.as_ref();
+    let file = fs::File::open(path).map_err(|cause| KeystorePropertiesError::OpenFailed {
+        path: path.to_owned(),
+        cause,
+    })?;
+    let mut props =
+        java_properties::read(file).map_err(|cause| KeystorePropertiesError::ParseFailed {
+            path: path.to_owned(),
+            cause,
+        })?;
+    let mut take = |key| props.remove(key).filter(|value| !value.is_empty());
+    let store_file = take("storeFile");
+    let store_password = take("storePassword").map(SecretString::new);
+    let key_alias = take("keyAlias");
+    let key_password = take("keyPassword").map(SecretString::new);
+    // Whatever's left isn't ours, but could still be sensitive.
+    for value in props.values_mut() {
+        value.zeroize();
+    }
+    match (store_file, store_password, key_alias, key_password) {
+        (Some(store_file), Some(store_password), Some(key_alias), Some(key_password)) => {
+            let dir = path.parent().unwrap_or_else(|| Path::new(""));
+            Ok(KeystoreConfig {
+                store_file: dir.join(store_file),
+                store_password,
+                key_alias,
+                key_password,
+            })
+        }
+        (store_file, store_password, key_alias, key_password) => {
+            let keys = [
+                ("storeFile", store_file.is_none()),
+                ("storePassword", store_password.is_none()),
+                ("keyAlias", key_alias.is_none()),
+                ("keyPassword", key_password.is_none()),
+            ]
+            .iter()
+            .filter(|(_, missing)| *missing)
+            .map(|(key, _)| *key)
+            .collect();
+            Err(KeystorePropertiesError::KeysMissing {
+                path: path.to_owned(),
+                keys,
+            })
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum SignError {
+    #[error("`android.keystore-password-env` refers to {var}, but it isn't set.")]
+    PasswordEnvVarMissing { var: String },
+    #[error("`android.keystore-password-env` refers to {var}, but its value isn't valid UTF-8.")]
+    PasswordEnvVarInvalidUtf8 { var: String },
+    #[error("Failed to read keystore password from {path:?}: {cause}")]
+    PasswordFileReadFailed { path: PathBuf, cause: io::Error },
+    #[error("The keystore password from {source_desc} was empty.")]
+    PasswordEmpty { source_desc: String },
+    #[error("Failed to prompt for keystore password: {0}")]
+    PasswordPromptFailed(io::Error),
+    #[error("No keystore password was configured, and we can't prompt for one since we're not running interactively.")]
+    PasswordUnavailable,
+    #[error(transparent)]
+    KeystorePropertiesInvalid(KeystorePropertiesError),
+    #[error("Failed to sign {apk:?} with `apksigner`: {cause}")]
+    ApksignerFailed {
+        apk: PathBuf,
+        cause: Box<bossy::Error>,
+    },
+}
+
+impl Reportable for SignError {
+    fn report(&self) -> Report {
+        let msg = "Failed to get keystore password";
+        match self {
+            Self::KeystorePropertiesInvalid(err) => err.report(),
+            Self::ApksignerFailed { cause, .. } => Report::error("Failed to sign APK", &**cause),
+            Self::PasswordUnavailable => Report::action_request(
+                msg,
+                format!(
+                    "{} Set `android.keystore-password-env` or `android.keystore-password-file` in your config, and then try again.",
+                    self
+                ),
+            ),
+            _ => Report::error(msg, self),
+        }
+    }
+}
+
+fn non_empty(password: SecretString, source_desc: String) -> Result<SecretString, SignError> {
+    if password.is_empty() {
+        Err(SignError::PasswordEmpty { source_desc })
+    } else {
+        Ok(password)
+    }
+}
+
+fn from_env(var: &str) -> Result<SecretString, SignError> {
+    let password = env::var(var).map_err(|err| match err {
+        env::VarError::NotPresent => SignError::PasswordEnvVarMissing {
+            var: var.to_owned(),
+        },
+        env::VarError::NotUnicode(_) => SignError::PasswordEnvVarInvalidUtf8 {
+            var: var.to_owned(),
+        },
+    })?;
+    non_empty(SecretString::new(password), format!("env var {}", var))
+}
+
+fn from_file(path: PathBuf) -> Result<SecretString, SignError> {
+    let mut password = SecretString::new(fs::read_to_string(&path).map_err(|cause| {
+        SignError::PasswordFileReadFailed {
+            path: path.clone(),
+            cause,
+        }
+    })?);
+    // Editors love adding a trailing newline, which is never part of the
+    // password. Truncating in place, so we don't leave a copy behind.
+    let len = password.0.trim_end_matches(&['\r', '\n'][..]).len();
+    password.0.truncate(len);
+    non_empty(password, format!("{:?}", path))
+}
+
+/// Where the keystore password might come from, in the order they're tried.
+#[derive(Debug)]
+struct PasswordSources<'a> {
+    env_var: Option<&'a str>,
+    file: Option<&'a Path>,
+    plaintext: Option<&'a SecretString>,
+    properties: PathBuf,
+}
+
+impl<'a> PasswordSources<'a> {
+    fn from_config(config: &'a Config) -> Self {
+        Self {
+            env_var: config.keystore_password_env(),
+            file: config.keystore_password_file(),
+            plaintext: config.keystore_password(),
+            properties: config.keystore_properties_path(),
+        }
+    }
+
+    /// The password from the first source that's configured, or `None` if
+    /// none are.
+    fn first(&self) -> Option<Result<SecretString, SignError>> {
+        if let Some(var) = self.env_var {
+            log::info!("reading keystore password from env var {}", var);
+            return Some(from_env(var));
+        }
+        if let Some(path) = self.file {
+            log::info!("reading keystore password from {:?}", path);
+            return Some(from_file(path.to_owned()));
+        }
+        if let Some(password) = self.plaintext {
+            log::warn!(
+                "`android.keystore-password` is stored in plaintext in your config; consider using `android.keystore-password-env` or `android.keystore-password-file` instead"
+            );
+            return Some(non_empty(
+                password.clone(),
+                "`android.keystore-password`".to_owned(),
+            ));
+        }
+        if self.properties.is_file() {
+            log::info!("reading keystore password from {:?}", self.properties);
+            return Some(
+                load_keystore_properties(&self.properties)
+                    .map(|keystore| keystore.store_password)
+                    .map_err(SignError::KeystorePropertiesInvalid),
+            );
+        }
+        None
+    }
+}
+
+/// Gets the keystore password from the first source that's configured, in
+/// order: the env var named by `android.keystore-password-env`, the file at
+/// `android.keystore-password-file`, `android.keystore-password` itself,
+/// `storePassword` in the Android project's `keystore.properties`, and
+/// finally a prompt if [`os::can_prompt`] allows it. The password itself is
+/// never logged.
+pub fn resolve_password(config: &Config) -> Result<SecretString, SignError> {
+    if let Some(password) = PasswordSources::from_config(config).first() {
+        return password;
+    }
+    if !os::can_prompt() {
+        return Err(SignError::PasswordUnavailable);
+    }
+    let password = SecretString::new(
+        prompt::hidden("Keystore password").map_err(SignError::PasswordPromptFailed)?,
+    );
+    non_empty(password, "the prompt".to_owned())
+}
+
+/// Whether release APKs get signed by us, which is whenever the Android
+/// project has a `keystore.properties` saying which keystore and key to use.
+pub fn signs(config: &Config, profile: Profile) -> bool {
+    profile.release() && config.keystore_properties_path().is_file()
+}
+
+// `apksigner` reads the passwords from these, so they don't end up in its
+// args for anyone to see.
+static STORE_PASSWORD_VAR: &str = "CARGO_MOBILE_KEYSTORE_PASSWORD";
+static KEY_PASSWORD_VAR: &str = "CARGO_MOBILE_KEY_PASSWORD";
+
+/// Signs `unsigned` with the keystore and key from `keystore.properties`,
+/// writing the result to `signed`. The keystore password comes from
+/// [`resolve_password`], so any of its sources can stand in for
+/// `storePassword`.
+pub fn sign_apk(
+    config: &Config,
+    env: &Env,
+    unsigned: &Path,
+    signed: &Path,
+) -> Result<(), SignError> {
+    let keystore = load_keystore_properties(config.keystore_properties_path())
+        .map_err(SignError::KeystorePropertiesInvalid)?;
+    let store_password = resolve_password(config)?;
+    log::info!(
+        "signing {:?} with key {:?} from {:?}",
+        unsigned,
+        keystore.key_alias,
+        keystore.store_file
+    );
+    bossy::Command::impure(aapt::build_tool_path(env, consts::APKSIGNER))
+        .with_env_vars(env.explicit_env())
+        .with_env_var(STORE_PASSWORD_VAR, store_password.expose())
+        .with_env_var(KEY_PASSWORD_VAR, keystore.key_password.expose())
+        .with_args(["sign", "--ks"])
+        .with_arg(&keystore.store_file)
+        .with_args(["--ks-key-alias", &keystore.key_alias])
+        .with_arg("--ks-pass")
+        .with_arg(format!("env:{}", STORE_PASSWORD_VAR))
+        .with_arg("--key-pass")
+        .with_arg(format!("env:{}", KEY_PASSWORD_VAR))
+        .with_arg("--out")
+        .with_arg(signed)
+        .with_arg(unsigned)
+        .run_and_wait()
+        .map_err(|cause| SignError::ApksignerFailed {
+            apk: unsigned.to_owned(),
+            cause: Box::new(cause),
+        })?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    fn load(name: &str, contents: &str) -> Result<KeystoreConfig, KeystorePropertiesError> {
+        let dir = crate::util::temp_dir().join(name);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("keystore.properties");
+        fs::write(&path, contents).unwrap();
+        let result = load_keystore_properties(&path);
+        fs::remove_dir_all(&dir).unwrap();
+        result
+    }
+
+    #[rstest(
+        store_file,
+        expected,
+        case("release.jks", "keystore-relative-test/release.jks"),
+        case("../keys/release.jks", "keystore-relative-test/../keys/release.jks")
+    )]
+    fn resolves_relative_store_files(store_file: &str, expected: &str) {
+        let keystore = load(
+            "keystore-relative-test",
+            &format!(
+                "# Kept out of version control\nstoreFile={}\nstorePassword = hunter2\nkeyAlias: upload\nkeyPassword=hunter3\n",
+                store_file
+            ),
+        )
+        .unwrap();
+        assert_eq!(keystore.store_file, crate::util::temp_dir().join(expected));
+        assert_eq!(keystore.store_password.expose(), "hunter2");
+        assert_eq!(keystore.key_alias, "upload");
+        assert_eq!(keystore.key_password.expose(), "hunter3");
+    }
+
+    #[test]
+    fn keeps_absolute_store_files() {
+        let store_file = crate::util::temp_dir().join("keys").join("release.jks");
+        let keystore = load(
+            "keystore-absolute-test",
+            &format!(
+                "storeFile={}\nstorePassword=hunter2\nkeyAlias=upload\nkeyPassword=hunter3\n",
+                store_file.display()
+            ),
+        )
+        .unwrap();
+        assert_eq!(keystore.store_file, store_file);
+    }
+
+    #[test]
+    fn names_missing_keys() {
+        let err = load(
+            "keystore-missing-test",
+            "storeFile=release.jks\nstorePassword=\nkeyPassword=hunter3\n",
+        )
+        .unwrap_err();
+        match err {
+            KeystorePropertiesError::KeysMissing { keys, .. } => {
+                assert_eq!(keys, vec!["storePassword", "keyAlias"])
+            }
+            _ => panic!("expected missing keys, got {:?}", err),
+        }
+    }
+
+    #[test]
+    fn tries_password_sources_in_order() {
+        let dir = crate::util::temp_dir().join("keystore-password-test");
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("password");
+        fs::write(&file, "from-file\n").unwrap();
+        let properties = dir.join("keystore.properties");
+        fs::write(
+            &properties,
+            "storeFile=release.jks\nstorePassword=from-properties\nkeyAlias=upload\nkeyPassword=hunter3\n",
+        )
+        .unwrap();
+        let var = "CARGO_MOBILE_TEST_KEYSTORE_PASSWORD";
+        env::set_var(var, "from-env");
+        let plaintext = SecretString::new("from-config".to_owned());
+        let mut sources = PasswordSources {
+            env_var: Some(var),
+            file: Some(&file),
+            plaintext: Some(&plaintext),
+            properties: properties.clone(),
+        };
+        let password = |sources: &PasswordSources<'_>| {
+            sources
+                .first()
+                .map(|password| password.unwrap().expose().to_owned())
+        };
+        let mut found = vec![password(&sources)];
+        sources.env_var = None;
+        found.push(password(&sources));
+        sources.file = None;
+        found.push(password(&sources));
+        sources.plaintext = None;
+        found.push(password(&sources));
+        sources.properties = dir.join("missing.properties");
+        found.push(password(&sources));
+        env::remove_var(var);
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(
+            found,
+            vec![
+                Some("from-env".to_owned()),
+                Some("from-file".to_owned()),
+                Some("from-config".to_owned()),
+                Some("from-properties".to_owned()),
+                None
+            ]
+        );
+    }
+}
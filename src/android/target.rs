@@ -1,17 +1,25 @@
 use super::{
     config::{Config, Metadata},
     env::Env,
+    fast_linker::{self, Linker},
     jnilibs::{self, JniLibs},
     ndk,
+    package::{self, StageLibsError},
 };
 use crate::{
     bossy,
+    build_events::Emitter,
+    build_log,
+    config::{self, LibNameError},
     dot_cargo::DotCargoTarget,
     opts::{NoiseLevel, Profile},
     target::TargetTrait,
     util::{
+        self,
         cli::{Report, Reportable},
-        CargoCommand,
+        hook::HookError,
+        toolchain::{self, ToolchainError},
+        BuildStdError, CargoCommand,
     },
 };
 use once_cell_regex::exports::once_cell::sync::OnceCell;
@@ -54,11 +62,19 @@ pub enum CompileLibError {
     },
     #[error("`Failed to write file at {path} : {cause}")]
     FileWrite { path: PathBuf, cause: io::Error },
+    #[error(transparent)]
+    BuildStdUnavailable(BuildStdError),
+    #[error(transparent)]
+    ToolchainInvalid(ToolchainError),
 }
 
 impl Reportable for CompileLibError {
     fn report(&self) -> Report {
-        Report::error("Failed to compile lib", self)
+        match self {
+            Self::BuildStdUnavailable(err) => err.report(),
+            Self::ToolchainInvalid(err) => err.report(),
+            _ => Report::error("Failed to compile lib", self),
+        }
     }
 }
 
@@ -72,11 +88,16 @@ pub enum SymlinkLibsError {
     RequiredLibsFailed(ndk::RequiredLibsError),
     #[error("Failed to locate \"libc++_shared.so\": {0}")]
     LibcxxSharedPathFailed(ndk::MissingToolError),
+    #[error(transparent)]
+    LibNameFailed(LibNameError),
 }
 
 impl Reportable for SymlinkLibsError {
     fn report(&self) -> Report {
-        Report::error("Failed to symlink lib", self)
+        match self {
+            Self::LibNameFailed(err) => err.report(),
+            _ => Report::error("Failed to symlink lib", self),
+        }
     }
 }
 
@@ -86,6 +107,10 @@ pub enum BuildError {
     BuildFailed(CompileLibError),
     #[error(transparent)]
     SymlinkLibsFailed(SymlinkLibsError),
+    #[error(transparent)]
+    StageLibsFailed(StageLibsError),
+    #[error(transparent)]
+    HookFailed(HookError),
 }
 
 impl Reportable for BuildError {
@@ -93,6 +118,8 @@ impl Reportable for BuildError {
         match self {
             Self::BuildFailed(err) => err.report(),
             Self::SymlinkLibsFailed(err) => err.report(),
+            Self::StageLibsFailed(err) => err.report(),
+            Self::HookFailed(err) => err.report(),
         }
     }
 }
@@ -167,11 +194,11 @@ impl<'a> TargetTrait<'a> for Target<'a> {
 }
 
 impl<'a> Target<'a> {
-    fn clang_triple(&self) -> &'a str {
+    pub fn clang_triple(&self) -> &'a str {
         self.clang_triple_override.unwrap_or_else(|| self.triple)
     }
 
-    fn binutils_triple(&self) -> &'a str {
+    pub(super) fn binutils_triple(&self) -> &'a str {
         self.binutils_triple_override.unwrap_or_else(|| self.triple)
     }
 
@@ -179,6 +206,35 @@ impl<'a> Target<'a> {
         Self::all().values().find(|target| target.abi == abi)
     }
 
+    /// Drops any targets the installed NDK can't build for, warning about each
+    /// one. If that would leave nothing to build, all targets are kept so that
+    /// the build fails with the actual error.
+    pub fn retain_supported<'t>(
+        targets: Vec<&'t Self>,
+        config: &Config,
+        env: &Env,
+        events: &Emitter,
+    ) -> Vec<&'t Self> {
+        let (supported, unsupported): (Vec<&Self>, Vec<&Self>) =
+            targets.into_iter().partition(|target| {
+                ndk::supports_target(&env.ndk, target.clang_triple(), config.min_sdk_version())
+            });
+        if supported.is_empty() {
+            return unsupported;
+        }
+        for target in unsupported {
+            let message = format!(
+                "skipping ABI {:?}, since your NDK doesn't support {:?} at API level {}",
+                target.abi,
+                target.clang_triple(),
+                config.min_sdk_version()
+            );
+            log::warn!("{}", message);
+            events.warning(message);
+        }
+        supported
+    }
+
     pub fn generate_cargo_config(
         &self,
         config: &Config,
@@ -217,9 +273,32 @@ impl<'a> Target<'a> {
         force_color: bool,
         profile: Profile,
         mode: CargoMode,
+        parallel: bool,
     ) -> Result<(), CompileLibError> {
         let min_sdk_version = config.min_sdk_version();
 
+        if config.app().build_std() {
+            util::check_build_std().map_err(CompileLibError::BuildStdUnavailable)?;
+        }
+
+        let toolchain = toolchain::for_target(config.app().root_dir(), self.triple)
+            .map_err(CompileLibError::ToolchainInvalid)?;
+        if let Some(toolchain) = &toolchain {
+            if !noise_level.polite() {
+                println!("Building {} with toolchain {}", self.triple, toolchain);
+            }
+        }
+
+        let linker = config.fast_linker().and_then(|preference| {
+            fast_linker::resolve(preference, env, self.clang_triple(), min_sdk_version)
+        });
+        if let Some(linker) = linker {
+            if !noise_level.polite() {
+                println!("Linking {} with {}", self.triple, linker.as_str());
+            }
+        }
+        let rustflags = linker.map(Linker::rustflags).unwrap_or_default();
+
         // workaround for missing libgcc in ndk versions higher then 23
         // see https://github.com/rust-windowing/android-ndk-rs/pull/189
         if env.ndk.version().unwrap_or_default().triple.major >= 23 {
@@ -233,16 +312,29 @@ impl<'a> Target<'a> {
         // Force color, since gradle would otherwise give us uncolored output
         // (which Android Studio makes red, which is extra gross!)
         let color = if force_color { "always" } else { "auto" };
-        CargoCommand::new(mode.as_str())
-            .with_verbose(noise_level.pedantic())
+        let command = CargoCommand::new(mode.as_str())
+            .with_noise_level(noise_level)
             .with_package(Some(config.app().name()))
             .with_manifest_path(Some(config.app().manifest_path()))
+            .with_target_dir(if parallel {
+                Some(self.target_dir(config, parallel))
+            } else {
+                config.app().custom_target_dir()
+            })
             .with_target(Some(self.triple))
             .with_no_default_features(metadata.no_default_features())
             .with_args(metadata.cargo_args())
             .with_features(metadata.features())
             .with_release(profile.release())
+            .with_panic_abort(config.app().panic_abort())
+            .with_build_std(config.app().build_std())
+            .with_target_rustflags(&rustflags)
             .into_command_pure(env)
+            .with_env_vars(
+                toolchain
+                    .as_ref()
+                    .map(|toolchain| (toolchain::OVERRIDE_VAR, toolchain.name())),
+            )
             .with_env_var("ANDROID_NATIVE_API_LEVEL", min_sdk_version.to_string())
             .with_env_var(
                 "TARGET_CC",
@@ -256,8 +348,8 @@ impl<'a> Target<'a> {
                     .compiler_path(ndk::Compiler::Clangxx, self.clang_triple(), min_sdk_version)
                     .map_err(CompileLibError::MissingTool)?,
             )
-            .with_args(&["--color", color])
-            .run_and_wait()
+            .with_args(["--color", color]);
+        build_log::run_labelled(command, Some(self.triple).filter(|_| parallel))
             .map_err(|cause| CompileLibError::CargoFailed { mode, cause })?;
         Ok(())
     }
@@ -278,26 +370,52 @@ impl<'a> Target<'a> {
             force_color,
             Profile::Debug,
             CargoMode::Check,
+            false,
         )
     }
 
+    /// Targets built in parallel each get their own target dir, since cargo
+    /// locks the whole target dir for as long as it's building.
+    fn target_dir(&self, config: &Config, parallel: bool) -> PathBuf {
+        let target_dir = config.app().target_dir();
+        if parallel {
+            target_dir.join("parallel").join(self.triple)
+        } else {
+            target_dir
+        }
+    }
+
+    fn lib_path(
+        &self,
+        config: &Config,
+        profile: Profile,
+        parallel: bool,
+    ) -> Result<PathBuf, LibNameError> {
+        let lib_name = config::lib_name(config.app().root_dir())?;
+        Ok(self
+            .target_dir(config, parallel)
+            .join(self.triple)
+            .join(profile.as_str())
+            .join(format!("lib{}.so", lib_name)))
+    }
+
     pub fn symlink_libs(
         &self,
         config: &Config,
         ndk: &ndk::Env,
         profile: Profile,
+        parallel: bool,
     ) -> Result<(), SymlinkLibsError> {
         let jnilibs =
             JniLibs::create(config, *self).map_err(SymlinkLibsError::JniLibsCreationFailed)?;
 
-        let src = config.app().prefix_path(format!(
-            "target/{}/{}/{}",
-            &self.triple,
-            profile.as_str(),
-            config.so_name(),
-        ));
+        let src = self
+            .lib_path(config, profile, parallel)
+            .map_err(SymlinkLibsError::LibNameFailed)?;
+        // The Java side loads the lib by the app's name, which the lib
+        // itself isn't necessarily named after.
         jnilibs
-            .symlink_lib(&src)
+            .symlink_lib_as(&src, config.so_name())
             .map_err(SymlinkLibsError::SymlinkFailed)?;
 
         let needs_cxx_shared = ndk
@@ -317,6 +435,32 @@ impl<'a> Target<'a> {
         Ok(())
     }
 
+    /// Like [`Target::symlink_libs`], but copying, for `android.copy-libs`.
+    pub fn stage_libs(
+        &self,
+        config: &Config,
+        env: &Env,
+        profile: Profile,
+        parallel: bool,
+    ) -> Result<(), StageLibsError> {
+        let src = self
+            .lib_path(config, profile, parallel)
+            .map_err(StageLibsError::LibNameFailed)?;
+        package::stage_libs(config, env, &[(self.abi, src)], &jnilibs::root(config))?;
+        Ok(())
+    }
+
+    /// Builds the lib for this target. This doesn't run hooks or check the
+    /// disk; that's done once for the whole build by a
+    /// [`util::BuildSession`]. Likewise, emitting [`Phase::Compile`] and
+    /// [`BuildEvent::TargetBuilt`] is up to the caller.
+    ///
+    /// If other targets are being built at the same time, `parallel` should be
+    /// set, so that this one builds in its own target dir and labels its
+    /// output.
+    ///
+    /// [`Phase::Compile`]: crate::build_events::Phase::Compile
+    /// [`BuildEvent::TargetBuilt`]: crate::build_events::BuildEvent::TargetBuilt
     pub fn build(
         &self,
         config: &Config,
@@ -325,6 +469,7 @@ impl<'a> Target<'a> {
         noise_level: NoiseLevel,
         force_color: bool,
         profile: Profile,
+        parallel: bool,
     ) -> Result<(), BuildError> {
         self.compile_lib(
             config,
@@ -334,9 +479,16 @@ impl<'a> Target<'a> {
             force_color,
             profile,
             CargoMode::Build,
+            parallel,
         )
         .map_err(BuildError::BuildFailed)?;
-        self.symlink_libs(config, &env.ndk, profile)
-            .map_err(BuildError::SymlinkLibsFailed)
+        if config.copy_libs() {
+            self.stage_libs(config, env, profile, parallel)
+                .map_err(BuildError::StageLibsFailed)?;
+        } else {
+            self.symlink_libs(config, &env.ndk, profile, parallel)
+                .map_err(BuildError::SymlinkLibsFailed)?;
+        }
+        Ok(())
     }
 }
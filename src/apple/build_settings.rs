@@ -0,0 +1,87 @@
+use once_cell_regex::regex;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum BuildSettingError {
+    #[error("Build setting {key:?} isn't a valid name; names can only contain letters, digits, and underscores, optionally followed by a condition like `[sdk=iphoneos*]`.")]
+    KeyInvalid { key: String },
+    #[error("Build setting {key:?} has a value containing a newline, which `xcodebuild` can't take on the command line.")]
+    ValueInvalid { key: String },
+}
+
+/// Parses `KEY=value` from the command line. The value can be empty, and can
+/// contain `=`.
+pub fn parse(setting: &str) -> Result<(String, String), String> {
+    let (key, value) = setting
+        .split_once('=')
+        .ok_or_else(|| format!("expected `KEY=value`, but got {:?}", setting))?;
+    Ok((key.to_owned(), value.to_owned()))
+}
+
+/// Turns build setting overrides into `xcodebuild` args. Each one is a single
+/// arg, so values with spaces or quotes don't need any escaping.
+pub fn args(settings: &[(String, String)]) -> Result<Vec<String>, BuildSettingError> {
+    settings
+        .iter()
+        .map(|(key, value)| {
+            if !regex!(r"^[A-Za-z_][A-Za-z0-9_]*(\[[^\]\s]+\])*$").is_match(key) {
+                Err(BuildSettingError::KeyInvalid { key: key.clone() })
+            } else if value.contains('\n') {
+                Err(BuildSettingError::ValueInvalid { key: key.clone() })
+            } else {
+                Ok(format!("{}={}", key, value))
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    fn settings(settings: &[(&str, &str)]) -> Vec<(String, String)> {
+        settings
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[rstest(input, expected,
+        case(&[], &[]),
+        case(&[("CODE_SIGN_STYLE", "Manual")], &["CODE_SIGN_STYLE=Manual"]),
+        case(
+            &[("DEVELOPMENT_TEAM", "ABCDE12345"), ("PRODUCT_NAME", "My App")],
+            &["DEVELOPMENT_TEAM=ABCDE12345", "PRODUCT_NAME=My App"]
+        ),
+        case(
+            &[("CODE_SIGN_IDENTITY[sdk=iphoneos*]", "iPhone Distribution")],
+            &["CODE_SIGN_IDENTITY[sdk=iphoneos*]=iPhone Distribution"]
+        ),
+        case(&[("OTHER_FLAGS", "a=b")], &["OTHER_FLAGS=a=b"])
+    )]
+    fn test_args(input: &[(&str, &str)], expected: &[&str]) {
+        assert_eq!(args(&settings(input)).unwrap(), expected);
+    }
+
+    #[rstest(key, case(""), case("CODE SIGN"), case("1ABC"), case("KEY="))]
+    fn test_args_key_invalid(key: &str) {
+        assert!(matches!(
+            args(&settings(&[(key, "value")])),
+            Err(BuildSettingError::KeyInvalid { .. })
+        ));
+    }
+
+    #[rstest(setting, expected,
+        case("CODE_SIGN_STYLE=Manual", Some(("CODE_SIGN_STYLE", "Manual"))),
+        case("EMPTY=", Some(("EMPTY", ""))),
+        case("OTHER_FLAGS=a=b", Some(("OTHER_FLAGS", "a=b"))),
+        case("NO_VALUE", None)
+    )]
+    fn test_parse(setting: &str, expected: Option<(&str, &str)>) {
+        assert_eq!(
+            parse(setting).ok(),
+            expected.map(|(key, value)| (key.to_owned(), value.to_owned()))
+        );
+    }
+}
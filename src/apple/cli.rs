@@ -1,27 +1,34 @@
 use crate::{
     apple::{
+        build_settings,
         config::{Config, Metadata},
-        device::{Device, RunError},
+        device::{DeepLinkError, Device, RunError},
+        instruments::RecordError,
         ios_deploy, rust_version_check,
         target::{ArchiveError, BuildError, CheckError, CompileLibError, ExportError, Target},
+        version_number::VersionNumber,
         NAME,
     },
     bossy,
+    build_events::{BuildEvent, Emitter, Phase},
+    build_log,
+    build_manifest::{self, Artifact, ArtifactKind, Inputs},
     config::{
+        defaults::Defaults,
         metadata::{self, Metadata as OmniMetadata},
         Config as OmniConfig, LoadOrGenError,
     },
     define_device_prompt,
     device::PromptError,
     env::{Env, Error as EnvError},
-    opts, os,
+    init, opts, os,
     target::{call_for_targets_with_fallback, TargetInvalid, TargetTrait as _},
     util::{
         self,
         cli::{
             self, Exec, GlobalFlags, Report, Reportable, TextWrapper, VERSION_LONG, VERSION_SHORT,
         },
-        prompt,
+        prompt, BuildSession,
     },
 };
 use std::{collections::HashMap, ffi::OsStr, path::PathBuf};
@@ -53,11 +60,13 @@ fn macos_from_platform(platform: &str) -> bool {
 }
 
 fn profile_from_configuration(configuration: &str) -> opts::Profile {
-    if configuration == "release" {
-        opts::Profile::Release
-    } else {
+    opts::Profile::from_xcode_configuration(configuration).unwrap_or_else(|| {
+        log::warn!(
+            "unrecognized Xcode configuration {:?}; building Rust in debug mode",
+            configuration
+        );
         opts::Profile::Debug
-    }
+    })
 }
 
 #[derive(Clone, Debug, StructOpt)]
@@ -68,27 +77,90 @@ pub enum Command {
     Check {
         #[structopt(name = "targets", default_value = Target::DEFAULT_KEY, possible_values = Target::name_list())]
         targets: Vec<String>,
+        #[structopt(flatten)]
+        target_dir: cli::TargetDir,
     },
     #[structopt(name = "build", about = "Builds static libraries for target(s)")]
     Build {
-        #[structopt(name = "targets", default_value = Target::DEFAULT_KEY, possible_values = Target::name_list())]
+        #[structopt(name = "targets", possible_values = Target::name_list())]
         targets: Vec<String>,
         #[structopt(flatten)]
         profile: cli::Profile,
+        #[structopt(flatten)]
+        target_dir: cli::TargetDir,
+        #[structopt(
+            long = "xcode-setting",
+            value_name = "KEY=value",
+            help = "Overrides an Xcode build setting; can be given more than once",
+            number_of_values = 1,
+            parse(try_from_str = build_settings::parse)
+        )]
+        settings: Vec<(String, String)>,
     },
     #[structopt(name = "archive", about = "Builds and archives for targets(s)")]
     Archive {
         #[structopt(long = "build-number")]
         build_number: Option<u32>,
-        #[structopt(name = "targets", default_value = Target::DEFAULT_KEY, possible_values = Target::name_list())]
+        #[structopt(name = "targets", possible_values = Target::name_list())]
         targets: Vec<String>,
         #[structopt(flatten)]
         profile: cli::Profile,
+        #[structopt(
+            long = "force",
+            help = "Build even if nothing changed since the last build"
+        )]
+        force: bool,
+        #[structopt(flatten)]
+        out_dir: cli::OutDir,
+        #[structopt(flatten)]
+        target_dir: cli::TargetDir,
+        #[structopt(
+            long = "xcode-setting",
+            value_name = "KEY=value",
+            help = "Overrides an Xcode build setting; can be given more than once",
+            number_of_values = 1,
+            parse(try_from_str = build_settings::parse)
+        )]
+        settings: Vec<(String, String)>,
     },
     #[structopt(name = "run", about = "Deploys IPA to connected device")]
     Run {
         #[structopt(flatten)]
         profile: cli::Profile,
+        #[structopt(flatten)]
+        device: cli::DeviceId,
+        #[structopt(flatten)]
+        target_dir: cli::TargetDir,
+    },
+    #[structopt(
+        name = "deep-link",
+        about = "Opens a URL in the installed app on a device, for testing deep links"
+    )]
+    DeepLink {
+        #[structopt(flatten)]
+        device: cli::DeviceId,
+        #[structopt(name = "URL", help = "The URL to open, i.e. `myapp://settings`")]
+        url: String,
+    },
+    #[structopt(
+        name = "trace",
+        about = "Records an Instruments trace of the installed app on a device"
+    )]
+    Trace {
+        #[structopt(flatten)]
+        device: cli::DeviceId,
+        #[structopt(
+            long = "template",
+            default_value = "Time Profiler",
+            help = "The Instruments template to record with; `xcrun xctrace list templates` lists them"
+        )]
+        template: String,
+        #[structopt(
+            long = "output",
+            value_name = "PATH",
+            help = "Where to write the `.trace`, instead of the Xcode project's build dir"
+        )]
+        output: Option<PathBuf>,
     },
     #[structopt(name = "list", about = "Lists connected devices")]
     List,
@@ -124,6 +196,8 @@ pub enum Command {
         profile: opts::Profile,
         #[structopt(long = "force-color", help = "Value of `FORCE_COLOR` env var")]
         force_color: bool,
+        #[structopt(flatten)]
+        target_dir: cli::TargetDir,
         #[structopt(
             name = "ARCHS",
             help = "Value of `ARCHS` env var",
@@ -149,7 +223,10 @@ pub enum Error {
     BuildFailed(BuildError),
     ArchiveFailed(ArchiveError),
     ExportFailed(ExportError),
+    CopyArtifactsFailed(util::CopyArtifactsError),
     RunFailed(RunError),
+    DeepLinkFailed(DeepLinkError),
+    TraceFailed(RecordError),
     ListFailed(ios_deploy::DeviceListError),
     NoHomeDir(util::NoHomeDir),
     CargoEnvFailed(bossy::Error),
@@ -180,7 +257,10 @@ impl Reportable for Error {
             Self::BuildFailed(err) => err.report(),
             Self::ArchiveFailed(err) => err.report(),
             Self::ExportFailed(err) => err.report(),
+            Self::CopyArtifactsFailed(err) => err.report(),
             Self::RunFailed(err) => err.report(),
+            Self::DeepLinkFailed(err) => err.report(),
+            Self::TraceFailed(err) => err.report(),
             Self::ListFailed(err) => err.report(),
             Self::NoHomeDir(err) => Report::error("Failed to load cargo env profile", err),
             Self::CargoEnvFailed(err) => Report::error("Failed to load cargo env profile", err),
@@ -215,21 +295,59 @@ impl Exec for Input {
 
     fn exec(self, wrapper: &TextWrapper) -> Result<(), Self::Report> {
         define_device_prompt!(ios_deploy::device_list, ios_deploy::DeviceListError, iOS);
+        /// What `xcodebuild` is asked to do when archiving, for telling
+        /// whether an archive is fresh; the project itself is covered by the
+        /// fingerprint.
+        fn archive_request(
+            target: &Target,
+            profile: opts::Profile,
+            app_version: &VersionNumber,
+            settings: &[(String, String)],
+        ) -> Vec<String> {
+            let mut request = vec![
+                "archive".to_owned(),
+                target.triple.to_owned(),
+                profile.as_str().to_owned(),
+                app_version.to_string(),
+            ];
+            request.extend(
+                settings
+                    .iter()
+                    .map(|(key, value)| format!("{}={}", key, value)),
+            );
+            request
+        }
+
         fn detect_target_ok<'a>(env: &Env) -> Option<&'a Target<'a>> {
-            device_prompt(env).map(|device| device.target()).ok()
+            device_prompt(env, None, None)
+                .map(|device| device.target())
+                .ok()
         }
 
         fn with_config(
+            env: Env,
             non_interactive: bool,
             wrapper: &TextWrapper,
-            f: impl FnOnce(&Config, &Metadata) -> Result<(), Error>,
+            target_dir: Option<PathBuf>,
+            f: impl FnOnce(&Config, &Metadata, &Defaults, &Env) -> Result<(), Error>,
         ) -> Result<(), Error> {
-            let (config, _origin) = OmniConfig::load_or_gen(".", non_interactive, wrapper)
+            let (mut config, _origin) = OmniConfig::load_or_gen(".", non_interactive, wrapper)
                 .map_err(Error::ConfigFailed)?;
+            if let Some(target_dir) = target_dir {
+                config.override_target_dir(target_dir);
+            }
             let metadata =
                 OmniMetadata::load(&config.app().root_dir()).map_err(Error::MetadataFailed)?;
+            init::warn_if_outdated(config.app().root_dir(), wrapper);
+            if let Err(err) = init::fix_permissions(config.app().root_dir()) {
+                log::warn!("{}", err);
+            }
             if metadata.apple().supported() {
-                f(config.apple(), metadata.apple())
+                let env = env.suppress_telemetry(config.telemetry().suppressions());
+                build_log::start(config.app().root_dir());
+                let result = f(config.apple(), metadata.apple(), config.defaults(), &env);
+                build_log::finish(result.is_ok(), config.open().build_log_on_failure());
+                result
             } else {
                 Err(Error::Unsupported)
             }
@@ -246,7 +364,13 @@ impl Exec for Input {
         }
 
         fn open_in_xcode(config: &Config) -> Result<(), Error> {
-            os::open_file_with("Xcode", config.project_dir()).map_err(Error::OpenFailed)
+            if !os::open_file_with("Xcode", config.project_dir()).map_err(Error::OpenFailed)? {
+                println!(
+                    "Not opening Xcode, since opening is disabled by `--no-open` or {}",
+                    os::NO_OPEN_VAR
+                );
+            }
+            Ok(())
         }
 
         let version_check = || rust_version_check(wrapper).map_err(Error::RustVersionCheckFailed);
@@ -256,6 +380,7 @@ impl Exec for Input {
                 GlobalFlags {
                     noise_level,
                     non_interactive,
+                    ..
                 },
             command,
         } = self;
@@ -263,195 +388,358 @@ impl Exec for Input {
         match command {
             Command::Open => {
                 version_check()?;
-                with_config(non_interactive, wrapper, |config, _| {
+                with_config(env, non_interactive, wrapper, None, |config, _, _, _| {
                     ensure_init(config)?;
                     open_in_xcode(config)
                 })
             }
-            Command::Check { targets } => {
+            Command::Check {
+                targets,
+                target_dir: cli::TargetDir { target_dir },
+            } => {
                 version_check()?;
-                with_config(non_interactive, wrapper, |config, metadata| {
+                with_config(
+                    env,
+                    non_interactive,
+                    wrapper,
+                    target_dir,
+                    |config, metadata, _, env| {
+                        call_for_targets_with_fallback(
+                            targets.iter(),
+                            &detect_target_ok,
+                            env,
+                            |target: &Target| {
+                                target
+                                    .check(config, metadata, env, noise_level)
+                                    .map_err(Error::CheckFailed)
+                            },
+                        )
+                        .map_err(Error::TargetInvalid)?
+                    },
+                )
+            }
+            Command::Build {
+                targets,
+                profile,
+                target_dir: cli::TargetDir { target_dir },
+                settings,
+            } => with_config(
+                env,
+                non_interactive,
+                wrapper,
+                target_dir,
+                |config, _, defaults, env| {
+                    version_check()?;
+                    ensure_init(config)?;
+                    let targets = defaults.targets_or_default::<Target>(targets);
+                    let profile = defaults.profile(profile.requested());
+                    let events = Emitter::terminal();
+                    let session = BuildSession::begin(config.app(), env, noise_level, &events)
+                        .map_err(BuildError::HookFailed)
+                        .map_err(Error::BuildFailed)?;
                     call_for_targets_with_fallback(
                         targets.iter(),
                         &detect_target_ok,
-                        &env,
+                        env,
                         |target: &Target| {
                             target
-                                .check(config, metadata, &env, noise_level)
-                                .map_err(Error::CheckFailed)
+                                .build(config, env, noise_level, profile, &settings, &events)
+                                .map_err(Error::BuildFailed)
                         },
                     )
-                    .map_err(Error::TargetInvalid)?
-                })
-            }
-            Command::Build {
-                targets,
-                profile: cli::Profile { profile },
-            } => with_config(non_interactive, wrapper, |config, _| {
-                version_check()?;
-                ensure_init(config)?;
-                call_for_targets_with_fallback(
-                    targets.iter(),
-                    &detect_target_ok,
-                    &env,
-                    |target: &Target| {
-                        target
-                            .build(config, &env, noise_level, profile)
-                            .map_err(Error::BuildFailed)
-                    },
-                )
-                .map_err(Error::TargetInvalid)?
-            }),
+                    .map_err(Error::TargetInvalid)??;
+                    session
+                        .finish(env, &events)
+                        .map_err(BuildError::HookFailed)
+                        .map_err(Error::BuildFailed)
+                },
+            ),
             Command::Archive {
                 targets,
                 build_number,
-                profile: cli::Profile { profile },
-            } => with_config(non_interactive, wrapper, |config, _| {
-                version_check()?;
-                ensure_init(config)?;
-                call_for_targets_with_fallback(
-                    targets.iter(),
-                    &detect_target_ok,
-                    &env,
-                    |target: &Target| {
-                        let mut app_version = config.bundle_version().clone();
-                        if let Some(build_number) = build_number {
-                            app_version.push_extra(build_number);
-                        }
-
-                        target
-                            .build(config, &env, noise_level, profile)
-                            .map_err(Error::BuildFailed)?;
-                        target
-                            .archive(config, &env, noise_level, profile, Some(app_version))
-                            .map_err(Error::ArchiveFailed)
-                    },
-                )
-                .map_err(Error::TargetInvalid)?
-            }),
+                profile,
+                force,
+                out_dir: cli::OutDir { out_dir },
+                target_dir: cli::TargetDir { target_dir },
+                settings,
+            } => with_config(
+                env,
+                non_interactive,
+                wrapper,
+                target_dir,
+                |config, _, defaults, env| {
+                    version_check()?;
+                    ensure_init(config)?;
+                    let targets = defaults.targets_or_default::<Target>(targets);
+                    let profile = defaults.profile(profile.requested());
+                    let events = Emitter::terminal();
+                    let session = BuildSession::begin(config.app(), env, noise_level, &events)
+                        .map_err(BuildError::HookFailed)
+                        .map_err(Error::BuildFailed)?;
+                    call_for_targets_with_fallback(
+                        targets.iter(),
+                        &detect_target_ok,
+                        env,
+                        |target: &Target| {
+                            let mut app_version = config.bundle_version().clone();
+                            if let Some(build_number) = build_number {
+                                app_version.push_extra(build_number);
+                            }
+                            let archive_path = config
+                                .archive_dir()
+                                .join(format!("{}.xcarchive", config.scheme()));
+                            let inputs =
+                                build_manifest::fingerprint(config.app()).map(|fingerprint| {
+                                    Inputs {
+                                        request: archive_request(
+                                            target,
+                                            profile,
+                                            &app_version,
+                                            &settings,
+                                        ),
+                                        fingerprint,
+                                    }
+                                });
+                            if !force
+                                && inputs.as_ref().is_some_and(|inputs| {
+                                    build_manifest::is_fresh(
+                                        config.app(),
+                                        std::slice::from_ref(&archive_path),
+                                        inputs,
+                                    )
+                                })
+                            {
+                                println!("Nothing changed since the last build, so skipped `xcodebuild`; pass `--force` to build anyway.");
+                            } else {
+                                target
+                                    .build(config, env, noise_level, profile, &settings, &events)
+                                    .map_err(Error::BuildFailed)?;
+                                events.phase_started(Phase::Archive, Some(target.triple));
+                                target
+                                    .archive(
+                                        config,
+                                        env,
+                                        noise_level,
+                                        profile,
+                                        Some(app_version),
+                                        &settings,
+                                    )
+                                    .map_err(Error::ArchiveFailed)?;
+                                let artifact =
+                                    Artifact::new(&archive_path, ArtifactKind::XcArchive, profile)
+                                        .with_inputs(inputs);
+                                events.emit(BuildEvent::ArtifactProduced(artifact.clone()));
+                                build_manifest::record(config.app(), Some(artifact));
+                            }
+                            if let Some(out_dir) = config.app().out_dir(out_dir.as_deref()) {
+                                for path in util::copy_artifacts(&out_dir, &[archive_path])
+                                    .map_err(Error::CopyArtifactsFailed)?
+                                {
+                                    println!("Copied archive to {}", path.display());
+                                }
+                            }
+                            Ok(())
+                        },
+                    )
+                    .map_err(Error::TargetInvalid)??;
+                    session
+                        .finish(env, &events)
+                        .map_err(BuildError::HookFailed)
+                        .map_err(Error::BuildFailed)
+                },
+            ),
             Command::Run {
-                profile: cli::Profile { profile },
-            } => with_config(non_interactive, wrapper, |config, _| {
-                version_check()?;
-                ensure_init(config)?;
-                device_prompt(&env)
+                profile,
+                device: cli::DeviceId { device },
+                target_dir: cli::TargetDir { target_dir },
+            } => with_config(
+                env,
+                non_interactive,
+                wrapper,
+                target_dir,
+                |config, _, defaults, env| {
+                    version_check()?;
+                    ensure_init(config)?;
+                    device_prompt(
+                        env,
+                        defaults.device(device).as_deref(),
+                        Some(config.app().root_dir()),
+                    )
                     .map_err(Error::DevicePromptFailed)?
-                    .run(config, &env, noise_level, non_interactive, profile)
+                    .run(
+                        config,
+                        env,
+                        noise_level,
+                        non_interactive,
+                        defaults.profile(profile.requested()),
+                    )
                     .and_then(|h| {
                         h.wait().map(|_| ()).map_err(|e| {
                             RunError::DeployFailed(ios_deploy::RunAndDebugError::DeployFailed(e))
                         })
                     })
                     .map_err(Error::RunFailed)
+                },
+            ),
+            Command::DeepLink {
+                device: cli::DeviceId { device },
+                url,
+            } => with_config(env, non_interactive, wrapper, None, |config, _, _, env| {
+                device_prompt(env, device.as_deref(), Some(config.app().root_dir()))
+                    .map_err(Error::DevicePromptFailed)?
+                    .open_deep_link(config, &url)
+                    .map_err(Error::DeepLinkFailed)
             }),
+            Command::Trace {
+                device: cli::DeviceId { device },
+                template,
+                output,
+            } => with_config(
+                env,
+                non_interactive,
+                wrapper,
+                None,
+                |config, _, defaults, env| {
+                    ensure_init(config)?;
+                    let output = output.unwrap_or_else(|| {
+                        config
+                            .export_dir()
+                            .join(format!("{}.trace", config.app().name()))
+                    });
+                    let trace = device_prompt(
+                        env,
+                        defaults.device(device).as_deref(),
+                        Some(config.app().root_dir()),
+                    )
+                    .map_err(Error::DevicePromptFailed)?
+                    .record_trace(config, &template, &output)
+                    .map_err(Error::TraceFailed)?;
+                    println!("Recorded trace to {}", trace.display());
+                    Ok(())
+                },
+            ),
             Command::List => ios_deploy::device_list(&env)
                 .map_err(Error::ListFailed)
                 .map(|device_list| {
-                    prompt::list_display_only(device_list.iter(), device_list.len());
+                    prompt::list_display_only(
+                        device_list
+                            .iter()
+                            .map(|device| format!("{} [{}]", device, device.id())),
+                        device_list.len(),
+                    );
                 }),
-            Command::Pod { arguments } => with_config(non_interactive, wrapper, |config, _| {
-                bossy::Command::impure_parse("pod")
-                    .with_args(arguments)
-                    .with_arg(format!(
-                        "--project-directory={}",
-                        config.project_dir().display()
-                    ))
-                    .run_and_wait()
-                    .map_err(Error::PodCommandFailed)?;
-                Ok(())
-            }),
+            Command::Pod { arguments } => {
+                with_config(env, non_interactive, wrapper, None, |config, _, _, _| {
+                    bossy::Command::impure_parse("pod")
+                        .with_args(arguments)
+                        .with_arg(format!(
+                            "--project-directory={}",
+                            config.project_dir().display()
+                        ))
+                        .run_and_wait()
+                        .map_err(Error::PodCommandFailed)?;
+                    Ok(())
+                })
+            }
             Command::XcodeScript {
                 macos,
                 sdk_root,
                 profile,
                 force_color,
+                target_dir: cli::TargetDir { target_dir },
                 arches,
-            } => with_config(non_interactive, wrapper, |config, metadata| {
-                // The `PATH` env var Xcode gives us is missing any additions
-                // made by the user's profile, so we'll manually add cargo's
-                // `PATH`.
-                let env = env.prepend_to_path(
-                    util::home_dir()
-                        .map_err(Error::NoHomeDir)?
-                        .join(".cargo/bin"),
-                );
+            } => with_config(
+                env,
+                non_interactive,
+                wrapper,
+                target_dir,
+                |config, metadata, _, env| {
+                    // The `PATH` env var Xcode gives us is missing any additions
+                    // made by the user's profile, so we'll manually add cargo's
+                    // `PATH`.
+                    let env = env.clone().prepend_to_path(
+                        util::home_dir()
+                            .map_err(Error::NoHomeDir)?
+                            .join(".cargo/bin"),
+                    );
 
-                if !sdk_root.is_dir() {
-                    return Err(Error::SdkRootInvalid { sdk_root });
-                }
-                let include_dir = sdk_root.join("usr/include");
-                if !include_dir.is_dir() {
-                    return Err(Error::IncludeDirInvalid { include_dir });
-                }
+                    if !sdk_root.is_dir() {
+                        return Err(Error::SdkRootInvalid { sdk_root });
+                    }
+                    let include_dir = sdk_root.join("usr/include");
+                    if !include_dir.is_dir() {
+                        return Err(Error::IncludeDirInvalid { include_dir });
+                    }
 
-                let mut host_env = HashMap::<&str, &OsStr>::new();
+                    let mut host_env = HashMap::<&str, &OsStr>::new();
 
-                // Host flags that are used by build scripts
-                let (macos_isysroot, library_path) = {
-                    let macos_sdk_root =
-                        sdk_root.join("../../../../MacOSX.platform/Developer/SDKs/MacOSX.sdk");
-                    if !macos_sdk_root.is_dir() {
-                        return Err(Error::MacosSdkRootInvalid { macos_sdk_root });
-                    }
-                    (
-                        format!("-isysroot {}", macos_sdk_root.display()),
-                        format!("{}/usr/lib", macos_sdk_root.display()),
-                    )
-                };
-                host_env.insert("MAC_FLAGS", macos_isysroot.as_ref());
-                host_env.insert("CFLAGS_x86_64_apple_darwin", macos_isysroot.as_ref());
-                host_env.insert("CXXFLAGS_x86_64_apple_darwin", macos_isysroot.as_ref());
+                    // Host flags that are used by build scripts
+                    let (macos_isysroot, library_path) = {
+                        let macos_sdk_root =
+                            sdk_root.join("../../../../MacOSX.platform/Developer/SDKs/MacOSX.sdk");
+                        if !macos_sdk_root.is_dir() {
+                            return Err(Error::MacosSdkRootInvalid { macos_sdk_root });
+                        }
+                        (
+                            format!("-isysroot {}", macos_sdk_root.display()),
+                            format!("{}/usr/lib", macos_sdk_root.display()),
+                        )
+                    };
+                    host_env.insert("MAC_FLAGS", macos_isysroot.as_ref());
+                    host_env.insert("CFLAGS_x86_64_apple_darwin", macos_isysroot.as_ref());
+                    host_env.insert("CXXFLAGS_x86_64_apple_darwin", macos_isysroot.as_ref());
 
-                host_env.insert(
-                    "OBJC_INCLUDE_PATH_x86_64_apple_darwin",
-                    include_dir.as_os_str(),
-                );
+                    host_env.insert(
+                        "OBJC_INCLUDE_PATH_x86_64_apple_darwin",
+                        include_dir.as_os_str(),
+                    );
 
-                host_env.insert("RUST_BACKTRACE", "1".as_ref());
+                    host_env.insert("RUST_BACKTRACE", "1".as_ref());
 
-                let macos_target = Target::macos();
+                    let macos_target = Target::macos();
 
-                let isysroot = format!("-isysroot {}", sdk_root.display());
+                    let isysroot = format!("-isysroot {}", sdk_root.display());
 
-                for arch in arches {
-                    // Set target-specific flags
-                    let triple = match arch.as_str() {
-                        "arm64" => "aarch64_apple_ios",
-                        "x86_64" => "x86_64_apple_ios",
-                        _ => return Err(Error::ArchInvalid { arch }),
-                    };
-                    let cflags = format!("CFLAGS_{}", triple);
-                    let cxxflags = format!("CFLAGS_{}", triple);
-                    let objc_include_path = format!("OBJC_INCLUDE_PATH_{}", triple);
-                    let mut target_env = host_env.clone();
-                    target_env.insert(cflags.as_ref(), isysroot.as_ref());
-                    target_env.insert(cxxflags.as_ref(), isysroot.as_ref());
-                    target_env.insert(objc_include_path.as_ref(), include_dir.as_ref());
-                    // Prevents linker errors in build scripts and proc macros:
-                    // https://github.com/signalapp/libsignal-client/commit/02899cac643a14b2ced7c058cc15a836a2165b6d
-                    target_env.insert("LIBRARY_PATH", library_path.as_ref());
+                    for arch in arches {
+                        // Set target-specific flags
+                        let triple = match arch.as_str() {
+                            "arm64" => "aarch64_apple_ios",
+                            "x86_64" => "x86_64_apple_ios",
+                            _ => return Err(Error::ArchInvalid { arch }),
+                        };
+                        let cflags = format!("CFLAGS_{}", triple);
+                        let cxxflags = format!("CFLAGS_{}", triple);
+                        let objc_include_path = format!("OBJC_INCLUDE_PATH_{}", triple);
+                        let mut target_env = host_env.clone();
+                        target_env.insert(cflags.as_ref(), isysroot.as_ref());
+                        target_env.insert(cxxflags.as_ref(), isysroot.as_ref());
+                        target_env.insert(objc_include_path.as_ref(), include_dir.as_ref());
+                        // Prevents linker errors in build scripts and proc macros:
+                        // https://github.com/signalapp/libsignal-client/commit/02899cac643a14b2ced7c058cc15a836a2165b6d
+                        target_env.insert("LIBRARY_PATH", library_path.as_ref());
 
-                    let target = if macos {
-                        &macos_target
-                    } else {
-                        Target::for_arch(&arch).ok_or_else(|| Error::ArchInvalid {
-                            arch: arch.to_owned(),
-                        })?
-                    };
-                    target
-                        .compile_lib(
-                            config,
-                            metadata,
-                            noise_level,
-                            force_color,
-                            profile,
-                            &env,
-                            target_env,
-                        )
-                        .map_err(Error::CompileLibFailed)?;
-                }
-                Ok(())
-            }),
+                        let target = if macos {
+                            &macos_target
+                        } else {
+                            Target::for_arch(&arch).ok_or_else(|| Error::ArchInvalid {
+                                arch: arch.to_owned(),
+                            })?
+                        };
+                        target
+                            .compile_lib(
+                                config,
+                                metadata,
+                                noise_level,
+                                force_color,
+                                profile,
+                                &env,
+                                target_env,
+                            )
+                            .map_err(Error::CompileLibFailed)?;
+                    }
+                    Ok(())
+                },
+            ),
         }
     }
 }
@@ -351,6 +351,10 @@ impl Config {
         &self.app
     }
 
+    pub(crate) fn app_mut(&mut self) -> &mut App {
+        &mut self.app
+    }
+
     pub fn project_dir(&self) -> PathBuf {
         self.app.prefix_path(&self.project_dir)
     }
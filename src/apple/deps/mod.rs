@@ -4,7 +4,7 @@ pub(crate) mod xcode_plugin;
 use self::update::{Outdated, OutdatedError};
 use super::system_profile::{self, DeveloperTools};
 use crate::{
-    bossy,
+    bossy, os,
     util::{
         self,
         cli::{Report, TextWrapper},
@@ -198,7 +198,7 @@ pub fn install_all(
     gem_cache.initialize()?;
     let outdated = Outdated::load(&mut gem_cache)?;
     outdated.print_notice();
-    if !outdated.is_empty() && !non_interactive {
+    if !outdated.is_empty() && !non_interactive && os::can_prompt() {
         let answer = loop {
             if let Some(answer) = prompt::yes_no(
                 "Would you like these outdated dependencies to be updated for you?",
@@ -1,17 +1,22 @@
 use super::{
     config::Config,
+    instruments::{self, RecordError},
     ios_deploy,
     target::{ArchiveError, BuildError, ExportError, Target},
 };
 use crate::{
     bossy,
+    build_events::{Emitter, Phase},
     env::{Env, ExplicitEnv as _},
     opts,
-    util::cli::{Report, Reportable},
+    util::{
+        cli::{Report, Reportable},
+        BuildSession,
+    },
 };
 use std::{
     fmt::{self, Display},
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 use thiserror::Error;
 
@@ -47,6 +52,31 @@ impl Reportable for RunError {
     }
 }
 
+#[derive(Debug, Error)]
+pub enum DeepLinkError {
+    #[error("{0:?} isn't a URL; deep links look like `myapp://path`.")]
+    UrlInvalid(String),
+    #[error("{bundle_id} isn't installed on the device, so there's nothing to open {url:?} in; run it first.")]
+    AppNotInstalled { bundle_id: String, url: String },
+    #[error("{bundle_id} doesn't handle {scheme}: URLs; it needs to list the scheme under `CFBundleURLTypes` in its Info.plist.")]
+    SchemeUnhandled { bundle_id: String, scheme: String },
+    #[error("Failed to open {url:?} via `devicectl`: {cause}")]
+    OpenFailed {
+        url: String,
+        cause: Box<bossy::Error>,
+    },
+}
+
+impl Reportable for DeepLinkError {
+    fn report(&self) -> Report {
+        let msg = "Failed to open deep link";
+        match self {
+            Self::OpenFailed { .. } => Report::error(msg, self),
+            _ => Report::action_request(msg, self),
+        }
+    }
+}
+
 #[derive(Debug, Eq, Ord, PartialEq, PartialOrd)]
 pub struct Device<'a> {
     id: String,
@@ -71,6 +101,10 @@ impl<'a> Device<'a> {
         }
     }
 
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
     pub fn target(&self) -> &'a Target<'a> {
         self.target
     }
@@ -84,13 +118,20 @@ impl<'a> Device<'a> {
         profile: opts::Profile,
     ) -> Result<bossy::Handle, RunError> {
         // TODO: These steps are run unconditionally, which is slooooooow
-        println!("Building app...");
+        let events = Emitter::terminal();
+        let session = BuildSession::begin(config.app(), env, noise_level, &events)
+            .map_err(BuildError::HookFailed)
+            .map_err(RunError::BuildFailed)?;
         self.target
-            .build(config, env, noise_level, profile)
+            .build(config, env, noise_level, profile, &[], &events)
+            .map_err(RunError::BuildFailed)?;
+        session
+            .finish(env, &events)
+            .map_err(BuildError::HookFailed)
             .map_err(RunError::BuildFailed)?;
-        println!("Archiving app...");
+        events.phase_started(Phase::Archive, Some(self.target.triple));
         self.target
-            .archive(config, env, noise_level, profile, None)
+            .archive(config, env, noise_level, profile, None, &[])
             .map_err(RunError::ArchiveFailed)?;
         println!("Exporting app...");
         self.target
@@ -117,4 +158,54 @@ impl<'a> Device<'a> {
         ios_deploy::run_and_debug(config, env, non_interactive, &self.id)
             .map_err(RunError::DeployFailed)
     }
+
+    /// Launches the app under Instruments with `template`, recording to
+    /// `out_trace` until the app exits. The app has to already be installed,
+    /// i.e. by [`Device::run`].
+    pub fn record_trace(
+        &self,
+        config: &Config,
+        template: &str,
+        out_trace: &Path,
+    ) -> Result<PathBuf, RecordError> {
+        instruments::record(&self.id, &config.app_path(), template, out_trace)
+    }
+
+    /// Launches the app with `url` as its payload, the same as tapping a link
+    /// to it would. The app has to already be installed. This uses
+    /// `devicectl`, so it needs Xcode 15 or later.
+    pub fn open_deep_link(&self, config: &Config, url: &str) -> Result<(), DeepLinkError> {
+        let scheme = crate::device::url_scheme(url)
+            .ok_or_else(|| DeepLinkError::UrlInvalid(url.to_owned()))?;
+        let bundle_id = format!("{}.{}", config.app().reverse_domain(), config.app().name());
+        bossy::Command::impure_parse("xcrun devicectl device process launch")
+            .with_args(["--device", &self.id, "--payload-url", url, &bundle_id])
+            .run_and_wait_for_output()
+            .map(|_| ())
+            .map_err(|cause| {
+                let stderr = cause
+                    .stderr_str()
+                    .and_then(Result::ok)
+                    .unwrap_or_default()
+                    .to_owned();
+                // -10814 is `kLSApplicationNotFoundErr`, which is what Launch
+                // Services says when nothing claims the scheme.
+                if stderr.contains("not installed") || stderr.contains("could not be found") {
+                    DeepLinkError::AppNotInstalled {
+                        bundle_id,
+                        url: url.to_owned(),
+                    }
+                } else if stderr.contains("-10814") {
+                    DeepLinkError::SchemeUnhandled {
+                        bundle_id,
+                        scheme: scheme.to_owned(),
+                    }
+                } else {
+                    DeepLinkError::OpenFailed {
+                        url: url.to_owned(),
+                        cause: Box::new(cause),
+                    }
+                }
+            })
+    }
 }
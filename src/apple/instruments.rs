@@ -0,0 +1,143 @@
+use crate::{
+    bossy,
+    util::cli::{Report, Reportable},
+};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum RecordError {
+    #[error("Failed to list Instruments templates: {0}")]
+    TemplateListFailed(#[source] Box<bossy::Error>),
+    #[error("{template:?} isn't an Instruments template; the available templates are: {}", .available.join(", "))]
+    TemplateUnknown {
+        template: String,
+        available: Vec<String>,
+    },
+    #[error("There's no app to launch at {0:?}; run it on the device first, so that it's built and installed.")]
+    AppMissing(PathBuf),
+    #[error("`xctrace record` failed: {}", .stderr.as_deref().unwrap_or("it didn't say why"))]
+    RecordFailed {
+        #[source]
+        cause: Box<bossy::Error>,
+        stderr: Option<String>,
+    },
+    #[error("`xctrace record` succeeded, but didn't produce a trace at {0:?}")]
+    TraceMissing(PathBuf),
+}
+
+impl Reportable for RecordError {
+    fn report(&self) -> Report {
+        match self {
+            Self::TemplateUnknown { .. } => Report::action_request(
+                "Please specify a valid Instruments template and try again",
+                self,
+            ),
+            Self::AppMissing(_) => {
+                Report::action_request("Failed to record Instruments trace", self)
+            }
+            _ => Report::error("Failed to record Instruments trace", self),
+        }
+    }
+}
+
+// `xctrace list templates` prints something like:
+//
+// == Standard Templates ==
+// Activity Monitor
+// Allocations
+// ...
+//
+// == Custom Templates ==
+// My Template
+fn parse_templates(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with("=="))
+        .map(ToOwned::to_owned)
+        .collect()
+}
+
+/// The names of the Instruments templates `xctrace` knows about, including
+/// custom ones.
+pub fn templates() -> Result<Vec<String>, RecordError> {
+    bossy::Command::impure("xcrun")
+        .with_args(["xctrace", "list", "templates"])
+        .run_and_wait_for_str(parse_templates)
+        .map_err(|cause| RecordError::TemplateListFailed(Box::new(cause)))
+}
+
+/// Launches `app_bundle` on the device with `udid` under Instruments, using
+/// `template` (i.e. "Time Profiler"), and records until the app exits or the
+/// recording is stopped. The app has to already be installed on the device.
+/// Returns the path of the recorded `.trace`.
+pub fn record(
+    udid: &str,
+    app_bundle: &Path,
+    template: &str,
+    out_trace: &Path,
+) -> Result<PathBuf, RecordError> {
+    if !app_bundle.is_dir() {
+        return Err(RecordError::AppMissing(app_bundle.to_owned()));
+    }
+    let available = templates()?;
+    if !available.iter().any(|name| name == template) {
+        return Err(RecordError::TemplateUnknown {
+            template: template.to_owned(),
+            available,
+        });
+    }
+    bossy::Command::impure("xcrun")
+        .with_args([
+            "xctrace",
+            "record",
+            "--template",
+            template,
+            "--device",
+            udid,
+        ])
+        .with_arg("--output")
+        .with_arg(out_trace)
+        .with_arg("--launch")
+        .with_arg("--")
+        .with_arg(app_bundle)
+        .run_and_wait_for_output()
+        .map_err(|cause| RecordError::RecordFailed {
+            stderr: cause
+                .stderr_str()
+                .and_then(Result::ok)
+                .map(|stderr| stderr.trim().to_owned())
+                .filter(|stderr| !stderr.is_empty()),
+            cause: Box::new(cause),
+        })?;
+    if out_trace.exists() {
+        Ok(out_trace.to_owned())
+    } else {
+        Err(RecordError::TraceMissing(out_trace.to_owned()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parses_templates() {
+        let output = "== Standard Templates ==\nActivity Monitor\nAllocations\nTime Profiler\n\n== Custom Templates ==\n  My Template  \n";
+        assert_eq!(
+            parse_templates(output),
+            vec![
+                "Activity Monitor",
+                "Allocations",
+                "Time Profiler",
+                "My Template"
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_no_templates() {
+        assert!(parse_templates("== Standard Templates ==\n\n").is_empty());
+    }
+}
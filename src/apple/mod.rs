@@ -1,13 +1,17 @@
+pub mod build_settings;
 pub mod cli;
 pub mod config;
 pub mod deps;
 pub mod device;
+pub mod instruments;
 pub mod ios_deploy;
 pub mod project;
+pub mod simulator;
 pub(crate) mod system_profile;
 pub mod target;
 pub(crate) mod teams;
 mod version_number;
+pub mod xcode;
 
 use crate::util::{
     self,
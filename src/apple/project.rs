@@ -11,6 +11,7 @@ use crate::{
         self,
         cli::{Report, Reportable, TextWrapper},
         ln,
+        toolchain::{Toolchain, ToolchainError},
     },
 };
 use std::path::{Path, PathBuf};
@@ -19,6 +20,7 @@ pub static TEMPLATE_PACK: &str = "xcode";
 
 #[derive(Debug)]
 pub enum Error {
+    ToolchainInvalid(ToolchainError),
     RustupFailed(bossy::Error),
     RustVersionCheckFailed(util::RustVersionError),
     DepsInstallFailed(deps::Error),
@@ -36,6 +38,7 @@ pub enum Error {
 impl Reportable for Error {
     fn report(&self) -> Report {
         match self {
+            Self::ToolchainInvalid(err) => err.report(),
             Self::RustupFailed(err) => Report::error("Failed to `rustup` Apple toolchains", err),
             Self::RustVersionCheckFailed(err) => err.report(),
             Self::DepsInstallFailed(err) => {
@@ -72,7 +75,11 @@ pub fn gen(
     filter: &templating::Filter,
 ) -> Result<(), Error> {
     println!("Installing iOS toolchains...");
-    Target::install_all().map_err(Error::RustupFailed)?;
+    let toolchain = Toolchain::detect(config.app().root_dir()).map_err(Error::ToolchainInvalid)?;
+    if let Some(toolchain) = &toolchain {
+        println!("Using pinned toolchain {}", toolchain);
+    }
+    Target::install_all(toolchain.as_ref()).map_err(Error::RustupFailed)?;
     rust_version_check(wrapper).map_err(Error::RustVersionCheckFailed)?;
 
     deps::install_all(wrapper, non_interactive, skip_dev_tools, reinstall_deps)
@@ -89,6 +96,14 @@ pub fn gen(
         .map_err(Error::MissingPack)?
         .expect_local();
 
+    let lib_name = crate::config::lib_name(config.app().root_dir()).unwrap_or_else(|err| {
+        log::warn!(
+            "failed to find lib name, so falling back to the app name: {}",
+            err
+        );
+        config.app().name_snake()
+    });
+
     let asset_catalogs = metadata.ios().asset_catalogs().unwrap_or_default();
     let ios_pods = metadata.ios().pods().unwrap_or_default();
     let macos_pods = metadata.macos().pods().unwrap_or_default();
@@ -102,6 +117,7 @@ pub fn gen(
         &dest,
         |map| {
             map.insert("file-groups", &source_dirs);
+            map.insert("lib-name", &lib_name);
             map.insert("ios-frameworks", metadata.ios().frameworks());
             map.insert(
                 "ios-valid-archs",
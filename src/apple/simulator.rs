@@ -0,0 +1,416 @@
+use crate::{
+    bossy,
+    util::{
+        cli::{Report, Reportable},
+        VersionTriple, VersionTripleError,
+    },
+};
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display},
+};
+use thiserror::Error;
+
+static INSTALL_HINT: &str =
+    "You can install more runtimes in Xcode > Settings > Platforms, or with `xcodebuild -downloadPlatform iOS`.";
+
+#[derive(Debug, Error)]
+pub enum RuntimeError {
+    #[error("Failed to list simulator runtimes: {0}")]
+    ListFailed(#[source] bossy::Error),
+    #[error("Failed to parse `simctl list runtimes` output: {0}")]
+    ParseFailed(#[source] serde_json::Error),
+    #[error("{requested:?} isn't an iOS version: {cause}")]
+    RequestInvalid {
+        requested: String,
+        #[source]
+        cause: VersionTripleError,
+    },
+    #[error("No installed simulator runtime matches iOS {requested}; {}", if .available.is_empty() { "no iOS runtimes are installed at all".to_owned() } else { format!("the installed ones are {}", .available.join(", ")) })]
+    NoneSuitable {
+        requested: String,
+        available: Vec<String>,
+    },
+}
+
+impl Reportable for RuntimeError {
+    fn report(&self) -> Report {
+        match self {
+            Self::NoneSuitable { .. } => Report::action_request(
+                "No suitable simulator runtime is installed",
+                format!("{}\n{}", self, INSTALL_HINT),
+            ),
+            _ => Report::error("Failed to resolve simulator runtime", self),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawRuntime {
+    name: String,
+    identifier: String,
+    version: String,
+    // Older versions of `simctl` don't include these.
+    #[serde(default)]
+    platform: Option<String>,
+    #[serde(default = "available_by_default")]
+    is_available: bool,
+}
+
+fn available_by_default() -> bool {
+    true
+}
+
+#[derive(Debug, Deserialize)]
+struct RawRuntimes {
+    runtimes: Vec<RawRuntime>,
+}
+
+impl RawRuntime {
+    fn is_ios(&self) -> bool {
+        match &self.platform {
+            Some(platform) => platform == "iOS",
+            None => self.name.starts_with("iOS "),
+        }
+    }
+}
+
+/// An installed simulator runtime, as listed by `simctl list runtimes`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Runtime {
+    pub name: String,
+    pub identifier: String,
+    pub version: VersionTriple,
+}
+
+impl Display for Runtime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.name, self.identifier)
+    }
+}
+
+fn parse_runtimes(json: &str) -> Result<Vec<Runtime>, RuntimeError> {
+    let raw: RawRuntimes = serde_json::from_str(json).map_err(RuntimeError::ParseFailed)?;
+    Ok(raw
+        .runtimes
+        .into_iter()
+        .filter(|runtime| runtime.is_available && runtime.is_ios())
+        .filter_map(|runtime| match VersionTriple::from_str(&runtime.version) {
+            Ok(version) => Some(Runtime {
+                name: runtime.name,
+                identifier: runtime.identifier,
+                version,
+            }),
+            Err(err) => {
+                log::warn!(
+                    "ignoring simulator runtime {:?} with unparseable version: {}",
+                    runtime.identifier,
+                    err
+                );
+                None
+            }
+        })
+        .collect())
+}
+
+/// The available iOS simulator runtimes.
+pub fn runtimes() -> Result<Vec<Runtime>, RuntimeError> {
+    let json = bossy::Command::impure_parse("xcrun simctl list runtimes -j")
+        .run_and_wait_for_str(ToOwned::to_owned)
+        .map_err(RuntimeError::ListFailed)?;
+    parse_runtimes(&json)
+}
+
+/// A requested version, along with how many of its components were given,
+/// so "17" can match any 17.x while "17.2" only matches 17.2.x.
+fn parse_request(requested: &str) -> Result<(VersionTriple, usize), RuntimeError> {
+    let trimmed = requested.trim();
+    let version = match trimmed.get(..3) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("ios") => trimmed[3..].trim_start(),
+        _ => trimmed,
+    };
+    VersionTriple::from_str(version)
+        .map(|triple| (triple, version.split('.').count()))
+        .map_err(|cause| RuntimeError::RequestInvalid {
+            requested: requested.to_owned(),
+            cause,
+        })
+}
+
+fn matches(runtime: &VersionTriple, requested: &VersionTriple, precision: usize) -> bool {
+    runtime.major == requested.major
+        && (precision < 2 || runtime.minor == requested.minor)
+        && (precision < 3 || runtime.patch == requested.patch)
+}
+
+fn best_match(requested: &str, runtimes: Vec<Runtime>) -> Result<Runtime, RuntimeError> {
+    let (version, precision) = parse_request(requested)?;
+    let exact = runtimes
+        .iter()
+        .filter(|runtime| matches(&runtime.version, &version, precision))
+        .max_by_key(|runtime| runtime.version);
+    let lower = || {
+        runtimes
+            .iter()
+            .filter(|runtime| runtime.version < version)
+            .max_by_key(|runtime| runtime.version)
+    };
+    match exact.or_else(lower) {
+        Some(runtime) => {
+            if !matches(&runtime.version, &version, precision) {
+                log::warn!(
+                    "no simulator runtime for iOS {} is installed, so using {}",
+                    requested,
+                    runtime
+                );
+            }
+            Ok(runtime.clone())
+        }
+        None => Err(RuntimeError::NoneSuitable {
+            requested: requested.to_owned(),
+            available: runtimes
+                .iter()
+                .map(|runtime| runtime.name.clone())
+                .collect(),
+        }),
+    }
+}
+
+/// Picks the installed runtime closest to `requested` (i.e. "17", "17.2", or
+/// "iOS 17"): the newest one matching it exactly, or failing that, the newest
+/// one older than it. Newer runtimes are never picked, since apps can't count
+/// on APIs from versions they didn't ask for.
+pub fn resolve_runtime(requested: &str) -> Result<Runtime, RuntimeError> {
+    best_match(requested, runtimes()?)
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawSimulator {
+    udid: String,
+    name: String,
+    state: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawSimulators {
+    /// Keyed by runtime identifier.
+    devices: BTreeMap<String, Vec<RawSimulator>>,
+}
+
+/// A booted simulator, as listed by `simctl list devices`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BootedSimulator {
+    pub udid: String,
+    pub name: String,
+}
+
+fn parse_booted(json: &str) -> Result<Vec<BootedSimulator>, serde_json::Error> {
+    let raw: RawSimulators = serde_json::from_str(json)?;
+    Ok(raw
+        .devices
+        .into_values()
+        .flatten()
+        .filter(|simulator| simulator.state == "Booted")
+        .map(|simulator| BootedSimulator {
+            udid: simulator.udid,
+            name: simulator.name,
+        })
+        .collect())
+}
+
+#[derive(Debug, Error)]
+pub enum BootedError {
+    #[error("Failed to list simulators: {0}")]
+    ListFailed(#[source] bossy::Error),
+    #[error("Failed to parse `simctl list devices` output: {0}")]
+    ParseFailed(#[source] serde_json::Error),
+}
+
+/// The simulators that are currently booted.
+pub fn booted() -> Result<Vec<BootedSimulator>, BootedError> {
+    let json = bossy::Command::impure_parse("xcrun simctl list devices booted -j")
+        .run_and_wait_for_str(ToOwned::to_owned)
+        .map_err(BootedError::ListFailed)?;
+    parse_booted(&json).map_err(BootedError::ParseFailed)
+}
+
+/// Whether `simctl` failed because the simulator was already shut down, or
+/// doesn't exist anymore.
+fn already_gone(err: &bossy::Error) -> bool {
+    err.stderr_str()
+        .and_then(Result::ok)
+        .map(|stderr| {
+            stderr.contains("current state: Shutdown") || stderr.contains("Invalid device")
+        })
+        .unwrap_or(false)
+}
+
+/// Shuts down the simulator with `udid`. Returns `false` if it was already
+/// shut down or gone, which isn't an error, since that's what we wanted
+/// anyway.
+pub fn shutdown(udid: &str) -> bossy::Result<bool> {
+    match bossy::Command::impure_parse("xcrun simctl shutdown")
+        .with_arg(udid)
+        .run_and_wait_for_output()
+    {
+        Ok(_) => Ok(true),
+        Err(err) if already_gone(&err) => {
+            log::info!("simulator {:?} was already shut down", udid);
+            Ok(false)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    // Trimmed from Xcode 15's `xcrun simctl list runtimes -j`.
+    const RUNTIMES: &str = r#"{
+  "runtimes" : [
+    {
+      "bundlePath" : "/Library/Developer/CoreSimulator/Volumes/iOS_20C52/Library/Developer/CoreSimulator/Profiles/Runtimes/iOS 16.2.simruntime",
+      "buildversion" : "20C52",
+      "platform" : "iOS",
+      "runtimeRoot" : "/Library/Developer/CoreSimulator/Volumes/iOS_20C52/Library/Developer/CoreSimulator/Profiles/Runtimes/iOS 16.2.simruntime/Contents/Resources/RuntimeRoot",
+      "identifier" : "com.apple.CoreSimulator.SimRuntime.iOS-16-2",
+      "version" : "16.2",
+      "isInternal" : false,
+      "isAvailable" : true,
+      "name" : "iOS 16.2",
+      "supportedDeviceTypes" : []
+    },
+    {
+      "bundlePath" : "/Library/Developer/CoreSimulator/Volumes/iOS_21A328/Library/Developer/CoreSimulator/Profiles/Runtimes/iOS 17.0.simruntime",
+      "buildversion" : "21A328",
+      "platform" : "iOS",
+      "runtimeRoot" : "/Library/Developer/CoreSimulator/Volumes/iOS_21A328/Library/Developer/CoreSimulator/Profiles/Runtimes/iOS 17.0.simruntime/Contents/Resources/RuntimeRoot",
+      "identifier" : "com.apple.CoreSimulator.SimRuntime.iOS-17-0",
+      "version" : "17.0",
+      "isInternal" : false,
+      "isAvailable" : true,
+      "name" : "iOS 17.0",
+      "supportedDeviceTypes" : []
+    },
+    {
+      "bundlePath" : "/Library/Developer/CoreSimulator/Volumes/iOS_21C62/Library/Developer/CoreSimulator/Profiles/Runtimes/iOS 17.2.simruntime",
+      "buildversion" : "21C62",
+      "platform" : "iOS",
+      "runtimeRoot" : "/Library/Developer/CoreSimulator/Volumes/iOS_21C62/Library/Developer/CoreSimulator/Profiles/Runtimes/iOS 17.2.simruntime/Contents/Resources/RuntimeRoot",
+      "identifier" : "com.apple.CoreSimulator.SimRuntime.iOS-17-2",
+      "version" : "17.2",
+      "isInternal" : false,
+      "isAvailable" : true,
+      "name" : "iOS 17.2",
+      "supportedDeviceTypes" : []
+    },
+    {
+      "bundlePath" : "/Library/Developer/CoreSimulator/Volumes/iOS_21E213/Library/Developer/CoreSimulator/Profiles/Runtimes/iOS 17.4.simruntime",
+      "buildversion" : "21E213",
+      "platform" : "iOS",
+      "runtimeRoot" : "/Library/Developer/CoreSimulator/Volumes/iOS_21E213/Library/Developer/CoreSimulator/Profiles/Runtimes/iOS 17.4.simruntime/Contents/Resources/RuntimeRoot",
+      "identifier" : "com.apple.CoreSimulator.SimRuntime.iOS-17-4",
+      "version" : "17.4",
+      "isInternal" : false,
+      "isAvailable" : false,
+      "name" : "iOS 17.4",
+      "supportedDeviceTypes" : []
+    },
+    {
+      "bundlePath" : "/Library/Developer/CoreSimulator/Volumes/watchOS_21R355/Library/Developer/CoreSimulator/Profiles/Runtimes/watchOS 10.0.simruntime",
+      "buildversion" : "21R355",
+      "platform" : "watchOS",
+      "runtimeRoot" : "/Library/Developer/CoreSimulator/Volumes/watchOS_21R355/Library/Developer/CoreSimulator/Profiles/Runtimes/watchOS 10.0.simruntime/Contents/Resources/RuntimeRoot",
+      "identifier" : "com.apple.CoreSimulator.SimRuntime.watchOS-10-0",
+      "version" : "10.0",
+      "isInternal" : false,
+      "isAvailable" : true,
+      "name" : "watchOS 10.0",
+      "supportedDeviceTypes" : []
+    }
+  ]
+}"#;
+
+    #[test]
+    fn parses_available_ios_runtimes() {
+        let names = parse_runtimes(RUNTIMES)
+            .unwrap()
+            .into_iter()
+            .map(|runtime| runtime.name)
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["iOS 16.2", "iOS 17.0", "iOS 17.2"]);
+    }
+
+    #[rstest(
+        requested,
+        expected,
+        case("17", "com.apple.CoreSimulator.SimRuntime.iOS-17-2"),
+        case("iOS 17", "com.apple.CoreSimulator.SimRuntime.iOS-17-2"),
+        case("ios17.0", "com.apple.CoreSimulator.SimRuntime.iOS-17-0"),
+        case("17.1", "com.apple.CoreSimulator.SimRuntime.iOS-17-0"),
+        case("17.4", "com.apple.CoreSimulator.SimRuntime.iOS-17-2"),
+        case("16", "com.apple.CoreSimulator.SimRuntime.iOS-16-2"),
+        case("18", "com.apple.CoreSimulator.SimRuntime.iOS-17-2")
+    )]
+    fn resolves_closest_runtime(requested: &str, expected: &str) {
+        let runtime = best_match(requested, parse_runtimes(RUNTIMES).unwrap()).unwrap();
+        assert_eq!(runtime.identifier, expected);
+    }
+
+    #[test]
+    fn errors_when_nothing_is_old_enough() {
+        match best_match("15", parse_runtimes(RUNTIMES).unwrap()) {
+            Err(RuntimeError::NoneSuitable { available, .. }) => {
+                assert_eq!(available, ["iOS 16.2", "iOS 17.0", "iOS 17.2"])
+            }
+            other => panic!("expected `NoneSuitable`, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn rejects_nonsense_requests() {
+        assert!(matches!(
+            best_match("latest", parse_runtimes(RUNTIMES).unwrap()),
+            Err(RuntimeError::RequestInvalid { .. })
+        ));
+    }
+
+    #[test]
+    fn parses_booted_simulators() {
+        let json = r#"{
+  "devices" : {
+    "com.apple.CoreSimulator.SimRuntime.iOS-17-2" : [
+      {
+        "lastBootedAt" : "2024-01-10T09:12:44Z",
+        "dataPath" : "/Users/me/Library/Developer/CoreSimulator/Devices/5B9E6C5A-7B21-4E1F-9A35-2D1F0C9F1A10/data",
+        "udid" : "5B9E6C5A-7B21-4E1F-9A35-2D1F0C9F1A10",
+        "isAvailable" : true,
+        "deviceTypeIdentifier" : "com.apple.CoreSimulator.SimDeviceType.iPhone-15",
+        "state" : "Booted",
+        "name" : "iPhone 15"
+      },
+      {
+        "udid" : "0D7F3A4E-2C4B-4B8E-8E0A-6F1B2C3D4E5F",
+        "isAvailable" : true,
+        "deviceTypeIdentifier" : "com.apple.CoreSimulator.SimDeviceType.iPad-Air-5th-generation",
+        "state" : "Shutdown",
+        "name" : "iPad Air (5th generation)"
+      }
+    ],
+    "com.apple.CoreSimulator.SimRuntime.iOS-16-2" : []
+  }
+}"#;
+        assert_eq!(
+            parse_booted(json).unwrap(),
+            [BootedSimulator {
+                udid: "5B9E6C5A-7B21-4E1F-9A35-2D1F0C9F1A10".to_owned(),
+                name: "iPhone 15".to_owned(),
+            }]
+        );
+    }
+}
@@ -1,34 +1,32 @@
 use super::{
+    build_settings::{self, BuildSettingError},
     config::{Config, Metadata},
     system_profile::{self, DeveloperTools},
     version_number::VersionNumber,
 };
 use crate::{
     bossy,
+    build_events::{Emitter, Phase},
+    build_failure, build_log,
     env::{Env, ExplicitEnv as _},
     opts::{self, NoiseLevel, Profile},
     target::TargetTrait,
     util::{
         self,
-        cli::{Report, Reportable},
-        CargoCommand, WithWorkingDirError,
+        cli::{self, Report, Reportable},
+        hook::HookError,
+        toolchain::{self, ToolchainError},
+        BuildStdError, CargoCommand, WithWorkingDirError,
     },
 };
 use once_cell_regex::exports::once_cell::sync::OnceCell;
 use std::{
     collections::{BTreeMap, HashMap},
     ffi::OsStr,
+    path::PathBuf,
 };
 use thiserror::Error;
 
-fn verbosity(noise_level: opts::NoiseLevel) -> Option<&'static str> {
-    if noise_level.pedantic() {
-        None
-    } else {
-        Some("-quiet")
-    }
-}
-
 #[derive(Debug, Error)]
 pub enum VersionCheckError {
     #[error("Failed to lookup Xcode version: {0}")]
@@ -85,24 +83,44 @@ pub enum CompileLibError {
     VersionCheckFailed(VersionCheckError),
     #[error("Failed to run `cargo build`: {0}")]
     CargoBuildFailed(bossy::Error),
+    #[error(transparent)]
+    BuildStdUnavailable(BuildStdError),
+    #[error(transparent)]
+    ToolchainInvalid(ToolchainError),
 }
 
 impl Reportable for CompileLibError {
     fn report(&self) -> Report {
         match self {
             Self::VersionCheckFailed(err) => err.report(),
-            Self::CargoBuildFailed(err) => Report::error("Failed to run `cargo build`", err),
+            Self::CargoBuildFailed(err) => {
+                build_failure::report("Failed to run `cargo build`", err)
+            }
+            Self::BuildStdUnavailable(err) => err.report(),
+            Self::ToolchainInvalid(err) => err.report(),
         }
     }
 }
 
 #[derive(Debug, Error)]
-#[error(transparent)]
-pub struct BuildError(bossy::Error);
+pub enum BuildError {
+    #[error(transparent)]
+    SettingInvalid(BuildSettingError),
+    #[error(transparent)]
+    BuildFailed(bossy::Error),
+    #[error(transparent)]
+    HookFailed(HookError),
+}
 
 impl Reportable for BuildError {
     fn report(&self) -> Report {
-        Report::error("Failed to build via `xcodebuild`", &self.0)
+        match self {
+            Self::SettingInvalid(err) => Report::error("Invalid `xcodebuild` build setting", err),
+            Self::BuildFailed(err) => {
+                build_failure::report("Failed to build via `xcodebuild`", err)
+            }
+            Self::HookFailed(err) => err.report(),
+        }
     }
 }
 
@@ -110,6 +128,8 @@ impl Reportable for BuildError {
 pub enum ArchiveError {
     #[error("Failed to set app version number: {0}")]
     SetVersionFailed(WithWorkingDirError<bossy::Error>),
+    #[error(transparent)]
+    SettingInvalid(BuildSettingError),
     #[error("Failed to archive via `xcodebuild`: {0}")]
     ArchiveFailed(bossy::Error),
 }
@@ -118,7 +138,10 @@ impl Reportable for ArchiveError {
     fn report(&self) -> Report {
         match self {
             Self::SetVersionFailed(err) => Report::error("Failed to set app version number", err),
-            Self::ArchiveFailed(err) => Report::error("Failed to archive via `xcodebuild`", err),
+            Self::SettingInvalid(err) => Report::error("Invalid `xcodebuild` build setting", err),
+            Self::ArchiveFailed(err) => {
+                build_failure::report("Failed to archive via `xcodebuild`", err)
+            }
         }
     }
 }
@@ -238,10 +261,13 @@ impl<'a> Target<'a> {
             CargoCommand::new(subcommand)
                 .with_package(Some(config.app().name()))
                 .with_manifest_path(Some(config.app().manifest_path()))
+                .with_target_dir(config.app().custom_target_dir())
                 .with_target(Some(&self.triple))
                 .with_no_default_features(metadata.no_default_features())
                 .with_args(metadata.cargo_args())
                 .with_features(metadata.features())
+                .with_panic_abort(config.app().panic_abort())
+                .with_build_std(config.app().build_std())
         })
     }
 
@@ -254,7 +280,7 @@ impl<'a> Target<'a> {
     ) -> Result<(), CheckError> {
         self.cargo(config, metadata, "check")
             .map_err(CheckError::VersionCheckFailed)?
-            .with_verbose(noise_level.pedantic())
+            .with_noise_level(noise_level)
             .into_command_pure(env)
             .run_and_wait()
             .map_err(CheckError::CargoCheckFailed)?;
@@ -276,42 +302,95 @@ impl<'a> Target<'a> {
     ) -> Result<(), CompileLibError> {
         // Force color when running from CLI
         let color = if force_color { "always" } else { "auto" };
-        self.cargo(config, metadata, "build")
+        if config.app().build_std() {
+            util::check_build_std().map_err(CompileLibError::BuildStdUnavailable)?;
+        }
+        let toolchain = toolchain::for_target(config.app().root_dir(), self.triple)
+            .map_err(CompileLibError::ToolchainInvalid)?;
+        if let Some(toolchain) = &toolchain {
+            if !noise_level.polite() {
+                println!("Building {} with toolchain {}", self.triple, toolchain);
+            }
+        }
+        let command = self
+            .cargo(config, metadata, "build")
             .map_err(CompileLibError::VersionCheckFailed)?
-            .with_verbose(noise_level.pedantic())
+            .with_noise_level(noise_level)
             .with_release(profile.release())
             .into_command_pure(env)
+            .with_env_vars(
+                toolchain
+                    .as_ref()
+                    .map(|toolchain| (toolchain::OVERRIDE_VAR, toolchain.name())),
+            )
             .with_env_vars(cc_env)
-            .with_args(&["--color", color])
-            .run_and_wait()
-            .map_err(CompileLibError::CargoBuildFailed)?;
+            .with_args(&["--color", color]);
+        build_log::run(command).map_err(CompileLibError::CargoBuildFailed)?;
         Ok(())
     }
 
+    /// Lets `cargo apple xcode-script` know about an overridden target dir,
+    /// since Xcode runs it with only the env we give `xcodebuild`.
+    fn target_dir_env(&self, config: &Config) -> Option<(&'static str, PathBuf)> {
+        config
+            .app()
+            .custom_target_dir()
+            .map(|target_dir| (cli::TARGET_DIR_VAR, target_dir))
+    }
+
+    /// The library search path baked into the Xcode project is from whatever
+    /// `app.target-dir` was when it was generated, so this points the linker
+    /// at the target dir we're actually building into. Settings given with
+    /// `--xcode-setting` come after this, so they still win.
+    fn target_dir_setting(&self, config: &Config) -> Option<String> {
+        config.app().custom_target_dir().map(|target_dir| {
+            format!(
+                "LIBRARY_SEARCH_PATHS=$(inherited) \"{}/{}/$(CONFIGURATION)\"",
+                target_dir.display(),
+                self.triple
+            )
+        })
+    }
+
+    /// Builds the app for this target. This doesn't run hooks or check the
+    /// disk; that's done once for the whole build by a
+    /// [`util::BuildSession`].
     pub fn build(
         &self,
         config: &Config,
         env: &Env,
         noise_level: opts::NoiseLevel,
         profile: opts::Profile,
+        settings: &[(String, String)],
+        events: &Emitter,
     ) -> Result<(), BuildError> {
-        let configuration = profile.as_str();
-        bossy::Command::pure("xcodebuild")
+        let settings = build_settings::args(settings).map_err(BuildError::SettingInvalid)?;
+        events.phase_started(Phase::Package, Some(self.triple));
+        let configuration = profile.xcode_configuration();
+        let command = bossy::Command::pure("xcodebuild")
             .with_env_vars(env.explicit_env())
             .with_env_var("FORCE_COLOR", "--force-color")
-            .with_args(verbosity(noise_level))
+            .with_args(noise_level.xcodebuild_flag())
             .with_args(&["-scheme", &config.scheme()])
             .with_arg("-workspace")
             .with_arg(&config.workspace_path())
             .with_args(&["-configuration", configuration])
             .with_args(&["-arch", self.arch])
             .with_arg("-allowProvisioningUpdates")
-            .with_arg("build")
-            .run_and_wait()
-            .map_err(BuildError)?;
+            .with_env_vars(self.target_dir_env(config))
+            .with_args(self.target_dir_setting(config))
+            .with_args(&settings)
+            .with_arg("build");
+        build_log::run(command).map_err(BuildError::BuildFailed)?;
+        events.target_built(self.triple);
         Ok(())
     }
 
+    /// Archives the app for this target, which has to have been built first.
+    /// Emitting [`Phase::Archive`] and [`BuildEvent::ArtifactProduced`] is up
+    /// to the caller.
+    ///
+    /// [`BuildEvent::ArtifactProduced`]: crate::build_events::BuildEvent::ArtifactProduced
     pub fn archive(
         &self,
         config: &Config,
@@ -319,7 +398,9 @@ impl<'a> Target<'a> {
         noise_level: opts::NoiseLevel,
         profile: opts::Profile,
         build_number: Option<VersionNumber>,
+        settings: &[(String, String)],
     ) -> Result<(), ArchiveError> {
+        let settings = build_settings::args(settings).map_err(ArchiveError::SettingInvalid)?;
         if let Some(build_number) = build_number {
             util::with_working_dir(config.project_dir(), || {
                 bossy::Command::pure_parse("xcrun agvtool new-version -all")
@@ -328,11 +409,11 @@ impl<'a> Target<'a> {
             })
             .map_err(ArchiveError::SetVersionFailed)?;
         }
-        let configuration = profile.as_str();
+        let configuration = profile.xcode_configuration();
         let archive_path = config.archive_dir().join(&config.scheme());
-        bossy::Command::pure("xcodebuild")
+        let command = bossy::Command::pure("xcodebuild")
             .with_env_vars(env.explicit_env())
-            .with_args(verbosity(noise_level))
+            .with_args(noise_level.xcodebuild_flag())
             .with_args(&["-scheme", &config.scheme()])
             .with_arg("-workspace")
             .with_arg(&config.workspace_path())
@@ -340,11 +421,13 @@ impl<'a> Target<'a> {
             .with_args(&["-configuration", configuration])
             .with_args(&["-arch", self.arch])
             .with_arg("-allowProvisioningUpdates")
+            .with_env_vars(self.target_dir_env(config))
+            .with_args(self.target_dir_setting(config))
+            .with_args(&settings)
             .with_arg("archive")
             .with_arg("-archivePath")
-            .with_arg(&archive_path)
-            .run_and_wait()
-            .map_err(ArchiveError::ArchiveFailed)?;
+            .with_arg(&archive_path);
+        build_log::run(command).map_err(ArchiveError::ArchiveFailed)?;
         Ok(())
     }
 
@@ -358,18 +441,17 @@ impl<'a> Target<'a> {
         let archive_path = config
             .archive_dir()
             .join(&format!("{}.xcarchive", config.scheme()));
-        bossy::Command::pure("xcodebuild")
+        let command = bossy::Command::pure("xcodebuild")
             .with_env_vars(env.explicit_env())
-            .with_args(verbosity(noise_level))
+            .with_args(noise_level.xcodebuild_flag())
             .with_arg("-exportArchive")
             .with_arg("-archivePath")
             .with_arg(&archive_path)
             .with_arg("-exportOptionsPlist")
             .with_arg(&config.export_plist_path())
             .with_arg("-exportPath")
-            .with_arg(&config.export_dir())
-            .run_and_wait()
-            .map_err(ExportError)?;
+            .with_arg(&config.export_dir());
+        build_log::run(command).map_err(ExportError)?;
         Ok(())
     }
 }
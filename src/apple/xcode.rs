@@ -0,0 +1,119 @@
+use crate::{
+    bossy,
+    util::cli::{Report, Reportable},
+};
+use once_cell_regex::regex;
+use std::{
+    ffi::OsStr,
+    os::unix::ffi::OsStrExt as _,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+
+static COMMAND_LINE_TOOLS_DIR: &str = "/Library/Developer/CommandLineTools";
+
+#[derive(Debug, Error)]
+pub enum XcodeError {
+    #[error("No developer directory is selected, which usually means the Xcode command-line tools aren't installed: {0}")]
+    XcodeSelectFailed(#[source] bossy::Error),
+    #[error("The selected developer directory {developer_dir:?} doesn't exist.")]
+    DeveloperDirMissing { developer_dir: PathBuf },
+    #[error("The selected developer directory {developer_dir:?} belongs to the command-line tools rather than Xcode.")]
+    CommandLineToolsOnly { developer_dir: PathBuf },
+    #[error("Failed to run `xcodebuild -version`: {0}")]
+    XcodebuildFailed(#[source] bossy::Error),
+    #[error("`xcodebuild -version` output didn't contain a version: {output:?}")]
+    VersionMissing { output: String },
+}
+
+impl Reportable for XcodeError {
+    fn report(&self) -> Report {
+        let msg = "Xcode isn't set up correctly";
+        match self {
+            Self::XcodeSelectFailed(_) | Self::DeveloperDirMissing { .. } => {
+                Report::action_request(
+                    msg,
+                    format!(
+                        "{} Run `xcode-select --install` to install them, and then try again.",
+                        self
+                    ),
+                )
+            }
+            Self::CommandLineToolsOnly { .. } => Report::action_request(
+                msg,
+                format!(
+                    "{} If Xcode is installed, run `sudo xcode-select -s /Applications/Xcode.app/Contents/Developer` to select it.",
+                    self
+                ),
+            ),
+            Self::XcodebuildFailed(_) | Self::VersionMissing { .. } => Report::error(msg, self),
+        }
+    }
+}
+
+impl XcodeError {
+    /// Whether installing the command-line tools is the fix.
+    pub fn tools_missing(&self) -> bool {
+        matches!(
+            self,
+            Self::XcodeSelectFailed(_) | Self::DeveloperDirMissing { .. }
+        )
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct XcodeInfo {
+    pub developer_dir: PathBuf,
+    /// Major and minor version, i.e. `(14, 2)`.
+    pub version: (u32, u32),
+    /// i.e. `14C18`
+    pub build: Option<String>,
+}
+
+impl XcodeInfo {
+    pub fn version_string(&self) -> String {
+        format!("{}.{}", self.version.0, self.version.1)
+    }
+}
+
+fn developer_dir() -> Result<PathBuf, XcodeError> {
+    bossy::Command::impure("xcode-select")
+        .with_arg("-p")
+        .run_and_wait_for_output()
+        .map(|output| {
+            let stdout = output.stdout();
+            OsStr::from_bytes(stdout.strip_suffix(b"\n").unwrap_or(stdout)).into()
+        })
+        .map_err(XcodeError::XcodeSelectFailed)
+}
+
+fn parse_version(output: &str) -> Option<((u32, u32), Option<String>)> {
+    let caps = regex!(r"\bXcode (?P<major>\d+)\.(?P<minor>\d+)").captures(output)?;
+    let major = caps["major"].parse().ok()?;
+    let minor = caps["minor"].parse().ok()?;
+    let build = regex!(r"\bBuild version (?P<build>\S+)")
+        .captures(output)
+        .map(|caps| caps["build"].to_owned());
+    Some(((major, minor), build))
+}
+
+/// Checks that Xcode is installed and selected, returning its version and
+/// developer dir.
+pub fn check() -> Result<XcodeInfo, XcodeError> {
+    let developer_dir = developer_dir()?;
+    if !developer_dir.is_dir() {
+        return Err(XcodeError::DeveloperDirMissing { developer_dir });
+    }
+    if developer_dir == Path::new(COMMAND_LINE_TOOLS_DIR) {
+        return Err(XcodeError::CommandLineToolsOnly { developer_dir });
+    }
+    let output = bossy::Command::impure_parse("xcodebuild -version")
+        .run_and_wait_for_string()
+        .map_err(XcodeError::XcodebuildFailed)?;
+    let (version, build) = parse_version(&output).ok_or(XcodeError::VersionMissing { output })?;
+    Ok(XcodeInfo {
+        developer_dir,
+        version,
+        build,
+    })
+}
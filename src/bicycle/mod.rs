@@ -186,6 +186,18 @@ impl Bicycle {
         }
     }
 
+    /// Registers a partial, which templates can then include via
+    /// `{{> name}}`.
+    pub fn register_partial(
+        &mut self,
+        name: &str,
+        template: &str,
+    ) -> Result<(), Box<handlebars::TemplateError>> {
+        self.handlebars
+            .register_partial(name, template)
+            .map_err(Box::new)
+    }
+
     /// Renders a template.
     ///
     /// Use `insert_data` to define any variables needed for the template.
@@ -1,7 +1,7 @@
 #![forbid(unsafe_code)]
 
 use cargo_mobile::{
-    doctor, init, update,
+    build_manifest, config, device, diagnostics, doctor, init, open, os, update,
     util::{
         self,
         cli::{
@@ -62,8 +62,22 @@ pub enum Command {
         )]
         directory: PathBuf,
     },
-    #[structopt(name = "open", about = "Open project in default code editor")]
-    Open,
+    #[structopt(
+        name = "open",
+        about = "Open project in the tools listed in `open.tools`, or your default code editor"
+    )]
+    Open {
+        #[structopt(
+            long = "print-command",
+            help = "Print the shell command that would open the project in your editor, rather than running it"
+        )]
+        print_command: bool,
+    },
+    #[structopt(
+        name = "open-output",
+        about = "Reveal the most recently built artifact"
+    )]
+    OpenOutput,
     #[structopt(name = "update", about = "Update `cargo-mobile`")]
     Update {
         #[structopt(long = "init", help = "Regenerate project if update succeeds")]
@@ -87,7 +101,23 @@ pub enum Command {
         name = "doctor",
         about = "Perform a check-up on your installation and environment"
     )]
-    Doctor,
+    Doctor {
+        #[structopt(
+            long = "check-editor",
+            help = "Only check that your default code editor can be launched, without opening anything"
+        )]
+        check_editor: bool,
+    },
+    #[structopt(
+        name = "diagnostics",
+        about = "Gather diagnostics into a single file to attach to bug reports"
+    )]
+    Diagnostics,
+    #[structopt(
+        name = "shutdown-emulators",
+        about = "Shut down all running Android emulators and iOS simulators"
+    )]
+    ShutdownEmulators,
 }
 
 #[derive(Debug)]
@@ -102,11 +132,19 @@ pub enum Error {
         source: std::io::Error,
     },
     OpenFailed(util::OpenInEditorError),
+    ConfigFailed(config::LoadOrGenError),
+    OpenOutputFailed(build_manifest::OpenLatestError),
     UpdateFailed(update::Error),
     #[cfg(target_os = "macos")]
     AppleFailed(cargo_mobile::apple::cli::Error),
     AndroidFailed(cargo_mobile::android::cli::Error),
     DoctorFailed(doctor::Unrecoverable),
+    DiagnosticsFailed(diagnostics::Error),
+    EditorDetectionFailed(os::DetectEditorError),
+    EditorInvalid(os::OpenFileError),
+    ShutdownIncomplete {
+        failed: usize,
+    },
 }
 
 impl Reportable for Error {
@@ -123,11 +161,24 @@ impl Reportable for Error {
             Self::OpenFailed(err) => {
                 Report::error("Failed to open project in default code editor", err)
             }
+            Self::ConfigFailed(err) => err.report(),
+            Self::OpenOutputFailed(err) => err.report(),
             Self::UpdateFailed(err) => Report::error("Failed to update `cargo-mobile`", err),
             #[cfg(target_os = "macos")]
             Self::AppleFailed(err) => err.report(),
             Self::AndroidFailed(err) => err.report(),
             Self::DoctorFailed(err) => Report::error("Failed to run doctor", err),
+            Self::DiagnosticsFailed(err) => err.report(),
+            Self::EditorDetectionFailed(err) => {
+                Report::error("Failed to detect default code editor", err)
+            }
+            Self::EditorInvalid(err) => {
+                Report::error("Your default code editor can't be launched", err)
+            }
+            Self::ShutdownIncomplete { failed } => Report::error(
+                "Failed to shut down every emulator and simulator",
+                format!("{} failure(s) are listed above.", failed),
+            ),
         }
     }
 }
@@ -188,7 +239,48 @@ impl Exec for Input {
                 .map(|_| ())
                 .map_err(Error::InitFailed)
             }
-            Command::Open => util::open_in_editor(".").map_err(Error::OpenFailed),
+            Command::Open { print_command } => {
+                if print_command {
+                    let root = config::Raw::discover_root(".")
+                        .ok()
+                        .flatten()
+                        .unwrap_or_else(|| ".".into());
+                    let root = dunce::canonicalize(&root).unwrap_or(root);
+                    println!(
+                        "{}",
+                        os::open_command_line(
+                            &root,
+                            &config::editor::discover_allowed_pagers(&root)
+                        )
+                        .map_err(Error::OpenFailed)?
+                    );
+                    return Ok(());
+                }
+                // Without a config, there's no `open.tools`, and nothing to
+                // open besides the current dir.
+                if config::Raw::discover_root(".").ok().flatten().is_none() {
+                    // There's no config, so no pagers are allowed either.
+                    return util::open_in_editor(".", &[]).map_err(Error::OpenFailed);
+                }
+                let (config, _origin) = config::Config::load_or_gen(".", non_interactive, wrapper)
+                    .map_err(Error::ConfigFailed)?;
+                for failure in open::open_all(&config) {
+                    failure.report().print(wrapper);
+                }
+                Ok(())
+            }
+            Command::OpenOutput => {
+                let (config, _origin) = config::Config::load_or_gen(".", non_interactive, wrapper)
+                    .map_err(Error::ConfigFailed)?;
+                match build_manifest::open_latest(config.app()).map_err(Error::OpenOutputFailed)? {
+                    Some(path) => println!("Opened {}", path.display()),
+                    None => println!(
+                        "Not opening anything, since opening is disabled by `--no-open` or {}",
+                        os::NO_OPEN_VAR
+                    ),
+                }
+                Ok(())
+            }
             Command::Update { init } => {
                 update::update(wrapper).map_err(Error::UpdateFailed)?;
                 if init {
@@ -212,7 +304,47 @@ impl Exec for Input {
             Command::Android(command) => cargo_mobile::android::cli::Input::new(flags, command)
                 .exec(wrapper)
                 .map_err(Error::AndroidFailed),
-            Command::Doctor => doctor::exec(wrapper).map_err(Error::DoctorFailed),
+            Command::Doctor { check_editor: true } => {
+                let editor =
+                    os::Application::detect_editor(&config::editor::discover_allowed_pagers("."))
+                        .map_err(Error::EditorDetectionFailed)?;
+                editor.validate().map_err(Error::EditorInvalid)?;
+                println!(
+                    "Your default code editor ({}) is good to go.",
+                    editor.exec_string()
+                );
+                Ok(())
+            }
+            Command::Doctor {
+                check_editor: false,
+            } => doctor::exec(wrapper).map_err(Error::DoctorFailed),
+            Command::Diagnostics => {
+                let path = diagnostics::bundle(".").map_err(Error::DiagnosticsFailed)?;
+                println!(
+                    "Wrote diagnostics to {}; please attach it to your bug report, after checking there's nothing in it you'd rather not share.",
+                    path.display()
+                );
+                Ok(())
+            }
+            Command::ShutdownEmulators => {
+                let device::ShutdownAll { stopped, failures } = device::shutdown_all_emulators();
+                if stopped.is_empty() && failures.is_empty() {
+                    println!("No emulators or simulators were running.");
+                }
+                for instance in stopped {
+                    println!("Shut down {}", instance);
+                }
+                for failure in &failures {
+                    failure.report().print(wrapper);
+                }
+                if failures.is_empty() {
+                    Ok(())
+                } else {
+                    Err(Error::ShutdownIncomplete {
+                        failed: failures.len(),
+                    })
+                }
+            }
         }
     }
 }
@@ -12,6 +12,12 @@ pub enum Cause {
     WaitFailed(io::Error),
     CommandFailed(ExitStatus),
     CommandFailedWithOutput(Output),
+    /// The command's output was already streamed to the caller, so this is
+    /// just the tail of it, for figuring out what went wrong.
+    CommandFailedAfterStreaming {
+        status: ExitStatus,
+        tail: String,
+    },
     InvalidUtf8 {
         stream: OutputStream,
         source: std::str::Utf8Error,
@@ -41,10 +47,11 @@ impl Cause {
     }
 
     fn status(&self) -> Option<ExitStatus> {
-        if let Self::CommandFailed(status) = self {
-            Some(*status)
-        } else {
-            self.output().map(|output| output.status())
+        match self {
+            Self::CommandFailed(status) | Self::CommandFailedAfterStreaming { status, .. } => {
+                Some(*status)
+            }
+            _ => self.output().map(|output| output.status()),
         }
     }
 
@@ -90,7 +97,9 @@ impl Display for Error {
                 "Failed to wait for child process for command {:?} to exit: {}",
                 self.command, err
             ),
-            Cause::CommandFailed(status) => command_failed(f, &self.command, *status),
+            Cause::CommandFailed(status) | Cause::CommandFailedAfterStreaming { status, .. } => {
+                command_failed(f, &self.command, *status)
+            }
             Cause::CommandFailedWithOutput(output) => {
                 command_failed(f, &self.command, output.status())?;
                 if !output.stderr().is_empty() {
@@ -200,4 +209,23 @@ impl Error {
     pub fn stderr_str(&self) -> Option<Result<&str>> {
         self.output().map(|output| output.stderr_str())
     }
+
+    /// The I/O error, if the command couldn't be run or waited on at all.
+    pub fn io_error(&self) -> Option<&io::Error> {
+        match &self.cause {
+            Cause::SpawnFailed(err) | Cause::WaitFailed(err) => Some(err),
+            _ => None,
+        }
+    }
+
+    /// The last of what the command printed, if it failed after its output
+    /// was streamed via [`super::Command::run_streaming`]. Stdout and stderr
+    /// are interleaved in the order their lines arrived.
+    pub fn streamed_tail(&self) -> Option<&str> {
+        if let Cause::CommandFailedAfterStreaming { tail, .. } = &self.cause {
+            Some(tail)
+        } else {
+            None
+        }
+    }
 }
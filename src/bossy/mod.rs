@@ -18,11 +18,17 @@ pub use self::{error::*, handle::*, output::*, result::*};
 pub use std::process::{ChildStderr, ChildStdin, ChildStdout, ExitStatus, Stdio};
 
 use std::{
+    collections::VecDeque,
     ffi::OsStr,
     fmt::{self, Display},
     process,
 };
 
+/// How much of a streamed command's output to hold on to for its error if it
+/// fails. Builds can print a lot, and the interesting part is usually near
+/// the end.
+const STREAMED_TAIL_LIMIT: usize = 1 << 20;
+
 /// Build and run commands to your heart's content.
 #[derive(Debug)]
 pub struct Command {
@@ -235,6 +241,18 @@ impl Command {
         self
     }
 
+    pub fn set_current_dir(&mut self, dir: impl AsRef<std::path::Path>) -> &mut Self {
+        let dir = dir.as_ref();
+        log::debug!("setting cwd to {:?} for command {:?}", dir, self.display);
+        self.inner.current_dir(dir);
+        self
+    }
+
+    pub fn with_current_dir(mut self, dir: impl AsRef<std::path::Path>) -> Self {
+        self.set_current_dir(dir);
+        self
+    }
+
     pub fn add_arg(&mut self, name: impl AsRef<OsStr>) -> &mut Self {
         let name = name.as_ref();
         log::debug!("adding arg {:?} to command {:?}", name, self.display);
@@ -273,6 +291,55 @@ impl Command {
         self
     }
 
+    /// Lower (or raise, if you're privileged) the scheduling priority of the
+    /// spawned process. `nice` is passed to `setpriority`, and `ionice` is a
+    /// best-effort IO priority level from 0 (highest) to 7 (lowest), which is
+    /// only supported on Linux and is otherwise ignored.
+    #[cfg(unix)]
+    pub fn set_priority(&mut self, nice: Option<i32>, ionice: Option<u8>) -> &mut Self {
+        use std::os::unix::process::CommandExt as _;
+
+        if nice.is_none() && ionice.is_none() {
+            return self;
+        }
+        log::debug!(
+            "setting priority of command {:?} to nice {:?}, ionice {:?}",
+            self.display,
+            nice,
+            ionice
+        );
+        unsafe {
+            self.inner.pre_exec(move || {
+                if let Some(nice) = nice {
+                    if libc::setpriority(libc::PRIO_PROCESS, 0, nice) == -1 {
+                        return Err(std::io::Error::last_os_error());
+                    }
+                }
+                #[cfg(target_os = "linux")]
+                if let Some(ionice) = ionice {
+                    const IOPRIO_WHO_PROCESS: libc::c_int = 1;
+                    const IOPRIO_CLASS_BE: libc::c_int = 2;
+                    const IOPRIO_CLASS_SHIFT: libc::c_int = 13;
+                    let ioprio =
+                        (IOPRIO_CLASS_BE << IOPRIO_CLASS_SHIFT) | libc::c_int::from(ionice.min(7));
+                    // Failing to set IO priority isn't worth failing the
+                    // launch over, since not all kernels/schedulers support it.
+                    libc::syscall(libc::SYS_ioprio_set, IOPRIO_WHO_PROCESS, 0, ioprio);
+                }
+                #[cfg(not(target_os = "linux"))]
+                let _ = ionice;
+                Ok(())
+            });
+        }
+        self
+    }
+
+    #[cfg(unix)]
+    pub fn with_priority(mut self, nice: Option<i32>, ionice: Option<u8>) -> Self {
+        self.set_priority(nice, ionice);
+        self
+    }
+
     fn run_inner(&mut self) -> Result<Handle> {
         Error::from_child_result(self.display.clone(), self.inner.spawn())
     }
@@ -343,6 +410,111 @@ impl Command {
         self.run_inner()?.wait()
     }
 
+    /// Run the command and block until it exits, calling `on_line` with each
+    /// line of its output as it arrives, along with which stream it came from.
+    /// The two streams are read separately, so lines from stdout and stderr
+    /// that were written at nearly the same time might arrive out of order.
+    /// Invalid UTF-8 is replaced rather than treated as an error, since
+    /// there's no sensible way to recover mid-stream. If the command fails, the
+    /// end of its output is kept in the error; see [`Error::streamed_tail`].
+    pub fn run_streaming(
+        &mut self,
+        mut on_line: impl FnMut(OutputStream, &str),
+    ) -> Result<ExitStatus> {
+        use std::{
+            io::{BufRead as _, BufReader, Read},
+            sync::mpsc,
+            thread,
+        };
+
+        fn forward(
+            display: String,
+            stream: OutputStream,
+            reader: impl Read,
+            tx: mpsc::Sender<(OutputStream, String)>,
+        ) {
+            let mut reader = BufReader::new(reader);
+            let mut buf = Vec::new();
+            loop {
+                buf.clear();
+                match reader.read_until(b'\n', &mut buf) {
+                    Ok(0) => break,
+                    Ok(_) => {
+                        let line = String::from_utf8_lossy(&buf);
+                        let line = line.trim_end_matches(&['\r', '\n'][..]).to_owned();
+                        if tx.send((stream, line)).is_err() {
+                            break;
+                        }
+                    }
+                    Err(err) => {
+                        log::error!(
+                            "failed to read {} of command {:?}: {}",
+                            stream,
+                            display,
+                            err
+                        );
+                        break;
+                    }
+                }
+            }
+        }
+
+        log::info!("running command {:?} and streaming output", self.display);
+        let spawn_failed = |err| Error {
+            command: self.display.clone(),
+            cause: Cause::SpawnFailed(err),
+        };
+        let (stdout_reader, stdout_writer) = os_pipe::pipe().map_err(spawn_failed)?;
+        let (stderr_reader, stderr_writer) = os_pipe::pipe().map_err(spawn_failed)?;
+        self.set_stdout(stdout_writer).set_stderr(stderr_writer);
+        let handle = self.run_inner();
+        // Our `Command` still holds the write ends of the pipes, so we have to
+        // drop them, or we'd never see EOF.
+        self.set_stdout_null().set_stderr_null();
+        let handle = handle?;
+        let (tx, rx) = mpsc::channel();
+        let readers = vec![
+            (OutputStream::Out, stdout_reader),
+            (OutputStream::Err, stderr_reader),
+        ]
+        .into_iter()
+        .map(|(stream, reader)| {
+            let display = self.display.clone();
+            let tx = tx.clone();
+            thread::spawn(move || forward(display, stream, reader, tx))
+        })
+        .collect::<Vec<_>>();
+        // Otherwise the channel would stay open after both readers are done.
+        drop(tx);
+        let mut tail = VecDeque::new();
+        let mut tail_len = 0;
+        for (stream, line) in rx {
+            on_line(stream, &line);
+            tail_len += line.len() + 1;
+            tail.push_back(line);
+            while tail_len > STREAMED_TAIL_LIMIT {
+                if let Some(dropped) = tail.pop_front() {
+                    tail_len -= dropped.len() + 1;
+                }
+            }
+        }
+        for reader in readers {
+            if reader.join().is_err() {
+                log::error!("reader for command {:?} panicked", self.display);
+            }
+        }
+        handle.wait().map_err(|err| match err.cause {
+            Cause::CommandFailed(status) => Error {
+                command: err.command,
+                cause: Cause::CommandFailedAfterStreaming {
+                    status,
+                    tail: Vec::from(tail).join("\n"),
+                },
+            },
+            _ => err,
+        })
+    }
+
     /// Run the command and block until its output is collected. This will
     /// automatically set stdout and stderr to use [`Stdio::piped`], so if you
     /// don't want that to happen, then you're screwed.
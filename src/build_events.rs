@@ -0,0 +1,140 @@
+use crate::{build_log, build_manifest::Artifact};
+use std::sync::mpsc::{self, Receiver, Sender};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Phase {
+    /// Running `app.pre-build`.
+    PreBuild,
+    /// Compiling the Rust lib for a target.
+    Compile,
+    /// Running `app.post-build`.
+    PostBuild,
+    /// Having the native build system produce an APK, AAB, or app.
+    Package,
+    /// Archiving via `xcodebuild archive`.
+    Archive,
+}
+
+impl Phase {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::PreBuild => "pre-build",
+            Self::Compile => "compile",
+            Self::PostBuild => "post-build",
+            Self::Package => "package",
+            Self::Archive => "archive",
+        }
+    }
+}
+
+/// Something that happened during a build, in enough detail to drive a UI.
+#[derive(Clone, Debug)]
+pub enum BuildEvent {
+    PhaseStarted {
+        phase: Phase,
+        /// The Rust target triple this phase is for, if it's specific to one.
+        triple: Option<String>,
+    },
+    TargetBuilt {
+        triple: String,
+    },
+    ArtifactProduced(Artifact),
+    Warning {
+        message: String,
+    },
+    Finished {
+        success: bool,
+    },
+}
+
+fn print(event: &BuildEvent) {
+    match event {
+        BuildEvent::PhaseStarted {
+            phase: Phase::Compile | Phase::Package,
+            triple: Some(triple),
+        } => println!("Building {}...", triple),
+        BuildEvent::PhaseStarted {
+            phase: Phase::Archive,
+            triple: Some(triple),
+        } => println!("Archiving {}...", triple),
+        BuildEvent::TargetBuilt { triple } => println!("Finished building {}", triple),
+        // Warnings are already logged, and whatever drove the build reports
+        // how it went.
+        _ => (),
+    }
+}
+
+/// Sends [`BuildEvent`]s to whoever's listening, if anyone. The default
+/// emitter goes nowhere; the CLI uses [`Emitter::terminal`].
+///
+/// `apk::build` and `aab::build` emit [`BuildEvent::Finished`] themselves;
+/// when driving a target's `build` directly, call [`Emitter::finish`] once
+/// everything's done.
+#[derive(Clone, Debug, Default)]
+pub struct Emitter {
+    tx: Option<Sender<BuildEvent>>,
+    /// Whether to print per-target progress as it happens.
+    print: bool,
+}
+
+impl Emitter {
+    pub fn channel() -> (Self, Receiver<BuildEvent>) {
+        let (tx, rx) = mpsc::channel();
+        (
+            Self {
+                tx: Some(tx),
+                print: false,
+            },
+            rx,
+        )
+    }
+
+    /// Prints which target is being built as it happens. Gradle builds don't
+    /// need this, since the `cargo android build` that Gradle runs for each
+    /// target uses it itself.
+    pub fn terminal() -> Self {
+        Self {
+            tx: None,
+            print: true,
+        }
+    }
+
+    pub fn emit(&self, event: BuildEvent) {
+        if self.print {
+            print(&event);
+        }
+        if let Some(tx) = &self.tx {
+            // If the receiver hung up, they don't care anymore, which isn't
+            // a reason to stop building.
+            let _ = tx.send(event);
+        }
+    }
+
+    pub fn phase_started(&self, phase: Phase, triple: Option<&str>) {
+        build_log::phase(phase, triple);
+        self.emit(BuildEvent::PhaseStarted {
+            phase,
+            triple: triple.map(ToOwned::to_owned),
+        })
+    }
+
+    pub fn target_built(&self, triple: &str) {
+        self.emit(BuildEvent::TargetBuilt {
+            triple: triple.to_owned(),
+        })
+    }
+
+    pub fn warning(&self, message: impl Into<String>) {
+        self.emit(BuildEvent::Warning {
+            message: message.into(),
+        })
+    }
+
+    /// Emits [`BuildEvent::Finished`] for `result`, passing it through.
+    pub fn finish<T, E>(&self, result: Result<T, E>) -> Result<T, E> {
+        self.emit(BuildEvent::Finished {
+            success: result.is_ok(),
+        });
+        result
+    }
+}
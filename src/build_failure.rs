@@ -0,0 +1,336 @@
+use crate::{bossy, util::cli::Report};
+use once_cell_regex::{exports::regex::Regex, regex};
+use std::fmt::{self, Display};
+
+/// The toolchain component a build failure came from.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FailurePhase {
+    /// Compiling Rust code.
+    Cargo,
+    /// Linking, whether driven by cargo or by the native build.
+    Link,
+    Gradle,
+    /// Codesigning, on either platform.
+    Sign,
+    Xcode,
+}
+
+impl FailurePhase {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Cargo => "compiling",
+            Self::Link => "linking",
+            Self::Gradle => "gradle",
+            Self::Sign => "codesigning",
+            Self::Xcode => "xcodebuild",
+        }
+    }
+}
+
+impl Display for FailurePhase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// What went wrong in a failed build, as best we can tell from its output.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BuildFailure {
+    pub phase: FailurePhase,
+    pub detail: String,
+}
+
+impl Display for BuildFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} failed: {}", self.phase, self.detail)
+    }
+}
+
+struct Rule {
+    phase: FailurePhase,
+    pattern: fn() -> &'static Regex,
+    /// Put in front of the detail, which is the `detail` capture group if the
+    /// pattern has one, or the whole match otherwise.
+    prefix: Option<&'static str>,
+}
+
+impl Rule {
+    fn classify(&self, output: &str) -> Option<BuildFailure> {
+        let caps = (self.pattern)().captures(output)?;
+        let detail = caps
+            .name("detail")
+            .unwrap_or_else(|| caps.get(0).unwrap())
+            .as_str()
+            .trim();
+        Some(BuildFailure {
+            phase: self.phase,
+            detail: match self.prefix {
+                Some(prefix) => format!("{}{}", prefix, detail),
+                None => detail.to_owned(),
+            },
+        })
+    }
+}
+
+// Ordered from most to least specific, since i.e. a link failure also makes
+// cargo fail, and a cargo failure also makes gradle fail; the root cause is
+// whichever's deepest.
+static RULES: &[Rule] = &[
+    Rule {
+        phase: FailurePhase::Sign,
+        pattern: || {
+            regex!(
+                r#"(?m)(?P<detail>No signing certificate .*|Signing for .* requires a development team.*|.* requires a provisioning profile.*|No profiles for .* were found.*|errSecInternalComponent)"#
+            )
+        },
+        prefix: None,
+    },
+    Rule {
+        phase: FailurePhase::Sign,
+        pattern: || {
+            regex!(
+                r"(?m)(?P<detail>Execution failed for task '[^']*[sS]ign[^']*'.*|Keystore was tampered with.*|Failed to read key .* from store.*)$"
+            )
+        },
+        prefix: None,
+    },
+    Rule {
+        phase: FailurePhase::Link,
+        pattern: || regex!(r#"Undefined symbols for architecture \S+:\s*"(?P<detail>[^"]+)""#),
+        prefix: Some("undefined symbol "),
+    },
+    Rule {
+        phase: FailurePhase::Link,
+        pattern: || {
+            regex!(
+                r"(?m)(?P<detail>undefined (?:symbol|reference to)[: ].*|ld: library not found for .*|cannot find -l\S+)$"
+            )
+        },
+        prefix: None,
+    },
+    Rule {
+        phase: FailurePhase::Link,
+        pattern: || regex!(r"(?m)^error: linking with `[^`]+` failed.*$"),
+        prefix: None,
+    },
+    // A bare `error: ` is printed by plenty besides cargo (i.e. xcodebuild),
+    // so these only match what's unmistakably rustc or cargo: an error code,
+    // a source location, or cargo's own summary.
+    Rule {
+        phase: FailurePhase::Cargo,
+        pattern: || regex!(r"(?m)^error\[E\d+\]: (?P<detail>.*)$"),
+        prefix: None,
+    },
+    Rule {
+        phase: FailurePhase::Cargo,
+        pattern: || regex!(r"(?m)^error: (?P<detail>.*)\n\s*--> "),
+        prefix: None,
+    },
+    Rule {
+        phase: FailurePhase::Cargo,
+        pattern: || {
+            regex!(
+                r"(?m)^error: (?P<detail>(?:could not compile|failed to run custom build command for) .*)$"
+            )
+        },
+        prefix: None,
+    },
+    Rule {
+        phase: FailurePhase::Gradle,
+        pattern: || regex!(r"\* What went wrong:\s*(?P<detail>.*)"),
+        prefix: None,
+    },
+    Rule {
+        phase: FailurePhase::Gradle,
+        pattern: || regex!(r"(?m)^FAILURE: (?P<detail>.*)$"),
+        prefix: None,
+    },
+    Rule {
+        phase: FailurePhase::Xcode,
+        pattern: || regex!(r"(?m)^(?:xcodebuild: )?error: (?P<detail>.*)$"),
+        prefix: None,
+    },
+    Rule {
+        phase: FailurePhase::Xcode,
+        pattern: || regex!(r"\*\* (?P<detail>(?:BUILD|ARCHIVE) FAILED) \*\*"),
+        prefix: None,
+    },
+];
+
+/// Figures out which toolchain component a failed build's `output` blames,
+/// based on the errors each is known to print. Returns `None` if nothing
+/// recognizable was printed.
+pub fn classify(output: &str) -> Option<BuildFailure> {
+    RULES.iter().find_map(|rule| rule.classify(output))
+}
+
+/// Like [`classify`], using whatever output `err` captured, which for builds
+/// streamed via [`bossy::Command::run_streaming`] (i.e. by
+/// [`crate::build_log::run`] when the build's being logged) is the end of
+/// what they printed.
+pub fn classify_error(err: &bossy::Error) -> Option<BuildFailure> {
+    if let Some(tail) = err.streamed_tail() {
+        return classify(tail);
+    }
+    let stderr = err.stderr_str().and_then(Result::ok).unwrap_or_default();
+    let stdout = err.stdout_str().and_then(Result::ok).unwrap_or_default();
+    classify(stderr).or_else(|| classify(stdout))
+}
+
+/// An error report for a failed build, headed by what we could tell about the
+/// failure, or `msg` if we couldn't tell anything.
+pub fn report(msg: &str, err: &bossy::Error) -> Report {
+    match classify_error(err) {
+        Some(failure) => Report::error(format!("{} ({})", msg, failure), err),
+        None => Report::error(msg, err),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest(
+        output,
+        phase,
+        detail,
+        case(
+            r#"   Compiling hello v0.1.0 (/tmp/hello)
+error[E0425]: cannot find value `greeting` in this scope
+ --> src/lib.rs:3:20
+  |
+3 |     println!("{}", greeting);
+  |                    ^^^^^^^^ not found in this scope
+
+error: could not compile `hello` due to previous error
+"#,
+            FailurePhase::Cargo,
+            "cannot find value `greeting` in this scope"
+        ),
+        case(
+            r#"error: linking with `cc` failed: exit status: 1
+  |
+  = note: ld.lld: error: undefined symbol: ANativeActivity_onCreate
+          >>> referenced by lib.rs:12
+"#,
+            FailurePhase::Link,
+            "undefined symbol: ANativeActivity_onCreate"
+        ),
+        case(
+            r#"Undefined symbols for architecture arm64:
+  "_start_app", referenced from:
+      -[AppDelegate application:didFinishLaunchingWithOptions:] in AppDelegate.o
+ld: symbol(s) not found for architecture arm64
+** BUILD FAILED **
+"#,
+            FailurePhase::Link,
+            "undefined symbol _start_app"
+        ),
+        case(
+            "ld: library not found for -lhello\nclang: error: linker command failed with exit code 1\n",
+            FailurePhase::Link,
+            "ld: library not found for -lhello"
+        ),
+        case(
+            r#"
+FAILURE: Build failed with an exception.
+
+* What went wrong:
+Could not determine the dependencies of task ':app:compileArm64DebugJavaWithJavac'.
+
+* Try:
+Run with --stacktrace option to get the stack trace.
+"#,
+            FailurePhase::Gradle,
+            "Could not determine the dependencies of task ':app:compileArm64DebugJavaWithJavac'."
+        ),
+        case(
+            r#"* What went wrong:
+Execution failed for task ':app:validateSigningRelease'.
+> Keystore file '/home/me/release.jks' not found for signing config 'release'.
+"#,
+            FailurePhase::Sign,
+            "Execution failed for task ':app:validateSigningRelease'."
+        ),
+        case(
+            r#"error: No signing certificate "iOS Development" found: No "iOS Development" signing certificate matching team ID "ABCDE12345" with a private key was found. (in target 'hello_iOS' from project 'hello')
+** BUILD FAILED **
+"#,
+            FailurePhase::Sign,
+            r#"No signing certificate "iOS Development" found: No "iOS Development" signing certificate matching team ID "ABCDE12345" with a private key was found. (in target 'hello_iOS' from project 'hello')"#
+        ),
+        case(
+            "xcodebuild: error: Unable to find a destination matching the provided destination specifier\n",
+            FailurePhase::Xcode,
+            "Unable to find a destination matching the provided destination specifier"
+        ),
+        case(
+            "The following build commands failed:\n\tCompileSwift normal arm64\n** ARCHIVE FAILED **\n",
+            FailurePhase::Xcode,
+            "ARCHIVE FAILED"
+        ),
+        case(
+            r#"error: unused variable: `unused`
+ --> src/lib.rs:2:9
+  |
+2 |     let unused = 1;
+  |         ^^^^^^ help: if this is intentional, prefix it with an underscore: `_unused`
+"#,
+            FailurePhase::Cargo,
+            "unused variable: `unused`"
+        ),
+        case(
+            "error: failed to run custom build command for `openssl-sys v0.9.75`\n",
+            FailurePhase::Cargo,
+            "failed to run custom build command for `openssl-sys v0.9.75`"
+        ),
+        case(
+            "error: Multiple commands produce '/Users/me/Library/Developer/Xcode/DerivedData/hello/Build/Products/Debug-iphoneos/hello.app/Info.plist'\n** BUILD FAILED **\n",
+            FailurePhase::Xcode,
+            "Multiple commands produce '/Users/me/Library/Developer/Xcode/DerivedData/hello/Build/Products/Debug-iphoneos/hello.app/Info.plist'"
+        )
+    )]
+    fn classifies_captured_failures(output: &str, phase: FailurePhase, detail: &str) {
+        assert_eq!(
+            classify(output),
+            Some(BuildFailure {
+                phase,
+                detail: detail.to_owned()
+            })
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn classifies_streamed_failures() {
+        let err = bossy::Command::impure("sh")
+            .with_args([
+                "-c",
+                "echo 'Compiling hello'; echo 'error[E0425]: cannot find value `x` in this scope' >&2; exit 101",
+            ])
+            .run_streaming(|_stream, _line| ())
+            .unwrap_err();
+        assert_eq!(
+            classify_error(&err),
+            Some(BuildFailure {
+                phase: FailurePhase::Cargo,
+                detail: "cannot find value `x` in this scope".to_owned(),
+            })
+        );
+    }
+
+    #[test]
+    fn unrecognized_output_is_unclassified() {
+        assert_eq!(classify("Everything is fine.\n"), None);
+    }
+
+    #[test]
+    fn displays_phase_and_detail() {
+        let failure = BuildFailure {
+            phase: FailurePhase::Link,
+            detail: "undefined symbol: X".to_owned(),
+        };
+        assert_eq!(failure.to_string(), "linking failed: undefined symbol: X");
+    }
+}
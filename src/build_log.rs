@@ -0,0 +1,251 @@
+use crate::{bossy, build_events::Phase, config, os, util::NoHomeDir};
+use std::{
+    fs::{self, File},
+    io::{self, Write as _},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum StartError {
+    #[error(transparent)]
+    NoHomeDir(#[from] NoHomeDir),
+    #[error("Failed to create build log dir {dir:?}: {cause}")]
+    DirCreationFailed {
+        dir: PathBuf,
+        #[source]
+        cause: io::Error,
+    },
+    #[error("Failed to create build log {path:?}: {cause}")]
+    CreateFailed {
+        path: PathBuf,
+        #[source]
+        cause: io::Error,
+    },
+}
+
+fn stamp(elapsed: Duration) -> String {
+    format!("[{:>4}.{:03}s]", elapsed.as_secs(), elapsed.subsec_millis())
+}
+
+#[derive(Debug)]
+struct Log {
+    path: PathBuf,
+    file: File,
+    started: Instant,
+}
+
+impl Log {
+    fn create(dir: &Path) -> Result<Self, StartError> {
+        fs::create_dir_all(dir).map_err(|cause| StartError::DirCreationFailed {
+            dir: dir.to_owned(),
+            cause,
+        })?;
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|since| since.as_secs())
+            .unwrap_or_default();
+        let path = dir.join(format!("build-{}-{}.log", timestamp, std::process::id()));
+        let mut file = File::create(&path).map_err(|cause| StartError::CreateFailed {
+            path: path.clone(),
+            cause,
+        })?;
+        let args = std::env::args().collect::<Vec<_>>().join(" ");
+        // If we can't write the header, we'll find out on the next line.
+        let _ = writeln!(file, "# {} (started at {})", args, timestamp);
+        Ok(Self {
+            path,
+            file,
+            started: Instant::now(),
+        })
+    }
+
+    fn write_line(&mut self, text: &str) {
+        if let Err(err) = writeln!(self.file, "{} {}", stamp(self.started.elapsed()), text) {
+            log::warn!("failed to write to build log {:?}: {}", self.path, err);
+        }
+    }
+}
+
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+
+static ACTIVE: Mutex<Option<Log>> = Mutex::new(None);
+
+fn with_active(f: impl FnOnce(&mut Log)) {
+    let mut active = ACTIVE.lock().unwrap_or_else(|err| err.into_inner());
+    if let Some(log) = active.as_mut() {
+        f(log)
+    }
+}
+
+/// Asks for builds to be logged, which is what `--build-log` does. Nothing's
+/// logged until [`start`] knows which project the log belongs to.
+pub fn request() {
+    REQUESTED.store(true, Ordering::Relaxed);
+}
+
+/// Starts a combined log of everything the build runs and prints, under
+/// `project_dir`'s cache dir, if one was [`request`]ed. Logging is only a
+/// convenience, so failing to start it just gets a warning.
+pub fn start(project_dir: &Path) {
+    if !REQUESTED.load(Ordering::Relaxed) {
+        return;
+    }
+    let mut active = ACTIVE.lock().unwrap_or_else(|err| err.into_inner());
+    if active.is_some() {
+        return;
+    }
+    match config::cache_dir(project_dir)
+        .map_err(StartError::from)
+        .and_then(|dir| Log::create(&dir.join("logs")))
+    {
+        Ok(log) => {
+            log::info!("logging build to {:?}", log.path);
+            *active = Some(log);
+        }
+        Err(err) => log::warn!("not logging build: {}", err),
+    }
+}
+
+/// Where the build's being logged to, if it is.
+pub fn path() -> Option<PathBuf> {
+    ACTIVE
+        .lock()
+        .unwrap_or_else(|err| err.into_inner())
+        .as_ref()
+        .map(|log| log.path.clone())
+}
+
+/// Adds a line of output to the log, if there is one.
+pub fn line(text: &str) {
+    with_active(|log| log.write_line(text))
+}
+
+pub fn phase(phase: Phase, triple: Option<&str>) {
+    with_active(|log| match triple {
+        Some(triple) => log.write_line(&format!("=== {} ({})", phase.as_str(), triple)),
+        None => log.write_line(&format!("=== {}", phase.as_str())),
+    })
+}
+
+/// Runs `command` like [`bossy::Command::run_and_wait`]. When the build's
+/// being logged, its output is streamed through us instead, to the terminal
+/// and into the log, so tools that check whether they're writing to a
+/// terminal will see that they aren't, and might print less color or progress
+/// than usual. That also means that if it fails, the error has the end of the
+/// output for [`crate::build_failure`] to go on.
+pub fn run(command: bossy::Command) -> bossy::Result<bossy::ExitStatus> {
+    run_labelled(command, None)
+}
+
+/// Like [`run`], but when there's a `label`, the output is always streamed
+/// through us, with each line starting with the label. That's for commands
+/// running alongside each other, whose output would otherwise be impossible
+/// to untangle.
+pub fn run_labelled(
+    mut command: bossy::Command,
+    label: Option<&str>,
+) -> bossy::Result<bossy::ExitStatus> {
+    if label.is_none() && path().is_none() {
+        return command.run_and_wait();
+    }
+    let label = |text: &str| match label {
+        Some(label) => format!("[{}] {}", label, text),
+        None => text.to_owned(),
+    };
+    line(&label(&format!("$ {}", command.display())));
+    let result = command.run_streaming(|stream, output| {
+        let output = label(output);
+        match stream {
+            bossy::OutputStream::Out => println!("{}", output),
+            bossy::OutputStream::Err => eprintln!("{}", output),
+        }
+        line(&output);
+    });
+    match &result {
+        Ok(status) => line(&label(&format!("exited with {}", status))),
+        Err(err) => line(&label(&format!("failed: {}", err))),
+    }
+    result
+}
+
+/// Closes out the log. If the build failed, we print where the log is, and
+/// offer it up in the user's editor if `open` is set.
+pub fn finish(success: bool, open: bool) {
+    let path = match ACTIVE.lock().unwrap_or_else(|err| err.into_inner()).take() {
+        Some(mut log) => {
+            log.write_line(if success {
+                "build succeeded"
+            } else {
+                "build failed"
+            });
+            log.path
+        }
+        None => return,
+    };
+    if success {
+        return;
+    }
+    println!("Full build log: {}", path.display());
+    if open {
+        match os::try_open_files(&[path.as_path()], None, &Default::default()) {
+            os::OpenOutcome::Opened(_) => (),
+            os::OpenOutcome::Skipped(reason) => {
+                log::info!("not opening build log, since {}", reason)
+            }
+            os::OpenOutcome::Failed(err) => log::warn!("failed to open build log: {}", err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest(
+        elapsed,
+        expected,
+        case(Duration::from_millis(0), "[   0.000s]"),
+        case(Duration::from_millis(1_234), "[   1.234s]"),
+        case(Duration::from_secs(12_345), "[12345.000s]")
+    )]
+    fn stamps_elapsed_time(elapsed: Duration, expected: &str) {
+        assert_eq!(stamp(elapsed), expected);
+    }
+
+    #[test]
+    fn writes_stamped_lines() {
+        let dir = crate::util::temp_dir().join("build-log-test");
+        let mut log = Log::create(&dir).unwrap();
+        log.write_line("$ cargo build");
+        log.write_line("error: oh no");
+        let contents = fs::read_to_string(&log.path).unwrap();
+        fs::remove_file(&log.path).unwrap();
+        let lines = contents.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("# "));
+        assert!(lines[1].ends_with("] $ cargo build"));
+        assert!(lines[2].ends_with("] error: oh no"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn streams_keep_their_lines() {
+        let mut lines = Vec::new();
+        bossy::Command::impure("sh")
+            .with_args(["-c", "echo out; echo err >&2"])
+            .run_streaming(|stream, line| lines.push((stream.as_str(), line.to_owned())))
+            .unwrap();
+        lines.sort();
+        assert_eq!(
+            lines,
+            vec![("stderr", "err".to_owned()), ("stdout", "out".to_owned())]
+        );
+    }
+}
@@ -0,0 +1,475 @@
+use crate::{
+    config::app::App,
+    opts::Profile,
+    os,
+    util::cli::{Report, Reportable},
+};
+use once_cell_regex::regex;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::hash_map::DefaultHasher,
+    ffi::OsStr,
+    fs,
+    hash::{Hash, Hasher},
+    io,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use thiserror::Error;
+
+static FILE_NAME: &str = "build-manifest.toml";
+
+#[derive(Debug, Error)]
+pub enum LoadError {
+    #[error("Failed to read build manifest from {path}: {cause}")]
+    ReadFailed { path: PathBuf, cause: io::Error },
+    #[error("Failed to deserialize build manifest at {path}: {cause}")]
+    DeserializeFailed {
+        path: PathBuf,
+        cause: toml::de::Error,
+    },
+}
+
+impl Reportable for LoadError {
+    fn report(&self) -> Report {
+        Report::error("Failed to load build manifest", self)
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum WriteError {
+    #[error("Failed to serialize build manifest: {0}")]
+    SerializeFailed(toml::ser::Error),
+    #[error("Failed to create build manifest directory at {path}: {cause}")]
+    DirCreationFailed { path: PathBuf, cause: io::Error },
+    #[error("Failed to write build manifest to {path}: {cause}")]
+    WriteFailed { path: PathBuf, cause: io::Error },
+}
+
+impl Reportable for WriteError {
+    fn report(&self) -> Report {
+        Report::error("Failed to write build manifest", self)
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum OpenLatestError {
+    #[error(transparent)]
+    LoadFailed(LoadError),
+    #[error("Nothing has been built yet.")]
+    NothingBuilt,
+    #[error("The most recently built artifact {path:?} no longer exists.")]
+    ArtifactMissing { path: PathBuf },
+    #[error("Failed to open {path:?}: {cause}")]
+    OpenFailed {
+        path: PathBuf,
+        cause: Box<os::OpenFileError>,
+    },
+}
+
+impl Reportable for OpenLatestError {
+    fn report(&self) -> Report {
+        match self {
+            Self::LoadFailed(err) => err.report(),
+            Self::NothingBuilt | Self::ArtifactMissing { .. } => Report::action_request(
+                "No artifact to open",
+                format!(
+                    "{} Build an APK, AAB, or archive first, and then try again.",
+                    self
+                ),
+            ),
+            Self::OpenFailed { .. } => Report::error("Failed to open artifact", self),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ArtifactKind {
+    Apk,
+    Aab,
+    XcArchive,
+    Ipa,
+}
+
+impl ArtifactKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Apk => "APK",
+            Self::Aab => "AAB",
+            Self::XcArchive => "archive",
+            Self::Ipa => "IPA",
+        }
+    }
+}
+
+/// What an artifact was built from, so later builds can tell whether there's
+/// anything new to build.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Inputs {
+    /// What the native build was asked to do, i.e. its args.
+    pub request: Vec<String>,
+    /// From [`fingerprint`], taken before the build started.
+    pub fingerprint: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Artifact {
+    pub path: PathBuf,
+    pub kind: ArtifactKind,
+    pub profile: String,
+    /// Seconds since the Unix epoch.
+    pub built_at: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inputs: Option<Inputs>,
+}
+
+impl Artifact {
+    pub fn new(path: impl Into<PathBuf>, kind: ArtifactKind, profile: Profile) -> Self {
+        Self {
+            path: path.into(),
+            kind,
+            profile: profile.as_str().to_owned(),
+            built_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|since| since.as_secs())
+                .unwrap_or_default(),
+            inputs: None,
+        }
+    }
+
+    pub fn with_inputs(mut self, inputs: Option<Inputs>) -> Self {
+        self.inputs = inputs;
+        self
+    }
+}
+
+/// Keeps track of the artifacts produced by the most recent builds, so that
+/// other commands can find them without having to know where each build
+/// system puts its outputs.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct BuildManifest {
+    #[serde(default)]
+    artifacts: Vec<Artifact>,
+}
+
+impl BuildManifest {
+    pub fn path(app: &App) -> PathBuf {
+        app.prefix_path("target/cargo-mobile").join(FILE_NAME)
+    }
+
+    pub fn load(app: &App) -> Result<Self, LoadError> {
+        let path = Self::path(app);
+        if path.is_file() {
+            let bytes = fs::read(&path).map_err(|cause| LoadError::ReadFailed {
+                path: path.clone(),
+                cause,
+            })?;
+            toml::from_slice(&bytes).map_err(|cause| LoadError::DeserializeFailed { path, cause })
+        } else {
+            Ok(Self::default())
+        }
+    }
+
+    pub fn artifacts(&self) -> &[Artifact] {
+        &self.artifacts
+    }
+
+    pub fn latest(&self) -> Option<&Artifact> {
+        self.artifacts
+            .iter()
+            .max_by_key(|artifact| artifact.built_at)
+    }
+
+    /// Whether every one of `outputs` exists and was last built from exactly
+    /// `inputs`. Anything we can't be sure of counts as stale. Outputs can be
+    /// dirs, since that's what archives are.
+    pub fn is_fresh(&self, outputs: &[PathBuf], inputs: &Inputs) -> bool {
+        !outputs.is_empty()
+            && outputs.iter().all(|output| {
+                output.exists()
+                    && self.artifacts.iter().any(|artifact| {
+                        artifact.path == *output && artifact.inputs.as_ref() == Some(inputs)
+                    })
+            })
+    }
+
+    /// Adds `artifact`, replacing any previous record of the same path.
+    pub fn insert(&mut self, artifact: Artifact) {
+        self.artifacts
+            .retain(|existing| existing.path != artifact.path);
+        self.artifacts.push(artifact);
+    }
+
+    pub fn write(&self, app: &App) -> Result<(), WriteError> {
+        let path = Self::path(app);
+        let dir = path
+            .parent()
+            .expect("developer error: build manifest path had no parent");
+        fs::create_dir_all(dir).map_err(|cause| WriteError::DirCreationFailed {
+            path: dir.to_owned(),
+            cause,
+        })?;
+        let ser = toml::to_string_pretty(self).map_err(WriteError::SerializeFailed)?;
+        fs::write(&path, ser).map_err(|cause| WriteError::WriteFailed { path, cause })
+    }
+}
+
+/// Records freshly built artifacts. This is only bookkeeping, so failures are
+/// logged rather than failing the build.
+pub fn record(app: &App, artifacts: impl IntoIterator<Item = Artifact>) {
+    let result = BuildManifest::load(app)
+        .map_err(|err| err.to_string())
+        .and_then(|mut manifest| {
+            for artifact in artifacts {
+                manifest.insert(artifact);
+            }
+            manifest.write(app).map_err(|err| err.to_string())
+        });
+    if let Err(err) = result {
+        log::warn!("failed to record built artifacts: {}", err);
+    }
+}
+
+/// Whether `outputs` are already up to date with `inputs`, per the build
+/// manifest. Failing to load the manifest just means they aren't.
+pub fn is_fresh(app: &App, outputs: &[PathBuf], inputs: &Inputs) -> bool {
+    match BuildManifest::load(app) {
+        Ok(manifest) => manifest.is_fresh(outputs, inputs),
+        Err(err) => {
+            log::info!("treating build as stale, since {}", err);
+            false
+        }
+    }
+}
+
+// Build system state and outputs, which change on every build without being
+// inputs to it. `build` only counts when it belongs to a Gradle or Xcode
+// project, `.cxx` only when it belongs to a Gradle project, and `target` only
+// when it belongs to a Cargo project, since otherwise they could just as well
+// be source dirs.
+fn skipped(name: &OsStr, gradle_project: bool, xcode_project: bool, cargo_project: bool) -> bool {
+    name == ".git"
+        || name == ".gradle"
+        || name == ".idea"
+        || name == "xcuserdata"
+        || ((gradle_project || xcode_project) && name == "build")
+        || (gradle_project && name == ".cxx")
+        || (cargo_project && name == "target")
+}
+
+fn hash_tree(dir: &Path, hasher: &mut impl Hasher) -> io::Result<()> {
+    let gradle_project =
+        dir.join("build.gradle").is_file() || dir.join("build.gradle.kts").is_file();
+    let cargo_project = dir.join("Cargo.toml").is_file();
+    let mut entries = fs::read_dir(dir)?.collect::<Result<Vec<_>, _>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+    // XcodeGen projects have a `project.yml`, and whatever generated them
+    // leaves an `.xcodeproj`.
+    let xcode_project = entries.iter().any(|entry| {
+        let path = entry.path();
+        entry.file_name() == "project.yml" || path.extension().is_some_and(|ext| ext == "xcodeproj")
+    });
+    for entry in entries {
+        let file_type = entry.file_type()?;
+        if file_type.is_dir()
+            && skipped(
+                &entry.file_name(),
+                gradle_project,
+                xcode_project,
+                cargo_project,
+            )
+        {
+            continue;
+        }
+        let path = entry.path();
+        // This follows symlinks, so the libs linked into `jniLibs` count by
+        // when they were built rather than when they were linked. A broken
+        // link fails this, which is fine, since that's plenty of doubt.
+        let metadata = fs::metadata(&path)?;
+        path.hash(hasher);
+        metadata.len().hash(hasher);
+        metadata.modified()?.hash(hasher);
+        // Linked dirs aren't followed, since they could lead anywhere.
+        if file_type.is_dir() {
+            hash_tree(&path, hasher)?;
+        }
+    }
+    Ok(())
+}
+
+// Path dependencies can live outside of the project, but they're still inputs.
+fn path_dependencies(root_dir: &Path) -> Vec<PathBuf> {
+    let manifest = fs::read_to_string(root_dir.join("Cargo.toml")).unwrap_or_default();
+    regex!(r#"\bpath\s*=\s*"([^"]+)""#)
+        .captures_iter(&manifest)
+        .filter_map(|caps| dunce::canonicalize(root_dir.join(&caps[1])).ok())
+        .filter(|path| !path.starts_with(root_dir))
+        .collect()
+}
+
+/// A fingerprint of the path, size, and modification time of everything in
+/// the project, and of any path dependencies outside of it. It's only
+/// meaningful to compare against another fingerprint of the same project,
+/// and is `None` if anything couldn't be read. Environment variables aren't
+/// accounted for, so builds depending on those need `--force`.
+pub fn fingerprint(app: &App) -> Option<String> {
+    fingerprint_dir(app.root_dir())
+}
+
+fn fingerprint_dir(root_dir: &Path) -> Option<String> {
+    let root_dir = dunce::canonicalize(root_dir).ok()?;
+    let mut hasher = DefaultHasher::new();
+    let mut dirs = vec![root_dir.clone()];
+    dirs.extend(path_dependencies(&root_dir));
+    for dir in dirs {
+        if let Err(err) = hash_tree(&dir, &mut hasher) {
+            log::info!("failed to fingerprint {:?}: {}", dir, err);
+            return None;
+        }
+    }
+    Some(format!("{:016x}", hasher.finish()))
+}
+
+fn open_artifact(artifact: &Artifact) -> Result<bool, os::OpenFileError> {
+    // Archives open in Xcode's organizer, which is far more useful than
+    // seeing the bundle in Finder.
+    if cfg!(target_os = "macos") && artifact.kind == ArtifactKind::XcArchive {
+        os::open_file_with("Xcode", &artifact.path)
+    } else {
+        os::reveal_in_file_manager(&artifact.path)
+    }
+}
+
+/// Opens the most recently built artifact in the most relevant tool we know
+/// of, returning its path, or `None` if [`os::opening_disabled`].
+pub fn open_latest(app: &App) -> Result<Option<PathBuf>, OpenLatestError> {
+    let manifest = BuildManifest::load(app).map_err(OpenLatestError::LoadFailed)?;
+    let artifact = manifest.latest().ok_or(OpenLatestError::NothingBuilt)?;
+    if !Path::new(&artifact.path).exists() {
+        return Err(OpenLatestError::ArtifactMissing {
+            path: artifact.path.clone(),
+        });
+    }
+    let opened = open_artifact(artifact).map_err(|cause| OpenLatestError::OpenFailed {
+        path: artifact.path.clone(),
+        cause: Box::new(cause),
+    })?;
+    Ok(opened.then(|| artifact.path.clone()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    fn write(path: &Path, contents: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    fn hash(dir: &Path) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        hash_tree(dir, &mut hasher).unwrap();
+        hasher.finish()
+    }
+
+    fn inputs(fingerprint: &str) -> Inputs {
+        Inputs {
+            request: vec!["assembleUniversalDebug".to_owned()],
+            fingerprint: fingerprint.to_owned(),
+        }
+    }
+
+    #[test]
+    fn fresh_only_when_every_output_matches() {
+        let dir = crate::util::temp_dir().join("build-manifest-fresh-test");
+        fs::create_dir_all(&dir).unwrap();
+        let apk = dir.join("app.apk");
+        let archive = dir.join("app.xcarchive");
+        let missing = dir.join("missing.apk");
+        write(&apk, "apk");
+        fs::create_dir_all(&archive).unwrap();
+        let mut manifest = BuildManifest::default();
+        for path in &[&apk, &archive, &missing] {
+            manifest.insert(
+                Artifact::new(*path, ArtifactKind::Apk, Profile::Debug)
+                    .with_inputs(Some(inputs("a"))),
+            );
+        }
+        let fresh = |outputs: &[&PathBuf], fingerprint| {
+            let outputs = outputs
+                .iter()
+                .map(|path| (*path).clone())
+                .collect::<Vec<_>>();
+            manifest.is_fresh(&outputs, &inputs(fingerprint))
+        };
+        let results = (
+            fresh(&[&apk, &archive], "a"),
+            fresh(&[&apk], "b"),
+            fresh(&[&apk, &missing], "a"),
+            fresh(&[], "a"),
+        );
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(results, (true, false, false, false));
+    }
+
+    #[rstest(
+        changed,
+        expected_same,
+        case("src/lib.rs", false),
+        case("gen/android/app/build/outputs/app.apk", true),
+        case("gen/android/app/.cxx/cmake.log", true),
+        case("gen/apple/build/app.xcarchive/Info.plist", true),
+        case("target/debug/libapp.so", true),
+        case("gen/apple/Sources/build/main.rs", false)
+    )]
+    fn fingerprint_skips_build_outputs(changed: &str, expected_same: bool) {
+        let dir = crate::util::temp_dir()
+            .join("build-manifest-hash-test")
+            .join(changed.replace('/', "-"));
+        write(&dir.join("Cargo.toml"), "[package]");
+        write(&dir.join("src/lib.rs"), "");
+        write(&dir.join("gen/android/app/build.gradle.kts"), "");
+        write(&dir.join("gen/apple/project.yml"), "");
+        write(&dir.join("gen/apple/Sources/build/main.rs"), "");
+        // Build output dirs stick around between builds; it's what's in them
+        // that changes.
+        fs::create_dir_all(dir.join(changed).parent().unwrap()).unwrap();
+        let before = hash(&dir);
+        write(&dir.join(changed), "changed");
+        let after = hash(&dir);
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(before == after, expected_same);
+    }
+
+    #[test]
+    fn fingerprint_covers_outside_path_dependencies() {
+        let dir = crate::util::temp_dir().join("build-manifest-deps-test");
+        let root = dir.join("app");
+        let outside = dir.join("shared");
+        write(
+            &root.join("Cargo.toml"),
+            r#"[dependencies]
+inside = { path = "crates/inside" }
+shared = { path = "../shared" }
+missing = { path = "../missing" }
+"#,
+        );
+        write(&root.join("crates/inside/Cargo.toml"), "[package]");
+        write(&outside.join("Cargo.toml"), "[package]");
+        let root = dunce::canonicalize(&root).unwrap();
+        let outside = dunce::canonicalize(&outside).unwrap();
+        let deps = path_dependencies(&root);
+        let before = fingerprint_dir(&root);
+        write(&outside.join("src/lib.rs"), "");
+        let after = fingerprint_dir(&root);
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(deps, vec![outside]);
+        assert!(before.is_some());
+        assert_ne!(before, after);
+    }
+}
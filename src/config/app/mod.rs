@@ -16,6 +16,8 @@ use thiserror::Error;
 pub static KEY: &str = "app";
 
 pub static DEFAULT_ASSET_DIR: &str = "assets";
+pub static DEFAULT_TARGET_DIR: &str = "target";
+pub const DEFAULT_MIN_FREE_SPACE_GB: u64 = 5;
 pub static IMPLIED_TEMPLATE_PACK: &str = "brainstorm";
 pub static DEFAULT_TEMPLATE_PACK: &str = if cfg!(feature = "brainium") {
     IMPLIED_TEMPLATE_PACK
@@ -62,6 +64,24 @@ pub struct App {
     asset_dir: PathBuf,
     #[serde(skip)]
     template_pack: Pack,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    out_dir: Option<PathBuf>,
+    target_dir: PathBuf,
+    #[serde(skip)]
+    custom_target_dir: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pre_build: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    post_build: Option<String>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    panic_abort: bool,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    build_std: bool,
+    min_free_space_gb: u64,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    open_bindings: Vec<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    build_jobs: Option<usize>,
 }
 
 impl App {
@@ -128,6 +148,26 @@ impl App {
             domain,
             asset_dir,
             template_pack,
+            out_dir: raw.out_dir.as_deref().map(util::expand_config_path),
+            custom_target_dir: raw.target_dir.is_some(),
+            target_dir: raw
+                .target_dir
+                .as_deref()
+                .map(util::expand_config_path)
+                .unwrap_or_else(|| DEFAULT_TARGET_DIR.into()),
+            pre_build: raw.pre_build.filter(|command| !command.trim().is_empty()),
+            post_build: raw.post_build.filter(|command| !command.trim().is_empty()),
+            panic_abort: raw.panic_abort.unwrap_or_default(),
+            build_std: raw.build_std.unwrap_or_default(),
+            min_free_space_gb: raw.min_free_space_gb.unwrap_or(DEFAULT_MIN_FREE_SPACE_GB),
+            open_bindings: raw
+                .open_bindings
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|path| !path.trim().is_empty())
+                .map(PathBuf::from)
+                .collect(),
+            build_jobs: raw.build_jobs.filter(|jobs| *jobs != 0),
         })
     }
 
@@ -176,4 +216,76 @@ impl App {
     pub fn template_pack(&self) -> &Pack {
         &self.template_pack
     }
+
+    /// The directory final artifacts get copied to, if any. `out_dir_override`
+    /// (i.e. from `--out-dir`) takes precedence over `app.out-dir`, and either
+    /// is resolved relative to the app root rather than the cwd.
+    pub fn out_dir(&self, out_dir_override: Option<&Path>) -> Option<PathBuf> {
+        out_dir_override
+            .or(self.out_dir.as_deref())
+            .map(|out_dir| self.prefix_path(out_dir))
+    }
+
+    /// Where cargo puts build output for mobile builds.
+    pub fn target_dir(&self) -> PathBuf {
+        self.prefix_path(&self.target_dir)
+    }
+
+    /// The target dir to explicitly pass to cargo, if one was configured. When
+    /// it isn't, we leave it up to cargo, so `CARGO_TARGET_DIR` and
+    /// `build.target-dir` keep working.
+    pub fn custom_target_dir(&self) -> Option<PathBuf> {
+        self.custom_target_dir.then(|| self.target_dir())
+    }
+
+    /// Shell command to run before building, from `app.pre-build`.
+    pub fn pre_build(&self) -> Option<&str> {
+        self.pre_build.as_deref()
+    }
+
+    /// Shell command to run after a successful build, from `app.post-build`.
+    pub fn post_build(&self) -> Option<&str> {
+        self.post_build.as_deref()
+    }
+
+    /// Whether mobile builds use `panic = "abort"`, from `app.panic-abort`.
+    pub fn panic_abort(&self) -> bool {
+        self.panic_abort
+    }
+
+    /// Whether mobile builds rebuild std via `-Z build-std`, from
+    /// `app.build-std`. This requires nightly.
+    pub fn build_std(&self) -> bool {
+        self.build_std
+    }
+
+    /// How much free space (in GB) the target dir's filesystem should have
+    /// before we warn about it, from `app.min-free-space-gb`. `0` disables
+    /// the check.
+    pub fn min_free_space_gb(&self) -> u64 {
+        self.min_free_space_gb
+    }
+
+    /// Generated binding files (or dirs of them) to offer to open after a
+    /// build that changed them, from `app.open-bindings`. Relative paths are
+    /// relative to the app root.
+    pub fn open_bindings(&self) -> Vec<PathBuf> {
+        self.open_bindings
+            .iter()
+            .map(|path| self.root_dir.join(path))
+            .collect()
+    }
+
+    /// How many targets to build at once, from `app.build-jobs`. Defaults to
+    /// the number of CPUs, capped so that memory-hungry builds don't run the
+    /// machine out of memory. `1` builds targets one at a time.
+    pub fn build_jobs(&self) -> usize {
+        self.build_jobs
+            .unwrap_or_else(crate::target::default_build_jobs)
+    }
+
+    pub(crate) fn set_target_dir(&mut self, target_dir: impl Into<PathBuf>) {
+        self.target_dir = target_dir.into();
+        self.custom_target_dir = true;
+    }
 }
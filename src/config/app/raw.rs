@@ -147,6 +147,15 @@ pub struct Raw {
     pub domain: String,
     pub asset_dir: Option<String>,
     pub template_pack: Option<String>,
+    pub out_dir: Option<String>,
+    pub target_dir: Option<String>,
+    pub pre_build: Option<String>,
+    pub post_build: Option<String>,
+    pub panic_abort: Option<bool>,
+    pub build_std: Option<bool>,
+    pub min_free_space_gb: Option<u64>,
+    pub open_bindings: Option<Vec<String>>,
+    pub build_jobs: Option<usize>,
 }
 
 impl Raw {
@@ -159,6 +168,15 @@ impl Raw {
             asset_dir: None,
             template_pack: Some(super::DEFAULT_TEMPLATE_PACK.to_owned())
                 .filter(|pack| pack != super::IMPLIED_TEMPLATE_PACK),
+            out_dir: None,
+            target_dir: None,
+            pre_build: None,
+            post_build: None,
+            panic_abort: None,
+            build_std: None,
+            min_free_space_gb: None,
+            open_bindings: None,
+            build_jobs: None,
         })
     }
 
@@ -175,6 +193,15 @@ impl Raw {
             domain,
             asset_dir: None,
             template_pack,
+            out_dir: None,
+            target_dir: None,
+            pre_build: None,
+            post_build: None,
+            panic_abort: None,
+            build_std: None,
+            min_free_space_gb: None,
+            open_bindings: None,
+            build_jobs: None,
         })
     }
 }
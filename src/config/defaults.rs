@@ -0,0 +1,246 @@
+use crate::{
+    opts::{FilterLevel, Profile},
+    target::TargetTrait,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+
+/// Checked in next to the generated config, so a team can share the defaults
+/// they'd otherwise all be passing as flags.
+pub static FILE_NAME: &str = "cargo-mobile.toml";
+
+static KEYS: &[&str] = &["release", "device", "targets", "jobs", "filter", "open"];
+
+#[derive(Debug, Error)]
+pub enum LoadError {
+    #[error("Failed to read {path:?}: {cause}")]
+    ReadFailed {
+        path: PathBuf,
+        #[source]
+        cause: io::Error,
+    },
+    #[error("Failed to parse {path:?}: {cause}")]
+    ParseFailed {
+        path: PathBuf,
+        #[source]
+        cause: toml::de::Error,
+    },
+}
+
+/// Defaults for the build, run, and open commands, from [`FILE_NAME`] in the
+/// project root. Flags win over these, and these win over the generated
+/// config.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Defaults {
+    /// Build with release optimizations, as if `--release` was passed.
+    pub release: Option<bool>,
+    /// The device to use, as if `--device` was passed.
+    pub device: Option<String>,
+    /// The targets (or ABIs) to build for when none are given.
+    pub targets: Option<Vec<String>>,
+    /// Overrides `app.build-jobs`.
+    pub jobs: Option<usize>,
+    /// The log level to filter by when running, i.e. `"Warn"`.
+    pub filter: Option<FilterLevel>,
+    /// Overrides `open.auto`.
+    pub open: Option<bool>,
+}
+
+impl Defaults {
+    fn unknown_keys(table: &toml::value::Table) -> Vec<String> {
+        table
+            .keys()
+            .filter(|key| !KEYS.contains(&key.as_str()))
+            .cloned()
+            .collect()
+    }
+
+    fn parse(path: &Path, contents: &str) -> Result<Self, toml::de::Error> {
+        let mut table = toml::from_str::<toml::value::Table>(contents)?;
+        // A typo shouldn't stop anyone from building, but it shouldn't go
+        // unnoticed either.
+        for key in Self::unknown_keys(&table) {
+            log::warn!(
+                "ignoring unknown key `{}` in {:?}; the known keys are {}",
+                key,
+                path,
+                KEYS.join(", ")
+            );
+            table.remove(&key);
+        }
+        toml::Value::Table(table).try_into()
+    }
+
+    /// Loads [`FILE_NAME`] from `root_dir`, which is fine to not exist.
+    pub fn load(root_dir: &Path) -> Result<Self, LoadError> {
+        let path = root_dir.join(FILE_NAME);
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(cause) => return Err(LoadError::ReadFailed { path, cause }),
+        };
+        let defaults = Self::parse(&path, &contents).map_err(|cause| LoadError::ParseFailed {
+            path: path.clone(),
+            cause,
+        })?;
+        log::info!("loaded defaults from {:?}: {:#?}", path, defaults);
+        Ok(defaults)
+    }
+
+    /// `requested` is from `--release` or `--debug`, if either was given.
+    pub fn profile(&self, requested: Option<Profile>) -> Profile {
+        requested.unwrap_or_else(|| Profile::from_flag(self.release.unwrap_or_default()))
+    }
+
+    pub fn device(&self, device: Option<String>) -> Option<String> {
+        device.or_else(|| self.device.clone())
+    }
+
+    pub fn targets(&self, targets: Vec<String>) -> Vec<String> {
+        if targets.is_empty() {
+            self.targets.clone().unwrap_or_default()
+        } else {
+            targets
+        }
+    }
+
+    /// Like [`Defaults::targets`], but falling back on `T::DEFAULT_KEY` when
+    /// neither gives any, for commands that build for that by default.
+    pub fn targets_or_default<'a, T: TargetTrait<'a>>(&self, targets: Vec<String>) -> Vec<String> {
+        let targets = self.targets(targets);
+        if targets.is_empty() {
+            vec![T::DEFAULT_KEY.to_owned()]
+        } else {
+            targets
+        }
+    }
+
+    pub fn jobs(&self, jobs: Option<usize>) -> Option<usize> {
+        jobs.or(self.jobs)
+    }
+
+    pub fn filter(&self, filter: Option<FilterLevel>) -> Option<FilterLevel> {
+        filter.or(self.filter)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    fn parse(contents: &str) -> Defaults {
+        Defaults::parse(Path::new(FILE_NAME), contents).unwrap()
+    }
+
+    #[test]
+    fn parses_every_key() {
+        assert_eq!(
+            parse(
+                r#"
+release = true
+device = "emulator-5554"
+targets = ["aarch64", "x86_64"]
+jobs = 2
+filter = "Warn"
+open = false
+"#
+            ),
+            Defaults {
+                release: Some(true),
+                device: Some("emulator-5554".to_owned()),
+                targets: Some(vec!["aarch64".to_owned(), "x86_64".to_owned()]),
+                jobs: Some(2),
+                filter: Some(FilterLevel::Warn),
+                open: Some(false),
+            }
+        );
+    }
+
+    #[test]
+    fn skips_unknown_keys() {
+        let table = toml::from_str("relase = true\ndevice = \"pixel\"").unwrap();
+        assert_eq!(Defaults::unknown_keys(&table), vec!["relase".to_owned()]);
+        assert_eq!(
+            parse("relase = true\ndevice = \"pixel\""),
+            Defaults {
+                device: Some("pixel".to_owned()),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[rstest(
+        flag,
+        release,
+        expected,
+        case(None, None, Profile::Debug),
+        case(None, Some(true), Profile::Release),
+        case(Some(Profile::Debug), Some(true), Profile::Debug),
+        case(Some(Profile::Release), Some(false), Profile::Release),
+        case(Some(Profile::Release), None, Profile::Release)
+    )]
+    fn flags_win_over_defaults(flag: Option<Profile>, release: Option<bool>, expected: Profile) {
+        let defaults = Defaults {
+            release,
+            ..Default::default()
+        };
+        assert_eq!(defaults.profile(flag), expected);
+    }
+
+    #[rstest(
+        flag,
+        expected,
+        case(None, Some("pixel")),
+        case(Some("emulator-5554"), Some("emulator-5554"))
+    )]
+    fn device_flag_wins(flag: Option<&str>, expected: Option<&str>) {
+        let defaults = Defaults {
+            device: Some("pixel".to_owned()),
+            ..Default::default()
+        };
+        assert_eq!(
+            defaults.device(flag.map(ToOwned::to_owned)).as_deref(),
+            expected
+        );
+    }
+
+    #[rstest(
+        flag,
+        expected,
+        case(&[], &["aarch64"]),
+        case(&["x86_64"], &["x86_64"])
+    )]
+    fn target_flags_win(flag: &[&str], expected: &[&str]) {
+        let defaults = Defaults {
+            targets: Some(vec!["aarch64".to_owned()]),
+            ..Default::default()
+        };
+        assert_eq!(
+            defaults.targets(flag.iter().map(|target| target.to_string()).collect()),
+            expected
+        );
+    }
+
+    #[test]
+    fn falls_back_on_default_target() {
+        use crate::android::target::Target;
+        assert_eq!(
+            Defaults::default().targets_or_default::<Target>(Vec::new()),
+            vec![Target::DEFAULT_KEY]
+        );
+        let defaults = Defaults {
+            targets: Some(vec!["x86_64".to_owned()]),
+            ..Default::default()
+        };
+        assert_eq!(
+            defaults.targets_or_default::<Target>(Vec::new()),
+            vec!["x86_64"]
+        );
+    }
+}
@@ -0,0 +1,105 @@
+use crate::os::LaunchOptions;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+/// The scratch file opened alongside the project when `editor.open-notes` is
+/// set, relative to the app root.
+pub static NOTES_FILE_NAME: &str = "NOTES.md";
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Raw {
+    pub profile: Option<String>,
+    pub env: Option<BTreeMap<String, String>>,
+    pub open_notes: Option<bool>,
+    pub allowed_pagers: Option<Vec<String>>,
+    pub reuse_window: Option<bool>,
+}
+
+/// `editor.allowed-pagers` from the config above `cwd`, for detecting the
+/// editor without loading the whole config. Not being able to load it just
+/// means no pagers are allowed.
+pub fn discover_allowed_pagers(cwd: impl AsRef<Path>) -> Vec<String> {
+    match super::Raw::load(cwd) {
+        Ok(raw) => raw
+            .and_then(|(_root_dir, raw)| raw.editor)
+            .and_then(|editor| editor.allowed_pagers)
+            .unwrap_or_default(),
+        Err(err) => {
+            log::info!(
+                "not allowing any pagers, since loading the config failed: {}",
+                err
+            );
+            Vec::new()
+        }
+    }
+}
+
+/// How generated projects get opened in the user's editor.
+#[derive(Clone, Debug, Default, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Config {
+    profile: Option<String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    env: BTreeMap<String, String>,
+    open_notes: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    allowed_pagers: Vec<String>,
+    reuse_window: bool,
+}
+
+impl Config {
+    pub fn from_raw(raw: Option<Raw>) -> Self {
+        let raw = raw.unwrap_or_default();
+        Self {
+            profile: raw.profile.filter(|profile| !profile.trim().is_empty()),
+            env: raw.env.unwrap_or_default(),
+            open_notes: raw.open_notes.unwrap_or_default(),
+            allowed_pagers: raw.allowed_pagers.unwrap_or_default(),
+            reuse_window: raw.reuse_window.unwrap_or_default(),
+        }
+    }
+
+    /// The editor profile to open projects in, for editors that have them
+    /// (i.e. VS Code's `--profile`). Other editors ignore this.
+    pub fn profile(&self) -> Option<&str> {
+        self.profile.as_deref()
+    }
+
+    /// Env vars to launch the editor with, on top of our own, from
+    /// `editor.env`. Electron editors on Wayland often want
+    /// `ELECTRON_OZONE_PLATFORM_HINT=auto`, for instance.
+    pub fn env(&self) -> &BTreeMap<String, String> {
+        &self.env
+    }
+
+    /// Pagers (i.e. `less` or `bat`) that are fine to open projects in, from
+    /// `editor.allowed-pagers`. Otherwise, we skip past them when detecting
+    /// the editor, since they're usually only the default by accident.
+    pub fn allowed_pagers(&self) -> &[String] {
+        &self.allowed_pagers
+    }
+
+    /// Includes [`Config::allowed_pagers`], for detecting the editor.
+    pub fn launch_options(&self) -> LaunchOptions {
+        LaunchOptions {
+            env: self.env.clone(),
+            reuse_window: self.reuse_window,
+            allowed_pagers: self.allowed_pagers.clone(),
+            ..Default::default()
+        }
+    }
+
+    /// The notes file to open alongside the project, if `editor.open-notes`
+    /// is set.
+    pub fn notes_path(&self, root_dir: &Path) -> Option<PathBuf> {
+        if self.open_notes {
+            Some(root_dir.join(NOTES_FILE_NAME))
+        } else {
+            None
+        }
+    }
+}
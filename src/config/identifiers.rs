@@ -0,0 +1,214 @@
+use super::Config;
+use once_cell_regex::regex;
+use std::{
+    fmt::{self, Display},
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// A generated project that identifies the app differently than the config
+/// does, which happens when `app.domain` or `app.name` changes without the
+/// project being regenerated.
+#[derive(Debug, Eq, PartialEq)]
+pub struct Mismatch {
+    pub file: PathBuf,
+    /// What the identifier is called in `file`.
+    pub key: &'static str,
+    pub found: String,
+    pub expected: String,
+}
+
+impl Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:?} has {} {:?}, but the config says it should be {:?}",
+            self.file, self.key, self.found, self.expected
+        )
+    }
+}
+
+fn manifest_package(manifest: &str) -> Option<&str> {
+    regex!(r#"<manifest\b[^>]*?\spackage\s*=\s*"(?P<id>[^"]*)""#)
+        .captures(manifest)
+        .map(|caps| caps.name("id").unwrap().as_str())
+}
+
+fn gradle_application_id(gradle: &str) -> Option<&str> {
+    regex!(r#"\bapplicationId\s*=?\s*"(?P<id>[^"]*)""#)
+        .captures(gradle)
+        .map(|caps| caps.name("id").unwrap().as_str())
+}
+
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+fn xcodegen_bundle_id(spec: &str) -> Option<&str> {
+    regex!(r"(?m)^\s*PRODUCT_BUNDLE_IDENTIFIER:\s*(?P<id>\S+)\s*$")
+        .captures(spec)
+        .map(|caps| caps.name("id").unwrap().as_str())
+}
+
+/// Only literal values count; `$(PRODUCT_BUNDLE_IDENTIFIER)` and the like
+/// are checked wherever they're defined.
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+fn plist_bundle_id(plist: &str) -> Option<&str> {
+    regex!(r"<key>CFBundleIdentifier</key>\s*<string>(?P<id>[^<]*)</string>")
+        .captures(plist)
+        .map(|caps| caps.name("id").unwrap().as_str())
+        .filter(|id| !id.contains("$("))
+}
+
+fn check(
+    file: PathBuf,
+    key: &'static str,
+    read: fn(&str) -> Option<&str>,
+    expected: &str,
+    mismatches: &mut Vec<Mismatch>,
+) {
+    // A missing file means the project wasn't generated, which isn't ours to
+    // complain about.
+    let contents = match fs::read_to_string(&file) {
+        Ok(contents) => contents,
+        Err(err) => {
+            log::info!("not checking {} in {:?}: {}", key, file, err);
+            return;
+        }
+    };
+    if let Some(found) = read(&contents) {
+        if found != expected {
+            mismatches.push(Mismatch {
+                key,
+                found: found.to_owned(),
+                expected: expected.to_owned(),
+                file,
+            });
+        }
+    }
+}
+
+fn check_android(project_dir: &Path, expected: &str, mismatches: &mut Vec<Mismatch>) {
+    let app_dir = project_dir.join("app");
+    check(
+        app_dir.join("src/main/AndroidManifest.xml"),
+        "package",
+        manifest_package,
+        expected,
+        mismatches,
+    );
+    check(
+        app_dir.join("build.gradle.kts"),
+        "applicationId",
+        gradle_application_id,
+        expected,
+        mismatches,
+    );
+}
+
+#[cfg(target_os = "macos")]
+fn check_apple(project_dir: &Path, app_name: &str, expected: &str, mismatches: &mut Vec<Mismatch>) {
+    check(
+        project_dir.join("project.yml"),
+        "PRODUCT_BUNDLE_IDENTIFIER",
+        xcodegen_bundle_id,
+        expected,
+        mismatches,
+    );
+    check(
+        project_dir.join(format!("{}_iOS/Info.plist", app_name)),
+        "CFBundleIdentifier",
+        plist_bundle_id,
+        expected,
+        mismatches,
+    );
+}
+
+/// Checks that the generated Android and Xcode projects still identify the
+/// app the way the config does. Drift here means builds get signed and
+/// installed as some other app, which is a confusing thing to debug.
+pub fn verify_identifiers(config: &Config) -> Result<(), Vec<Mismatch>> {
+    let app = config.app();
+    let mut mismatches = Vec::new();
+    check_android(
+        &config.android().project_dir(),
+        &format!("{}.{}", app.reverse_domain(), app.name_snake()),
+        &mut mismatches,
+    );
+    #[cfg(target_os = "macos")]
+    check_apple(
+        &config.apple().project_dir(),
+        app.name(),
+        &format!("{}.{}", app.reverse_domain(), app.name()),
+        &mut mismatches,
+    );
+    if mismatches.is_empty() {
+        Ok(())
+    } else {
+        Err(mismatches)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest(
+        manifest,
+        expected,
+        case(
+            r#"<?xml version="1.0" encoding="utf-8"?>
+<manifest xmlns:android="http://schemas.android.com/apk/res/android"
+    package="com.example.hello">
+    <application android:label="hello" />
+</manifest>"#,
+            Some("com.example.hello")
+        ),
+        case(
+            r#"<manifest xmlns:android="http://schemas.android.com/apk/res/android">
+    <uses-permission android:name="android.permission.INTERNET" />
+</manifest>"#,
+            None
+        )
+    )]
+    fn reads_manifest_package(manifest: &str, expected: Option<&str>) {
+        assert_eq!(manifest_package(manifest), expected);
+    }
+
+    #[rstest(
+        gradle,
+        expected,
+        case(
+            "android {\n    defaultConfig {\n        applicationId = \"com.example.hello\"\n    }\n}",
+            Some("com.example.hello")
+        ),
+        case(
+            "android {\n    defaultConfig {\n        applicationId \"com.example.groovy\"\n    }\n}",
+            Some("com.example.groovy")
+        ),
+        case("android {}", None)
+    )]
+    fn reads_gradle_application_id(gradle: &str, expected: Option<&str>) {
+        assert_eq!(gradle_application_id(gradle), expected);
+    }
+
+    #[test]
+    fn reads_xcodegen_bundle_id() {
+        let spec = "settingGroups:\n  app:\n    base:\n      PRODUCT_NAME: hello\n      PRODUCT_BUNDLE_IDENTIFIER: com.example.hello\n";
+        assert_eq!(xcodegen_bundle_id(spec), Some("com.example.hello"));
+    }
+
+    #[rstest(
+        plist,
+        expected,
+        case(
+            "<dict>\n\t<key>CFBundleIdentifier</key>\n\t<string>com.example.hello</string>\n</dict>",
+            Some("com.example.hello")
+        ),
+        case(
+            "<dict>\n\t<key>CFBundleIdentifier</key>\n\t<string>$(PRODUCT_BUNDLE_IDENTIFIER)</string>\n</dict>",
+            None
+        )
+    )]
+    fn reads_plist_bundle_id(plist: &str, expected: Option<&str>) {
+        assert_eq!(plist_bundle_id(plist), expected);
+    }
+}
@@ -0,0 +1,185 @@
+use crate::{
+    bossy,
+    util::cli::{Report, Reportable},
+};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+static LIB_CRATE_TYPES: &[&str] = &["cdylib", "staticlib"];
+
+#[derive(Debug, Error)]
+pub enum LibNameError {
+    #[error("Failed to run `cargo metadata`: {0}")]
+    MetadataFailed(#[source] bossy::Error),
+    #[error("Failed to parse `cargo metadata` output: {0}")]
+    MetadataInvalid(#[source] serde_json::Error),
+    #[error("`cargo metadata` didn't list a package for {manifest_path:?}")]
+    PackageMissing { manifest_path: PathBuf },
+    #[error("Package `{package}` has no lib target with a `cdylib` or `staticlib` crate type, so there's nothing to package.")]
+    NoLibTarget { package: String },
+}
+
+impl Reportable for LibNameError {
+    fn report(&self) -> Report {
+        let msg = "Failed to find the name of your lib";
+        match self {
+            Self::NoLibTarget { .. } => Report::action_request(
+                msg,
+                format!(
+                    "{} Add `crate-type = [\"staticlib\", \"cdylib\", \"rlib\"]` to the `[lib]` section of your Cargo.toml, and then try again.",
+                    self
+                ),
+            ),
+            _ => Report::error(msg, self),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Metadata {
+    packages: Vec<Package>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Package {
+    name: String,
+    manifest_path: PathBuf,
+    targets: Vec<Target>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Target {
+    name: String,
+    crate_types: Vec<String>,
+}
+
+fn find_lib_name(metadata: Metadata, manifest_path: &Path) -> Result<String, LibNameError> {
+    let package = metadata
+        .packages
+        .into_iter()
+        .find(|package| {
+            dunce::canonicalize(&package.manifest_path)
+                .map(|path| path == manifest_path)
+                .unwrap_or_else(|_| package.manifest_path == manifest_path)
+        })
+        .ok_or_else(|| LibNameError::PackageMissing {
+            manifest_path: manifest_path.to_owned(),
+        })?;
+    package
+        .targets
+        .iter()
+        .find(|target| {
+            target
+                .crate_types
+                .iter()
+                .any(|ty| LIB_CRATE_TYPES.contains(&ty.as_str()))
+        })
+        // Cargo reports the lib name as written, which defaults to the
+        // package name; either way, rustc swaps hyphens for underscores.
+        .map(|target| target.name.replace('-', "_"))
+        .ok_or(LibNameError::NoLibTarget {
+            package: package.name,
+        })
+}
+
+/// The name of the lib built for the package at `project_dir`, as it appears
+/// in the artifact's file name (i.e. `lib<name>.so` or `lib<name>.a`). This
+/// comes from the first lib target with a `cdylib` or `staticlib` crate type,
+/// so it respects `[lib] name` in Cargo.toml.
+pub fn lib_name(project_dir: impl AsRef<Path>) -> Result<String, LibNameError> {
+    let manifest_path = project_dir.as_ref().join("Cargo.toml");
+    let manifest_path = dunce::canonicalize(&manifest_path).unwrap_or(manifest_path);
+    let output = bossy::Command::impure("cargo")
+        .with_args(["metadata", "--format-version", "1", "--no-deps"])
+        .with_arg("--manifest-path")
+        .with_arg(&manifest_path)
+        .run_and_wait_for_output()
+        .map_err(LibNameError::MetadataFailed)?;
+    let metadata =
+        serde_json::from_slice(output.stdout()).map_err(LibNameError::MetadataInvalid)?;
+    find_lib_name(metadata, &manifest_path)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    // Trimmed down from what `cargo metadata --format-version 1 --no-deps`
+    // prints, with a workspace member alongside the app.
+    fn metadata(lib: Option<(&str, &[&str])>) -> Metadata {
+        let mut targets = vec![serde_json::json!({
+            "kind": ["bin"],
+            "crate_types": ["bin"],
+            "name": "my-app-desktop",
+            "src_path": "/project/src/bin/desktop.rs",
+        })];
+        if let Some((name, crate_types)) = lib {
+            targets.push(serde_json::json!({
+                "kind": crate_types,
+                "crate_types": crate_types,
+                "name": name,
+                "src_path": "/project/src/lib.rs",
+            }));
+        }
+        serde_json::from_value(serde_json::json!({
+            "packages": [
+                {
+                    "name": "shared",
+                    "manifest_path": "/project/shared/Cargo.toml",
+                    "targets": [{
+                        "kind": ["cdylib"],
+                        "crate_types": ["cdylib"],
+                        "name": "shared",
+                        "src_path": "/project/shared/src/lib.rs",
+                    }],
+                },
+                {
+                    "name": "my-app",
+                    "manifest_path": "/project/Cargo.toml",
+                    "targets": targets,
+                },
+            ],
+            "workspace_root": "/project",
+        }))
+        .unwrap()
+    }
+
+    #[rstest(
+        name,
+        crate_types,
+        expected,
+        case("my-app", &["staticlib", "cdylib", "rlib"], "my_app"),
+        case("my_app_core", &["cdylib"], "my_app_core"),
+        case("renamed-lib", &["staticlib"], "renamed_lib")
+    )]
+    fn finds_lib_name(name: &str, crate_types: &[&str], expected: &str) {
+        assert_eq!(
+            find_lib_name(
+                metadata(Some((name, crate_types))),
+                Path::new("/project/Cargo.toml")
+            )
+            .unwrap(),
+            expected
+        );
+    }
+
+    // No crate types means no `[lib]` section, and so no lib target at all.
+    #[rstest(crate_types, case(&[]), case(&["rlib"]))]
+    fn requires_a_packageable_lib(crate_types: &[&str]) {
+        let lib = (!crate_types.is_empty()).then_some(("my-app", crate_types));
+        match find_lib_name(metadata(lib), Path::new("/project/Cargo.toml")) {
+            Err(LibNameError::NoLibTarget { package }) => assert_eq!(package, "my-app"),
+            other => panic!("expected no lib target, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn requires_the_package() {
+        assert!(matches!(
+            find_lib_name(metadata(None), Path::new("/elsewhere/Cargo.toml")),
+            Err(LibNameError::PackageMissing { .. })
+        ));
+    }
+}
@@ -1,13 +1,26 @@
 pub mod app;
+pub mod defaults;
+pub mod editor;
+mod identifiers;
+mod lib_name;
 pub mod metadata;
+pub mod open;
+mod project_id;
 mod raw;
+pub mod state;
+pub mod telemetry;
+pub use self::{
+    identifiers::{verify_identifiers, Mismatch},
+    lib_name::{lib_name, LibNameError},
+    project_id::{cache_dir, project_id, project_id_with_bundle},
+};
 pub use raw::Raw;
 
 use self::{app::App, raw::*};
 #[cfg(target_os = "macos")]
 use crate::apple;
 use crate::{
-    android, bicycle, templating,
+    android, bicycle, os, templating,
     util::cli::{Report, Reportable, TextWrapper},
 };
 use serde::Serialize;
@@ -31,6 +44,8 @@ pub enum FromRawError {
     AppleConfigInvalid(apple::config::Error),
     #[error(transparent)]
     AndroidConfigInvalid(android::config::Error),
+    #[error(transparent)]
+    DefaultsInvalid(defaults::LoadError),
 }
 
 impl FromRawError {
@@ -40,6 +55,7 @@ impl FromRawError {
             #[cfg(target_os = "macos")]
             Self::AppleConfigInvalid(err) => err.report(msg),
             Self::AndroidConfigInvalid(err) => err.report(msg),
+            Self::DefaultsInvalid(err) => Report::error(msg, err),
         }
     }
 }
@@ -99,21 +115,37 @@ pub struct Config {
     #[cfg(target_os = "macos")]
     apple: apple::config::Config,
     android: android::config::Config,
+    editor: editor::Config,
+    open: open::Config,
+    telemetry: telemetry::Config,
+    defaults: defaults::Defaults,
 }
 
 impl Config {
     pub fn from_raw(root_dir: PathBuf, raw: Raw) -> Result<Self, FromRawError> {
+        let defaults =
+            defaults::Defaults::load(&root_dir).map_err(FromRawError::DefaultsInvalid)?;
         let app = App::from_raw(root_dir, raw.app).map_err(FromRawError::AppConfigInvalid)?;
         #[cfg(target_os = "macos")]
         let apple = apple::config::Config::from_raw(app.clone(), raw.apple)
             .map_err(FromRawError::AppleConfigInvalid)?;
         let android = android::config::Config::from_raw(app.clone(), raw.android)
             .map_err(FromRawError::AndroidConfigInvalid)?;
+        let editor = editor::Config::from_raw(raw.editor);
+        let mut open = open::Config::from_raw(raw.open);
+        if let Some(auto) = defaults.open {
+            open.set_auto(auto);
+        }
+        let telemetry = telemetry::Config::from_raw(raw.telemetry);
         Ok(Self {
             app,
             #[cfg(target_os = "macos")]
             apple,
             android,
+            editor,
+            open,
+            telemetry,
+            defaults,
         })
     }
 
@@ -122,7 +154,7 @@ impl Config {
         non_interactive: bool,
         wrapper: &TextWrapper,
     ) -> Result<Self, GenError> {
-        let raw = if !non_interactive {
+        let raw = if !non_interactive && os::can_prompt() {
             Raw::prompt(wrapper).map_err(GenError::PromptFailed)
         } else {
             Raw::detect(wrapper).map_err(GenError::DetectFailed)
@@ -166,6 +198,15 @@ impl Config {
         &self.app
     }
 
+    /// Overrides `app.target-dir`, i.e. with the value of `--target-dir`.
+    pub fn override_target_dir(&mut self, target_dir: impl Into<PathBuf>) {
+        let target_dir = self.app.prefix_path(target_dir.into());
+        self.app.set_target_dir(&target_dir);
+        #[cfg(target_os = "macos")]
+        self.apple.app_mut().set_target_dir(&target_dir);
+        self.android.app_mut().set_target_dir(target_dir);
+    }
+
     #[cfg(target_os = "macos")]
     pub fn apple(&self) -> &apple::config::Config {
         &self.apple
@@ -175,6 +216,24 @@ impl Config {
         &self.android
     }
 
+    pub fn editor(&self) -> &editor::Config {
+        &self.editor
+    }
+
+    pub fn open(&self) -> &open::Config {
+        &self.open
+    }
+
+    pub fn telemetry(&self) -> &telemetry::Config {
+        &self.telemetry
+    }
+
+    /// The project's shared defaults for flags, from
+    /// [`defaults::FILE_NAME`].
+    pub fn defaults(&self) -> &defaults::Defaults {
+        &self.defaults
+    }
+
     pub fn build_a_bike(&self) -> bicycle::Bicycle {
         templating::init(Some(self))
     }
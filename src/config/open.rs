@@ -0,0 +1,203 @@
+use crate::util;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ToolRaw {
+    /// `editor`, `terminal`, `android-studio`, `xcode`, or the name of any
+    /// other application.
+    pub app: String,
+    /// `project`, `android`, `apple`, or a path relative to the app root.
+    /// Defaults to `project`.
+    pub open: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Raw {
+    #[serde(default)]
+    pub tools: Vec<ToolRaw>,
+    /// Open the project after `cargo mobile init`, as if `--open` was passed.
+    pub auto: Option<bool>,
+    /// Open the build log in the editor when a build with `--build-log`
+    /// fails.
+    pub build_log_on_failure: Option<bool>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ToolApp {
+    /// Whatever the user's default editor is.
+    Editor,
+    /// Whatever the user's default terminal is, opened in the dir.
+    Terminal,
+    AndroidStudio,
+    Xcode,
+    Other(String),
+}
+
+impl ToolApp {
+    fn from_raw(app: String) -> Self {
+        match app.as_str() {
+            "editor" => Self::Editor,
+            "terminal" => Self::Terminal,
+            "android-studio" => Self::AndroidStudio,
+            "xcode" => Self::Xcode,
+            _ => Self::Other(
+                util::expand_config_path(&app)
+                    .to_string_lossy()
+                    .into_owned(),
+            ),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ToolTarget {
+    /// The app root.
+    Project,
+    /// The generated Android Studio project.
+    Android,
+    /// The generated Xcode project.
+    Apple,
+    /// A path relative to the app root.
+    Path(PathBuf),
+}
+
+impl ToolTarget {
+    fn from_raw(open: Option<String>) -> Self {
+        match open.as_deref() {
+            None | Some("project") => Self::Project,
+            Some("android") => Self::Android,
+            Some("apple") => Self::Apple,
+            Some(path) => Self::Path(util::expand_config_path(path)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Tool {
+    pub app: ToolApp,
+    pub target: ToolTarget,
+}
+
+/// The tools that `cargo mobile open` launches together.
+#[derive(Clone, Debug, Default, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Config {
+    tools: Vec<Tool>,
+    auto: bool,
+    build_log_on_failure: bool,
+}
+
+impl Config {
+    pub fn from_raw(raw: Option<Raw>) -> Self {
+        let raw = raw.unwrap_or_default();
+        Self {
+            tools: raw
+                .tools
+                .into_iter()
+                .map(|tool| Tool {
+                    app: ToolApp::from_raw(tool.app),
+                    target: ToolTarget::from_raw(tool.open),
+                })
+                .collect(),
+            auto: raw.auto.unwrap_or_default(),
+            build_log_on_failure: raw.build_log_on_failure.unwrap_or_default(),
+        }
+    }
+
+    /// The configured tools, which default to just opening the app root in
+    /// the user's editor.
+    pub fn tools(&self) -> Vec<Tool> {
+        if self.tools.is_empty() {
+            vec![Tool {
+                app: ToolApp::Editor,
+                target: ToolTarget::Project,
+            }]
+        } else {
+            self.tools.clone()
+        }
+    }
+
+    /// Whether to open the project once `init` is done, which it does when
+    /// `--open` is passed or `open.auto` is set. `no_open` (from `--no-open`
+    /// or `CARGO_MOBILE_NO_OPEN`) wins over both.
+    pub fn open_after_init(&self, open_flag: bool, no_open: bool) -> bool {
+        !no_open && (open_flag || self.auto)
+    }
+
+    /// Overrides `open.auto`, i.e. with the project's shared defaults.
+    pub(crate) fn set_auto(&mut self, auto: bool) {
+        self.auto = auto;
+    }
+
+    /// Whether to open the build log when a build fails, from
+    /// `open.build-log-on-failure`.
+    pub fn build_log_on_failure(&self) -> bool {
+        self.build_log_on_failure
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest(
+        open_flag,
+        no_open,
+        auto,
+        expected,
+        case(false, false, false, false),
+        case(true, false, false, true),
+        case(false, false, true, true),
+        case(true, true, false, false),
+        case(false, true, true, false),
+        case(true, true, true, false)
+    )]
+    fn no_open_wins(open_flag: bool, no_open: bool, auto: bool, expected: bool) {
+        let config = Config::from_raw(Some(Raw {
+            tools: Vec::new(),
+            auto: Some(auto),
+            build_log_on_failure: None,
+        }));
+        assert_eq!(config.open_after_init(open_flag, no_open), expected);
+    }
+
+    #[test]
+    fn expands_tildes_in_tools() {
+        let home = util::home_dir().unwrap();
+        let tools = Config::from_raw(Some(Raw {
+            tools: vec![
+                ToolRaw {
+                    app: "~/bin/my-editor".to_owned(),
+                    open: Some("~/notes".to_owned()),
+                },
+                ToolRaw {
+                    app: "editor".to_owned(),
+                    open: Some("docs/~draft".to_owned()),
+                },
+            ],
+            auto: None,
+            build_log_on_failure: None,
+        }))
+        .tools();
+        assert_eq!(
+            tools,
+            vec![
+                Tool {
+                    app: ToolApp::Other(home.join("bin/my-editor").to_string_lossy().into_owned()),
+                    target: ToolTarget::Path(home.join("notes")),
+                },
+                Tool {
+                    app: ToolApp::Editor,
+                    target: ToolTarget::Path("docs/~draft".into()),
+                },
+            ]
+        );
+    }
+}
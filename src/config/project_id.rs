@@ -0,0 +1,55 @@
+use crate::util::{self, NoHomeDir};
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
+
+// FNV-1a, since `DefaultHasher` isn't guaranteed to be stable across Rust
+// releases, and these ids get persisted on disk.
+fn fnv1a(bytes: impl IntoIterator<Item = u8>) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0100_0000_01b3;
+    bytes.into_iter().fold(OFFSET_BASIS, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(PRIME)
+    })
+}
+
+/// A stable identifier for the project at `project_dir`, for namespacing
+/// caches and other state that lives outside of the project. It's derived from
+/// the canonical path, so it's the same no matter how the project is reached,
+/// and the dir name is kept as a prefix to make it recognizable.
+pub fn project_id(project_dir: impl AsRef<Path>) -> String {
+    project_id_with_bundle(project_dir, None)
+}
+
+/// Like [`project_id`], but also mixes in the bundle id, for state that
+/// shouldn't be shared between differently identified builds of the same
+/// project.
+pub fn project_id_with_bundle(project_dir: impl AsRef<Path>, bundle_id: Option<&str>) -> String {
+    let project_dir = project_dir.as_ref();
+    let canonical = dunce::canonicalize(project_dir).unwrap_or_else(|_| project_dir.to_owned());
+    let path = canonical.to_string_lossy();
+    let hash = fnv1a(path.bytes().chain(bundle_id.into_iter().flat_map(|id| {
+        // The separator keeps `("/a", "b")` and `("/ab", "")` apart.
+        std::iter::once(0).chain(id.bytes())
+    })));
+    let name = canonical
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    if name.is_empty() {
+        format!("{:016x}", hash)
+    } else {
+        format!("{}-{:016x}", name, hash)
+    }
+}
+
+/// `$XDG_CACHE_HOME/cargo-mobile/<project id>`, falling back to
+/// `~/.cache` if `XDG_CACHE_HOME` isn't set.
+pub fn cache_dir(project_dir: impl AsRef<Path>) -> Result<PathBuf, NoHomeDir> {
+    let cache_home = match env::var_os("XDG_CACHE_HOME").filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => util::home_dir()?.join(".cache"),
+    };
+    Ok(cache_home.join(crate::NAME).join(project_id(project_dir)))
+}
@@ -1,4 +1,4 @@
-use super::app;
+use super::{app, editor, open, telemetry};
 #[cfg(target_os = "macos")]
 use crate::apple;
 use crate::{
@@ -77,6 +77,9 @@ pub struct Raw {
     #[cfg(target_os = "macos")]
     pub apple: Option<apple::config::Raw>,
     pub android: Option<android::config::Raw>,
+    pub editor: Option<editor::Raw>,
+    pub open: Option<open::Raw>,
+    pub telemetry: Option<telemetry::Raw>,
 }
 
 impl Raw {
@@ -89,6 +92,9 @@ impl Raw {
             #[cfg(target_os = "macos")]
             apple: Some(apple),
             android: None,
+            editor: None,
+            open: None,
+            telemetry: None,
         })
     }
 
@@ -101,6 +107,9 @@ impl Raw {
             #[cfg(target_os = "macos")]
             apple: Some(apple),
             android: None,
+            editor: None,
+            open: None,
+            telemetry: None,
         })
     }
 
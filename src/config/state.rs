@@ -0,0 +1,133 @@
+use super::cache_dir;
+use crate::util::NoHomeDir;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+
+static FILE_NAME: &str = "state.toml";
+
+#[derive(Debug, Error)]
+pub enum StateError {
+    #[error("Failed to locate cache dir: {0}")]
+    CacheDirUnavailable(NoHomeDir),
+    #[error("Failed to read project state from {path:?}: {cause}")]
+    ReadFailed { path: PathBuf, cause: io::Error },
+    #[error("Failed to parse project state at {path:?}: {cause}")]
+    ParseFailed {
+        path: PathBuf,
+        cause: toml::de::Error,
+    },
+    #[error("Failed to serialize project state: {0}")]
+    SerializeFailed(toml::ser::Error),
+    #[error("Failed to create directory {path:?}: {cause}")]
+    DirCreationFailed { path: PathBuf, cause: io::Error },
+    #[error("Failed to write project state to {path:?}: {cause}")]
+    WriteFailed { path: PathBuf, cause: io::Error },
+}
+
+/// Things worth remembering between runs that don't belong in the project,
+/// like which device was used last. This lives in the project's cache dir,
+/// so losing it is never a problem.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct State {
+    /// Device IDs, keyed by platform.
+    #[serde(default)]
+    last_device: BTreeMap<String, String>,
+}
+
+impl State {
+    fn path(project_dir: &Path) -> Result<PathBuf, StateError> {
+        cache_dir(project_dir)
+            .map(|dir| dir.join(FILE_NAME))
+            .map_err(StateError::CacheDirUnavailable)
+    }
+
+    pub fn load(project_dir: impl AsRef<Path>) -> Result<Self, StateError> {
+        let path = Self::path(project_dir.as_ref())?;
+        match fs::read(&path) {
+            Ok(bytes) => {
+                toml::from_slice(&bytes).map_err(|cause| StateError::ParseFailed { path, cause })
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(cause) => Err(StateError::ReadFailed { path, cause }),
+        }
+    }
+
+    pub fn write(&self, project_dir: impl AsRef<Path>) -> Result<(), StateError> {
+        let path = Self::path(project_dir.as_ref())?;
+        let dir = path
+            .parent()
+            .expect("developer error: state path had no parent");
+        fs::create_dir_all(dir).map_err(|cause| StateError::DirCreationFailed {
+            path: dir.to_owned(),
+            cause,
+        })?;
+        let ser = toml::to_string_pretty(self).map_err(StateError::SerializeFailed)?;
+        fs::write(&path, ser).map_err(|cause| StateError::WriteFailed { path, cause })
+    }
+
+    pub fn last_device(&self, platform: &str) -> Option<&str> {
+        self.last_device.get(platform).map(String::as_str)
+    }
+
+    pub fn set_last_device(&mut self, platform: &str, id: &str) {
+        self.last_device.insert(platform.to_owned(), id.to_owned());
+    }
+}
+
+/// The ID of the device last used for `platform` in this project, if any.
+/// Failing to load the state just means we don't remember.
+pub fn last_device(project_dir: impl AsRef<Path>, platform: &str) -> Option<String> {
+    match State::load(project_dir) {
+        Ok(state) => state.last_device(platform).map(ToOwned::to_owned),
+        Err(err) => {
+            log::warn!("failed to recall last-used device: {}", err);
+            None
+        }
+    }
+}
+
+/// Records `id` as the device last used for `platform`. This is only a
+/// convenience, so failures are logged rather than returned.
+pub fn remember_device(project_dir: impl AsRef<Path>, platform: &str, id: &str) {
+    let project_dir = project_dir.as_ref();
+    let result = State::load(project_dir).and_then(|mut state| {
+        if state.last_device(platform) == Some(id) {
+            return Ok(());
+        }
+        state.set_last_device(platform, id);
+        state.write(project_dir)
+    });
+    if let Err(err) = result {
+        log::warn!("failed to remember last-used device: {}", err);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn remembers_devices_per_platform() {
+        let project_dir = crate::util::temp_dir().join("state-test");
+        fs::create_dir_all(&project_dir).unwrap();
+        let state_dir = cache_dir(&project_dir).unwrap();
+        // In case an earlier run didn't get to clean up.
+        let _ = fs::remove_dir_all(&state_dir);
+        assert_eq!(last_device(&project_dir, "Android"), None);
+        remember_device(&project_dir, "Android", "emulator-5554");
+        remember_device(&project_dir, "iOS", "00008110-001A2B3C4D5E6F70");
+        remember_device(&project_dir, "Android", "R5CT1234567");
+        let android = last_device(&project_dir, "Android");
+        let ios = last_device(&project_dir, "iOS");
+        fs::remove_dir_all(&state_dir).unwrap();
+        fs::remove_dir_all(&project_dir).unwrap();
+        assert_eq!(android.as_deref(), Some("R5CT1234567"));
+        assert_eq!(ios.as_deref(), Some("00008110-001A2B3C4D5E6F70"));
+    }
+}
@@ -0,0 +1,146 @@
+use crate::env::Suppression;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, ffi::OsString};
+
+/// What Gradle's told so that it won't publish build scans, even if the
+/// project or an init script asks it to.
+static NO_GRADLE_SCANS: &str = "-Dscan=false";
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Raw {
+    /// Set `DO_NOT_TRACK=1` for every tool we run. Defaults to `false`.
+    pub do_not_track: Option<bool>,
+    /// Allow Gradle to publish build scans. Defaults to `true`; set it to
+    /// `false` to add `-Dscan=false` to `GRADLE_OPTS`.
+    pub gradle_scans: Option<bool>,
+    /// Extra env vars for every tool we run, i.e. to give them a user agent
+    /// or identifier that a corporate proxy expects.
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Config {
+    do_not_track: bool,
+    gradle_scans: bool,
+    env: BTreeMap<String, String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::from_raw(None)
+    }
+}
+
+impl Config {
+    pub fn from_raw(raw: Option<Raw>) -> Self {
+        let raw = raw.unwrap_or_default();
+        Self {
+            do_not_track: raw.do_not_track.unwrap_or_default(),
+            gradle_scans: raw.gradle_scans.unwrap_or(true),
+            env: raw.env,
+        }
+    }
+
+    fn suppressions_with(&self, gradle_opts: Option<OsString>) -> Vec<Suppression> {
+        let mut suppressions = Vec::new();
+        if self.do_not_track {
+            suppressions.push(Suppression {
+                var: "DO_NOT_TRACK".into(),
+                value: "1".into(),
+                reason: "`telemetry.do-not-track` is set".into(),
+            });
+        }
+        if !self.gradle_scans {
+            // Since `GRADLE_OPTS` isn't ours alone, we add to whatever the
+            // user already had.
+            let value = match gradle_opts.filter(|opts| !opts.is_empty()) {
+                Some(mut opts) => {
+                    opts.push(" ");
+                    opts.push(NO_GRADLE_SCANS);
+                    opts
+                }
+                None => NO_GRADLE_SCANS.into(),
+            };
+            suppressions.push(Suppression {
+                var: "GRADLE_OPTS".into(),
+                value,
+                reason: "`telemetry.gradle-scans` is disabled".into(),
+            });
+        }
+        for (var, value) in &self.env {
+            suppressions.push(Suppression {
+                var: var.clone(),
+                value: value.into(),
+                reason: "it's listed in `telemetry.env`".into(),
+            });
+        }
+        suppressions
+    }
+
+    /// The env vars to set on spawned tools, as configured in the `telemetry`
+    /// section. These are applied using [`crate::env::Env::suppress_telemetry`].
+    pub fn suppressions(&self) -> Vec<Suppression> {
+        self.suppressions_with(std::env::var_os("GRADLE_OPTS"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    fn vars(config: &Config, gradle_opts: Option<&str>) -> Vec<(String, OsString)> {
+        config
+            .suppressions_with(gradle_opts.map(Into::into))
+            .into_iter()
+            .map(|suppression| (suppression.var, suppression.value))
+            .collect()
+    }
+
+    #[test]
+    fn suppresses_nothing_by_default() {
+        assert!(vars(&Config::default(), Some("-Xmx2g")).is_empty());
+    }
+
+    #[rstest(
+        gradle_opts,
+        expected,
+        case(None, "-Dscan=false"),
+        case(Some(""), "-Dscan=false"),
+        case(Some("-Xmx2g"), "-Xmx2g -Dscan=false")
+    )]
+    fn adds_to_gradle_opts(gradle_opts: Option<&str>, expected: &str) {
+        let config = Config::from_raw(Some(Raw {
+            gradle_scans: Some(false),
+            ..Default::default()
+        }));
+        assert_eq!(
+            vars(&config, gradle_opts),
+            vec![("GRADLE_OPTS".to_owned(), OsString::from(expected))]
+        );
+    }
+
+    #[test]
+    fn includes_extra_env() {
+        let config = Config::from_raw(Some(Raw {
+            do_not_track: Some(true),
+            gradle_scans: None,
+            env: vec![("HTTP_USER_AGENT_SUFFIX".to_owned(), "acme-ci".to_owned())]
+                .into_iter()
+                .collect(),
+        }));
+        assert_eq!(
+            vars(&config, None),
+            vec![
+                ("DO_NOT_TRACK".to_owned(), OsString::from("1")),
+                (
+                    "HTTP_USER_AGENT_SUFFIX".to_owned(),
+                    OsString::from("acme-ci")
+                ),
+            ]
+        );
+    }
+}
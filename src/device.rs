@@ -1,5 +1,15 @@
-use crate::util::cli::{Report, Reportable};
+#[cfg(target_os = "macos")]
+use crate::apple::simulator;
+use crate::{
+    android::{self, emulator},
+    bossy, os,
+    util::{
+        cli::{Report, Reportable},
+        prompt,
+    },
+};
 use std::{
+    collections::BTreeSet,
     error::Error,
     fmt::{self, Debug, Display},
     io,
@@ -13,6 +23,10 @@ pub enum PromptErrorCause<T: Reportable + Error> {
     PromptFailed(io::Error),
     #[error("No connected devices detected")]
     NoneDetected,
+    #[error("Multiple devices are connected, so one needs to be picked with `--device`")]
+    MultipleDetected,
+    #[error("No connected device has the ID {0:?}")]
+    NotFound(String),
 }
 
 #[derive(Debug)]
@@ -33,6 +47,16 @@ impl<T: Reportable + Error> Display for PromptError<T> {
                 "Failed to prompt for {} device: No connected devices detected",
                 self.name
             ),
+            PromptErrorCause::MultipleDetected => write!(
+                f,
+                "Failed to pick {} device: Multiple devices are connected, so one needs to be picked with `--device`",
+                self.name
+            ),
+            PromptErrorCause::NotFound(id) => write!(
+                f,
+                "Failed to pick {} device: No connected device has the ID {:?}",
+                self.name, id
+            ),
         }
     }
 }
@@ -50,6 +74,14 @@ impl<T: Reportable + Error> Reportable for PromptError<T> {
                 format!("Failed to prompt for {} device", self.name),
                 format!("No connected {} devices detected", self.name),
             ),
+            PromptErrorCause::MultipleDetected => Report::action_request(
+                format!("Multiple {} devices are connected", self.name),
+                "We can't ask which one to use without an interactive terminal, so please pick one with `--device <id>`; `list` shows each device's ID.",
+            ),
+            PromptErrorCause::NotFound(id) => Report::action_request(
+                format!("No connected {} device has the ID {:?}", self.name, id),
+                "Check that the device is connected, and that the ID matches one shown by `list`.",
+            ),
         }
     }
 }
@@ -72,40 +104,409 @@ impl<T: Reportable + Error> PromptError<T> {
     }
 }
 
+/// IDs shared by more than one device. Tools like `adb` pick devices by ID,
+/// so they can't reliably reach any of these.
+pub fn duplicate_ids<'a>(ids: impl IntoIterator<Item = &'a str>) -> BTreeSet<&'a str> {
+    let mut seen = BTreeSet::new();
+    ids.into_iter().filter(|id| !seen.insert(*id)).collect()
+}
+
+/// Picks which of `devices` to use: the one with the ID `id` if given,
+/// otherwise the only one, otherwise the `remembered` one if it's still
+/// connected, otherwise whichever the user chooses. If we can't prompt, the
+/// user has to say which one with `--device`.
+pub fn select<T: Display, E: Reportable + Error>(
+    name: &'static str,
+    devices: impl IntoIterator<Item = T>,
+    id: Option<&str>,
+    remembered: Option<&str>,
+    id_of: impl Fn(&T) -> &str,
+) -> Result<T, PromptError<E>> {
+    let devices = devices.into_iter().collect::<Vec<_>>();
+    if let Some(id) = id {
+        return devices
+            .into_iter()
+            .find(|device| id_of(device) == id)
+            .ok_or_else(|| PromptError::new(name, PromptErrorCause::NotFound(id.to_owned())));
+    }
+    let remembered = remembered
+        .filter(|_| devices.len() > 1)
+        .and_then(|remembered| {
+            devices
+                .iter()
+                .position(|device| id_of(device) == remembered)
+        });
+    let index = match (devices.len(), remembered) {
+        (0, _) => return Err(PromptError::none_detected(name)),
+        (1, _) => 0,
+        (_, Some(index)) => {
+            println!(
+                "Using {}, since it was used last; pass `--device` to use another device.",
+                devices[index]
+            );
+            index
+        }
+        _ if !os::can_prompt() => {
+            return Err(PromptError::new(name, PromptErrorCause::MultipleDetected))
+        }
+        _ => prompt::list(
+            format!("Detected {} devices", name),
+            devices.iter(),
+            "device",
+            None,
+            "Device",
+        )
+        .map_err(|cause| PromptError::prompt_failed(name, cause))?,
+    };
+    Ok(devices
+        .into_iter()
+        .nth(index)
+        .expect("developer error: selected device index was out of bounds"))
+}
+
+/// The scheme of `url`, i.e. `myapp` for `myapp://settings`, or `None` if it
+/// doesn't start with a valid one.
+pub fn url_scheme(url: &str) -> Option<&str> {
+    let (scheme, _) = url.split_once(':')?;
+    let mut chars = scheme.chars();
+    let valid = chars.next()?.is_ascii_alphabetic()
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+    valid.then_some(scheme)
+}
+
+/// The kind of virtual device an instance is.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum InstanceKind {
+    /// An Android emulator.
+    Emulator,
+    /// An iOS simulator.
+    Simulator,
+}
+
+/// A running emulator or simulator.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RunningInstance {
+    pub kind: InstanceKind,
+    /// The serial number for emulators, or the UDID for simulators.
+    pub id: String,
+    pub name: Option<String>,
+}
+
+impl Display for RunningInstance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self.kind {
+            InstanceKind::Emulator => "Android emulator",
+            InstanceKind::Simulator => "iOS simulator",
+        };
+        match &self.name {
+            Some(name) => write!(f, "{} {} ({})", kind, name, self.id),
+            None => write!(f, "{} {}", kind, self.id),
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ShutdownError {
+    #[error(transparent)]
+    AndroidEnvFailed(android::env::Error),
+    #[error("Failed to list running emulators: {0}")]
+    EmulatorListFailed(#[source] Box<bossy::Error>),
+    #[error("Failed to kill emulator {id:?}: {cause}")]
+    EmulatorKillFailed {
+        id: String,
+        #[source]
+        cause: Box<bossy::Error>,
+    },
+    #[cfg(target_os = "macos")]
+    #[error(transparent)]
+    SimulatorListFailed(simulator::BootedError),
+    #[cfg(target_os = "macos")]
+    #[error("Failed to shut down simulator {id:?}: {cause}")]
+    SimulatorShutdownFailed {
+        id: String,
+        #[source]
+        cause: Box<bossy::Error>,
+    },
+    #[error("{0:?} isn't the ID of an emulator or simulator.")]
+    NotVirtual(String),
+}
+
+impl Reportable for ShutdownError {
+    fn report(&self) -> Report {
+        match self {
+            Self::AndroidEnvFailed(err) => err.report(),
+            Self::NotVirtual(_) => Report::action_request("Failed to shut down device", self),
+            _ => Report::error("Failed to shut down device", self),
+        }
+    }
+}
+
+/// Emulators are only there if the Android SDK is, so not having one just
+/// means there's nothing to find.
+fn android_env() -> Option<android::env::Env> {
+    android::env::Env::new()
+        .map_err(|err| {
+            log::info!(
+                "not looking for Android emulators, since the Android environment isn't usable: {}",
+                err
+            )
+        })
+        .ok()
+}
+
+fn running_emulators(env: &android::env::Env) -> Result<Vec<RunningInstance>, ShutdownError> {
+    Ok(emulator::running(env)
+        .map_err(|err| ShutdownError::EmulatorListFailed(Box::new(err)))?
+        .into_iter()
+        .map(|emulator| RunningInstance {
+            kind: InstanceKind::Emulator,
+            id: emulator.serial_no,
+            name: emulator.avd_name,
+        })
+        .collect())
+}
+
+#[cfg(target_os = "macos")]
+fn booted_simulators() -> Result<Vec<RunningInstance>, ShutdownError> {
+    Ok(simulator::booted()
+        .map_err(ShutdownError::SimulatorListFailed)?
+        .into_iter()
+        .map(|simulator| RunningInstance {
+            kind: InstanceKind::Simulator,
+            id: simulator.udid,
+            name: Some(simulator.name),
+        })
+        .collect())
+}
+
+/// The emulators and simulators that are currently running.
+pub fn list_running() -> Result<Vec<RunningInstance>, ShutdownError> {
+    let running = match android_env() {
+        Some(env) => running_emulators(&env)?,
+        None => Vec::new(),
+    };
+    #[cfg(target_os = "macos")]
+    let running = running.into_iter().chain(booted_simulators()?).collect();
+    Ok(running)
+}
+
+/// Shuts down the emulator or simulator with `id`. Returns `false` if it was
+/// already gone.
+pub fn shutdown(id: &str) -> Result<bool, ShutdownError> {
+    if id.starts_with("emulator-") {
+        let env = android::env::Env::new().map_err(ShutdownError::AndroidEnvFailed)?;
+        return emulator::kill(&env, id).map_err(|cause| ShutdownError::EmulatorKillFailed {
+            id: id.to_owned(),
+            cause: Box::new(cause),
+        });
+    }
+    // On macOS, anything else is taken to be a simulator's UDID, and
+    // `simctl` treats ones it doesn't know as already gone. Elsewhere, it
+    // could only be a physical device.
+    #[cfg(target_os = "macos")]
+    return simulator::shutdown(id).map_err(|cause| ShutdownError::SimulatorShutdownFailed {
+        id: id.to_owned(),
+        cause: Box::new(cause),
+    });
+    #[cfg(not(target_os = "macos"))]
+    Err(ShutdownError::NotVirtual(id.to_owned()))
+}
+
+/// What came of [`shutdown_all_emulators`].
+#[derive(Debug, Default)]
+pub struct ShutdownAll {
+    /// The ones that were actually shut down; any that went away on their
+    /// own in the meantime are left out.
+    pub stopped: Vec<RunningInstance>,
+    /// Everything that went wrong, including failing to list what was
+    /// running in the first place.
+    pub failures: Vec<ShutdownError>,
+}
+
+/// Kills every running emulator and shuts down every booted simulator, which
+/// is handy for cleaning up after CI runs. One failing to shut down doesn't
+/// stop the rest from being shut down.
+pub fn shutdown_all_emulators() -> ShutdownAll {
+    let mut result = ShutdownAll::default();
+    if let Some(env) = android_env() {
+        match running_emulators(&env) {
+            Ok(running) => {
+                for instance in running {
+                    match emulator::kill(&env, &instance.id) {
+                        Ok(true) => result.stopped.push(instance),
+                        Ok(false) => (),
+                        Err(cause) => result.failures.push(ShutdownError::EmulatorKillFailed {
+                            id: instance.id,
+                            cause: Box::new(cause),
+                        }),
+                    }
+                }
+            }
+            Err(err) => result.failures.push(err),
+        }
+    }
+    #[cfg(target_os = "macos")]
+    match booted_simulators() {
+        Ok(booted) => {
+            for instance in booted {
+                match simulator::shutdown(&instance.id) {
+                    Ok(true) => result.stopped.push(instance),
+                    Ok(false) => (),
+                    Err(cause) => result
+                        .failures
+                        .push(ShutdownError::SimulatorShutdownFailed {
+                            id: instance.id,
+                            cause: Box::new(cause),
+                        }),
+                }
+            }
+        }
+        Err(err) => result.failures.push(err),
+    }
+    result
+}
+
 #[macro_export]
 macro_rules! define_device_prompt {
     ($func:path, $e:ty, $name:ident) => {
-        fn device_prompt<'a>(env: &'_ Env) -> Result<Device<'a>, $crate::device::PromptError<$e>> {
+        fn device_prompt<'a>(
+            env: &'_ Env,
+            id: Option<&str>,
+            project_dir: Option<&std::path::Path>,
+        ) -> Result<Device<'a>, $crate::device::PromptError<$e>> {
             let device_list = $func(env).map_err(|cause| {
                 $crate::device::PromptError::detection_failed(stringify!($name), cause)
             })?;
-            if device_list.len() > 0 {
-                let index = if device_list.len() > 1 {
-                    prompt::list(
-                        concat!("Detected ", stringify!($name), " devices"),
-                        device_list.iter(),
-                        "device",
-                        None,
-                        "Device",
-                    )
-                    .map_err(|cause| {
-                        $crate::device::PromptError::prompt_failed(stringify!($name), cause)
-                    })?
-                } else {
-                    0
-                };
-                let device = device_list.into_iter().nth(index).unwrap();
-                println!(
-                    "Detected connected device: {} with target {:?}",
-                    device,
-                    device.target().triple,
-                );
-                Ok(device)
-            } else {
-                Err($crate::device::PromptError::none_detected(stringify!(
-                    $name
-                )))
+            let remembered = project_dir
+                .and_then(|dir| $crate::config::state::last_device(dir, stringify!($name)));
+            let device = $crate::device::select(
+                stringify!($name),
+                device_list,
+                id,
+                remembered.as_deref(),
+                Device::id,
+            )?;
+            if let Some(dir) = project_dir {
+                $crate::config::state::remember_device(dir, stringify!($name), device.id());
             }
+            println!(
+                "Detected connected device: {} with target {:?}",
+                device,
+                device.target().triple,
+            );
+            Ok(device)
         }
     };
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest(
+        url,
+        expected,
+        case("myapp://settings?tab=2", Some("myapp")),
+        case("com.example.hello:/path", Some("com.example.hello")),
+        case("x-callback+v2://", Some("x-callback+v2")),
+        case("https://example.com", Some("https")),
+        case("settings", None),
+        case("://settings", None),
+        case("2fa://code", None),
+        case("my app://settings", None)
+    )]
+    fn parses_url_scheme(url: &str, expected: Option<&str>) {
+        assert_eq!(url_scheme(url), expected);
+    }
+
+    #[rstest(
+        kind,
+        id,
+        name,
+        expected,
+        case(
+            InstanceKind::Emulator,
+            "emulator-5554",
+            Some("Pixel_7_API_34"),
+            "Android emulator Pixel_7_API_34 (emulator-5554)"
+        ),
+        case(
+            InstanceKind::Emulator,
+            "emulator-5554",
+            None,
+            "Android emulator emulator-5554"
+        ),
+        case(
+            InstanceKind::Simulator,
+            "5B9E6C5A-7B21-4E1F-9A35-2D1F0C9F1A10",
+            Some("iPhone 15"),
+            "iOS simulator iPhone 15 (5B9E6C5A-7B21-4E1F-9A35-2D1F0C9F1A10)"
+        )
+    )]
+    fn describes_running_instances(
+        kind: InstanceKind,
+        id: &str,
+        name: Option<&str>,
+        expected: &str,
+    ) {
+        let instance = RunningInstance {
+            kind,
+            id: id.to_owned(),
+            name: name.map(ToOwned::to_owned),
+        };
+        assert_eq!(instance.to_string(), expected);
+    }
+
+    fn select_from(
+        devices: &[&'static str],
+        id: Option<&str>,
+        remembered: Option<&str>,
+    ) -> Result<&'static str, Box<PromptErrorCause<android::adb::device_list::Error>>> {
+        select(
+            "Android",
+            devices.iter().copied(),
+            id,
+            remembered,
+            |device| device,
+        )
+        .map_err(|err| Box::new(err.cause))
+    }
+
+    #[rstest(
+        devices,
+        id,
+        remembered,
+        expected,
+        case(&["pixel"], None, None, "pixel"),
+        case(&["pixel"], None, Some("tablet"), "pixel"),
+        case(&["pixel", "tablet", "emulator-5554"], Some("tablet"), None, "tablet"),
+        case(&["pixel", "tablet"], Some("pixel"), Some("tablet"), "pixel"),
+        case(&["pixel", "tablet", "emulator-5554"], None, Some("emulator-5554"), "emulator-5554")
+    )]
+    fn selects_devices(
+        devices: &[&'static str],
+        id: Option<&str>,
+        remembered: Option<&str>,
+        expected: &str,
+    ) {
+        assert_eq!(select_from(devices, id, remembered).unwrap(), expected);
+    }
+
+    #[test]
+    fn fails_without_a_match() {
+        let cause = |devices, id, remembered| *select_from(devices, id, remembered).unwrap_err();
+        assert!(matches!(
+            cause(&["pixel", "tablet"], Some("phone"), Some("pixel")),
+            PromptErrorCause::NotFound(id) if id == "phone"
+        ));
+        assert!(matches!(
+            cause(&[], Some("phone"), None),
+            PromptErrorCause::NotFound(_)
+        ));
+        assert!(matches!(
+            cause(&[], None, Some("pixel")),
+            PromptErrorCause::NoneDetected
+        ));
+    }
+}
@@ -0,0 +1,236 @@
+use crate::{
+    android, bossy,
+    build_manifest::BuildManifest,
+    config::{self, app::App, defaults::Defaults},
+    doctor, init,
+    os::Env,
+    target::TargetTrait,
+    util::{
+        self,
+        cli::{Report, Reportable, VERSION_LONG},
+        toolchain::Toolchain,
+    },
+};
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    fs, io,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use thiserror::Error;
+
+static REDACTED: &str = "[redacted]";
+
+// Env vars with any of these in their name are assumed to hold secrets.
+static SECRET_MARKERS: &[&str] = &[
+    "AUTH",
+    "CREDENTIAL",
+    "KEY",
+    "PASS",
+    "SECRET",
+    "SESSION",
+    "TOKEN",
+];
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error(transparent)]
+    DoctorFailed(doctor::Unrecoverable),
+    #[error("Failed to locate cache dir: {0}")]
+    CacheDirUnavailable(util::NoHomeDir),
+    #[error("Failed to create diagnostics directory at {path:?}: {cause}")]
+    DirCreationFailed { path: PathBuf, cause: io::Error },
+    #[error("Failed to write diagnostics bundle to {path:?}: {cause}")]
+    WriteFailed { path: PathBuf, cause: io::Error },
+}
+
+impl Reportable for Error {
+    fn report(&self) -> Report {
+        Report::error("Failed to gather diagnostics", self)
+    }
+}
+
+fn is_secret(name: &str, extra_secrets: &[&str]) -> bool {
+    let upper = name.to_ascii_uppercase();
+    extra_secrets.contains(&name) || SECRET_MARKERS.iter().any(|marker| upper.contains(marker))
+}
+
+fn heading(out: &mut String, title: &str) {
+    let _ = writeln!(out, "\n## {}\n", title);
+}
+
+fn tool_version(out: &mut String, name: &str) {
+    let version = bossy::Command::impure(name)
+        .with_arg("--version")
+        .run_and_wait_for_str(|output| output.trim().to_owned());
+    let _ = match version {
+        Ok(version) => writeln!(out, "{}: {}", name, version),
+        Err(err) => writeln!(out, "{}: unavailable ({})", name, err),
+    };
+}
+
+fn file_contents(out: &mut String, path: &Path) {
+    let _ = match fs::read_to_string(path) {
+        Ok(contents) => writeln!(out, "{:?}:\n{}", path, contents.trim_end()),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            writeln!(out, "{:?}: not present", path)
+        }
+        Err(err) => writeln!(out, "{:?}: unreadable ({})", path, err),
+    };
+}
+
+fn plan_targets<'a, T: TargetTrait<'a> + 'a>(
+    out: &mut String,
+    platform: &str,
+    defaults: &Defaults,
+) {
+    let targets = defaults
+        .targets_or_default::<T>(Vec::new())
+        .iter()
+        .map(|name| match T::for_name(name) {
+            Some(target) => format!("{} ({})", name, target.triple()),
+            None => format!("{} (unknown)", name),
+        })
+        .collect::<Vec<_>>();
+    let _ = writeln!(out, "{} targets: {}", platform, targets.join(", "));
+}
+
+// What `build` would do without any flags, which is what the defaults file
+// and a pinned toolchain quietly change.
+fn build_plan(out: &mut String, root_dir: &Path) {
+    let defaults = match Defaults::load(root_dir) {
+        Ok(defaults) => defaults,
+        Err(err) => {
+            let _ = writeln!(out, "Failed to load defaults: {}", err);
+            Defaults::default()
+        }
+    };
+    let _ = writeln!(out, "Profile: {}", defaults.profile(None).as_str());
+    plan_targets::<android::target::Target>(out, "Android", &defaults);
+    #[cfg(target_os = "macos")]
+    plan_targets::<crate::apple::target::Target>(out, "Apple", &defaults);
+    if let Some(jobs) = defaults.jobs(None) {
+        let _ = writeln!(out, "Jobs: {}", jobs);
+    }
+    let _ = match Toolchain::detect(root_dir) {
+        Ok(Some(toolchain)) => writeln!(out, "Toolchain: {}", toolchain),
+        Ok(None) => writeln!(out, "Toolchain: default"),
+        Err(err) => writeln!(out, "Toolchain: invalid ({})", err),
+    };
+}
+
+/// Gathers everything we'd want to see in a bug report into a single text
+/// file in the project's cache dir, and returns its path. Env vars that look
+/// like they hold secrets have their values redacted, as does whatever
+/// `android.keystore-password-env` points to.
+pub fn bundle(project_dir: impl AsRef<Path>) -> Result<PathBuf, Error> {
+    let project_dir = project_dir.as_ref();
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "# cargo-mobile diagnostics\n\n{}",
+        VERSION_LONG.as_str()
+    );
+
+    heading(&mut out, "Doctor");
+    let env = Env::new().map_err(|err| Error::DoctorFailed(err.into()))?;
+    for section in doctor::sections(&env).map_err(Error::DoctorFailed)? {
+        let _ = writeln!(out, "{}", section);
+    }
+
+    heading(&mut out, "Tool versions");
+    for tool in ["cargo", "rustc", "rustup", "git"] {
+        tool_version(&mut out, tool);
+    }
+
+    // A broken config is exactly the kind of thing people need help with, so
+    // we just note it rather than failing.
+    let config = match config::Raw::load(project_dir) {
+        Ok(config) => config,
+        Err(err) => {
+            let _ = writeln!(out, "\nFailed to load config: {}", err);
+            None
+        }
+    };
+    let keystore_password_env = config
+        .as_ref()
+        .and_then(|(_, raw)| raw.android.as_ref()?.keystore_password_env.clone());
+
+    heading(&mut out, "Environment");
+    let extra_secrets = keystore_password_env
+        .as_deref()
+        .into_iter()
+        .collect::<Vec<_>>();
+    let vars = std::env::vars_os()
+        .map(|(name, value)| (name.to_string_lossy().into_owned(), value))
+        .collect::<BTreeMap<_, _>>();
+    for (name, value) in vars {
+        if is_secret(&name, &extra_secrets) {
+            let _ = writeln!(out, "{}={}", name, REDACTED);
+        } else {
+            let _ = writeln!(out, "{}={}", name, value.to_string_lossy());
+        }
+    }
+
+    heading(&mut out, "Generated project");
+    let root_dir = config
+        .as_ref()
+        .map(|(root_dir, _)| root_dir.clone())
+        .unwrap_or_else(|| project_dir.to_owned());
+    file_contents(&mut out, &init::marker_path(&root_dir));
+
+    heading(&mut out, "Build plan");
+    build_plan(&mut out, &root_dir);
+
+    heading(&mut out, "Last build");
+    match config.and_then(|(root_dir, raw)| App::from_raw(root_dir, raw.app).ok()) {
+        Some(app) => file_contents(&mut out, &BuildManifest::path(&app)),
+        None => {
+            let _ = writeln!(
+                out,
+                "No valid config, so there's no build manifest to include"
+            );
+        }
+    }
+
+    let dir = config::cache_dir(&root_dir).map_err(Error::CacheDirUnavailable)?;
+    fs::create_dir_all(&dir).map_err(|cause| Error::DirCreationFailed {
+        path: dir.clone(),
+        cause,
+    })?;
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default();
+    let path = dir.join(format!("diagnostics-{}.txt", timestamp));
+    fs::write(&path, out).map_err(|cause| Error::WriteFailed {
+        path: path.clone(),
+        cause,
+    })?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest(
+        name,
+        expected,
+        case("PATH", false),
+        case("ANDROID_HOME", false),
+        case("GITHUB_TOKEN", true),
+        case("AWS_SECRET_ACCESS_KEY", true),
+        case("npm_config__authToken", true),
+        case("KEYSTORE_PASSWORD", true),
+        case("GOOGLE_APPLICATION_CREDENTIALS", true),
+        case("SSH_AUTH_SOCK", true),
+        case("STORE_PW", false),
+        case("RELEASE_STORE_PW", true)
+    )]
+    fn redacts_secret_vars(name: &str, expected: bool) {
+        assert_eq!(is_secret(name, &["RELEASE_STORE_PW"]), expected);
+    }
+}
@@ -21,12 +21,24 @@ pub enum Unrecoverable {
     ContractHomeFailed(#[from] util::ContractHomeError),
 }
 
+pub use self::section::Section;
+
+/// Runs every check-up, in the order they're shown.
+pub fn sections(env: &Env) -> Result<Vec<Section>, Unrecoverable> {
+    Ok(vec![
+        section::cargo_mobile::check()?,
+        #[cfg(target_os = "macos")]
+        section::apple::check(),
+        section::android::check(env)?,
+        section::editor::check()?,
+        section::device_list::check(env),
+    ])
+}
+
 pub fn exec(wrapper: &TextWrapper) -> Result<(), Unrecoverable> {
     let env = Env::new()?;
-    section::cargo_mobile::check()?.print(wrapper);
-    #[cfg(target_os = "macos")]
-    section::apple::check().print(wrapper);
-    section::android::check(&env)?.print(wrapper);
-    section::device_list::check(&env).print(wrapper);
+    for section in sections(&env)? {
+        section.print(wrapper);
+    }
     Ok(())
 }
@@ -1,5 +1,39 @@
-use super::Section;
-use crate::{android, doctor::Unrecoverable, os::Env, util};
+use super::{Item, Section};
+use crate::{
+    android,
+    doctor::Unrecoverable,
+    os::{self, Env},
+    util,
+};
+
+/// Warns about having more than one NDK installed, listing them all, since
+/// which one gets used is otherwise a mystery.
+fn ndk_resolution(env: &android::env::Env) -> Vec<Item> {
+    let resolution = android::ndk::resolution_report(env);
+    match resolution.warning() {
+        Some(warning) => std::iter::once(Item::warning(warning))
+            .chain(
+                resolution
+                    .ndks
+                    .iter()
+                    .map(|ndk| Item::warning(format!("Found {}", ndk))),
+            )
+            .collect(),
+        None => Vec::new(),
+    }
+}
+
+/// Only some NDK helper scripts need Python, so not having it is just a
+/// warning.
+fn check_python(env: &Env) -> Result<Item, Unrecoverable> {
+    Ok(match os::python(env) {
+        Ok(path) => Item::victory(format!(
+            "Python 3 found at {:?}",
+            util::contract_home(path)?
+        )),
+        Err(err) => Item::warning(err),
+    })
+}
 
 pub fn check(env: &Env) -> Result<Section, Unrecoverable> {
     let section = Section::new("Android developer tools");
@@ -22,7 +56,9 @@ pub fn check(env: &Env) -> Result<Section, Unrecoverable> {
                     util::contract_home(android_env.ndk.home())?,
                 )),
                 Err(err) => Err(format!("Failed to get NDK version: {}", err)),
-            }),
+            })
+            .with_items(ndk_resolution(&android_env))
+            .with_item(check_python(env)?),
         Err(err) => section.with_failure(err),
     })
 }
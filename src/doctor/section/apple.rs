@@ -1,6 +1,6 @@
 use super::{Item, Section};
 use crate::{
-    apple::{deps::xcode_plugin, system_profile::DeveloperTools, teams},
+    apple::{deps::xcode_plugin, system_profile::DeveloperTools, teams, xcode},
     bossy,
     util::prompt,
 };
@@ -48,6 +48,24 @@ fn validate_developer_dir() -> Result<String, String> {
     Ok(format!("Active developer dir: {:?}", xcode_developer_dir))
 }
 
+fn check_xcode() -> Result<String, String> {
+    xcode::check()
+        .map(|info| {
+            let mut msg = format!("Xcode v{}", info.version_string());
+            if let Some(build) = &info.build {
+                msg.push_str(&format!(" ({})", build));
+            }
+            format!("{} at {:?}", msg, info.developer_dir)
+        })
+        .map_err(|err| {
+            if err.tools_missing() {
+                format!("{} Run `xcode-select --install` to install them.", err)
+            } else {
+                err.to_string()
+            }
+        })
+}
+
 fn validate_xcode_plugin(xcode_version: (u32, u32), section: Section) -> Section {
     match xcode_plugin::Context::new(xcode_version) {
         Ok(ctx) => match ctx.check_installation() {
@@ -112,6 +130,9 @@ pub fn check() -> Section {
                 .map_err(|err| format!("Failed to check Xcode version: {}", err)),
         )
         .with_item(validate_developer_dir())
+        // This goes after `validate_developer_dir`, since that can fix the
+        // developer dir for us.
+        .with_item(check_xcode())
         .with_item(
             bossy::Command::impure_parse("ios-deploy --version")
                 .run_and_wait_for_str(|version| format!("ios-deploy v{}", version.trim()))
@@ -1,8 +1,9 @@
-use super::Section;
+use super::{Item, Section};
 use crate::{
+    config,
     doctor::Unrecoverable,
     os,
-    util::{self, cli::VERSION_SHORT},
+    util::{self, cli::VERSION_SHORT, toolchain},
 };
 
 fn check_os() -> Result<String, String> {
@@ -27,6 +28,70 @@ fn check_rust() -> Result<String, String> {
         })
 }
 
+fn check_cargo() -> Result<String, String> {
+    os::cargo_version()
+        .map(|version| format!("cargo v{}", version))
+        .map_err(|err| err.to_string())
+}
+
+// Only worth mentioning if the project in the cwd actually wants it.
+fn check_build_std() -> Option<Result<String, String>> {
+    let (_, raw) = config::Raw::load(".").ok().flatten()?;
+    if !raw.app.build_std.unwrap_or_default() {
+        return None;
+    }
+    Some(
+        util::check_build_std()
+            .map(|version| {
+                format!(
+                    "`app.build-std` is enabled, and rustc v{} is nightly",
+                    version
+                )
+            })
+            .map_err(|err| {
+                format!(
+                    "{} Run `rustup override set nightly` and `rustup component add rust-src`.",
+                    err
+                )
+            }),
+    )
+}
+
+// Only worth mentioning if something's actually pinned, since otherwise it's
+// just whatever `check_rust` reported.
+fn check_toolchain() -> Option<Result<String, String>> {
+    let root_dir = config::Raw::load(".")
+        .ok()
+        .flatten()
+        .map(|(root_dir, _)| root_dir)
+        .unwrap_or_else(|| ".".into());
+    toolchain::Toolchain::detect(root_dir)
+        .map(|toolchain| toolchain.map(|toolchain| format!("Using pinned toolchain {}", toolchain)))
+        .map_err(|err| err.to_string())
+        .transpose()
+}
+
+// Only possible to check from inside a project, and only worth mentioning if
+// something's off.
+fn check_identifiers() -> Vec<Item> {
+    let config = config::Raw::load(".")
+        .ok()
+        .flatten()
+        .and_then(|(root_dir, raw)| config::Config::from_raw(root_dir, raw).ok());
+    match config.map(|config| config::verify_identifiers(&config)) {
+        Some(Err(mismatches)) => mismatches
+            .into_iter()
+            .map(|mismatch| {
+                Item::warning(format!(
+                    "{}; run `cargo mobile init` to regenerate it",
+                    mismatch
+                ))
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
 pub fn check() -> Result<Section, Unrecoverable> {
     let section = Section::new(format!("cargo-mobile {}", VERSION_SHORT));
     Ok(match util::install_dir() {
@@ -49,5 +114,9 @@ pub fn check() -> Result<Section, Unrecoverable> {
         Err(err) => section.with_failure(err),
     }
     .with_item(check_os())
-    .with_item(check_rust()))
+    .with_item(check_rust())
+    .with_item(check_cargo())
+    .with_items(check_toolchain())
+    .with_items(check_build_std())
+    .with_items(check_identifiers()))
 }
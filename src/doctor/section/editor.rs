@@ -0,0 +1,33 @@
+use super::Section;
+use crate::{config::editor, doctor::Unrecoverable, os, util};
+
+fn describe(editor: &os::Application) -> Result<String, Unrecoverable> {
+    let name = editor
+        .name()
+        .map(|name| name.to_string())
+        .unwrap_or_else(|| editor.exec_string());
+    let mut desc = format!("Editor: {}", name);
+    if let Some(detail) = editor.generic_name().or_else(|| editor.comment()) {
+        desc.push_str(&format!(" — {}", detail));
+    }
+    if let Some(location) = editor.location() {
+        desc.push_str(&format!(" ({})", util::contract_home(location)?));
+    }
+    Ok(desc)
+}
+
+pub fn check() -> Result<Section, Unrecoverable> {
+    let section = Section::new("Code editor");
+    Ok(
+        match os::Application::detect_editor(&editor::discover_allowed_pagers(".")) {
+            Ok(editor) => {
+                let section = section.with_victory(describe(&editor)?);
+                match editor.validate() {
+                    Ok(()) => section,
+                    Err(err) => section.with_failure(format!("Editor can't be launched: {}", err)),
+                }
+            }
+            Err(err) => section.with_failure(format!("Failed to detect default editor: {}", err)),
+        },
+    )
+}
@@ -3,13 +3,14 @@ pub mod android;
 pub mod apple;
 pub mod cargo_mobile;
 pub mod device_list;
+pub mod editor;
 
 use crate::util::{
     self,
     cli::{colors, TextWrapper},
 };
 use colored::Colorize as _;
-use std::fmt::Debug;
+use std::fmt::{self, Debug, Display};
 
 #[derive(Clone, Copy, Debug)]
 enum Label {
@@ -80,7 +81,6 @@ impl Item {
         Self::new(Label::Victory, msg)
     }
 
-    #[cfg(target_os = "macos")]
     fn warning(msg: impl ToString) -> Self {
         Self::new(Label::Warning, msg)
     }
@@ -106,6 +106,18 @@ impl Item {
     }
 }
 
+/// Renders without colors or wrapping, for when the output isn't going to a
+/// terminal.
+impl Display for Section {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "[{}] {}", self.label().title_symbol(), self.title)?;
+        for item in &self.items {
+            writeln!(f, "    {} {}", item.label.item_symbol(), item.msg)?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug)]
 pub struct Section {
     title: String,
@@ -119,6 +119,10 @@ impl DotCargo {
         self.build = Some(DotCargoBuild::new(target));
     }
 
+    pub fn linker(&self, target: &str) -> Option<&str> {
+        self.target.get(target)?.linker.as_deref()
+    }
+
     pub fn insert_target(&mut self, name: impl Into<String>, target: DotCargoTarget) {
         if !target.is_empty() {
             // merging could be nice, but is also very painful...
@@ -18,9 +18,20 @@ impl Reportable for Error {
     }
 }
 
+/// An env var set on every tool we spawn, to keep it from reporting telemetry
+/// or to tell it how to identify itself.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Suppression {
+    pub var: String,
+    pub value: OsString,
+    /// Why it's being set, for verbose output.
+    pub reason: String,
+}
+
 #[derive(Clone, Debug)]
 pub struct Env {
     vars: HashMap<String, std::ffi::OsString>,
+    suppressions: Vec<Suppression>,
 }
 
 impl Env {
@@ -39,7 +50,14 @@ impl Env {
         vars.insert("HOME".into(), home);
         vars.insert("PATH".into(), path);
 
-        Ok(Self { vars })
+        Ok(Self {
+            vars,
+            suppressions: Vec::new(),
+        })
+    }
+
+    pub fn home(&self) -> &Path {
+        Path::new(self.vars.get("HOME").unwrap())
     }
 
     pub fn path(&self) -> &OsString {
@@ -58,10 +76,32 @@ impl Env {
         self.vars.extend(vars);
         self
     }
+
+    /// Sets `suppressions` on every tool spawned with this env, replacing any
+    /// that were set before. They're logged as they're applied, so `-v` shows
+    /// what we told the tools.
+    pub fn suppress_telemetry(mut self, suppressions: Vec<Suppression>) -> Self {
+        for suppression in &suppressions {
+            log::info!(
+                "setting {}={:?} for spawned tools, since {}",
+                suppression.var,
+                suppression.value,
+                suppression.reason
+            );
+        }
+        self.suppressions = suppressions;
+        self
+    }
 }
 
 impl ExplicitEnv for Env {
     fn explicit_env(&self) -> HashMap<String, OsString> {
-        self.vars.clone()
+        let mut vars = self.vars.clone();
+        vars.extend(
+            self.suppressions
+                .iter()
+                .map(|suppression| (suppression.var.clone(), suppression.value.clone())),
+        );
+        vars
     }
 }
@@ -1,3 +1,15 @@
+mod permissions;
+mod schema;
+mod verify;
+
+pub use self::{
+    permissions::{fix_permissions, PermissionsError},
+    schema::{
+        check_version, marker_path, warn_if_outdated, SchemaError, VersionCheck, SCHEMA_VERSION,
+    },
+    verify::{find_orphaned_links, verify, Platform, Problem},
+};
+
 use crate::android;
 #[cfg(target_os = "macos")]
 use crate::apple;
@@ -9,7 +21,7 @@ use crate::{
         Config,
     },
     dot_cargo,
-    os::code_command,
+    os::{self, code_command},
     project, templating,
     util::{
         self,
@@ -40,6 +52,10 @@ that, any generated files you modified will be overwritten!
 
 #[derive(Debug)]
 pub enum Error {
+    ReadOnly {
+        dir: PathBuf,
+        cause: io::Error,
+    },
     ConfigLoadOrGenFailed(config::LoadOrGenError),
     DotFirstInitWriteFailed {
         path: PathBuf,
@@ -65,12 +81,14 @@ pub enum Error {
         path: PathBuf,
         cause: io::Error,
     },
+    SchemaMarkerWriteFailed(SchemaError),
     OpenInEditorFailed(util::OpenInEditorError),
 }
 
 impl Reportable for Error {
     fn report(&self) -> Report {
         match self {
+            Self::ReadOnly { dir, cause } => Report::action_request(format!("Can't write to {:?}, so there's nowhere to generate the project; if it's on a read-only filesystem, please copy it somewhere writable and try again", dir), cause),
             Self::ConfigLoadOrGenFailed(err) => err.report(),
             Self::DotFirstInitWriteFailed { path, cause } => Report::error(format!("Failed to write first init dot file {:?}", path), cause),
             Self::FilterConfigureFailed(err) => Report::error("Failed to configure template filter", err),
@@ -87,6 +105,7 @@ impl Reportable for Error {
             Self::AppleInitFailed(err) => err.report(),
             Self::DotCargoWriteFailed(err) => err.report(),
             Self::DotFirstInitDeleteFailed { path, cause } => Report::action_request(format!("Failed to delete first init dot file {:?}; the project generated successfully, but `cargo mobile init` will have unexpected results unless you manually delete this file!", path), cause),
+            Self::SchemaMarkerWriteFailed(err) => err.report(),
             Self::OpenInEditorFailed(err) => Report::error("Failed to open project in editor (your project generated successfully though, so no worries!)", err),
         }
     }
@@ -102,8 +121,29 @@ pub fn exec(
     cwd: impl AsRef<Path>,
 ) -> Result<Config, Error> {
     let cwd = cwd.as_ref();
+    // Finding out halfway through generation leaves a half-generated project
+    // behind, so we check before touching anything.
+    os::check_writable(cwd).map_err(|cause| Error::ReadOnly {
+        dir: cwd.to_owned(),
+        cause,
+    })?;
     let (config, config_origin) =
         Config::load_or_gen(cwd, non_interactive, wrapper).map_err(Error::ConfigLoadOrGenFailed)?;
+    let root_dir = config.app().root_dir();
+    if root_dir != cwd {
+        os::check_writable(root_dir).map_err(|cause| Error::ReadOnly {
+            dir: root_dir.to_owned(),
+            cause,
+        })?;
+    }
+    let network_fs = util::network_fs_warnings(config.app());
+    if !network_fs.is_empty() {
+        Report::action_request(
+            "Your project is on a network filesystem, which builds don't get along with",
+            network_fs.join("\n"),
+        )
+        .print(wrapper);
+    }
     let dot_first_init_path = config.app().root_dir().join(DOT_FIRST_INIT_FILE_NAME);
     let dot_first_init_exists = {
         let dot_first_init_exists = dot_first_init_path.exists();
@@ -122,12 +162,20 @@ pub fn exec(
             dot_first_init_exists
         }
     };
-    let bike = config.build_a_bike();
+    let mut bike = config.build_a_bike();
     let filter = templating::Filter::new(&config, config_origin, dot_first_init_exists)
         .map_err(Error::FilterConfigureFailed)?;
 
     // Generate the base project
-    project::gen(&config, &bike, &filter, submodule_commit).map_err(Error::ProjectInitFailed)?;
+    project::gen(
+        &config,
+        &mut bike,
+        &filter,
+        submodule_commit,
+        wrapper,
+        non_interactive,
+    )
+    .map_err(Error::ProjectInitFailed)?;
 
     let asset_dir = config.app().asset_dir();
     if !asset_dir.is_dir() {
@@ -160,6 +208,7 @@ pub fn exec(
     );
 
     let metadata = Metadata::load(&config.app().root_dir()).map_err(Error::MetadataFailed)?;
+    let mut platforms = Vec::new();
 
     // Generate Xcode project
     #[cfg(target_os = "macos")]
@@ -176,6 +225,7 @@ pub fn exec(
             &filter,
         )
         .map_err(Error::AppleInitFailed)?;
+        platforms.push(Platform::Apple);
     } else {
         println!("Skipping iOS init, since it's marked as unsupported in your Cargo.toml metadata");
     }
@@ -183,16 +233,19 @@ pub fn exec(
     // Generate Android Studio project
     if metadata.android().supported() {
         match android::env::Env::new() {
-            Ok(env) => android::project::gen(
-                config.android(),
-                metadata.android(),
-                &env,
-                &bike,
-                wrapper,
-                &filter,
-                &mut dot_cargo,
-            )
-            .map_err(Error::AndroidInitFailed)?,
+            Ok(env) => {
+                android::project::gen(
+                    config.android(),
+                    metadata.android(),
+                    &env,
+                    &bike,
+                    wrapper,
+                    &filter,
+                    &mut dot_cargo,
+                )
+                .map_err(Error::AndroidInitFailed)?;
+                platforms.push(Platform::Android);
+            }
             Err(err) => {
                 if err.sdk_or_ndk_issue() {
                     Report::action_request(
@@ -221,13 +274,70 @@ pub fn exec(
             cause,
         })?;
     }
+    let mut generated_dirs = Vec::new();
+    for platform in &platforms {
+        match platform {
+            Platform::Android => generated_dirs.push(config.android().project_dir()),
+            #[cfg(target_os = "macos")]
+            Platform::Apple => generated_dirs.push(config.apple().project_dir()),
+            #[cfg(not(target_os = "macos"))]
+            Platform::Apple => (),
+        }
+    }
+    schema::write_marker(
+        config.app().root_dir(),
+        permissions::executable_modes(config.app().root_dir(), &generated_dirs),
+    )
+    .map_err(Error::SchemaMarkerWriteFailed)?;
+    let problems = verify(&config, &platforms);
+    if !problems.is_empty() {
+        Report::action_request(
+            "The generated project doesn't look quite right, so it might not open correctly",
+            problems
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join("\n"),
+        )
+        .print(wrapper);
+    }
+    // Broken links were already reported above.
+    let orphans = find_orphaned_links(&config)
+        .into_iter()
+        .filter(|link| link.exists())
+        .map(|link| format!("{:?}", link))
+        .collect::<Vec<_>>();
+    if !orphans.is_empty() {
+        Report::action_request(
+            "The generated project contains symlinks that cargo-mobile no longer generates, which you can safely delete",
+            orphans.join("\n"),
+        )
+        .print(wrapper);
+    }
     Report::victory(
         "Project generated successfully!",
         "Make cool apps! 🌻 🐕 🎉",
     )
     .print(wrapper);
-    if open_in_editor {
-        util::open_in_editor(cwd).map_err(Error::OpenInEditorFailed)?;
+    if config
+        .open()
+        .open_after_init(open_in_editor, os::opening_disabled())
+    {
+        match os::try_open_project_with_notes(
+            cwd,
+            config
+                .editor()
+                .notes_path(config.app().root_dir())
+                .as_deref(),
+            config.editor().profile(),
+            &config.editor().launch_options(),
+        ) {
+            os::OpenOutcome::Opened(name) => println!("Opened project in {}", name),
+            os::OpenOutcome::Skipped(reason) => {
+                println!("Not opening project in an editor, since {}", reason)
+            }
+            os::OpenOutcome::Failed(err) => return Err(Error::OpenInEditorFailed(err)),
+        }
     }
     Ok(config)
 }
@@ -0,0 +1,122 @@
+use super::schema::SchemaError;
+use crate::util::cli::{Report, Reportable};
+use std::{
+    collections::BTreeMap,
+    io,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum PermissionsError {
+    #[error(transparent)]
+    MarkerFailed(SchemaError),
+    #[error("Failed to make {path:?} executable: {cause}")]
+    SetFailed { path: PathBuf, cause: io::Error },
+}
+
+impl Reportable for PermissionsError {
+    fn report(&self) -> Report {
+        match self {
+            Self::MarkerFailed(err) => err.report(),
+            Self::SetFailed { .. } => Report::error("Failed to fix file permissions", self),
+        }
+    }
+}
+
+#[cfg(unix)]
+fn walk_files(dir: &Path, f: &mut impl FnMut(&Path, &std::fs::Metadata)) {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) => {
+            log::warn!(
+                "failed to read dir {:?} while recording modes: {}",
+                dir,
+                err
+            );
+            return;
+        }
+    };
+    for entry in entries.filter_map(Result::ok) {
+        let path = entry.path();
+        // `DirEntry::metadata` doesn't follow symlinks, which is what we
+        // want, since linked files aren't ours.
+        let metadata = match entry.metadata() {
+            Ok(metadata) => metadata,
+            Err(_) => continue,
+        };
+        if metadata.is_file() {
+            f(&path, &metadata);
+        } else if metadata.is_dir()
+            && !super::verify::SKIPPED_DIRS
+                .iter()
+                .any(|skipped| entry.file_name() == *skipped)
+        {
+            walk_files(&path, f);
+        }
+    }
+}
+
+/// The modes of every executable file in `dirs`, keyed by path relative to
+/// `project_dir`, for recording in the generated project marker.
+#[cfg(unix)]
+pub(super) fn executable_modes(project_dir: &Path, dirs: &[PathBuf]) -> BTreeMap<String, u32> {
+    use std::os::unix::fs::PermissionsExt as _;
+    let mut modes = BTreeMap::new();
+    for dir in dirs {
+        walk_files(dir, &mut |path, metadata| {
+            let mode = metadata.permissions().mode() & 0o7777;
+            if mode & 0o111 != 0 {
+                if let Ok(relative) = path.strip_prefix(project_dir) {
+                    modes.insert(relative.to_string_lossy().replace('\\', "/"), mode);
+                }
+            }
+        });
+    }
+    modes
+}
+
+#[cfg(not(unix))]
+pub(super) fn executable_modes(_project_dir: &Path, _dirs: &[PathBuf]) -> BTreeMap<String, u32> {
+    BTreeMap::new()
+}
+
+/// Restores the executable bit on generated files that lost it, i.e. after
+/// a round-trip through a zip or a `cp -r`, using the modes recorded when the
+/// project was generated. Files that have since been deleted are skipped.
+/// Returns the files that were fixed.
+#[cfg(unix)]
+pub fn fix_permissions(project_dir: impl AsRef<Path>) -> Result<Vec<PathBuf>, PermissionsError> {
+    use std::{fs, os::unix::fs::PermissionsExt as _};
+    let project_dir = project_dir.as_ref();
+    let mut fixed = Vec::new();
+    for (relative, mode) in
+        super::schema::recorded_modes(project_dir).map_err(PermissionsError::MarkerFailed)?
+    {
+        let path = project_dir.join(relative);
+        let mut permissions = match fs::symlink_metadata(&path) {
+            Ok(metadata) if metadata.is_file() => metadata.permissions(),
+            _ => continue,
+        };
+        let current = permissions.mode();
+        let wanted = current | (mode & 0o111);
+        if wanted != current {
+            log::info!("restoring mode {:o} on {:?}", wanted, path);
+            permissions.set_mode(wanted);
+            fs::set_permissions(&path, permissions).map_err(|cause| {
+                PermissionsError::SetFailed {
+                    path: path.clone(),
+                    cause,
+                }
+            })?;
+            fixed.push(path);
+        }
+    }
+    Ok(fixed)
+}
+
+/// Windows has no executable bit, so there's nothing to fix.
+#[cfg(not(unix))]
+pub fn fix_permissions(_project_dir: impl AsRef<Path>) -> Result<Vec<PathBuf>, PermissionsError> {
+    Ok(Vec::new())
+}
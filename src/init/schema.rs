@@ -0,0 +1,181 @@
+use crate::util::cli::{Report, Reportable, TextWrapper};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+
+/// Bump this whenever template changes mean existing projects need to be
+/// regenerated to keep working with this version of cargo-mobile.
+pub const SCHEMA_VERSION: u32 = 1;
+
+static FILE_NAME: &str = "gen/.generated.toml";
+
+#[derive(Debug, Error)]
+pub enum SchemaError {
+    #[error("Failed to read generated project marker at {path:?}: {cause}")]
+    ReadFailed { path: PathBuf, cause: io::Error },
+    #[error("Failed to parse generated project marker at {path:?}: {cause}")]
+    ParseFailed {
+        path: PathBuf,
+        cause: toml::de::Error,
+    },
+    #[error("Failed to serialize generated project marker: {0}")]
+    SerializeFailed(toml::ser::Error),
+    #[error("Failed to write generated project marker to {path:?}: {cause}")]
+    WriteFailed { path: PathBuf, cause: io::Error },
+}
+
+impl Reportable for SchemaError {
+    fn report(&self) -> Report {
+        Report::error("Failed to check generated project version", self)
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct Marker {
+    schema_version: u32,
+    cargo_mobile_version: String,
+    /// The modes of generated files that should be executable, keyed by
+    /// their path relative to the project dir.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    modes: BTreeMap<String, u32>,
+}
+
+/// Where the marker for the project at `project_dir` lives.
+pub fn marker_path(project_dir: &Path) -> PathBuf {
+    project_dir.join(FILE_NAME)
+}
+
+/// Records that the project at `project_dir` was just generated by this
+/// version of cargo-mobile, along with the `modes` of its executable files.
+pub fn write_marker(project_dir: &Path, modes: BTreeMap<String, u32>) -> Result<(), SchemaError> {
+    let path = marker_path(project_dir);
+    let marker = Marker {
+        schema_version: SCHEMA_VERSION,
+        cargo_mobile_version: env!("CARGO_PKG_VERSION").to_owned(),
+        modes,
+    };
+    let ser = toml::to_string_pretty(&marker).map_err(SchemaError::SerializeFailed)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|cause| SchemaError::WriteFailed {
+            path: path.clone(),
+            cause,
+        })?;
+    }
+    // Written to a temporary file and renamed into place, so an interrupted
+    // write can't leave a marker claiming the project is current.
+    let tmp = path.with_extension("toml.tmp");
+    fs::write(&tmp, ser)
+        .and_then(|()| fs::rename(&tmp, &path))
+        .map_err(|cause| {
+            let _ = fs::remove_file(&tmp);
+            SchemaError::WriteFailed { path, cause }
+        })
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum VersionCheck {
+    Current,
+    /// Generated before the marker existed, so we can't tell.
+    Unknown,
+    Outdated {
+        schema_version: u32,
+        generated_with: String,
+    },
+    /// Generated by a newer cargo-mobile than this one.
+    Newer {
+        schema_version: u32,
+        generated_with: String,
+    },
+}
+
+impl VersionCheck {
+    /// A warning to show the user, if there's anything to warn about.
+    pub fn report(&self) -> Option<Report> {
+        match self {
+            Self::Current | Self::Unknown => None,
+            Self::Outdated {
+                schema_version,
+                generated_with,
+            } => Some(Report::action_request(
+                "Your generated project is out of date",
+                format!(
+                    "It was generated by cargo-mobile {} (schema version {}), but this version of cargo-mobile expects schema version {}, so things might not work right. Run `cargo mobile init` to update it.",
+                    generated_with, schema_version, SCHEMA_VERSION
+                ),
+            )),
+            Self::Newer {
+                schema_version,
+                generated_with,
+            } => Some(Report::action_request(
+                "Your generated project is newer than cargo-mobile",
+                format!(
+                    "It was generated by cargo-mobile {} (schema version {}), but this version of cargo-mobile only knows about schema version {}. Run `cargo mobile update` to update cargo-mobile.",
+                    generated_with, schema_version, SCHEMA_VERSION
+                ),
+            )),
+        }
+    }
+}
+
+fn read_marker(project_dir: &Path) -> Result<Option<Marker>, SchemaError> {
+    let path = marker_path(project_dir);
+    if !path.is_file() {
+        log::info!("no generated project marker at {:?}", path);
+        return Ok(None);
+    }
+    let bytes = fs::read(&path).map_err(|cause| SchemaError::ReadFailed {
+        path: path.clone(),
+        cause,
+    })?;
+    toml::from_slice(&bytes)
+        .map(Some)
+        .map_err(|cause| SchemaError::ParseFailed { path, cause })
+}
+
+/// The modes recorded for the project at `project_dir`, which are empty if
+/// there's no marker.
+#[cfg(unix)]
+pub(super) fn recorded_modes(project_dir: &Path) -> Result<BTreeMap<String, u32>, SchemaError> {
+    Ok(read_marker(project_dir)?
+        .map(|marker| marker.modes)
+        .unwrap_or_default())
+}
+
+/// Compares the schema version the project at `project_dir` was generated
+/// with against the one this version of cargo-mobile expects.
+pub fn check_version(project_dir: impl AsRef<Path>) -> Result<VersionCheck, SchemaError> {
+    let marker = match read_marker(project_dir.as_ref())? {
+        Some(marker) => marker,
+        None => return Ok(VersionCheck::Unknown),
+    };
+    Ok(match marker.schema_version {
+        version if version < SCHEMA_VERSION => VersionCheck::Outdated {
+            schema_version: version,
+            generated_with: marker.cargo_mobile_version,
+        },
+        version if version > SCHEMA_VERSION => VersionCheck::Newer {
+            schema_version: version,
+            generated_with: marker.cargo_mobile_version,
+        },
+        _ => VersionCheck::Current,
+    })
+}
+
+/// Prints a warning if the project at `project_dir` was generated with a
+/// different schema version. This is only advisory, so failing to check is
+/// logged rather than returned.
+pub fn warn_if_outdated(project_dir: impl AsRef<Path>, wrapper: &TextWrapper) {
+    match check_version(project_dir) {
+        Ok(check) => {
+            if let Some(report) = check.report() {
+                report.print(wrapper);
+            }
+        }
+        Err(err) => log::warn!("{}", err),
+    }
+}
@@ -0,0 +1,270 @@
+use crate::config::Config;
+use std::{
+    fmt::{self, Display},
+    fs,
+    path::{Path, PathBuf},
+};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Platform {
+    Android,
+    Apple,
+}
+
+impl Platform {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Android => "Android",
+            Self::Apple => "Apple",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum Problem {
+    Missing {
+        platform: Platform,
+        path: PathBuf,
+    },
+    BrokenSymlink {
+        platform: Platform,
+        link: PathBuf,
+        target: Option<PathBuf>,
+    },
+    NotExecutable {
+        platform: Platform,
+        path: PathBuf,
+    },
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Missing { platform, path } => {
+                write!(f, "{}: {:?} is missing", platform.as_str(), path)
+            }
+            Self::BrokenSymlink {
+                platform,
+                link,
+                target,
+            } => match target {
+                Some(target) => write!(
+                    f,
+                    "{}: symlink {:?} points to {:?}, which doesn't exist",
+                    platform.as_str(),
+                    link,
+                    target
+                ),
+                None => write!(
+                    f,
+                    "{}: symlink {:?} couldn't be read",
+                    platform.as_str(),
+                    link
+                ),
+            },
+            Self::NotExecutable { platform, path } => {
+                write!(f, "{}: {:?} isn't executable", platform.as_str(), path)
+            }
+        }
+    }
+}
+
+// Build output can be huge, and any symlinks in it aren't ours anyway.
+pub(super) static SKIPPED_DIRS: &[&str] = &["build", ".gradle", "target", "DerivedData", "Pods"];
+
+fn walk_symlinks(dir: &Path, f: &mut impl FnMut(&Path, &Path)) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) => {
+            log::warn!(
+                "failed to read dir {:?} while verifying project: {}",
+                dir,
+                err
+            );
+            return;
+        }
+    };
+    for entry in entries.filter_map(Result::ok) {
+        let path = entry.path();
+        let file_type = match entry.file_type() {
+            Ok(file_type) => file_type,
+            Err(_) => continue,
+        };
+        if file_type.is_symlink() {
+            f(dir, &path);
+        } else if file_type.is_dir()
+            && !SKIPPED_DIRS
+                .iter()
+                .any(|skipped| entry.file_name() == *skipped)
+        {
+            walk_symlinks(&path, f);
+        }
+    }
+}
+
+fn check_symlinks(platform: Platform, dir: &Path, problems: &mut Vec<Problem>) {
+    walk_symlinks(dir, &mut |parent, link| {
+        // `exists` follows the link, so this is false for dangling ones.
+        if !link.exists() {
+            problems.push(Problem::BrokenSymlink {
+                platform,
+                target: fs::read_link(link).ok().map(|target| parent.join(target)),
+                link: link.to_owned(),
+            });
+        }
+    });
+}
+
+// The links we currently generate, relative to each platform's project dir.
+// Libs in `jniLibs` are linked by every build rather than by init, so those
+// are only orphaned once they're broken.
+static ANDROID_LINKS: &[&str] = &["app/src/main/assets"];
+static ANDROID_BUILD_LINK_DIR: &str = "app/src/main/jniLibs";
+#[cfg(target_os = "macos")]
+static APPLE_LINKS: &[&str] = &["assets"];
+
+fn find_orphans(
+    dir: &Path,
+    expected: &[&str],
+    build_dir: Option<&str>,
+    orphans: &mut Vec<PathBuf>,
+) {
+    if !dir.is_dir() {
+        return;
+    }
+    let build_dir = build_dir.map(|build_dir| dir.join(build_dir));
+    walk_symlinks(dir, &mut |_, link| {
+        let generated = expected.iter().any(|expected| link == dir.join(expected))
+            || build_dir
+                .as_ref()
+                .is_some_and(|build_dir| link.starts_with(build_dir));
+        if !generated || !link.exists() {
+            orphans.push(link.to_owned());
+        }
+    });
+}
+
+/// Finds symlinks in the generated projects that either aren't generated
+/// anymore (i.e. left over from an older template) or that point to something
+/// that's gone, so they can be cleaned up. Only the generated project dirs are
+/// searched, since links anywhere else are the user's business.
+pub fn find_orphaned_links(config: &Config) -> Vec<PathBuf> {
+    let mut orphans = Vec::new();
+    find_orphans(
+        &config.android().project_dir(),
+        ANDROID_LINKS,
+        Some(ANDROID_BUILD_LINK_DIR),
+        &mut orphans,
+    );
+    #[cfg(target_os = "macos")]
+    find_orphans(
+        &config.apple().project_dir(),
+        APPLE_LINKS,
+        None,
+        &mut orphans,
+    );
+    orphans
+}
+
+fn check_files(platform: Platform, dir: &Path, files: &[&str], problems: &mut Vec<Problem>) {
+    problems.extend(
+        files
+            .iter()
+            .map(|file| dir.join(file))
+            .filter(|path| !path.exists())
+            .map(|path| Problem::Missing { platform, path }),
+    );
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt as _;
+    fs::metadata(path)
+        .map(|metadata| metadata.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+fn verify_android(config: &Config, problems: &mut Vec<Problem>) {
+    let platform = Platform::Android;
+    let dir = config.android().project_dir();
+    if !dir.is_dir() {
+        problems.push(Problem::Missing {
+            platform,
+            path: dir,
+        });
+        return;
+    }
+    check_files(
+        platform,
+        &dir,
+        &[
+            "settings.gradle",
+            "build.gradle.kts",
+            "gradle.properties",
+            "gradlew",
+            "gradlew.bat",
+            "gradle/wrapper/gradle-wrapper.jar",
+            "gradle/wrapper/gradle-wrapper.properties",
+            "app/build.gradle.kts",
+            "app/src/main/AndroidManifest.xml",
+            "buildSrc/build.gradle.kts",
+        ],
+        problems,
+    );
+    let gradlew = dir.join("gradlew");
+    if gradlew.is_file() && !is_executable(&gradlew) {
+        problems.push(Problem::NotExecutable {
+            platform,
+            path: gradlew,
+        });
+    }
+    check_symlinks(platform, &dir, problems);
+}
+
+#[cfg(target_os = "macos")]
+fn verify_apple(config: &Config, problems: &mut Vec<Problem>) {
+    let platform = Platform::Apple;
+    let dir = config.apple().project_dir();
+    if !dir.is_dir() {
+        problems.push(Problem::Missing {
+            platform,
+            path: dir,
+        });
+        return;
+    }
+    let xcodeproj = format!("{}.xcodeproj", config.app().name());
+    check_files(
+        platform,
+        &dir,
+        &[
+            "project.yml",
+            &xcodeproj,
+            &format!("{}/project.pbxproj", xcodeproj),
+        ],
+        problems,
+    );
+    check_symlinks(platform, &dir, problems);
+}
+
+#[cfg(not(target_os = "macos"))]
+fn verify_apple(_config: &Config, _problems: &mut Vec<Problem>) {
+    log::info!("skipping Apple project verification, since this isn't macOS");
+}
+
+/// Checks that the generated projects for `platforms` look like they'll
+/// actually open, returning everything that looks wrong.
+pub fn verify(config: &Config, platforms: &[Platform]) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    for platform in platforms {
+        match platform {
+            Platform::Android => verify_android(config, &mut problems),
+            Platform::Apple => verify_apple(config, &mut problems),
+        }
+    }
+    problems
+}
@@ -5,12 +5,18 @@ pub mod android;
 pub mod apple;
 pub mod bicycle;
 pub mod bossy;
+pub mod build_events;
+pub mod build_failure;
+pub mod build_log;
+pub mod build_manifest;
 pub mod config;
 pub mod device;
+pub mod diagnostics;
 pub mod doctor;
 pub mod dot_cargo;
 pub mod env;
 pub mod init;
+pub mod open;
 pub mod opts;
 pub mod os;
 mod project;
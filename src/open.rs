@@ -0,0 +1,129 @@
+use crate::{
+    config::{
+        open::{Tool, ToolApp, ToolTarget},
+        Config,
+    },
+    os,
+    util::{
+        cli::{Report, Reportable},
+        OpenInEditorError,
+    },
+};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum OpenToolError {
+    #[error("{0}")]
+    EditorFailed(OpenInEditorError),
+    #[error("{0}")]
+    LaunchFailed(os::OpenFileError),
+    #[error("{0}")]
+    TerminalFailed(os::OpenTerminalError),
+    #[error("There's no {0} project to open on this platform.")]
+    PlatformUnavailable(&'static str),
+}
+
+/// A tool from `open.tools` that couldn't be launched.
+#[derive(Debug)]
+pub struct ToolFailed {
+    pub tool: Tool,
+    pub path: PathBuf,
+    pub cause: OpenToolError,
+}
+
+impl Reportable for ToolFailed {
+    fn report(&self) -> Report {
+        Report::error(
+            format!(
+                "Failed to open {:?} with {}",
+                self.path,
+                describe(&self.tool.app)
+            ),
+            &self.cause,
+        )
+    }
+}
+
+fn describe(app: &ToolApp) -> &str {
+    match app {
+        ToolApp::Editor => "your editor",
+        ToolApp::Terminal => "your terminal",
+        ToolApp::AndroidStudio => "Android Studio",
+        ToolApp::Xcode => "Xcode",
+        ToolApp::Other(name) => name,
+    }
+}
+
+fn resolve(config: &Config, target: &ToolTarget) -> Result<PathBuf, OpenToolError> {
+    match target {
+        ToolTarget::Project => Ok(config.app().root_dir().to_owned()),
+        ToolTarget::Android => Ok(config.android().project_dir()),
+        #[cfg(target_os = "macos")]
+        ToolTarget::Apple => Ok(config.apple().project_dir()),
+        #[cfg(not(target_os = "macos"))]
+        ToolTarget::Apple => Err(OpenToolError::PlatformUnavailable("Xcode")),
+        ToolTarget::Path(path) => Ok(config.app().prefix_path(path)),
+    }
+}
+
+fn launch(config: &Config, app: &ToolApp, path: &Path) -> Result<(), OpenToolError> {
+    match app {
+        ToolApp::Editor => os::try_open_project_with_notes(
+            path,
+            config
+                .editor()
+                .notes_path(config.app().root_dir())
+                .as_deref(),
+            config.editor().profile(),
+            &config.editor().launch_options(),
+        )
+        .into_result()
+        .map(|_| ())
+        .map_err(OpenToolError::EditorFailed),
+        ToolApp::Terminal => os::open_terminal_at(path).map_err(OpenToolError::TerminalFailed),
+        ToolApp::AndroidStudio => os::open_file_with("Android Studio", path)
+            .map(|_| ())
+            .map_err(OpenToolError::LaunchFailed),
+        ToolApp::Xcode if !cfg!(target_os = "macos") => {
+            Err(OpenToolError::PlatformUnavailable("Xcode"))
+        }
+        ToolApp::Xcode => os::open_file_with("Xcode", path)
+            .map(|_| ())
+            .map_err(OpenToolError::LaunchFailed),
+        ToolApp::Other(name) => os::open_file_with(name, path)
+            .map(|_| ())
+            .map_err(OpenToolError::LaunchFailed),
+    }
+}
+
+/// Launches every tool in `open.tools`. One tool failing doesn't stop the
+/// rest from launching, so failures are collected and returned together.
+pub fn open_all(config: &Config) -> Vec<ToolFailed> {
+    let mut failures = Vec::new();
+    if os::opening_disabled() {
+        println!(
+            "Not opening anything, since opening is disabled by `--no-open` or {}",
+            os::NO_OPEN_VAR
+        );
+        return failures;
+    }
+    for tool in config.open().tools() {
+        let path = match resolve(config, &tool.target) {
+            Ok(path) => path,
+            Err(cause) => {
+                failures.push(ToolFailed {
+                    path: config.app().root_dir().to_owned(),
+                    tool,
+                    cause,
+                });
+                continue;
+            }
+        };
+        log::info!("opening {:?} with {}", path, describe(&tool.app));
+        if let Err(cause) = launch(config, &tool.app, &path) {
+            failures.push(ToolFailed { tool, path, cause });
+        }
+    }
+    failures
+}
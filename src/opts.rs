@@ -34,6 +34,33 @@ impl NoiseLevel {
     pub fn pedantic(self) -> bool {
         matches!(self, Self::FranklyQuitePedantic)
     }
+
+    /// The flag that gets cargo to match this noise level.
+    pub fn cargo_flag(self) -> Option<&'static str> {
+        match self {
+            Self::Polite => None,
+            Self::LoudAndProud => Some("--verbose"),
+            Self::FranklyQuitePedantic => Some("-vv"),
+        }
+    }
+
+    /// The flag that gets gradle to match this noise level.
+    pub fn gradle_flag(self) -> &'static str {
+        match self {
+            Self::Polite => "--warn",
+            Self::LoudAndProud => "--info",
+            Self::FranklyQuitePedantic => "--debug",
+        }
+    }
+
+    /// The flag that gets `xcodebuild` to match this noise level.
+    pub fn xcodebuild_flag(self) -> Option<&'static str> {
+        match self {
+            Self::Polite => Some("-quiet"),
+            Self::LoudAndProud => None,
+            Self::FranklyQuitePedantic => Some("-verbose"),
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
@@ -73,10 +100,34 @@ impl Profile {
         }
     }
 
+    /// The build type in gradle task names (i.e. `assembleUniversalDebug`).
+    /// The generated gradle project builds the Rust lib with the profile of
+    /// the same name, so these have to stay in step.
+    pub fn gradle_variant(self) -> &'static str {
+        self.as_str_pascal_case()
+    }
+
+    /// The `-configuration` to pass to `xcodebuild`, which is what the
+    /// generated Xcode project names its configurations.
+    pub fn xcode_configuration(self) -> &'static str {
+        self.as_str()
+    }
+
+    /// The profile for an Xcode `CONFIGURATION`, which is what `xcode-script`
+    /// compiles the Rust lib with. Configurations are matched regardless of
+    /// case, since Xcode's own default is `Release`; anything else is
+    /// `None`.
+    pub fn from_xcode_configuration(configuration: &str) -> Option<Self> {
+        [Self::Debug, Self::Release]
+            .iter()
+            .copied()
+            .find(|profile| configuration.eq_ignore_ascii_case(profile.xcode_configuration()))
+    }
+
     pub fn suffix(&self) -> &str {
         match self {
             Profile::Debug => self.as_str(),
-            // TODO: how to handle signed APKs?
+            // Gradle leaves these unsigned; `android::sign` signs them when it can.
             Profile::Release => "release-unsigned",
         }
     }
@@ -106,3 +157,70 @@ impl FilterLevel {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest(
+        noise_level,
+        cargo,
+        gradle,
+        xcodebuild,
+        case(NoiseLevel::Polite, None, "--warn", Some("-quiet")),
+        case(NoiseLevel::LoudAndProud, Some("--verbose"), "--info", None),
+        case(
+            NoiseLevel::FranklyQuitePedantic,
+            Some("-vv"),
+            "--debug",
+            Some("-verbose")
+        )
+    )]
+    fn tool_flags(
+        noise_level: NoiseLevel,
+        cargo: Option<&str>,
+        gradle: &str,
+        xcodebuild: Option<&str>,
+    ) {
+        assert_eq!(noise_level.cargo_flag(), cargo);
+        assert_eq!(noise_level.gradle_flag(), gradle);
+        assert_eq!(noise_level.xcodebuild_flag(), xcodebuild);
+    }
+
+    #[rstest(
+        release_flag,
+        profile,
+        gradle_variant,
+        xcode_configuration,
+        case(false, Profile::Debug, "Debug", "debug"),
+        case(true, Profile::Release, "Release", "release")
+    )]
+    fn profiles_agree_across_builds(
+        release_flag: bool,
+        profile: Profile,
+        gradle_variant: &str,
+        xcode_configuration: &str,
+    ) {
+        assert_eq!(Profile::from_flag(release_flag), profile);
+        assert_eq!(profile.gradle_variant(), gradle_variant);
+        assert_eq!(profile.gradle_variant().to_lowercase(), profile.as_str());
+        assert_eq!(profile.xcode_configuration(), xcode_configuration);
+        assert_eq!(
+            Profile::from_xcode_configuration(profile.xcode_configuration()),
+            Some(profile)
+        );
+    }
+
+    #[rstest(
+        configuration,
+        expected,
+        case("debug", Some(Profile::Debug)),
+        case("Release", Some(Profile::Release)),
+        case("RELEASE", Some(Profile::Release)),
+        case("Staging", None)
+    )]
+    fn matches_xcode_configurations(configuration: &str, expected: Option<Profile>) {
+        assert_eq!(Profile::from_xcode_configuration(configuration), expected);
+    }
+}
@@ -0,0 +1,29 @@
+use super::command_path;
+use crate::{
+    bossy,
+    util::{self, RunAndSearchError, VersionTriple, VersionTripleError},
+};
+use once_cell_regex::regex;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum CargoVersionError {
+    #[error("`cargo` isn't on your `PATH`, so nothing can be built; if Rust is installed, make sure `~/.cargo/bin` is on your `PATH`: {0}")]
+    NotFound(#[source] bossy::Error),
+    #[error("Failed to check cargo version: {0}")]
+    CommandFailed(#[from] RunAndSearchError),
+    #[error(transparent)]
+    TripleInvalid(#[from] VersionTripleError),
+}
+
+/// The version of the `cargo` we'd build with. Since we shell out to cargo
+/// for everything, it not being found is otherwise a pretty opaque failure.
+pub fn cargo_version() -> Result<VersionTriple, CargoVersionError> {
+    command_path("cargo").map_err(CargoVersionError::NotFound)?;
+    util::run_and_search(
+        &mut bossy::Command::impure_parse("cargo --version"),
+        regex!(r"cargo (?P<version>(?P<major>\d+)\.(?P<minor>\d+)\.(?P<patch>\d+))"),
+        |_text, caps| VersionTriple::from_caps(&caps).map(|(triple, _version_str)| triple),
+    )?
+    .map_err(Into::into)
+}
@@ -0,0 +1,268 @@
+use std::{
+    fs, io,
+    io::Write as _,
+    path::{Path, PathBuf},
+};
+
+/// Bytes available to unprivileged users on the filesystem containing `path`.
+#[cfg(unix)]
+pub fn available_disk_space(path: impl AsRef<Path>) -> io::Result<u64> {
+    use std::{ffi::CString, mem::MaybeUninit, os::unix::ffi::OsStrExt as _};
+
+    let path = CString::new(path.as_ref().as_os_str().as_bytes())
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+    let mut stat = MaybeUninit::<libc::statvfs>::uninit();
+    if unsafe { libc::statvfs(path.as_ptr(), stat.as_mut_ptr()) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let stat = unsafe { stat.assume_init() };
+    #[allow(clippy::unnecessary_cast)]
+    Ok(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+/// Bytes available to the current user on the volume containing `path`.
+#[cfg(windows)]
+pub fn available_disk_space(path: impl AsRef<Path>) -> io::Result<u64> {
+    use std::os::windows::ffi::OsStrExt as _;
+    use windows::{core::PCWSTR, Win32::Storage::FileSystem::GetDiskFreeSpaceExW};
+
+    let path = path
+        .as_ref()
+        .as_os_str()
+        .encode_wide()
+        .chain(Some(0))
+        .collect::<Vec<_>>();
+    let mut available = 0u64;
+    unsafe {
+        GetDiskFreeSpaceExW(
+            PCWSTR::from_raw(path.as_ptr()),
+            &mut available,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+        )
+    }
+    .ok()?;
+    Ok(available)
+}
+
+/// Checks that we can actually create files in `dir`, by creating (and then
+/// deleting) one. Permission bits alone don't tell you about read-only
+/// mounts, ACLs, or container layers.
+pub fn check_writable(dir: impl AsRef<Path>) -> io::Result<()> {
+    let probe = dir
+        .as_ref()
+        .join(format!(".cargo-mobile-write-probe-{}", std::process::id()));
+    let result = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&probe)
+        .and_then(|mut file| file.write_all(b"probe"));
+    if fs::symlink_metadata(&probe).is_ok() {
+        if let Err(err) = fs::remove_file(&probe) {
+            log::warn!("failed to remove write probe {:?}: {}", probe, err);
+        }
+    }
+    result
+}
+
+/// Like [`check_writable`], for when you don't care why not.
+pub fn is_writable(dir: impl AsRef<Path>) -> bool {
+    check_writable(dir).is_ok()
+}
+
+/// What sort of filesystem a path lives on, as far as build performance is
+/// concerned.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FsKind {
+    Local,
+    /// Network shares, and the shared folders VMs and containers use to reach
+    /// the host, which are just as slow and tend to handle symlinks and
+    /// permissions strangely.
+    Network,
+    Unknown,
+}
+
+/// Filesystem types, as named by `/proc/mounts` or `statfs` (minus any
+/// `fuse.` prefix), that go over a network or out of a VM.
+static NETWORK_FS_TYPES: &[&str] = &[
+    "nfs",
+    "nfs4",
+    "cifs",
+    "smb3",
+    "smbfs",
+    "afpfs",
+    "webdav",
+    "davfs",
+    "afs",
+    "ceph",
+    "glusterfs",
+    "lustre",
+    "9p",
+    "virtiofs",
+    "vboxsf",
+    "prl_fs",
+    "vmhgfs",
+    "fakeowner",
+    "sshfs",
+    "rclone",
+    // Docker Desktop's bind mounts from the host.
+    "grpcfuse",
+    "osxfs",
+];
+
+fn classify_fs_type(fs_type: &str) -> FsKind {
+    // FUSE filesystems show up as `fuse.<name>`, i.e. `fuse.sshfs`.
+    let name = fs_type.strip_prefix("fuse.").unwrap_or(fs_type);
+    if NETWORK_FS_TYPES.contains(&name) {
+        FsKind::Network
+    } else {
+        FsKind::Local
+    }
+}
+
+/// `/proc/mounts` escapes spaces and such in octal, i.e. `\040`.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn unescape_mount_point(escaped: &str) -> PathBuf {
+    let mut bytes = Vec::with_capacity(escaped.len());
+    let mut rest = escaped.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        let octal = tail
+            .get(..3)
+            .and_then(|digits| std::str::from_utf8(digits).ok())
+            .and_then(|digits| u8::from_str_radix(digits, 8).ok());
+        match octal {
+            Some(unescaped) if byte == b'\\' => {
+                bytes.push(unescaped);
+                rest = &tail[3..];
+            }
+            _ => {
+                bytes.push(byte);
+                rest = tail;
+            }
+        }
+    }
+    PathBuf::from(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// The kind of filesystem mounted closest above `path`, according to
+/// `mounts` (in the format of `/proc/mounts`).
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn kind_from_mounts(mounts: &str, path: &Path) -> FsKind {
+    mounts
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace().skip(1);
+            let mount_point = unescape_mount_point(fields.next()?);
+            let fs_type = fields.next()?;
+            Some((mount_point, fs_type))
+        })
+        .filter(|(mount_point, _)| path.starts_with(mount_point))
+        // Later mounts shadow earlier ones on the same mount point.
+        .fold(
+            None,
+            |best: Option<(PathBuf, &str)>, (mount_point, fs_type)| match &best {
+                Some((best_point, _))
+                    if best_point.components().count() > mount_point.components().count() =>
+                {
+                    best
+                }
+                _ => Some((mount_point, fs_type)),
+            },
+        )
+        .map(|(_, fs_type)| classify_fs_type(fs_type))
+        .unwrap_or(FsKind::Unknown)
+}
+
+#[cfg(target_os = "linux")]
+fn filesystem_kind_of(path: &Path) -> io::Result<FsKind> {
+    Ok(kind_from_mounts(&fs::read_to_string("/proc/mounts")?, path))
+}
+
+#[cfg(target_os = "macos")]
+fn filesystem_kind_of(path: &Path) -> io::Result<FsKind> {
+    use std::{
+        ffi::{CStr, CString},
+        mem::MaybeUninit,
+        os::unix::ffi::OsStrExt as _,
+    };
+
+    let path = CString::new(path.as_os_str().as_bytes())
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+    let mut stat = MaybeUninit::<libc::statfs>::uninit();
+    if unsafe { libc::statfs(path.as_ptr(), stat.as_mut_ptr()) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let stat = unsafe { stat.assume_init() };
+    let fs_type = unsafe { CStr::from_ptr(stat.f_fstypename.as_ptr()) };
+    Ok(classify_fs_type(&fs_type.to_string_lossy()))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn filesystem_kind_of(_path: &Path) -> io::Result<FsKind> {
+    Ok(FsKind::Unknown)
+}
+
+/// Whether `path` is on a local or network filesystem. `path` doesn't have
+/// to exist yet; we check whichever of its ancestors does. Anything we can't
+/// figure out is [`FsKind::Unknown`].
+pub fn filesystem_kind(path: impl AsRef<Path>) -> FsKind {
+    let path = path.as_ref();
+    let existing = match path.ancestors().find(|ancestor| ancestor.exists()) {
+        Some(existing) => existing,
+        None => return FsKind::Unknown,
+    };
+    let existing = dunce::canonicalize(existing).unwrap_or_else(|_| existing.to_owned());
+    filesystem_kind_of(&existing).unwrap_or_else(|err| {
+        log::info!("failed to check filesystem type of {:?}: {}", existing, err);
+        FsKind::Unknown
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    const MOUNTS: &str = r"sysfs /sys sysfs rw,nosuid,nodev,noexec,relatime 0 0
+/dev/nvme0n1p2 / ext4 rw,relatime 0 0
+/dev/nvme0n1p1 /boot/efi vfat rw,relatime 0 0
+server:/export/home /home/me nfs4 rw,relatime,vers=4.2 0 0
+/dev/nvme0n1p3 /home/me/local btrfs rw,relatime 0 0
+//nas/projects /mnt/my\040projects cifs rw,relatime 0 0
+me@host:/src /mnt/remote fuse.sshfs rw,nosuid,nodev 0 0
+";
+
+    #[rstest(
+        path,
+        expected,
+        case("/usr/src/app", FsKind::Local),
+        case("/home/me/app", FsKind::Network),
+        case("/home/me/local/app", FsKind::Local),
+        case("/home/meh/app", FsKind::Local),
+        case("/mnt/my projects/app", FsKind::Network),
+        case("/mnt/remote/app", FsKind::Network)
+    )]
+    fn finds_closest_mount(path: &str, expected: FsKind) {
+        assert_eq!(kind_from_mounts(MOUNTS, Path::new(path)), expected);
+    }
+
+    #[test]
+    fn unknown_without_mounts() {
+        assert_eq!(kind_from_mounts("", Path::new("/app")), FsKind::Unknown);
+    }
+
+    #[test]
+    fn probes_for_writability() {
+        let dir = crate::util::temp_dir().join("disk-writable-test");
+        fs::create_dir_all(&dir).unwrap();
+        let writable = check_writable(&dir);
+        let missing = check_writable(dir.join("missing"));
+        let answers = (is_writable(&dir), is_writable(dir.join("missing")));
+        let leftovers = fs::read_dir(&dir).unwrap().count();
+        fs::remove_dir_all(&dir).unwrap();
+        writable.unwrap();
+        assert_eq!(missing.unwrap_err().kind(), io::ErrorKind::NotFound);
+        assert_eq!(answers, (true, false));
+        assert_eq!(leftovers, 0, "the write probe wasn't cleaned up");
+    }
+}
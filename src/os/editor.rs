@@ -0,0 +1,314 @@
+use crate::bossy;
+use std::{collections::BTreeMap, ffi::OsStr, path::Path};
+
+/// What we know about a particular editor, beyond what the OS tells us.
+///
+/// The OS can only tell us how to open a file, so anything fancier than that
+/// has to be looked up here by the name of the program (or app bundle).
+#[derive(Debug)]
+pub struct EditorInfo {
+    pub name: &'static str,
+    /// The command used to drive the editor from a shell.
+    pub command: &'static str,
+    /// Program names and app bundle names that identify this editor, without
+    /// any extension, since [`identify`] leaves those off.
+    aliases: &'static [&'static str],
+    /// Arguments that go before the two files to get a side-by-side diff.
+    diff_args: Option<&'static [&'static str]>,
+    /// The flag that picks which profile to open in, which is followed by the
+    /// profile name.
+    profile_flag: Option<&'static str>,
+    /// The flag that opens files in the most recently used window, rather
+    /// than in a new one.
+    reuse_window_flag: Option<&'static str>,
+    /// Whether the editor runs inside of a terminal, in which case we can't
+    /// detach from it.
+    pub terminal: bool,
+}
+
+impl EditorInfo {
+    pub fn supports_diff(&self) -> bool {
+        self.diff_args.is_some()
+    }
+
+    pub fn diff_args(&self) -> Option<&'static [&'static str]> {
+        self.diff_args
+    }
+
+    pub fn supports_profiles(&self) -> bool {
+        self.profile_flag.is_some()
+    }
+
+    pub fn supports_reusing_windows(&self) -> bool {
+        self.reuse_window_flag.is_some()
+    }
+
+    /// Whether opening another file adds it to the same window. Terminal
+    /// editors take over the terminal, so the next file wouldn't open until
+    /// the first was closed.
+    pub fn opens_multiple_files(&self) -> bool {
+        !self.terminal
+    }
+
+    fn matches(&self, name: &str) -> bool {
+        self.command.eq_ignore_ascii_case(name)
+            || self
+                .aliases
+                .iter()
+                .any(|alias| alias.eq_ignore_ascii_case(name))
+    }
+}
+
+static KNOWN_EDITORS: &[EditorInfo] = &[
+    EditorInfo {
+        name: "Visual Studio Code",
+        command: "code",
+        aliases: &["Code", "Visual Studio Code", "code-insiders"],
+        diff_args: Some(&["--diff"]),
+        profile_flag: Some("--profile"),
+        reuse_window_flag: Some("--reuse-window"),
+        terminal: false,
+    },
+    EditorInfo {
+        name: "VSCodium",
+        command: "codium",
+        aliases: &["VSCodium"],
+        diff_args: Some(&["--diff"]),
+        profile_flag: Some("--profile"),
+        reuse_window_flag: Some("--reuse-window"),
+        terminal: false,
+    },
+    EditorInfo {
+        name: "Android Studio",
+        command: "studio",
+        aliases: &["studio64", "Android Studio"],
+        diff_args: Some(&["diff"]),
+        profile_flag: None,
+        reuse_window_flag: None,
+        terminal: false,
+    },
+    EditorInfo {
+        name: "IntelliJ IDEA",
+        command: "idea",
+        aliases: &["idea64", "IntelliJ IDEA", "IntelliJ IDEA CE"],
+        diff_args: Some(&["diff"]),
+        profile_flag: None,
+        reuse_window_flag: None,
+        terminal: false,
+    },
+    EditorInfo {
+        name: "CLion",
+        command: "clion",
+        aliases: &["clion64", "CLion"],
+        diff_args: Some(&["diff"]),
+        profile_flag: None,
+        reuse_window_flag: None,
+        terminal: false,
+    },
+    EditorInfo {
+        name: "Vim",
+        command: "vim",
+        aliases: &["vi"],
+        diff_args: Some(&["-d"]),
+        profile_flag: None,
+        reuse_window_flag: None,
+        terminal: true,
+    },
+    EditorInfo {
+        name: "Neovim",
+        command: "nvim",
+        aliases: &[],
+        diff_args: Some(&["-d"]),
+        profile_flag: None,
+        reuse_window_flag: None,
+        terminal: true,
+    },
+    EditorInfo {
+        name: "GVim",
+        command: "gvim",
+        aliases: &["MacVim"],
+        diff_args: Some(&["-d"]),
+        profile_flag: None,
+        reuse_window_flag: Some("--remote-silent"),
+        terminal: false,
+    },
+    EditorInfo {
+        name: "Sublime Text",
+        command: "subl",
+        aliases: &["sublime_text", "Sublime Text"],
+        diff_args: None,
+        profile_flag: None,
+        reuse_window_flag: Some("--add"),
+        terminal: false,
+    },
+    EditorInfo {
+        name: "Emacs",
+        command: "emacs",
+        aliases: &["Emacs"],
+        diff_args: None,
+        profile_flag: None,
+        reuse_window_flag: None,
+        terminal: false,
+    },
+    EditorInfo {
+        name: "Kate",
+        command: "kate",
+        aliases: &[],
+        diff_args: None,
+        profile_flag: None,
+        reuse_window_flag: None,
+        terminal: false,
+    },
+    EditorInfo {
+        name: "gedit",
+        command: "gedit",
+        aliases: &["gnome-text-editor"],
+        diff_args: None,
+        profile_flag: None,
+        reuse_window_flag: None,
+        terminal: false,
+    },
+];
+
+/// Looks up an editor by its program path, program name, or app bundle path.
+/// Extensions are ignored, so JetBrains' `studio.sh` and `idea64.exe` are
+/// just `studio` and `idea64`.
+pub fn identify(program: impl AsRef<OsStr>) -> Option<&'static EditorInfo> {
+    let name = Path::new(program.as_ref()).file_stem()?.to_str()?;
+    KNOWN_EDITORS.iter().find(|info| info.matches(name))
+}
+
+/// Pagers and viewers, which a misconfigured `mimeapps.list` can leave as
+/// the default handler for text files. Opening a project in one of these is
+/// read-only, which is surprising enough that we'd rather pick something else.
+static PAGERS: &[&str] = &["less", "more", "most", "cat", "bat", "batcat"];
+
+fn is_blocked_pager(name: &str, allowed: &[String]) -> bool {
+    PAGERS.contains(&name) && !allowed.iter().any(|allowed| allowed == name)
+}
+
+/// Whether `program` (a path or name) is a pager or viewer rather than an
+/// editor, and isn't in `allowed`, which is from `editor.allowed-pagers`.
+pub fn is_pager(program: impl AsRef<OsStr>, allowed: &[String]) -> bool {
+    match Path::new(program.as_ref()).file_name() {
+        Some(name) => is_blocked_pager(&name.to_string_lossy(), allowed),
+        None => false,
+    }
+}
+
+/// Launches a side-by-side diff of `left` and `right` using `program`, which
+/// should be the resolved path (or name) of the editor described by `info`.
+///
+/// Returns `None` if the editor doesn't support diffing.
+pub fn open_diff(
+    info: &EditorInfo,
+    program: impl AsRef<OsStr>,
+    left: &Path,
+    right: &Path,
+) -> Option<bossy::Result<()>> {
+    let mut command = bossy::Command::impure(program)
+        .with_args(info.diff_args()?)
+        .with_args([left, right]);
+    Some(if info.terminal {
+        command.run_and_wait().map(|_status| ())
+    } else {
+        command.run_and_detach()
+    })
+}
+
+/// Opens `path` using `program`, which should be the resolved path (or name)
+/// of the editor described by `info`, passing whichever of `profile` and
+/// `reuse_window` the editor supports; the rest are ignored.
+///
+/// Returns `None` if the editor doesn't support any of them, since it might
+/// as well be opened the usual way.
+pub fn open_with_flags(
+    info: &EditorInfo,
+    program: impl AsRef<OsStr>,
+    path: &Path,
+    profile: Option<&str>,
+    reuse_window: bool,
+    env: &BTreeMap<String, String>,
+) -> Option<bossy::Result<()>> {
+    let mut args = Vec::new();
+    match (profile, info.profile_flag) {
+        (Some(profile), Some(flag)) => args.extend([flag, profile]),
+        (Some(profile), None) => log::debug!(
+            "{} doesn't support profiles, so ignoring profile {:?}",
+            info.name,
+            profile
+        ),
+        (None, _) => (),
+    }
+    match (reuse_window, info.reuse_window_flag) {
+        (true, Some(flag)) => args.push(flag),
+        (true, None) => log::debug!(
+            "{} doesn't support reusing windows, so ignoring `editor.reuse-window`",
+            info.name
+        ),
+        (false, _) => (),
+    }
+    if args.is_empty() {
+        return None;
+    }
+    Some(
+        bossy::Command::impure(program)
+            .with_args(args)
+            .with_arg(path)
+            .with_env_vars(env)
+            .run_and_detach(),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest(
+        name,
+        allowed,
+        expected,
+        case("less", &[], true),
+        case("batcat", &[], true),
+        case("vim", &[], false),
+        case("bat", &["bat"], false),
+        case("less", &["bat"], true)
+    )]
+    fn blocks_pagers_unless_allowed(name: &str, allowed: &[&str], expected: bool) {
+        let allowed = allowed.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!(is_blocked_pager(name, &allowed), expected);
+    }
+
+    #[rstest(
+        program,
+        expected,
+        case("/opt/android-studio/bin/studio.sh", Some("Android Studio")),
+        case("studio64.exe", Some("Android Studio")),
+        case("/Applications/Android Studio.app", Some("Android Studio")),
+        case("/opt/idea-IC/bin/idea.sh", Some("IntelliJ IDEA")),
+        case("idea64.exe", Some("IntelliJ IDEA")),
+        case("/Applications/IntelliJ IDEA CE.app", Some("IntelliJ IDEA")),
+        case("clion.sh", Some("CLion")),
+        case("/usr/bin/vi", Some("Vim")),
+        case("/usr/bin/nano", None)
+    )]
+    fn identifies_editors(program: &str, expected: Option<&str>) {
+        assert_eq!(identify(program).map(|info| info.name), expected);
+    }
+
+    #[rstest(
+        program,
+        expected,
+        case("/usr/bin/code", true),
+        case("/Applications/Sublime Text.app", true),
+        case("studio.sh", false),
+        case("nvim", false)
+    )]
+    fn knows_which_editors_reuse_windows(program: &str, expected: bool) {
+        assert_eq!(
+            identify(program).unwrap().supports_reusing_windows(),
+            expected
+        );
+    }
+}
@@ -0,0 +1,43 @@
+use std::{
+    env,
+    io::{self, IsTerminal as _},
+};
+
+/// Set to `1`/`true` or `0`/`false` to override [`can_prompt`]'s detection.
+/// Mainly useful for testing prompts in CI, or making sure nothing prompts
+/// locally.
+pub static INTERACTIVE_VAR: &str = "CARGO_MOBILE_INTERACTIVE";
+
+pub(super) fn flag(var: &str) -> Option<bool> {
+    let value = env::var(var).ok()?;
+    match value.trim().to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" => Some(true),
+        "0" | "false" | "no" | "" => Some(false),
+        _ => {
+            log::warn!("ignoring unrecognized value {:?} for {}", value, var);
+            None
+        }
+    }
+}
+
+fn in_ci() -> bool {
+    // Every major CI provider sets `CI`, though some set it to `false` when
+    // they're just being helpful.
+    flag("CI").unwrap_or_else(|| env::var_os("CI").is_some())
+}
+
+/// Whether it's okay to stop and ask the user something, which requires stdin
+/// to be a terminal and us to not be running in CI. Anything that prompts
+/// should check this first, and fall back to erroring or to a config-driven
+/// default otherwise.
+pub fn can_prompt() -> bool {
+    if let Some(interactive) = flag(INTERACTIVE_VAR) {
+        log::info!(
+            "{} is set, so `can_prompt` is {}",
+            INTERACTIVE_VAR,
+            interactive
+        );
+        return interactive;
+    }
+    io::stdin().is_terminal() && !in_ci()
+}
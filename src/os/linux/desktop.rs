@@ -0,0 +1,207 @@
+use super::xdg;
+use crate::bossy;
+use freedesktop_entry_parser::Entry;
+use std::{env, fs, path::PathBuf};
+
+/// Where a desktop environment keeps track of default applications.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Source {
+    /// The `mimeapps.list` files, read directly, including the
+    /// desktop-specific ones `xdg-mime` doesn't always know to look at.
+    MimeApps,
+    /// `xdg-mime query default`, which works everywhere, but whose answers
+    /// depend on which backend it picks for the desktop.
+    XdgMime,
+    /// `gio mime`, which answers the same way GNOME's settings do.
+    Gio,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Desktop {
+    Kde,
+    Gnome,
+    Other,
+}
+
+impl Desktop {
+    /// Picks the desktop out of a `XDG_CURRENT_DESKTOP` value, which is a
+    /// colon-separated list like `ubuntu:GNOME`, in which the first desktop
+    /// we know wins.
+    pub fn from_current_desktop(value: Option<&str>) -> Self {
+        value
+            .unwrap_or_default()
+            .split(':')
+            .find_map(|name| {
+                let name = name.trim().to_ascii_uppercase();
+                if name == "KDE" {
+                    Some(Self::Kde)
+                } else if name == "GNOME" || name.starts_with("GNOME-") {
+                    Some(Self::Gnome)
+                } else {
+                    None
+                }
+            })
+            .unwrap_or(Self::Other)
+    }
+
+    pub fn detect() -> Self {
+        Self::from_current_desktop(env::var("XDG_CURRENT_DESKTOP").ok().as_deref())
+    }
+
+    /// The prefix of this desktop's own `mimeapps.list` files, which take
+    /// precedence over the generic ones.
+    fn mimeapps_prefix(self) -> Option<&'static str> {
+        match self {
+            Self::Kde => Some("kde"),
+            Self::Gnome => Some("gnome"),
+            Self::Other => None,
+        }
+    }
+
+    /// The sources to ask for a default, in order.
+    pub fn sources(self) -> &'static [Source] {
+        match self {
+            // `xdg-mime` only reads KDE's config when it can find
+            // `kreadconfig`, and misses `kde-mimeapps.list` entirely.
+            Self::Kde => &[Source::MimeApps, Source::XdgMime],
+            // Defaults set through GNOME's settings always show up in `gio`,
+            // even when `xdg-mime` falls back to its generic backend.
+            Self::Gnome => &[Source::XdgMime, Source::Gio],
+            Self::Other => &[Source::XdgMime],
+        }
+    }
+
+    /// The desktop entry name of the default handler for `mime_type`, asking
+    /// each of this desktop's sources in turn.
+    pub fn default_handler(self, mime_type: &str) -> Option<PathBuf> {
+        self.sources().iter().find_map(|source| {
+            let handler = match source {
+                Source::MimeApps => self.query_mimeapps(mime_type),
+                Source::XdgMime => xdg::query_mime_entry(mime_type),
+                Source::Gio => query_gio(mime_type),
+            };
+            log::debug!("{:?} default for {:?}: {:?}", source, mime_type, handler);
+            handler
+        })
+    }
+
+    // https://specifications.freedesktop.org/mime-apps-spec/latest/file.html
+    fn mimeapps_paths(self) -> Vec<PathBuf> {
+        let config_home = env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| {
+                crate::util::home_dir()
+                    .ok()
+                    .map(|home| home.join(".config"))
+            });
+        let config_dirs = env::var("XDG_CONFIG_DIRS")
+            .map(|dirs| dirs.split(':').map(PathBuf::from).collect::<Vec<_>>())
+            .unwrap_or_else(|_| vec![PathBuf::from("/etc/xdg")]);
+        let data_dirs = xdg::get_xdg_data_dirs()
+            .into_iter()
+            .map(|dir| dir.join("applications"));
+        let mut names = self
+            .mimeapps_prefix()
+            .map(|prefix| format!("{}-mimeapps.list", prefix))
+            .into_iter()
+            .collect::<Vec<_>>();
+        names.push("mimeapps.list".to_owned());
+        config_home
+            .into_iter()
+            .chain(config_dirs)
+            .chain(data_dirs)
+            .flat_map(|dir| names.iter().map(|name| dir.join(name)).collect::<Vec<_>>())
+            .collect()
+    }
+
+    fn query_mimeapps(self, mime_type: &str) -> Option<PathBuf> {
+        self.mimeapps_paths().into_iter().find_map(|path| {
+            let contents = fs::read(&path).ok()?;
+            default_from_mimeapps(contents, mime_type)
+        })
+    }
+}
+
+/// The first default listed for `mime_type` in the contents of a
+/// `mimeapps.list` file.
+fn default_from_mimeapps(contents: impl Into<Vec<u8>>, mime_type: &str) -> Option<PathBuf> {
+    let entry = Entry::parse(contents).ok()?;
+    entry
+        .section("Default Applications")
+        .attr(mime_type)?
+        .split(';')
+        .map(str::trim)
+        .find(|name| !name.is_empty())
+        .map(PathBuf::from)
+}
+
+// `gio mime text/plain` prints something like:
+//
+// Default application for “text/plain”: org.gnome.TextEditor.desktop
+// Registered applications:
+//  ...
+//
+// That's translated, so we ask for it untranslated.
+fn query_gio(mime_type: &str) -> Option<PathBuf> {
+    bossy::Command::impure("gio")
+        .with_env_var("LC_ALL", "C")
+        .with_args(["mime", mime_type])
+        .run_and_wait_for_str(|out| {
+            out.lines()
+                .next()
+                .filter(|line| line.starts_with("Default application"))
+                .and_then(|line| line.rsplit(": ").next())
+                .map(str::trim)
+                .filter(|name| name.ends_with(".desktop"))
+                .map(PathBuf::from)
+        })
+        .ok()?
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest(
+        value,
+        expected,
+        case(Some("KDE"), Desktop::Kde),
+        case(Some("kde"), Desktop::Kde),
+        case(Some("GNOME"), Desktop::Gnome),
+        case(Some("ubuntu:GNOME"), Desktop::Gnome),
+        case(Some("GNOME-Flashback:GNOME"), Desktop::Gnome),
+        case(Some("KDE:GNOME"), Desktop::Kde),
+        case(Some("XFCE"), Desktop::Other),
+        case(Some(""), Desktop::Other),
+        case(None, Desktop::Other)
+    )]
+    fn detects_desktop(value: Option<&str>, expected: Desktop) {
+        assert_eq!(Desktop::from_current_desktop(value), expected);
+    }
+
+    #[rstest(desktop, expected,
+        case(Desktop::Kde, &[Source::MimeApps, Source::XdgMime]),
+        case(Desktop::Gnome, &[Source::XdgMime, Source::Gio]),
+        case(Desktop::Other, &[Source::XdgMime])
+    )]
+    fn dispatches_by_desktop(desktop: Desktop, expected: &[Source]) {
+        assert_eq!(desktop.sources(), expected);
+    }
+
+    #[test]
+    fn reads_default_from_mimeapps() {
+        let contents = "\
+[Added Associations]
+text/plain=org.kde.kwrite.desktop;
+
+[Default Applications]
+text/plain=org.kde.kate.desktop;org.kde.kwrite.desktop;
+";
+        assert_eq!(
+            default_from_mimeapps(contents, "text/plain"),
+            Some(PathBuf::from("org.kde.kate.desktop"))
+        );
+        assert_eq!(default_from_mimeapps(contents, "text/rust"), None);
+    }
+}
@@ -0,0 +1,430 @@
+// https://specifications.freedesktop.org/icon-theme-spec/latest/
+
+use super::{desktop::Desktop, xdg};
+use freedesktop_entry_parser::Entry;
+use std::{
+    collections::HashSet,
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+static FALLBACK_THEME: &str = "hicolor";
+static EXTENSIONS: &[&str] = &["png", "svg", "xpm"];
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum DirKind {
+    Fixed,
+    Scalable,
+    Threshold,
+}
+
+#[derive(Debug)]
+struct ThemeDir {
+    path: String,
+    size: u32,
+    scale: u32,
+    kind: DirKind,
+    min_size: u32,
+    max_size: u32,
+    threshold: u32,
+}
+
+impl ThemeDir {
+    fn parse(entry: &Entry, path: &str) -> Option<Self> {
+        let section = entry.section(path);
+        let num = |key| {
+            section
+                .attr(key)
+                .and_then(|value| value.trim().parse().ok())
+        };
+        let size = num("Size")?;
+        Some(Self {
+            path: path.to_owned(),
+            size,
+            scale: num("Scale").unwrap_or(1),
+            kind: match section.attr("Type").map(str::trim) {
+                Some("Fixed") => DirKind::Fixed,
+                Some("Scalable") => DirKind::Scalable,
+                _ => DirKind::Threshold,
+            },
+            min_size: num("MinSize").unwrap_or(size),
+            max_size: num("MaxSize").unwrap_or(size),
+            threshold: num("Threshold").unwrap_or(2),
+        })
+    }
+
+    fn matches_size(&self, size: u32, scale: u32) -> bool {
+        if self.scale != scale {
+            return false;
+        }
+        match self.kind {
+            DirKind::Fixed => self.size == size,
+            DirKind::Scalable => self.min_size <= size && size <= self.max_size,
+            DirKind::Threshold => {
+                self.size.saturating_sub(self.threshold) <= size
+                    && size <= self.size + self.threshold
+            }
+        }
+    }
+
+    fn size_distance(&self, size: u32, scale: u32) -> u32 {
+        let wanted = size * scale;
+        let (min, max) = match self.kind {
+            DirKind::Fixed => (self.size, self.size),
+            DirKind::Scalable => (self.min_size, self.max_size),
+            DirKind::Threshold => (
+                self.size.saturating_sub(self.threshold),
+                self.size + self.threshold,
+            ),
+        };
+        let (min, max) = (min * self.scale, max * self.scale);
+        if wanted < min {
+            min - wanted
+        } else {
+            wanted.saturating_sub(max)
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Theme {
+    /// Every base dir this theme has a dir in, since a theme can be spread
+    /// across several of them.
+    roots: Vec<PathBuf>,
+    dirs: Vec<ThemeDir>,
+    inherits: Vec<String>,
+}
+
+impl Theme {
+    fn parse(index: &str, roots: Vec<PathBuf>) -> Option<Self> {
+        let entry = Entry::parse(index).ok()?;
+        let section = entry.section("Icon Theme");
+        let list = |key| {
+            section
+                .attr(key)
+                .unwrap_or_default()
+                .split(',')
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .map(ToOwned::to_owned)
+                .collect::<Vec<_>>()
+        };
+        let dirs = list("Directories")
+            .into_iter()
+            .chain(list("ScaledDirectories"))
+            .filter_map(|path| ThemeDir::parse(&entry, &path))
+            .collect();
+        Some(Self {
+            roots,
+            dirs,
+            inherits: list("Inherits"),
+        })
+    }
+}
+
+/// What lookups need from the filesystem, so they can be tested without one.
+trait IconFs {
+    fn exists(&self, path: &Path) -> bool;
+    fn read_to_string(&self, path: &Path) -> Option<String>;
+}
+
+struct RealFs;
+
+impl IconFs for RealFs {
+    fn exists(&self, path: &Path) -> bool {
+        path.exists()
+    }
+
+    fn read_to_string(&self, path: &Path) -> Option<String> {
+        fs::read_to_string(path).ok()
+    }
+}
+
+struct Lookup<'a> {
+    base_dirs: Vec<PathBuf>,
+    fs: &'a dyn IconFs,
+}
+
+impl<'a> Lookup<'a> {
+    fn load_theme(&self, name: &str) -> Option<Theme> {
+        let roots = self
+            .base_dirs
+            .iter()
+            .map(|base| base.join(name))
+            .filter(|root| self.fs.exists(root))
+            .collect::<Vec<_>>();
+        let index = roots
+            .iter()
+            .find_map(|root| self.fs.read_to_string(&root.join("index.theme")))?;
+        Theme::parse(&index, roots)
+    }
+
+    fn find_in_dir(&self, theme: &Theme, dir: &ThemeDir, icon: &str) -> Option<PathBuf> {
+        theme.roots.iter().find_map(|root| {
+            EXTENSIONS
+                .iter()
+                .map(|ext| root.join(&dir.path).join(format!("{}.{}", icon, ext)))
+                .find(|path| self.fs.exists(path))
+        })
+    }
+
+    fn find_in_theme(
+        &self,
+        name: &str,
+        icon: &str,
+        size: u32,
+        scale: u32,
+        visited: &mut HashSet<String>,
+    ) -> Option<PathBuf> {
+        // Themes aren't supposed to inherit in cycles, but nothing stops
+        // them, and we'd rather not recurse forever when one does.
+        if !visited.insert(name.to_owned()) {
+            return None;
+        }
+        let theme = self.load_theme(name)?;
+        let exact = theme
+            .dirs
+            .iter()
+            .filter(|dir| dir.matches_size(size, scale))
+            .find_map(|dir| self.find_in_dir(&theme, dir, icon));
+        let closest = || {
+            theme
+                .dirs
+                .iter()
+                .filter_map(|dir| {
+                    self.find_in_dir(&theme, dir, icon)
+                        .map(|path| (dir.size_distance(size, scale), path))
+                })
+                .min_by_key(|(distance, _)| *distance)
+                .map(|(_, path)| path)
+        };
+        exact.or_else(closest).or_else(|| {
+            theme
+                .inherits
+                .iter()
+                .find_map(|parent| self.find_in_theme(parent, icon, size, scale, visited))
+        })
+    }
+
+    /// Icons that aren't in any theme, which are allowed to sit directly in
+    /// the base dirs.
+    fn find_unthemed(&self, icon: &str) -> Option<PathBuf> {
+        self.base_dirs.iter().find_map(|base| {
+            EXTENSIONS
+                .iter()
+                .map(|ext| base.join(format!("{}.{}", icon, ext)))
+                .find(|path| self.fs.exists(path))
+        })
+    }
+
+    fn find_icon(&self, icon: &str, size: u32, scale: u32, theme: Option<&str>) -> Option<PathBuf> {
+        let mut visited = HashSet::new();
+        theme
+            .and_then(|theme| self.find_in_theme(theme, icon, size, scale, &mut visited))
+            .or_else(|| self.find_in_theme(FALLBACK_THEME, icon, size, scale, &mut visited))
+            .or_else(|| self.find_unthemed(icon))
+    }
+}
+
+fn base_dirs() -> Vec<PathBuf> {
+    crate::util::home_dir()
+        .ok()
+        .map(|home| home.join(".icons"))
+        .into_iter()
+        .chain(
+            xdg::get_xdg_data_dirs()
+                .into_iter()
+                .map(|dir| dir.join("icons")),
+        )
+        .chain(std::iter::once(PathBuf::from("/usr/share/pixmaps")))
+        .collect()
+}
+
+fn config_home() -> Option<PathBuf> {
+    env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| {
+            crate::util::home_dir()
+                .ok()
+                .map(|home| home.join(".config"))
+        })
+}
+
+/// The icon theme the user picked, if we can tell. KDE keeps it in
+/// `kdeglobals`, and everything else we know of follows GTK's settings.
+fn current_theme() -> Option<String> {
+    let (file, section, key) = match Desktop::detect() {
+        Desktop::Kde => ("kdeglobals", "Icons", "Theme"),
+        _ => ("gtk-3.0/settings.ini", "Settings", "gtk-icon-theme-name"),
+    };
+    let contents = fs::read(config_home()?.join(file)).ok()?;
+    Entry::parse(contents)
+        .ok()?
+        .section(section)
+        .attr(key)
+        .map(|theme| theme.trim().to_owned())
+        .filter(|theme| !theme.is_empty())
+}
+
+/// Finds the file for the icon named `icon` at `size`, looking in the user's
+/// theme and the themes it inherits from, then `hicolor`, then for icons
+/// that aren't in any theme. `icon` can also just be an absolute path.
+pub fn resolve_icon(icon: &str, size: u32) -> Option<PathBuf> {
+    if Path::new(icon).is_absolute() {
+        return Some(PathBuf::from(icon)).filter(|path| path.is_file());
+    }
+    Lookup {
+        base_dirs: base_dirs(),
+        fs: &RealFs,
+    }
+    .find_icon(icon, size, 1, current_theme().as_deref())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[derive(Default)]
+    struct MemFs {
+        files: HashMap<PathBuf, String>,
+    }
+
+    impl MemFs {
+        fn with_file(mut self, path: &str, contents: &str) -> Self {
+            self.files.insert(PathBuf::from(path), contents.to_owned());
+            self
+        }
+    }
+
+    impl IconFs for MemFs {
+        fn exists(&self, path: &Path) -> bool {
+            self.files
+                .keys()
+                .any(|file| file == path || file.starts_with(path))
+        }
+
+        fn read_to_string(&self, path: &Path) -> Option<String> {
+            self.files.get(path).cloned()
+        }
+    }
+
+    fn lookup(fs: &MemFs) -> Lookup<'_> {
+        Lookup {
+            base_dirs: vec![
+                PathBuf::from("/home/me/.icons"),
+                PathBuf::from("/usr/share/icons"),
+            ],
+            fs,
+        }
+    }
+
+    const HICOLOR: &str = "\
+[Icon Theme]
+Name=Hicolor
+Directories=48x48/apps,scalable/apps
+
+[48x48/apps]
+Size=48
+Type=Threshold
+
+[scalable/apps]
+Size=128
+MinSize=8
+MaxSize=512
+Type=Scalable
+";
+
+    const BREEZE: &str = "\
+[Icon Theme]
+Name=Breeze
+Inherits=Adwaita,hicolor
+Directories=apps/48
+
+[apps/48]
+Size=48
+Type=Fixed
+";
+
+    const ADWAITA: &str = "\
+[Icon Theme]
+Name=Adwaita
+Inherits=Breeze
+Directories=32x32/apps
+
+[32x32/apps]
+Size=32
+Type=Fixed
+";
+
+    fn themes() -> MemFs {
+        MemFs::default()
+            .with_file("/usr/share/icons/hicolor/index.theme", HICOLOR)
+            .with_file("/usr/share/icons/Breeze/index.theme", BREEZE)
+            .with_file("/usr/share/icons/Adwaita/index.theme", ADWAITA)
+    }
+
+    #[test]
+    fn prefers_the_users_theme() {
+        let fs = themes()
+            .with_file("/usr/share/icons/Breeze/apps/48/code.svg", "")
+            .with_file("/usr/share/icons/hicolor/48x48/apps/code.png", "");
+        assert_eq!(
+            lookup(&fs).find_icon("code", 48, 1, Some("Breeze")),
+            Some(PathBuf::from("/usr/share/icons/Breeze/apps/48/code.svg"))
+        );
+    }
+
+    #[test]
+    fn finds_icon_in_inherited_theme() {
+        let fs = themes().with_file("/usr/share/icons/Adwaita/32x32/apps/code.png", "");
+        assert_eq!(
+            lookup(&fs).find_icon("code", 48, 1, Some("Breeze")),
+            Some(PathBuf::from(
+                "/usr/share/icons/Adwaita/32x32/apps/code.png"
+            ))
+        );
+    }
+
+    #[test]
+    fn falls_back_to_hicolor() {
+        let fs = themes().with_file("/home/me/.icons/hicolor/scalable/apps/code.svg", "");
+        assert_eq!(
+            lookup(&fs).find_icon("code", 48, 1, Some("Adwaita")),
+            Some(PathBuf::from(
+                "/home/me/.icons/hicolor/scalable/apps/code.svg"
+            ))
+        );
+    }
+
+    #[test]
+    fn survives_inheritance_cycles() {
+        // Breeze and Adwaita inherit from each other.
+        let fs = themes();
+        assert_eq!(lookup(&fs).find_icon("code", 48, 1, Some("Adwaita")), None);
+    }
+
+    #[test]
+    fn finds_unthemed_icons() {
+        let fs = themes().with_file("/usr/share/icons/code.xpm", "");
+        assert_eq!(
+            lookup(&fs).find_icon("code", 48, 1, Some("Breeze")),
+            Some(PathBuf::from("/usr/share/icons/code.xpm"))
+        );
+    }
+
+    #[test]
+    fn picks_closest_size() {
+        let fs = themes()
+            .with_file("/usr/share/icons/hicolor/48x48/apps/code.png", "")
+            .with_file("/usr/share/icons/hicolor/scalable/apps/code.svg", "");
+        // 256 doesn't fall in the 48x48 dir's threshold, but does in the
+        // scalable dir's range.
+        assert_eq!(
+            lookup(&fs).find_icon("code", 256, 1, None),
+            Some(PathBuf::from(
+                "/usr/share/icons/hicolor/scalable/apps/code.svg"
+            ))
+        );
+    }
+}
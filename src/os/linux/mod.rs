@@ -1,9 +1,15 @@
+mod desktop;
+mod icon;
 pub(super) mod info;
+mod startup_notify;
+mod wsl;
 mod xdg;
 
+use super::LaunchOptions;
 use std::{
     ffi::{OsStr, OsString},
     io,
+    os::unix::ffi::OsStrExt as _,
     path::{Path, PathBuf},
 };
 use thiserror::Error;
@@ -12,7 +18,7 @@ pub use crate::{bossy, env::Env, util::ln};
 
 #[derive(Debug, Error)]
 pub enum DetectEditorError {
-    #[error("No default editor is set: xdg-mime queries for \"text/rust\" and \"text/plain\" both failed")]
+    #[error("No default editor is set: queries for \"text/rust\", \"text/x-rust\", and \"text/plain\" all failed")]
     NoDefaultEditorSet,
     #[error("Entry Not Found: xdg-mime returned an entry name that could not be found")]
     FreeDesktopEntryNotFound,
@@ -26,93 +32,547 @@ pub enum DetectEditorError {
     ExecFieldMissing,
 }
 
+impl DetectEditorError {
+    /// Whether this just means the user hasn't picked an editor.
+    pub fn no_default_editor(&self) -> bool {
+        matches!(self, Self::NoDefaultEditorSet)
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum OpenFileError {
-    #[error("Launch failed: {0}")]
+    /// On hardened systems, the hint points at SELinux or AppArmor, since
+    /// their denials otherwise look like any other failure.
+    #[error("Launch failed: {0}{}", super::launch_failure_hint(super::denied_by(.0)))]
     LaunchFailed(bossy::Error),
     #[error("Command parsing failed")]
     CommandParsingFailed,
+    #[error("Failed to copy file somewhere the snap can read it: {0}")]
+    SnapCopyFailed(crate::util::CopyArtifactsError),
+    #[error("Program {program:?} wasn't found")]
+    ProgramNotFound { program: OsString },
+    #[error("The `{name}` snap isn't installed")]
+    SnapNotInstalled { name: String },
+    #[error("The Flatpak app `{app_id}` isn't installed")]
+    FlatpakNotInstalled { app_id: String },
+}
+
+/// How an application was installed, which matters since sandboxed apps can't
+/// necessarily open every file we hand them.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum InstallKind {
+    Native,
+    Snap { name: String },
+    Flatpak { app_id: String },
+    AppImage,
+}
+
+impl InstallKind {
+    fn detect(command_parts: &[OsString], xdg_entry_path: &Path) -> Self {
+        let mut parts = command_parts.iter().map(|part| part.to_string_lossy());
+        let program = parts.next().unwrap_or_default();
+        let program_name = Path::new(program.as_ref())
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        // `snap run` and `flatpak run` take flags before the app name
+        let mut run_target = || {
+            parts
+                .by_ref()
+                .skip_while(|part| part != "run")
+                .skip(1)
+                .find(|part| !part.starts_with('-'))
+                .map(|target| target.into_owned())
+        };
+        if program.starts_with("/snap/bin/") {
+            Self::Snap {
+                // `/snap/bin/<snap>.<app>` for snaps with multiple apps
+                name: program_name
+                    .split('.')
+                    .next()
+                    .unwrap_or_default()
+                    .to_owned(),
+            }
+        } else if program_name == "snap" {
+            run_target()
+                .map(|target| Self::Snap {
+                    name: target.split('.').next().unwrap_or_default().to_owned(),
+                })
+                .unwrap_or(Self::Native)
+        } else if program_name == "flatpak" {
+            run_target()
+                .map(|app_id| Self::Flatpak { app_id })
+                .unwrap_or(Self::Native)
+        } else if program_name.ends_with(".AppImage") {
+            Self::AppImage
+        } else if xdg_entry_path.starts_with("/var/lib/snapd/desktop/applications") {
+            // Entries exported by snapd are named `<snap>_<app>.desktop`
+            xdg_entry_path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(|stem| stem.split('_').next())
+                .map(|name| Self::Snap {
+                    name: name.to_owned(),
+                })
+                .unwrap_or(Self::Native)
+        } else {
+            Self::Native
+        }
+    }
 }
 
+/// Checks if `path` is somewhere under a hidden directory in `$HOME`, i.e.
+/// `~/.cargo/registry/...`.
+fn in_hidden_home_dir(path: &Path) -> bool {
+    let path = dunce::canonicalize(path).unwrap_or_else(|_| path.to_owned());
+    crate::util::home_dir()
+        .ok()
+        .and_then(|home| {
+            path.strip_prefix(&home).ok().map(|relative| {
+                relative
+                    .parent()
+                    .map(|parent| {
+                        parent
+                            .components()
+                            .any(|component| component.as_os_str().as_bytes().starts_with(b"."))
+                    })
+                    .unwrap_or_default()
+            })
+        })
+        .unwrap_or_default()
+}
+
+/// Programs that hand files off to the default app instead of opening them.
+const GENERIC_OPENERS: &[&str] = &[
+    "xdg-open",
+    "kde-open",
+    "kde-open5",
+    "gnome-open",
+    "exo-open",
+    "open",
+];
+
 #[derive(Debug)]
 pub struct Application {
     exec_command: OsString,
     icon: Option<OsString>,
+    name: Option<String>,
+    generic_name: Option<String>,
+    comment: Option<String>,
+    startup_wm_class: Option<String>,
+    startup_notify: bool,
     xdg_entry_path: PathBuf,
 }
 
 impl Application {
-    pub fn detect_editor() -> Result<Self, DetectEditorError> {
-        // Try a rust code editor, then a plain text editor. If neither are available,
-        // then return an error.
-        let entry = xdg::query_mime_entry("text/rust")
-            .or_else(|| xdg::query_mime_entry("text/plain"))
-            .ok_or(DetectEditorError::NoDefaultEditorSet)?;
-
-        xdg::get_xdg_data_dirs()
+    /// `allowed_pagers` is from `editor.allowed-pagers`; any other pagers are
+    /// skipped.
+    pub fn detect_editor(allowed_pagers: &[String]) -> Result<Self, DetectEditorError> {
+        // Prefer a rust code editor, then a plain text editor. If neither have
+        // a default set, then return an error.
+        let desktop = desktop::Desktop::detect();
+        let defaults = xdg::EDITOR_MIME_TYPES
             .iter()
-            .find_map(|dir| {
-                let dir = dir.join("applications");
-                xdg::find_entry_in_dir(&dir, &entry)
-                    // If finding an entry (a filename) in that directory, returns an error (such as if the directory
-                    // is non existent, or the directory exists but for whatever reason listing its contents failed),
-                    // we should skip it, as per the XDG Base Directory Specification v0.7 (latest as of today)
-                    .ok()? // This returns None on error, continuing the search (skiping this dir)
-                    .map(|entry_filepath| {
-                        // If something was found, we have to try parsing it, which may fail as well
-                        xdg::parse(&entry_filepath)
-                            .map_err(DetectEditorError::FreeDesktopEntryParseError)
-                            .and_then(|parsed_entry| {
-                                Ok(Self {
-                                    // We absolutely want the Exec value
-                                    exec_command: parsed_entry
-                                        .section("Desktop Entry")
-                                        .attr("Exec")
-                                        .ok_or(DetectEditorError::ExecFieldMissing)?
-                                        .into(),
-                                    // The icon is optional, we try getting it because the Exec value may need it
-                                    icon: parsed_entry
-                                        .section("Desktop Entry")
-                                        .attr("Icon")
-                                        .map(Into::into),
-                                    xdg_entry_path: entry_filepath,
-                                })
+            .map(|mime_type| (*mime_type, desktop.default_handler(mime_type)))
+            .collect::<Vec<_>>();
+        if defaults.iter().all(|(_, entry)| entry.is_none()) {
+            return Err(DetectEditorError::NoDefaultEditorSet);
+        }
+        // If the defaults' entries have gone missing, we fall back to the
+        // other handlers rather than giving up, but any default beats them.
+        let mut candidates = xdg::merge_handlers(
+            defaults
+                .iter()
+                .map(|(mime_type, default)| (*mime_type, default.iter().cloned().collect())),
+        );
+        for handler in xdg::merge_handlers(
+            xdg::EDITOR_MIME_TYPES
+                .iter()
+                .map(|mime_type| (*mime_type, xdg::cached_handlers(mime_type))),
+        ) {
+            if !candidates.contains(&handler) {
+                candidates.push(handler);
+            }
+        }
+        Self::skip_non_editors(
+            candidates
+                .iter()
+                .filter_map(|entry| Self::from_entry_name(entry)),
+            allowed_pagers,
+        )
+        // If this returns None, no errors ocurred, and no elements were found
+        .unwrap_or(Err(DetectEditorError::FreeDesktopEntryNotFound))
+    }
+
+    /// The first of `found` that's actually an editor. Generic openers would
+    /// at best bounce the file back into default app resolution, and pagers
+    /// would open it read-only.
+    fn skip_non_editors(
+        mut found: impl Iterator<Item = Result<Self, DetectEditorError>>,
+        allowed_pagers: &[String],
+    ) -> Option<Result<Self, DetectEditorError>> {
+        found.find(|result| match result {
+            Ok(app) if app.is_generic_opener() => {
+                log::info!(
+                    "skipping {:?}, since it runs a generic opener rather than an editor: {:?}",
+                    app.xdg_entry_path,
+                    app.exec_command
+                );
+                false
+            }
+            Ok(app) if app.is_pager(allowed_pagers) => {
+                log::warn!(
+                    "skipping {:?}, since it runs a pager rather than an editor: {:?} (add it to `editor.allowed-pagers` if that's what you want)",
+                    app.xdg_entry_path,
+                    app.exec_command
+                );
+                false
+            }
+            _ => true,
+        })
+    }
+
+    fn from_entry_name(entry: &Path) -> Option<Result<Self, DetectEditorError>> {
+        xdg::get_xdg_data_dirs().iter().find_map(|dir| {
+            let dir = dir.join("applications");
+            xdg::find_entry_in_dir(&dir, entry)
+                // If finding an entry (a filename) in that directory, returns an error (such as if the directory
+                // is non existent, or the directory exists but for whatever reason listing its contents failed),
+                // we should skip it, as per the XDG Base Directory Specification v0.7 (latest as of today)
+                .ok()? // This returns None on error, continuing the search (skiping this dir)
+                .map(|entry_filepath| {
+                    // If something was found, we have to try parsing it, which may fail as well
+                    xdg::parse(&entry_filepath)
+                        .map_err(DetectEditorError::FreeDesktopEntryParseError)
+                        .and_then(|parsed_entry| {
+                            let attr = |name| {
+                                parsed_entry
+                                    .section("Desktop Entry")
+                                    .attr(name)
+                                    .map(ToOwned::to_owned)
+                            };
+                            Ok(Self {
+                                // We absolutely want the Exec value
+                                exec_command: parsed_entry
+                                    .section("Desktop Entry")
+                                    .attr("Exec")
+                                    .ok_or(DetectEditorError::ExecFieldMissing)?
+                                    .into(),
+                                // The icon is optional, we try getting it because the Exec value may need it
+                                icon: parsed_entry
+                                    .section("Desktop Entry")
+                                    .attr("Icon")
+                                    .map(Into::into),
+                                name: attr("Name"),
+                                generic_name: attr("GenericName"),
+                                comment: attr("Comment"),
+                                startup_wm_class: attr("StartupWMClass"),
+                                startup_notify: attr("StartupNotify")
+                                    .is_some_and(|notify| notify == "true"),
+                                xdg_entry_path: entry_filepath,
                             })
-                    })
-            })
-            // If this returns None, no errors ocurred, and no elements were found
-            .unwrap_or(Err(DetectEditorError::FreeDesktopEntryNotFound))
+                        })
+                })
+        })
+    }
+
+    /// The display name of the application, i.e. "Kate".
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// The generic name of the application, i.e. "Advanced Text Editor".
+    pub fn generic_name(&self) -> Option<&str> {
+        self.generic_name.as_deref()
+    }
+
+    /// A tooltip-style description of the application.
+    pub fn comment(&self) -> Option<&str> {
+        self.comment.as_deref()
+    }
+
+    /// The `WM_CLASS` the application's windows are expected to have, which
+    /// is how window managers match windows up with the entry that launched
+    /// them.
+    pub fn startup_wm_class(&self) -> Option<&str> {
+        self.startup_wm_class.as_deref()
+    }
+
+    /// Where the application was found, which is its desktop entry here.
+    pub fn location(&self) -> Option<&Path> {
+        Some(&self.xdg_entry_path)
+    }
+
+    /// The raw command used to launch the application.
+    pub fn exec_string(&self) -> String {
+        self.exec_command.to_string_lossy().into_owned()
     }
 
     pub fn open_file(&self, path: impl AsRef<Path>) -> Result<(), OpenFileError> {
+        self.open_file_with_options(path, LaunchOptions::default())
+    }
+
+    /// Checks that launching this would find what it's supposed to, without
+    /// actually launching anything: the program has to resolve, and for
+    /// snaps and Flatpaks, the app it runs has to be installed.
+    pub fn validate(&self) -> Result<(), OpenFileError> {
+        let program = self.program().ok_or(OpenFileError::CommandParsingFailed)?;
+        let found = if Path::new(&program).is_absolute() {
+            Path::new(&program).is_file()
+        } else {
+            command_path(&program.to_string_lossy()).is_ok()
+        };
+        if !found {
+            return Err(OpenFileError::ProgramNotFound { program });
+        }
+        match self.install_kind() {
+            InstallKind::Snap { name } => bossy::Command::impure("snap")
+                .with_args(["list", &name])
+                .run_and_wait_for_output()
+                .map(|_| ())
+                .map_err(|_| OpenFileError::SnapNotInstalled { name }),
+            InstallKind::Flatpak { app_id } => bossy::Command::impure("flatpak")
+                .with_args(["info", &app_id])
+                .run_and_wait_for_output()
+                .map(|_| ())
+                .map_err(|_| OpenFileError::FlatpakNotInstalled { app_id }),
+            InstallKind::Native | InstallKind::AppImage => Ok(()),
+        }
+    }
+
+    pub fn install_kind(&self) -> InstallKind {
+        InstallKind::detect(
+            &xdg::parse_command(&self.exec_command, "".as_ref(), None, None),
+            &self.xdg_entry_path,
+        )
+    }
+
+    // Snaps can't traverse hidden dirs in `$HOME`, and fail to open the file
+    // without telling anyone why. We either warn about that, or (if the caller
+    // asked for it) copy the file to the snap's own data dir.
+    fn snap_accessible_path(
+        &self,
+        path: &Path,
+        options: &LaunchOptions,
+    ) -> Result<Option<PathBuf>, OpenFileError> {
+        let name = match self.install_kind() {
+            InstallKind::Snap { name } if in_hidden_home_dir(path) => name,
+            _ => return Ok(None),
+        };
+        if !options.copy_from_hidden_dirs {
+            log::warn!(
+                "{:?} is inside a hidden directory, which the `{}` snap can't open; it may fail to open it",
+                path,
+                name
+            );
+            return Ok(None);
+        }
+        let home = match crate::util::home_dir() {
+            Ok(home) => home,
+            Err(_) => return Ok(None),
+        };
+        let out_dir = home.join("snap").join(&name).join("common/cargo-mobile");
+        let copied = crate::util::copy_artifacts(&out_dir, Some(path))
+            .map_err(OpenFileError::SnapCopyFailed)?;
+        log::warn!(
+            "{:?} is inside a hidden directory, which the `{}` snap can't open, so a copy was made at {:?}; changes to it won't be reflected in the original",
+            path,
+            name,
+            copied[0]
+        );
+        Ok(copied.into_iter().next())
+    }
+
+    pub fn open_file_with_options(
+        &self,
+        path: impl AsRef<Path>,
+        options: LaunchOptions,
+    ) -> Result<(), OpenFileError> {
         let path = path.as_ref();
+        let copied = self.snap_accessible_path(path, &options)?;
+        let path = copied.as_deref().unwrap_or(path);
+        self.launch_command(path, &options)?
+            .run_and_detach()
+            .map_err(OpenFileError::LaunchFailed)
+    }
 
+    fn launch_command(
+        &self,
+        path: &Path,
+        options: &LaunchOptions,
+    ) -> Result<bossy::Command, OpenFileError> {
+        let command_parts = self.resolve_open_command(path)?;
+        // `resolve_open_command` never returns an empty command, so this works.
+        // If it has a single element, &command_parts[1..] should be an empty
+        // slice (&[]) and bossy `with_args` does not add any argument on that
+        // case, although the docs do not make it obvious.
+        let mut command = bossy::Command::impure(&command_parts[0]);
+        command
+            .add_args(&command_parts[1..])
+            .set_priority(options.nice, options.ionice)
+            .add_env_vars(&options.env);
+        if self.startup_notify {
+            let app_name = self
+                .startup_wm_class()
+                .or_else(|| self.name())
+                .map(ToOwned::to_owned)
+                .unwrap_or_else(|| command_parts[0].to_string_lossy().into_owned());
+            for (key, value) in startup_notify::launch_env(&app_name) {
+                log::info!("launching {:?} with {}={:?}", app_name, key, value);
+                command.add_env_var(key, value);
+            }
+        }
+        Ok(command)
+    }
+
+    /// The file for this app's icon at `size`, resolved through the user's
+    /// icon theme and the themes it inherits from, if there's an icon at all.
+    pub fn resolve_icon(&self, size: u32) -> Option<PathBuf> {
+        self.icon
+            .as_ref()
+            .and_then(|icon| icon::resolve_icon(&icon.to_string_lossy(), size))
+    }
+
+    /// The command that opening `path` would run, without running it.
+    pub fn resolve_open_command(
+        &self,
+        path: impl AsRef<Path>,
+    ) -> Result<Vec<OsString>, OpenFileError> {
+        let path = path.as_ref();
         let maybe_icon = self.icon.as_ref().map(|icon_str| icon_str.as_os_str());
 
+        // Windows programs launched from WSL need Windows paths, and can't be
+        // run by their Windows paths.
+        let windows = xdg::targets_windows(&self.exec_command) && wsl::is_wsl();
+        let argument = windows
+            .then(|| wsl::windows_path(path))
+            .flatten()
+            .unwrap_or_else(|| path.as_os_str().to_owned());
+
         // Parse the xdg command field with all the needed data
-        let command_parts = xdg::parse_command(
+        let mut command_parts = xdg::parse_command(
             &self.exec_command,
-            path.as_os_str(),
+            &argument,
             maybe_icon,
             Some(&self.xdg_entry_path),
         );
+        if windows && !command_parts.is_empty() && !command_parts[0].as_bytes().starts_with(b"/") {
+            if let Some(program) = wsl::wsl_path(&command_parts[0]) {
+                command_parts[0] = program;
+            }
+        }
 
-        if !command_parts.is_empty() {
-            // If command_parts has at least one element this works. If it has a single
-            // element, &command_parts[1..] should be an empty slice (&[]) and bossy
-            // `with_args` does not add any argument on that case, although the docs
-            // do not make it obvious.
-            bossy::Command::impure(&command_parts[0])
-                .with_args(&command_parts[1..])
-                .run_and_detach()
-                .map_err(OpenFileError::LaunchFailed)
-        } else {
+        if command_parts.is_empty() {
             Err(OpenFileError::CommandParsingFailed)
+        } else {
+            Ok(command_parts)
+        }
+    }
+
+    pub fn open_diff(
+        &self,
+        left: impl AsRef<Path>,
+        right: impl AsRef<Path>,
+    ) -> Result<(), OpenFileError> {
+        let (left, right) = (left.as_ref(), right.as_ref());
+        let diffed = self.program().and_then(|program| {
+            super::editor::identify(&program)
+                .and_then(|info| super::editor::open_diff(info, &program, left, right))
+        });
+        match diffed {
+            Some(result) => result.map_err(OpenFileError::LaunchFailed),
+            None => {
+                log::info!(
+                    "editor doesn't support diffs, so opening {:?} and {:?} separately",
+                    left,
+                    right
+                );
+                self.open_file(left)?;
+                self.open_file(right)
+            }
+        }
+    }
+
+    /// Opens `path` in `profile` and/or the most recent window (per
+    /// `options`), as far as the editor supports them; otherwise, this is the
+    /// same as [`Self::open_file_with_options`].
+    pub fn open_file_in_profile(
+        &self,
+        path: impl AsRef<Path>,
+        profile: Option<&str>,
+        options: LaunchOptions,
+    ) -> Result<(), OpenFileError> {
+        let path = path.as_ref();
+        if profile.is_none() && !options.reuse_window {
+            return self.open_file_with_options(path, options);
+        }
+        let opened = self.program().and_then(|program| {
+            super::editor::identify(&program).and_then(|info| {
+                super::editor::open_with_flags(
+                    info,
+                    &program,
+                    path,
+                    profile,
+                    options.reuse_window,
+                    &options.env,
+                )
+            })
+        });
+        match opened {
+            Some(result) => result.map_err(OpenFileError::LaunchFailed),
+            None => {
+                log::debug!(
+                    "editor doesn't support profiles or reusing windows, so opening {:?} normally",
+                    path
+                );
+                self.open_file_with_options(path, options)
+            }
+        }
+    }
+
+    pub fn editor_info(&self) -> Option<&'static super::editor::EditorInfo> {
+        self.program().and_then(super::editor::identify)
+    }
+
+    fn program(&self) -> Option<OsString> {
+        xdg::parse_command(&self.exec_command, "".as_ref(), None, None)
+            .into_iter()
+            .next()
+    }
+
+    /// The program names in the exec command, without their paths.
+    fn exec_names(&self) -> Vec<String> {
+        xdg::parse_command(&self.exec_command, "".as_ref(), None, None)
+            .iter()
+            .map(|arg| {
+                Path::new(arg)
+                    .file_name()
+                    .unwrap_or(arg.as_os_str())
+                    .to_string_lossy()
+                    .into_owned()
+            })
+            .collect()
+    }
+
+    fn is_generic_opener(&self) -> bool {
+        let args = self.exec_names();
+        let mut args = args.iter().map(String::as_str);
+        match args.next() {
+            Some("gio") => args.next() == Some("open"),
+            Some(program) => GENERIC_OPENERS.contains(&program),
+            None => false,
         }
     }
+
+    fn is_pager(&self, allowed: &[String]) -> bool {
+        self.exec_names()
+            .first()
+            .map(|program| super::editor::is_pager(program, allowed))
+            .unwrap_or(false)
+    }
 }
 
-pub fn open_file_with(
+pub fn launch_file_with(
     application: impl AsRef<OsStr>,
     path: impl AsRef<OsStr>,
 ) -> Result<(), OpenFileError> {
@@ -157,6 +617,58 @@ pub fn open_file_with(
         .map_err(OpenFileError::LaunchFailed)
 }
 
+/// A `file://` URI for `path`, made absolute, with everything but unreserved
+/// characters and separators percent-encoded. That includes commas, which
+/// `dbus-send` would otherwise split `array:` arguments on.
+fn file_uri(path: &Path) -> String {
+    let path = std::path::absolute(path).unwrap_or_else(|_| path.to_owned());
+    let mut uri = "file://".to_owned();
+    for byte in path.as_os_str().as_bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                uri.push(*byte as char)
+            }
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
+
+fn show_items_arg(path: &Path) -> String {
+    format!("array:string:{}", file_uri(path))
+}
+
+pub fn launch_file_manager(path: impl AsRef<Path>) -> Result<(), OpenFileError> {
+    let path = path.as_ref();
+    // File managers implementing the FileManager1 interface (Nautilus,
+    // Dolphin, Nemo, etc.) can highlight the file itself, so we try that first.
+    let shown = bossy::Command::impure("dbus-send")
+        .with_args([
+            "--session",
+            "--print-reply",
+            "--dest=org.freedesktop.FileManager1",
+            "/org/freedesktop/FileManager1",
+            "org.freedesktop.FileManager1.ShowItems",
+        ])
+        .with_arg(show_items_arg(path))
+        .with_arg("string:")
+        .run_and_wait_for_output()
+        .is_ok();
+    if shown {
+        Ok(())
+    } else {
+        let dir = if path.is_dir() {
+            path
+        } else {
+            path.parent().unwrap_or(path)
+        };
+        bossy::Command::impure("xdg-open")
+            .with_arg(dir)
+            .run_and_detach()
+            .map_err(OpenFileError::LaunchFailed)
+    }
+}
+
 // We use "sh" in order to access "command -v", as that is a bultin command on sh.
 // Linux does not require a binary "command" in path, so this seems the way to go.
 #[cfg(target_os = "linux")]
@@ -184,7 +696,166 @@ pub fn replace_path_separator(path: OsString) -> OsString {
 pub mod consts {
     pub const CLANG: &str = "clang";
     pub const CLANGXX: &str = "clang++";
+    pub const CLANG_BIN: &str = "clang";
     pub const LD: &str = "ld";
     pub const READELF: &str = "readelf";
     pub const NDK_STACK: &str = "ndk-stack";
+    pub const AVDMANAGER: &str = "avdmanager";
+    pub const SDKMANAGER: &str = "sdkmanager";
+    pub const AAPT2: &str = "aapt2";
+    pub const APKSIGNER: &str = "apksigner";
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    fn app(exec: &str) -> Application {
+        Application {
+            exec_command: exec.into(),
+            icon: None,
+            name: None,
+            generic_name: None,
+            comment: None,
+            startup_wm_class: None,
+            startup_notify: false,
+            xdg_entry_path: PathBuf::from("/usr/share/applications/test.desktop"),
+        }
+    }
+
+    #[rstest(
+        exec,
+        expected,
+        case("xdg-open %f", true),
+        case("/usr/bin/xdg-open %U", true),
+        case("gio open %f", true),
+        case("kde-open5 %u", true),
+        case("open %f", true),
+        case("code --reuse-window %F", false),
+        case("gio-editor %f", false),
+        case("/usr/bin/gedit %U", false)
+    )]
+    fn detects_generic_openers(exec: &str, expected: bool) {
+        assert_eq!(app(exec).is_generic_opener(), expected);
+    }
+
+    #[test]
+    fn launches_with_configured_env() {
+        let options = LaunchOptions {
+            env: vec![("CARGO_MOBILE_TEST_EDITOR_ENV".to_owned(), "auto".to_owned())]
+                .into_iter()
+                .collect(),
+            ..Default::default()
+        };
+        // `printenv` fails if any of the vars are missing, so this checks that
+        // the inherited env is still there too. The file ends up as `$0`.
+        let output = app(r#"sh -c "printenv CARGO_MOBILE_TEST_EDITOR_ENV PATH" %f"#)
+            .launch_command(Path::new("main.rs"), &options)
+            .unwrap()
+            .run_and_wait_for_string()
+            .unwrap();
+        let mut lines = output.lines();
+        assert_eq!(lines.next(), Some("auto"));
+        assert_eq!(lines.next().map(OsString::from), std::env::var_os("PATH"));
+    }
+
+    #[rstest(
+        exec,
+        expected,
+        case("less %f", true),
+        case("/usr/bin/bat --paging=always %f", true),
+        case("vim %F", false)
+    )]
+    fn detects_pagers(exec: &str, expected: bool) {
+        assert_eq!(app(exec).is_pager(&[]), expected);
+    }
+
+    #[test]
+    fn skips_non_editors() {
+        let picked = Application::skip_non_editors(
+            vec![
+                Ok(app("xdg-open %f")),
+                Ok(app("less %f")),
+                Ok(app("code --reuse-window %F")),
+            ]
+            .into_iter(),
+            &[],
+        );
+        assert_eq!(
+            picked.unwrap().unwrap().exec_command,
+            OsString::from("code --reuse-window %F")
+        );
+    }
+
+    #[test]
+    fn picks_allowed_pagers() {
+        let picked = Application::skip_non_editors(
+            vec![Ok(app("less %f")), Ok(app("code --reuse-window %F"))].into_iter(),
+            &["less".to_owned()],
+        );
+        assert_eq!(
+            picked.unwrap().unwrap().exec_command,
+            OsString::from("less %f")
+        );
+    }
+
+    fn snap(name: &str) -> InstallKind {
+        InstallKind::Snap {
+            name: name.to_owned(),
+        }
+    }
+
+    fn flatpak(app_id: &str) -> InstallKind {
+        InstallKind::Flatpak {
+            app_id: app_id.to_owned(),
+        }
+    }
+
+    #[rstest(
+        command,
+        entry,
+        expected,
+        case(&["/usr/bin/code", "%F"], "/usr/share/applications/code.desktop", InstallKind::Native),
+        case(&["/snap/bin/code", "%F"], "/var/lib/snapd/desktop/applications/code_code.desktop", snap("code")),
+        case(&["/snap/bin/android-studio.studio"], "/usr/share/applications/studio.desktop", snap("android-studio")),
+        case(&["snap", "run", "--experimental-gdb", "code.url-handler", "%U"], "/usr/share/applications/code.desktop", snap("code")),
+        case(&["snap", "list"], "/usr/share/applications/snap.desktop", InstallKind::Native),
+        case(&["env", "BAMF_DESKTOP_FILE_HINT=/x.desktop", "code"], "/var/lib/snapd/desktop/applications/code_code.desktop", snap("code")),
+        case(
+            &["/usr/bin/flatpak", "run", "--branch=stable", "--arch=x86_64", "--command=code", "com.visualstudio.code", "--reuse-window", "@@", "%F", "@@"],
+            "/var/lib/flatpak/exports/share/applications/com.visualstudio.code.desktop",
+            flatpak("com.visualstudio.code")
+        ),
+        case(&["flatpak", "run"], "/usr/share/applications/flatpak.desktop", InstallKind::Native),
+        case(&["/home/me/Apps/Zed-x86_64.AppImage", "%U"], "/home/me/.local/share/applications/zed.desktop", InstallKind::AppImage),
+        case(&[], "/usr/share/applications/empty.desktop", InstallKind::Native)
+    )]
+    fn detects_install_kinds(command: &[&str], entry: &str, expected: InstallKind) {
+        let command = command.iter().map(OsString::from).collect::<Vec<_>>();
+        assert_eq!(InstallKind::detect(&command, Path::new(entry)), expected);
+    }
+
+    #[rstest(
+        path,
+        expected,
+        case("/home/me/app/lib.so", "array:string:file:///home/me/app/lib.so"),
+        case(
+            "/home/me/my app,v2/lib.so",
+            "array:string:file:///home/me/my%20app%2Cv2/lib.so"
+        ),
+        case("/tmp/caf\u{e9}#1", "array:string:file:///tmp/caf%C3%A9%231")
+    )]
+    fn builds_show_items_arg(path: &str, expected: &str) {
+        assert_eq!(show_items_arg(Path::new(path)), expected);
+    }
+
+    #[test]
+    fn show_items_arg_is_absolute() {
+        let cwd = std::env::current_dir().unwrap();
+        assert_eq!(
+            show_items_arg(Path::new("lib.so")),
+            show_items_arg(&cwd.join("lib.so"))
+        );
+    }
 }
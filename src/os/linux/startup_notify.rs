@@ -0,0 +1,65 @@
+// Startup notification lets the launched app's first window take focus, and
+// lets the desktop clear its busy cursor once the app is up:
+// https://specifications.freedesktop.org/startup-notification-spec/startup-notification-latest.txt
+
+use std::{
+    env, fs, process,
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+static SEQUENCE: AtomicU32 = AtomicU32::new(0);
+
+fn hostname() -> String {
+    env::var("HOSTNAME")
+        .ok()
+        .or_else(|| fs::read_to_string("/proc/sys/kernel/hostname").ok())
+        .map(|host| host.trim().to_owned())
+        .filter(|host| !host.is_empty())
+        .unwrap_or_else(|| "localhost".to_owned())
+}
+
+fn sanitize(part: &str) -> String {
+    part.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+// The spec only requires ids to be unique, but this is the conventional shape.
+// We leave off the `_TIME` suffix, since we don't know the X server time of
+// whatever triggered the launch, and a made up one is worse than none.
+fn startup_id(app_name: &str) -> String {
+    format!(
+        "{}-{}-{}-{}-{}",
+        crate::NAME,
+        process::id(),
+        sanitize(&hostname()),
+        sanitize(app_name),
+        SEQUENCE.fetch_add(1, Ordering::Relaxed),
+    )
+}
+
+/// The env vars to launch an app with so it can complete startup
+/// notification. This is empty if there's no graphical session to notify.
+pub fn launch_env(app_name: &str) -> Vec<(&'static str, String)> {
+    // On Wayland, tokens have to come from the compositor, which we don't
+    // talk to, so the best we can do is pass along the one we were given.
+    if let Some(token) = env::var("XDG_ACTIVATION_TOKEN")
+        .ok()
+        .filter(|token| !token.is_empty())
+    {
+        return vec![
+            ("XDG_ACTIVATION_TOKEN", token.clone()),
+            ("DESKTOP_STARTUP_ID", token),
+        ];
+    }
+    if env::var_os("DISPLAY").is_none_or(|display| display.is_empty()) {
+        return Vec::new();
+    }
+    vec![("DESKTOP_STARTUP_ID", startup_id(app_name))]
+}
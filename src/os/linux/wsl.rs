@@ -0,0 +1,35 @@
+use crate::bossy;
+use std::{
+    env,
+    ffi::{OsStr, OsString},
+    fs,
+};
+
+/// Whether we're running under the Windows Subsystem for Linux.
+pub fn is_wsl() -> bool {
+    env::var_os("WSL_DISTRO_NAME").is_some()
+        || fs::read_to_string("/proc/sys/kernel/osrelease")
+            .map(|release| release.to_ascii_lowercase().contains("microsoft"))
+            .unwrap_or_default()
+}
+
+fn wslpath(flag: &str, path: &OsStr) -> Option<OsString> {
+    bossy::Command::impure("wslpath")
+        .with_arg(flag)
+        .with_arg(path)
+        .run_and_wait_for_str(|out| OsString::from(out.trim_end_matches('\n')))
+        .map_err(|err| log::warn!("failed to translate {:?} with `wslpath`: {}", path, err))
+        .ok()
+        .filter(|translated| !translated.is_empty())
+}
+
+/// Translates a WSL path to one Windows programs can open, i.e.
+/// `\\wsl.localhost\Ubuntu\home\...`.
+pub fn windows_path(path: impl AsRef<OsStr>) -> Option<OsString> {
+    wslpath("-w", path.as_ref())
+}
+
+/// Translates a Windows path (i.e. `C:\...`) to one we can run from WSL.
+pub fn wsl_path(path: impl AsRef<OsStr>) -> Option<OsString> {
+    wslpath("-u", path.as_ref())
+}
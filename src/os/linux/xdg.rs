@@ -25,6 +25,60 @@ pub fn query_mime_entry(mime_type: &str) -> Option<PathBuf> {
         .ok()?
 }
 
+/// The MIME types an editor can be associated with, from most to least
+/// specific. This is a total order over handlers too: when merging handlers
+/// from several types, each handler is ranked by the most specific type it
+/// was listed for, and then by its position within that type's list.
+pub const EDITOR_MIME_TYPES: &[&str] = &["text/rust", "text/x-rust", "text/plain"];
+
+fn mime_priority(mime_type: &str) -> usize {
+    EDITOR_MIME_TYPES
+        .iter()
+        .position(|known| *known == mime_type)
+        .unwrap_or(EDITOR_MIME_TYPES.len())
+}
+
+fn parse_handler_list(list: &str) -> impl Iterator<Item = PathBuf> + '_ {
+    list.split(';')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(PathBuf::from)
+}
+
+// The entries `update-desktop-database` recorded as handling `mime_type` in
+// each data dir's `mimeinfo.cache`, which doesn't include the default unless
+// it happens to be listed there too.
+pub fn cached_handlers(mime_type: &str) -> Vec<PathBuf> {
+    let mut handlers = Vec::new();
+    for dir in get_xdg_data_dirs() {
+        let cache = dir.join("applications/mimeinfo.cache");
+        if let Ok(parsed) = parse(&cache) {
+            if let Some(list) = parsed.section("MIME Cache").attr(mime_type) {
+                handlers.extend(parse_handler_list(list));
+            }
+        }
+    }
+    handlers
+}
+
+/// Merges handlers listed for several MIME types into one list, ordered as
+/// described on [`EDITOR_MIME_TYPES`]. Types we don't know go last, in the
+/// order given. Duplicates only keep their highest-priority occurrence.
+pub fn merge_handlers<'a>(
+    handlers: impl IntoIterator<Item = (&'a str, Vec<PathBuf>)>,
+) -> Vec<PathBuf> {
+    let mut handlers = handlers.into_iter().collect::<Vec<_>>();
+    // This sort is stable, so unknown types keep their relative order.
+    handlers.sort_by_key(|(mime_type, _)| mime_priority(mime_type));
+    let mut merged = Vec::new();
+    for handler in handlers.into_iter().flat_map(|(_, handlers)| handlers) {
+        if !merged.contains(&handler) {
+            merged.push(handler);
+        }
+    }
+    merged
+}
+
 // Returns the first entry on that directory whose filename is equal to target.
 //
 // This spec is what makes me believe the search is recursive:
@@ -118,12 +172,31 @@ fn replace_on_pattern(
     OsString::from_vec(result_text)
 }
 
+/// Whether `command` runs a Windows program, i.e. `C:\...\Code.exe` or
+/// `/mnt/c/.../Code.exe` from under WSL. Entries like this are written by hand
+/// (or by tools that don't know the XDG spec), so backslashes in them are path
+/// separators rather than escapes.
+pub fn targets_windows(command: &OsStr) -> bool {
+    byte_regex!(r#"(?i)^\s*["']?(?:[a-z]:\\|[a-z]:/|/mnt/[a-z]/[^"']*\.exe)"#)
+        .is_match(command.as_bytes())
+}
+
+/// Whether `command` has a `%f`/`%F`/`%u`/`%U` for the file to be substituted
+/// into. This matches what `parse_unquoted_text` substitutes.
+fn has_file_field_code(command: &OsStr) -> bool {
+    byte_regex!(r"%u|%U|%f|%F").is_match(command.as_bytes())
+}
+
 fn parse_quoted_text(
     text: &OsStr,
     argument: &OsStr,
     icon: Option<&OsStr>,
     desktop_entry_path: Option<&Path>,
+    windows: bool,
 ) -> OsString {
+    if windows {
+        return parse_unquoted_text(text, argument, icon, desktop_entry_path);
+    }
     // We parse the escape character (\) again on the quoted text
     let mut result = Vec::new();
     let mut escaping = false;
@@ -195,6 +268,7 @@ pub fn parse_command(
     );
 
     // let command_name_re = byte_regex!(r#"^[^ \t"]+|"[^ \t]+""#);
+    let windows = targets_windows(command);
     let mut escape_char = false;
     let mut reading_quoted = false;
     let mut reading_singlequoted = false;
@@ -216,7 +290,7 @@ pub fn parse_command(
             text_atom.push(c);
             escape_char = false;
         // Otherwise, we have to pay special attention to backslash
-        } else if c == b'\\' {
+        } else if c == b'\\' && !windows {
             // If we see a backslash and are not escaping anything we will not "read" the
             // backslash, and instead escape the next char.
             escape_char = true;
@@ -233,6 +307,7 @@ pub fn parse_command(
                         argument,
                         icon,
                         desktop_entry_path,
+                        windows,
                     );
                     parsed_command_parts.push(text_atom_string);
                     text_atom.clear();
@@ -254,6 +329,7 @@ pub fn parse_command(
                         argument,
                         icon,
                         desktop_entry_path,
+                        windows,
                     );
                     parsed_command_parts.push(text_atom_string);
                     text_atom.clear();
@@ -298,6 +374,12 @@ pub fn parse_command(
         text_atom.clear();
     }
 
+    // Launchers pass the file as a trailing arg when the entry doesn't say
+    // where to put it, so we do too, since otherwise it'd never get opened.
+    if !argument.is_empty() && !has_file_field_code(command) {
+        parsed_command_parts.push(argument.to_owned());
+    }
+
     log::debug!(
         "XDG parsed command {:?} to {:?}",
         command,
@@ -337,6 +419,24 @@ pub fn get_xdg_data_dirs() -> Vec<PathBuf> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn merge_handlers_prefers_specific_types() {
+        let paths = |names: &[&str]| names.iter().map(PathBuf::from).collect::<Vec<_>>();
+        assert_eq!(
+            merge_handlers(vec![
+                ("text/plain", paths(&["gedit.desktop", "code.desktop"])),
+                ("text/x-rust", paths(&["kate.desktop", "code.desktop"])),
+                ("text/rust", paths(&["code.desktop", "nvim.desktop"])),
+            ]),
+            paths(&[
+                "code.desktop",
+                "nvim.desktop",
+                "kate.desktop",
+                "gedit.desktop"
+            ])
+        );
+    }
+
     #[test]
     fn parse_command_simple() {
         assert_eq!(
@@ -350,6 +450,14 @@ mod tests {
         );
     }
 
+    #[test]
+    fn parse_command_without_field_code() {
+        assert_eq!(
+            parse_command("gedit".as_ref(), "/path".as_ref(), None, None),
+            ["gedit", "/path"]
+        );
+    }
+
     #[test]
     fn parse_command_simple_quote_test() {
         assert_eq!(
@@ -399,4 +507,47 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn parse_command_windows_paths() {
+        assert_eq!(
+            parse_command(
+                r#""C:\Program Files\Microsoft VS Code\Code.exe" --reuse-window %f"#.as_ref(),
+                r"\\wsl.localhost\Ubuntu\home\me\project".as_ref(),
+                None,
+                None,
+            ),
+            [
+                r"C:\Program Files\Microsoft VS Code\Code.exe",
+                "--reuse-window",
+                r"\\wsl.localhost\Ubuntu\home\me\project",
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_command_windows_program_from_wsl() {
+        assert_eq!(
+            parse_command(
+                r#"/mnt/c/Windows/notepad.exe C:\Users\me\notes.txt %f"#.as_ref(),
+                r"D:\src\app".as_ref(),
+                None,
+                None,
+            ),
+            [
+                "/mnt/c/Windows/notepad.exe",
+                r"C:\Users\me\notes.txt",
+                r"D:\src\app",
+            ]
+        );
+    }
+
+    #[test]
+    fn targets_windows_only_for_windows_programs() {
+        assert!(targets_windows(r"C:\Tools\editor.exe %f".as_ref()));
+        assert!(targets_windows(r#""c:/Tools/editor.exe" %f"#.as_ref()));
+        assert!(targets_windows("/mnt/c/Tools/Editor.EXE %f".as_ref()));
+        assert!(!targets_windows("/usr/bin/code %F".as_ref()));
+        assert!(!targets_windows("/mnt/data/bin/editor %f".as_ref()));
+    }
 }
@@ -1,6 +1,7 @@
 mod ffi;
 pub(super) mod info;
 
+use super::LaunchOptions;
 use crate::bossy;
 use core_foundation::{
     array::CFArray,
@@ -27,6 +28,14 @@ pub enum DetectEditorError {
     LookupFailed(CFError),
 }
 
+impl DetectEditorError {
+    /// Whether this just means the user hasn't picked an editor. Launch
+    /// Services always has some app to fall back to, so this never does.
+    pub fn no_default_editor(&self) -> bool {
+        false
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum OpenFileError {
     #[error("Failed to convert path {path} into a `CFURL`.")]
@@ -35,6 +44,8 @@ pub enum OpenFileError {
     LaunchFailed(OSStatus),
     #[error("Launch failed: {0}")]
     BossyLaunchFailed(bossy::Error),
+    #[error("App {url} wasn't found")]
+    AppNotFound { url: String },
 }
 
 #[derive(Debug)]
@@ -43,7 +54,9 @@ pub struct Application {
 }
 
 impl Application {
-    pub fn detect_editor() -> Result<Self, DetectEditorError> {
+    // Only Linux can end up with a pager as its default editor, so there's
+    // nothing to allow here.
+    pub fn detect_editor(_allowed_pagers: &[String]) -> Result<Self, DetectEditorError> {
         unsafe fn inner(uti: CFStringRef) -> Result<CFURL, CFError> {
             let mut err: CFErrorRef = ptr::null_mut();
             let out_url =
@@ -60,8 +73,64 @@ impl Application {
         Ok(Self { url })
     }
 
+    pub fn name(&self) -> Option<String> {
+        self.url.to_path().and_then(|path| {
+            path.file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+        })
+    }
+
+    // Launch Services doesn't give us a description, so these are only here
+    // to keep parity with the other platforms.
+    pub fn generic_name(&self) -> Option<&str> {
+        None
+    }
+
+    pub fn comment(&self) -> Option<&str> {
+        None
+    }
+
+    pub fn location(&self) -> Option<PathBuf> {
+        self.url.to_path()
+    }
+
+    pub fn exec_string(&self) -> String {
+        self.url.get_string().to_string()
+    }
+
+    /// Checks that the app bundle Launch Services gave us still exists,
+    /// without launching it.
+    pub fn validate(&self) -> Result<(), OpenFileError> {
+        if self.url.to_path().is_some_and(|path| path.exists()) {
+            Ok(())
+        } else {
+            Err(OpenFileError::AppNotFound {
+                url: self.exec_string(),
+            })
+        }
+    }
+
     pub fn open_file(&self, path: impl AsRef<Path>) -> Result<(), OpenFileError> {
+        self.open_file_with_options(path, LaunchOptions::default())
+    }
+
+    pub fn open_file_with_options(
+        &self,
+        path: impl AsRef<Path>,
+        options: LaunchOptions,
+    ) -> Result<(), OpenFileError> {
         let path = path.as_ref();
+        if options.nice.is_some() || options.ionice.is_some() || options.copy_from_hidden_dirs {
+            // Launch Services spawns the app from `launchd`, so there's no
+            // process for us to hook into.
+            log::warn!("launch priority options aren't supported on macOS; ignoring them");
+        }
+        if !options.env.is_empty() {
+            log::warn!(
+                "Launch Services can't pass env vars to apps, so ignoring {:?}",
+                options.env.keys().collect::<Vec<_>>()
+            );
+        }
         let item_url = CFURL::from_path(path, path.is_dir()).ok_or_else(|| {
             OpenFileError::PathToUrlFailed {
                 path: path.to_owned(),
@@ -80,9 +149,98 @@ impl Application {
             Err(OpenFileError::LaunchFailed(status))
         }
     }
+
+    /// The `open` command that would open `path` in this app, without running
+    /// it. We launch through Launch Services directly, but this does the same.
+    pub fn resolve_open_command(
+        &self,
+        path: impl AsRef<Path>,
+    ) -> Result<Vec<OsString>, OpenFileError> {
+        let app = self
+            .url
+            .to_path()
+            .ok_or_else(|| OpenFileError::AppNotFound {
+                url: self.exec_string(),
+            })?;
+        Ok(vec![
+            "open".into(),
+            "-a".into(),
+            app.into_os_string(),
+            path.as_ref().as_os_str().to_owned(),
+        ])
+    }
+
+    pub fn open_diff(
+        &self,
+        left: impl AsRef<Path>,
+        right: impl AsRef<Path>,
+    ) -> Result<(), OpenFileError> {
+        let (left, right) = (left.as_ref(), right.as_ref());
+        // We can't pass arguments through Launch Services, so we need the
+        // editor's command line tool to be installed.
+        let diffed = self
+            .editor_info()
+            .filter(|info| crate::util::command_present(info.command).unwrap_or_default())
+            .and_then(|info| super::editor::open_diff(info, info.command, left, right));
+        match diffed {
+            Some(result) => result.map_err(OpenFileError::BossyLaunchFailed),
+            None => {
+                log::info!(
+                    "editor doesn't support diffs, so opening {:?} and {:?} separately",
+                    left,
+                    right
+                );
+                self.open_file(left)?;
+                self.open_file(right)
+            }
+        }
+    }
+
+    /// Opens `path` in `profile` and/or the most recent window (per
+    /// `options`), as far as the editor supports them; otherwise, this is the
+    /// same as [`Self::open_file_with_options`].
+    pub fn open_file_in_profile(
+        &self,
+        path: impl AsRef<Path>,
+        profile: Option<&str>,
+        options: LaunchOptions,
+    ) -> Result<(), OpenFileError> {
+        let path = path.as_ref();
+        if profile.is_none() && !options.reuse_window {
+            return self.open_file_with_options(path, options);
+        }
+        // Like with diffs, this needs the editor's command line tool.
+        let opened = self
+            .editor_info()
+            .filter(|info| crate::util::command_present(info.command).unwrap_or_default())
+            .and_then(|info| {
+                super::editor::open_with_flags(
+                    info,
+                    info.command,
+                    path,
+                    profile,
+                    options.reuse_window,
+                    &options.env,
+                )
+            });
+        match opened {
+            Some(result) => result.map_err(OpenFileError::BossyLaunchFailed),
+            None => {
+                log::debug!(
+                    "editor doesn't support profiles or reusing windows, so opening {:?} normally",
+                    path
+                );
+                self.open_file_with_options(path, options)
+            }
+        }
+    }
+
+    pub fn editor_info(&self) -> Option<&'static super::editor::EditorInfo> {
+        self.url.to_path().and_then(super::editor::identify)
+    }
 }
 
-pub fn open_file_with(
+pub fn launch_file_with(
     application: impl AsRef<OsStr>,
     path: impl AsRef<OsStr>,
 ) -> Result<(), OpenFileError> {
@@ -94,6 +252,15 @@ pub fn open_file_with(
     Ok(())
 }
 
+pub fn launch_file_manager(path: impl AsRef<Path>) -> Result<(), OpenFileError> {
+    bossy::Command::impure("open")
+        .with_arg("-R")
+        .with_arg(path.as_ref())
+        .run_and_wait()
+        .map_err(OpenFileError::BossyLaunchFailed)?;
+    Ok(())
+}
+
 #[cfg(target_os = "macos")]
 pub fn command_path(name: &str) -> bossy::Result<bossy::Output> {
     bossy::Command::impure("command")
@@ -119,7 +286,12 @@ pub fn replace_path_separator(path: OsString) -> OsString {
 pub mod consts {
     pub const CLANG: &str = "clang";
     pub const CLANGXX: &str = "clang++";
+    pub const CLANG_BIN: &str = "clang";
     pub const LD: &str = "ld";
     pub const READELF: &str = "readelf";
     pub const NDK_STACK: &str = "ndk-stack";
+    pub const AVDMANAGER: &str = "avdmanager";
+    pub const SDKMANAGER: &str = "sdkmanager";
+    pub const AAPT2: &str = "aapt2";
+    pub const APKSIGNER: &str = "apksigner";
 }
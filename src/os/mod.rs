@@ -1,5 +1,36 @@
 #![allow(unsafe_code)]
 
+mod cargo;
+mod disk;
+pub mod editor;
+mod interactive;
+mod open;
+mod python;
+mod remote;
+mod security;
+mod shell;
+mod terminal;
+mod user;
+
+use std::collections::BTreeMap;
+
+pub use self::{
+    cargo::{cargo_version, CargoVersionError},
+    disk::{available_disk_space, check_writable, filesystem_kind, is_writable, FsKind},
+    interactive::{can_prompt, INTERACTIVE_VAR},
+    open::{
+        disable_opening, open_command_line, open_file_with, opening_disabled,
+        reveal_in_file_manager, try_open_diff, try_open_files, try_open_project,
+        try_open_project_with_notes, OpenOutcome, NO_OPEN_VAR,
+    },
+    python::{python, PythonError},
+    remote::{RemoteEditor, REMOTE_EDITOR_VAR, REMOTE_HOST_VAR},
+    security::{denied_by, launch_failure_hint, security_module, SecurityModule},
+    shell::{detect_shell, posix_command_line, posix_quote, Shell},
+    terminal::{default_terminal, open_terminal_at, OpenTerminalError, Terminal},
+    user::user_home_dir,
+};
+
 #[cfg(target_os = "macos")]
 mod macos;
 
@@ -21,6 +52,45 @@ pub use self::windows::*;
 #[cfg(not(any(target_os = "macos", target_os = "linux", windows)))]
 compile_error!("Host platform not yet supported by cargo-mobile! We'd love if you made a PR to add support for this platform ❤️");
 
+/// Extra knobs for launching applications. The defaults leave the launched
+/// process alone.
+#[derive(Clone, Debug, Default)]
+pub struct LaunchOptions {
+    /// Niceness to launch with, where higher values mean lower priority. This
+    /// is handy on shared build machines, where a heavy IDE shouldn't starve
+    /// the build.
+    pub nice: Option<i32>,
+    /// Best-effort IO priority from 0 (highest) to 7 (lowest). Only supported
+    /// on Linux.
+    pub ionice: Option<u8>,
+    /// Snap apps can't read hidden directories under `$HOME`, so by default
+    /// we just warn when that's going to be a problem. Setting this copies
+    /// the file somewhere the snap can read it and opens the copy instead.
+    /// Only relevant on Linux.
+    pub copy_from_hidden_dirs: bool,
+    /// Env vars to set for the launched process, on top of the ones it
+    /// inherits from us. Launch Services can't pass these along, so on macOS,
+    /// they only apply when we launch the editor's command line tool.
+    pub env: BTreeMap<String, String>,
+    /// Open files in the editor's most recent window instead of a new one,
+    /// for editors that can.
+    pub reuse_window: bool,
+    /// Pagers that are fine to pick when detecting the editor, from
+    /// `editor.allowed-pagers`. Only relevant on Linux.
+    pub allowed_pagers: Vec<String>,
+}
+
+impl LaunchOptions {
+    pub fn is_default(&self) -> bool {
+        self.nice.is_none()
+            && self.ionice.is_none()
+            && !self.copy_from_hidden_dirs
+            && self.env.is_empty()
+            && !self.reuse_window
+            && self.allowed_pagers.is_empty()
+    }
+}
+
 // TODO: we should probably expose common functionality throughout `os` in a
 // less ad-hoc way... since it's really easy to accidentally break things.
 #[derive(Debug)]
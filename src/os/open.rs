@@ -0,0 +1,246 @@
+use super::{
+    interactive, launch_file_manager, launch_file_with, shell, Application, LaunchOptions,
+    OpenFileError, RemoteEditor,
+};
+use crate::util::OpenInEditorError;
+use std::{
+    env,
+    ffi::OsStr,
+    fs,
+    path::Path,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+/// Set to `1`/`true` to never open editors or other tools, the same as
+/// passing `--no-open`. Meant for CI and scripts, where config that asks for
+/// things to be opened shouldn't apply.
+pub static NO_OPEN_VAR: &str = "CARGO_MOBILE_NO_OPEN";
+
+static NO_OPEN: AtomicBool = AtomicBool::new(false);
+
+/// Stops anything from being opened for the rest of the process. This is
+/// what `--no-open` does.
+pub fn disable_opening() {
+    NO_OPEN.store(true, Ordering::Relaxed);
+}
+
+/// Whether opening editors and tools was disabled by `--no-open` or
+/// [`NO_OPEN_VAR`], which take precedence over any config or `--open`.
+pub fn opening_disabled() -> bool {
+    NO_OPEN.load(Ordering::Relaxed) || interactive::flag(NO_OPEN_VAR).unwrap_or(false)
+}
+
+/// Opens `path` with `application`, unless [`opening_disabled`]. Returns
+/// whether it was opened.
+pub fn open_file_with(
+    application: impl AsRef<OsStr>,
+    path: impl AsRef<OsStr>,
+) -> Result<bool, OpenFileError> {
+    if opening_disabled() {
+        log::info!(
+            "not opening {:?} with {:?}, since opening is disabled",
+            path.as_ref(),
+            application.as_ref()
+        );
+        return Ok(false);
+    }
+    launch_file_with(application, path).map(|()| true)
+}
+
+/// Shows `path` in the file manager, unless [`opening_disabled`]. Returns
+/// whether it was shown.
+pub fn reveal_in_file_manager(path: impl AsRef<Path>) -> Result<bool, OpenFileError> {
+    if opening_disabled() {
+        log::info!(
+            "not revealing {:?}, since opening is disabled",
+            path.as_ref()
+        );
+        return Ok(false);
+    }
+    launch_file_manager(path).map(|()| true)
+}
+
+/// How an attempt to open a project went. Not having an editor to open is
+/// common enough (CI, containers, SSH) that it isn't treated as a failure.
+#[derive(Debug)]
+pub enum OpenOutcome {
+    /// Opened in the editor with this name.
+    Opened(String),
+    /// There was nothing to open it with, for this reason.
+    Skipped(String),
+    Failed(OpenInEditorError),
+}
+
+impl OpenOutcome {
+    pub fn into_result(self) -> Result<Option<String>, OpenInEditorError> {
+        match self {
+            Self::Opened(name) => Ok(Some(name)),
+            Self::Skipped(_) => Ok(None),
+            Self::Failed(err) => Err(err),
+        }
+    }
+}
+
+fn headless() -> Option<&'static str> {
+    if env::var_os("SSH_CONNECTION").is_some() {
+        return Some("connected over SSH, and no remote editor is configured");
+    }
+    if cfg!(target_os = "linux")
+        && env::var_os("DISPLAY").is_none_or(|display| display.is_empty())
+        && env::var_os("WAYLAND_DISPLAY").is_none_or(|display| display.is_empty())
+    {
+        return Some("there's no graphical session");
+    }
+    None
+}
+
+/// Opens `dir` in the user's editor if they have one, the same way as
+/// [`crate::util::open_in_editor`], but with a tri-state result so callers
+/// don't have to sort "no editor" out from real failures. `profile` is only
+/// used by editors that have profiles, and neither it nor `options` is passed
+/// to remote editors.
+pub fn try_open_project(
+    dir: impl AsRef<Path>,
+    profile: Option<&str>,
+    options: &LaunchOptions,
+) -> OpenOutcome {
+    try_open_files(&[dir.as_ref()], profile, options)
+}
+
+/// Like [`try_open_project`], but also opens `notes` in the same window,
+/// creating it empty if it doesn't exist yet. Editors that can't take more
+/// than one file at a time just get the project.
+pub fn try_open_project_with_notes(
+    dir: impl AsRef<Path>,
+    notes: Option<&Path>,
+    profile: Option<&str>,
+    options: &LaunchOptions,
+) -> OpenOutcome {
+    open_paths(&[dir.as_ref()], notes, profile, options)
+}
+
+fn create_notes(notes: &Path) -> bool {
+    match fs::OpenOptions::new().append(true).create(true).open(notes) {
+        Ok(_) => true,
+        Err(err) => {
+            log::warn!(
+                "not opening notes, since creating {:?} failed: {}",
+                notes,
+                err
+            );
+            false
+        }
+    }
+}
+
+/// Like [`try_open_project`], but for any number of files, which are opened
+/// one after another in the same editor. Stops at the first failure.
+pub fn try_open_files(
+    paths: &[impl AsRef<Path>],
+    profile: Option<&str>,
+    options: &LaunchOptions,
+) -> OpenOutcome {
+    let paths = paths.iter().map(AsRef::as_ref).collect::<Vec<_>>();
+    open_paths(&paths, None, profile, options)
+}
+
+fn open_paths(
+    paths: &[&Path],
+    notes: Option<&Path>,
+    profile: Option<&str>,
+    options: &LaunchOptions,
+) -> OpenOutcome {
+    if opening_disabled() {
+        return OpenOutcome::Skipped(format!(
+            "opening is disabled by `--no-open` or {}",
+            NO_OPEN_VAR
+        ));
+    }
+    if let Some(remote) = RemoteEditor::detect() {
+        let notes = notes.filter(|notes| create_notes(notes));
+        for path in paths.iter().copied().chain(notes) {
+            log::info!("opening {:?} through remote editor {:?}", path, remote);
+            if let Err(err) = remote.open_file(path) {
+                return OpenOutcome::Failed(OpenInEditorError::RemoteOpenFailed(err));
+            }
+        }
+        return OpenOutcome::Opened("remote editor".to_owned());
+    }
+    let (editor, name) = match local_editor(options) {
+        Ok(editor) => editor,
+        Err(outcome) => return outcome,
+    };
+    let notes = notes.filter(|notes| {
+        let multiple = editor
+            .editor_info()
+            .map(|info| info.opens_multiple_files())
+            .unwrap_or(false);
+        if !multiple {
+            log::debug!(
+                "not opening notes {:?}, since {} might only take one file at a time",
+                notes,
+                name
+            );
+        }
+        multiple && create_notes(notes)
+    });
+    for path in paths.iter().copied().chain(notes) {
+        if let Err(err) = editor.open_file_in_profile(path, profile, options.clone()) {
+            return OpenOutcome::Failed(OpenInEditorError::OpenFailed(err));
+        }
+    }
+    OpenOutcome::Opened(name)
+}
+
+/// The user's editor, along with its name, or how opening went if there's no
+/// editor to open anything in.
+fn local_editor(options: &LaunchOptions) -> Result<(Application, String), OpenOutcome> {
+    if let Some(reason) = headless() {
+        return Err(OpenOutcome::Skipped(reason.to_owned()));
+    }
+    let editor = match Application::detect_editor(&options.allowed_pagers) {
+        Ok(editor) => editor,
+        Err(err) if err.no_default_editor() => {
+            return Err(OpenOutcome::Skipped("no default editor is set".to_owned()))
+        }
+        Err(err) => return Err(OpenOutcome::Failed(OpenInEditorError::DetectFailed(err))),
+    };
+    let name = editor
+        .editor_info()
+        .map(|info| info.name.to_owned())
+        .or_else(|| editor.name().map(|name| name.to_string()))
+        .unwrap_or_else(|| "default editor".to_owned());
+    Ok((editor, name))
+}
+
+/// Like [`try_open_files`], but shows `left` and `right` side by side in
+/// editors that can diff them. Others, including remote editors, just get
+/// both files.
+pub fn try_open_diff(left: &Path, right: &Path, options: &LaunchOptions) -> OpenOutcome {
+    if opening_disabled() || RemoteEditor::detect().is_some() {
+        return try_open_files(&[left, right], None, options);
+    }
+    let (editor, name) = match local_editor(options) {
+        Ok(editor) => editor,
+        Err(outcome) => return outcome,
+    };
+    match editor.open_diff(left, right) {
+        Ok(()) => OpenOutcome::Opened(name),
+        Err(err) => OpenOutcome::Failed(OpenInEditorError::OpenFailed(err)),
+    }
+}
+
+/// The command that would open `path` in the user's editor, quoted so it can
+/// be pasted into a POSIX shell. This is for when we can't launch the editor
+/// ourselves, i.e. over SSH or in a sandbox, but the user can.
+pub fn open_command_line(
+    path: impl AsRef<Path>,
+    allowed_pagers: &[String],
+) -> Result<String, OpenInEditorError> {
+    let editor =
+        Application::detect_editor(allowed_pagers).map_err(OpenInEditorError::DetectFailed)?;
+    editor
+        .resolve_open_command(path)
+        .map(|argv| shell::posix_command_line(&argv))
+        .map_err(OpenInEditorError::OpenFailed)
+}
@@ -0,0 +1,113 @@
+use super::{command_path, Env};
+use crate::{
+    bossy,
+    env::ExplicitEnv as _,
+    util::{VersionTriple, VersionTripleError},
+};
+use once_cell_regex::regex;
+use std::path::PathBuf;
+use thiserror::Error;
+
+/// Tried in order, since `python` is still Python 2 on plenty of systems.
+static CANDIDATES: &[&str] = &["python3", "python"];
+
+#[derive(Debug, Error)]
+pub enum PythonError {
+    #[error("Neither `python3` nor `python` is on your `PATH`, but some NDK tools need Python 3")]
+    NotFound,
+    #[error("Failed to check the version of the Python at {path:?}: {cause}")]
+    VersionCheckFailed {
+        path: PathBuf,
+        #[source]
+        cause: Box<bossy::Error>,
+    },
+    #[error("Couldn't find a version in the output of `{path:?} --version`: {output:?}")]
+    VersionMissing { path: PathBuf, output: String },
+    #[error(transparent)]
+    VersionInvalid(#[from] VersionTripleError),
+    #[error("The only Python on your `PATH` is v{version} at {path:?}, but some NDK tools need Python 3; install it, and make sure `python3` is on your `PATH`")]
+    OnlyPython2 {
+        path: PathBuf,
+        version: VersionTriple,
+    },
+}
+
+// `command -v` prints one path, but `where.exe` prints every match.
+fn resolve(name: &str) -> Option<PathBuf> {
+    let output = command_path(name).ok()?;
+    let path = output.stdout_str().ok()?.lines().next()?.trim();
+    (!path.is_empty()).then(|| PathBuf::from(path))
+}
+
+// Python 2 prints its version to stderr, so we check both.
+fn parse_version(output: &str) -> Option<Result<VersionTriple, VersionTripleError>> {
+    regex!(r"Python (?P<version>(?P<major>\d+)\.(?P<minor>\d+)\.(?P<patch>\d+))")
+        .captures(output)
+        .map(|caps| VersionTriple::from_caps(&caps).map(|(triple, _version_str)| triple))
+}
+
+fn version(env: &Env, path: PathBuf) -> Result<(PathBuf, VersionTriple), PythonError> {
+    let output = bossy::Command::pure(&path)
+        .with_env_vars(env.explicit_env())
+        .with_arg("--version")
+        .run_and_wait_for_output()
+        .map_err(|cause| PythonError::VersionCheckFailed {
+            path: path.clone(),
+            cause: Box::new(cause),
+        })?;
+    let text = format!(
+        "{}{}",
+        String::from_utf8_lossy(output.stdout()),
+        String::from_utf8_lossy(output.stderr())
+    );
+    match parse_version(&text) {
+        Some(version) => Ok((path, version?)),
+        None => Err(PythonError::VersionMissing { path, output: text }),
+    }
+}
+
+/// The Python 3 interpreter to run NDK helper scripts (i.e. `ndk-stack`)
+/// with. `python3` is preferred, with `python` as the fallback as long as it's
+/// actually Python 3.
+pub fn python(env: &Env) -> Result<PathBuf, PythonError> {
+    let mut python2 = None;
+    for name in CANDIDATES {
+        let path = match resolve(name) {
+            Some(path) => path,
+            None => continue,
+        };
+        match version(env, path) {
+            Ok((path, version)) if version.major >= 3 => {
+                log::info!("using Python v{} at {:?}", version, path);
+                return Ok(path);
+            }
+            Ok((path, version)) => {
+                log::info!("skipping Python v{} at {:?}", version, path);
+                python2.get_or_insert(PythonError::OnlyPython2 { path, version });
+            }
+            Err(err) => log::warn!("{}", err),
+        }
+    }
+    Err(python2.unwrap_or(PythonError::NotFound))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest(
+        output,
+        expected,
+        case("Python 3.10.12\n", Some(VersionTriple::new(3, 10, 12))),
+        case("Python 2.7.18\n", Some(VersionTriple::new(2, 7, 18))),
+        case("Python 3.13.0rc2\n", Some(VersionTriple::new(3, 13, 0))),
+        case("python: command not found\n", None)
+    )]
+    fn parses_python_versions(output: &str, expected: Option<VersionTriple>) {
+        assert_eq!(
+            parse_version(output).map(|version| version.unwrap()),
+            expected
+        );
+    }
+}
@@ -0,0 +1,83 @@
+use crate::{bossy, env::Env, util};
+use std::{
+    env,
+    ffi::OsString,
+    path::{Path, PathBuf},
+};
+
+/// Command used to open files in the local editor when connected over SSH,
+/// i.e. `code --remote ssh-remote+{host} {path}`. `{path}` is appended if it
+/// doesn't appear anywhere, and a leading `~` in any word is expanded.
+pub static REMOTE_EDITOR_VAR: &str = "CARGO_MOBILE_REMOTE_EDITOR";
+/// The name the local machine knows this host by. Falls back to `hostname`.
+pub static REMOTE_HOST_VAR: &str = "CARGO_MOBILE_REMOTE_HOST";
+
+/// A bridge back to the editor on the machine we're being SSH'd into from.
+/// This is strictly opt-in, since there's no reliable way to tell what the
+/// local machine has installed.
+#[derive(Debug)]
+pub struct RemoteEditor {
+    template: String,
+    host: String,
+    home: PathBuf,
+}
+
+fn hostname() -> Option<String> {
+    env::var("HOSTNAME")
+        .ok()
+        .or_else(|| {
+            bossy::Command::impure("hostname")
+                .run_and_wait_for_string()
+                .ok()
+        })
+        .map(|host| host.trim().to_owned())
+        .filter(|host| !host.is_empty())
+}
+
+impl RemoteEditor {
+    /// Returns `None` unless we're in an SSH session and a bridge is
+    /// configured.
+    pub fn detect() -> Option<Self> {
+        env::var_os("SSH_CONNECTION")?;
+        let template = env::var(REMOTE_EDITOR_VAR)
+            .ok()
+            .filter(|template| !template.trim().is_empty())?;
+        let host = env::var(REMOTE_HOST_VAR).ok().or_else(hostname)?;
+        let home = Env::new().ok()?.home().to_owned();
+        Some(Self {
+            template,
+            host,
+            home,
+        })
+    }
+
+    fn args(&self, path: &Path) -> Vec<OsString> {
+        let mut has_path = false;
+        let mut args: Vec<OsString> = self
+            .template
+            .split_whitespace()
+            .map(|word| {
+                if word == "{path}" {
+                    has_path = true;
+                    path.as_os_str().to_owned()
+                } else {
+                    util::expand_tilde(&word.replace("{host}", &self.host), &self.home).into()
+                }
+            })
+            .collect();
+        if !has_path {
+            args.push(path.as_os_str().to_owned());
+        }
+        args
+    }
+
+    pub fn open_file(&self, path: impl AsRef<Path>) -> bossy::Result<()> {
+        let mut args = self.args(path.as_ref()).into_iter();
+        let program = args
+            .next()
+            .expect("developer error: remote editor template was empty");
+        bossy::Command::impure(program)
+            .with_args(args)
+            .run_and_detach()
+    }
+}
@@ -0,0 +1,146 @@
+use crate::bossy;
+use std::{
+    fmt::{self, Display},
+    io,
+};
+
+/// A Linux security module that can stop us from launching programs or
+/// handing them files, without the failure saying why.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SecurityModule {
+    /// SELinux in enforcing mode; permissive mode only logs denials.
+    SELinux,
+    AppArmor,
+}
+
+impl Display for SecurityModule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::SELinux => write!(f, "SELinux is enforcing"),
+            Self::AppArmor => write!(f, "AppArmor is enabled"),
+        }
+    }
+}
+
+impl SecurityModule {
+    /// Where to look for whatever denied us.
+    pub fn hint(self) -> &'static str {
+        match self {
+            Self::SELinux => "check `ausearch -m avc -ts recent` or `/var/log/audit/audit.log` for denials, and the SELinux policy for the program",
+            Self::AppArmor => "check `journalctl -k | grep apparmor` for denials, and `aa-status` for the profiles in use",
+        }
+    }
+}
+
+/// Decides from the contents of `/sys/fs/selinux/enforce` and
+/// `/sys/module/apparmor/parameters/enabled`, which aren't there when the
+/// module isn't loaded. If both are somehow active, SELinux is the one that
+/// enforces.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn detect(selinux_enforce: Option<&str>, apparmor_enabled: Option<&str>) -> Option<SecurityModule> {
+    if selinux_enforce.map(str::trim) == Some("1") {
+        Some(SecurityModule::SELinux)
+    } else if apparmor_enabled
+        .map(|enabled| enabled.trim().eq_ignore_ascii_case("y"))
+        .unwrap_or_default()
+    {
+        Some(SecurityModule::AppArmor)
+    } else {
+        None
+    }
+}
+
+/// The security module that might be getting in our way, as best we can tell.
+/// This is only for explaining failures after the fact, so it's always `None`
+/// on platforms besides Linux.
+#[cfg(target_os = "linux")]
+pub fn security_module() -> Option<SecurityModule> {
+    let read = |path| std::fs::read_to_string(path).ok();
+    detect(
+        read("/sys/fs/selinux/enforce").as_deref(),
+        read("/sys/module/apparmor/parameters/enabled").as_deref(),
+    )
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn security_module() -> Option<SecurityModule> {
+    None
+}
+
+/// The [`security_module`] that might have denied a launch, which is only
+/// worth wondering about when the launch failed with `EACCES` or `EPERM`.
+pub fn denied_by(err: &bossy::Error) -> Option<SecurityModule> {
+    if permission_denied(err) {
+        security_module()
+    } else {
+        None
+    }
+}
+
+fn permission_denied(err: &bossy::Error) -> bool {
+    err.io_error()
+        .is_some_and(|err| err.kind() == io::ErrorKind::PermissionDenied)
+}
+
+/// A suffix for launch failures, pointing at the module from [`denied_by`] if
+/// there is one; otherwise it's empty, so the error reads like it always did.
+pub fn launch_failure_hint(denied_by: Option<SecurityModule>) -> String {
+    denied_by
+        .map(|module| format!(" ({}, so {})", module, module.hint()))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest(
+        selinux_enforce,
+        apparmor_enabled,
+        expected,
+        case(None, None, None),
+        case(Some("1\n"), None, Some(SecurityModule::SELinux)),
+        case(Some("0\n"), None, None),
+        case(None, Some("Y\n"), Some(SecurityModule::AppArmor)),
+        case(Some("0\n"), Some("N\n"), None),
+        case(Some("1\n"), Some("Y\n"), Some(SecurityModule::SELinux))
+    )]
+    fn detects_security_modules(
+        selinux_enforce: Option<&str>,
+        apparmor_enabled: Option<&str>,
+        expected: Option<SecurityModule>,
+    ) {
+        assert_eq!(detect(selinux_enforce, apparmor_enabled), expected);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn only_permission_denied_counts() {
+        use std::os::unix::fs::PermissionsExt as _;
+        let dir = crate::util::temp_dir().join("security-denied-test");
+        std::fs::create_dir_all(&dir).unwrap();
+        let script = dir.join("not-executable");
+        std::fs::write(&script, "#!/bin/sh\n").unwrap();
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o644)).unwrap();
+        let denied = bossy::Command::impure(&script).run_and_wait().unwrap_err();
+        let missing = bossy::Command::impure(dir.join("missing"))
+            .run_and_wait()
+            .unwrap_err();
+        let failed = bossy::Command::impure("sh")
+            .with_args(["-c", "exit 1"])
+            .run_and_wait()
+            .unwrap_err();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(permission_denied(&denied));
+        assert!(!permission_denied(&missing));
+        assert!(!permission_denied(&failed));
+    }
+
+    #[test]
+    fn hints_only_when_denied() {
+        assert_eq!(launch_failure_hint(None), "");
+        assert!(launch_failure_hint(Some(SecurityModule::AppArmor))
+            .starts_with(" (AppArmor is enabled, so check `journalctl"));
+    }
+}
@@ -0,0 +1,272 @@
+use std::{env, ffi::OsStr, fmt, path::Path};
+
+/// How far up the process tree to look for a shell. We're usually run as
+/// `cargo mobile`, so the shell is our grandparent rather than our parent.
+#[cfg(unix)]
+static MAX_ANCESTORS: usize = 3;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+    Nushell,
+    PowerShell,
+    Cmd,
+    /// Anything we don't recognize, which we treat as a POSIX shell.
+    Posix,
+}
+
+impl fmt::Display for Shell {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl Shell {
+    pub fn from_name(name: impl AsRef<OsStr>) -> Self {
+        let name = Path::new(name.as_ref())
+            .file_stem()
+            .and_then(OsStr::to_str)
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        // Login shells show up in `ps` with a leading dash (i.e. `-zsh`)
+        match name.trim_start_matches('-') {
+            "bash" => Self::Bash,
+            "zsh" => Self::Zsh,
+            "fish" => Self::Fish,
+            "nu" => Self::Nushell,
+            "pwsh" | "powershell" => Self::PowerShell,
+            "cmd" => Self::Cmd,
+            _ => Self::Posix,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Bash => "bash",
+            Self::Zsh => "zsh",
+            Self::Fish => "fish",
+            Self::Nushell => "nu",
+            Self::PowerShell => "powershell",
+            Self::Cmd => "cmd",
+            Self::Posix => "sh",
+        }
+    }
+
+    /// The file users would typically put persistent env setup in, if there's
+    /// a conventional one.
+    pub fn config_file(self) -> Option<&'static str> {
+        match self {
+            Self::Bash => Some("~/.bashrc"),
+            Self::Zsh => Some("~/.zshrc"),
+            Self::Fish => Some("~/.config/fish/config.fish"),
+            Self::Nushell => Some("~/.config/nushell/env.nu"),
+            Self::PowerShell => Some("$PROFILE"),
+            Self::Cmd => None,
+            Self::Posix => Some("~/.profile"),
+        }
+    }
+
+    /// A command that sets `key` to `value`, quoted so that `value` is taken
+    /// literally.
+    pub fn export_snippet(self, key: &str, value: &str) -> String {
+        match self {
+            // Fish doesn't expand anything in single quotes either, and takes
+            // `\'` outside of them the same way.
+            Self::Fish => format!("set -gx {} {}", key, posix_quote(value)),
+            Self::Nushell => format!(
+                "$env.{} = \"{}\"",
+                key,
+                value.replace('\\', r"\\").replace('"', r#"\""#)
+            ),
+            Self::PowerShell => format!("$env:{} = '{}'", key, value.replace('\'', "''")),
+            // `cmd` has no way to quote a quote, but paths can't contain them.
+            Self::Cmd => format!("setx {} \"{}\"", key, value),
+            Self::Bash | Self::Zsh | Self::Posix => {
+                format!("export {}={}", key, posix_quote(value))
+            }
+        }
+    }
+}
+
+/// Quotes `arg` for POSIX shells, leaving it alone if it doesn't need it.
+/// Single quotes can't be escaped inside single quotes, so those are closed,
+/// escaped, and reopened.
+pub fn posix_quote(arg: &str) -> String {
+    let safe = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./:=@%+,".contains(c));
+    if safe {
+        arg.to_owned()
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''"))
+    }
+}
+
+/// Formats `argv` as a command that can be pasted into a POSIX shell.
+pub fn posix_command_line(argv: &[impl AsRef<OsStr>]) -> String {
+    argv.iter()
+        .map(|arg| posix_quote(&arg.as_ref().to_string_lossy()))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// Parses the output of `ps -o ppid= -o comm= -p <pid>`.
+#[cfg_attr(not(unix), allow(dead_code))]
+fn parse_ps(output: &str) -> Option<(u32, &str)> {
+    let (ppid, comm) = output.trim().split_once(char::is_whitespace)?;
+    Some((ppid.parse().ok()?, comm.trim()))
+}
+
+#[cfg(unix)]
+fn parent_of(pid: u32) -> Option<(u32, String)> {
+    crate::bossy::Command::impure("ps")
+        .with_args(["-o", "ppid=", "-o", "comm=", "-p"])
+        .with_arg(pid.to_string())
+        .run_and_wait_for_str(|output| parse_ps(output).map(|(ppid, comm)| (ppid, comm.to_owned())))
+        .map_err(|err| log::info!("failed to look up process {}: {}", pid, err))
+        .ok()
+        .flatten()
+}
+
+/// The nearest shell among the processes that started us, if we can tell.
+#[cfg(unix)]
+fn parent_shell() -> Option<Shell> {
+    let (mut pid, _) = parent_of(std::process::id())?;
+    for _ in 0..MAX_ANCESTORS {
+        if pid <= 1 {
+            break;
+        }
+        let (ppid, comm) = parent_of(pid)?;
+        let shell = Shell::from_name(&comm);
+        if shell != Shell::Posix {
+            log::info!("detected shell {} from process {} ({:?})", shell, pid, comm);
+            return Some(shell);
+        }
+        pid = ppid;
+    }
+    None
+}
+
+#[cfg(not(unix))]
+fn parent_shell() -> Option<Shell> {
+    None
+}
+
+/// The shell the user is most likely typing into. The process that started
+/// us is the best guess, since `$SHELL` is only the login shell.
+pub fn detect_shell() -> Shell {
+    parent_shell()
+        .or_else(|| {
+            env::var_os("SHELL")
+                .map(Shell::from_name)
+                .filter(|shell| *shell != Shell::Posix)
+        })
+        .unwrap_or_else(|| {
+            if cfg!(windows) {
+                // `PSModulePath` is set by PowerShell, though unfortunately
+                // it's also inherited by `cmd` on newer Windows versions.
+                if env::var_os("PSModulePath").is_some() {
+                    Shell::PowerShell
+                } else {
+                    Shell::Cmd
+                }
+            } else {
+                Shell::Posix
+            }
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest(
+        arg,
+        expected,
+        case("code", "code"),
+        case("/usr/bin/code", "/usr/bin/code"),
+        case("--reuse-window", "--reuse-window"),
+        case("", "''"),
+        case("My Project", "'My Project'"),
+        case("it's", r"'it'\''s'"),
+        case("$HOME", "'$HOME'"),
+        case("a;b", "'a;b'"),
+        case("*.rs", "'*.rs'")
+    )]
+    fn quotes_for_posix_shells(arg: &str, expected: &str) {
+        assert_eq!(posix_quote(arg), expected);
+    }
+
+    #[rstest(
+        shell,
+        value,
+        expected,
+        case(
+            Shell::Bash,
+            "/opt/android/sdk",
+            "export ANDROID_SDK_ROOT=/opt/android/sdk"
+        ),
+        case(
+            Shell::Zsh,
+            "/home/me/Android SDK",
+            "export ANDROID_SDK_ROOT='/home/me/Android SDK'"
+        ),
+        case(Shell::Posix, "$HOME/sdk", "export ANDROID_SDK_ROOT='$HOME/sdk'"),
+        case(
+            Shell::Fish,
+            "/home/me/it's",
+            r"set -gx ANDROID_SDK_ROOT '/home/me/it'\''s'"
+        ),
+        case(
+            Shell::Nushell,
+            r#"C:\sdk "x""#,
+            r#"$env.ANDROID_SDK_ROOT = "C:\\sdk \"x\"""#
+        ),
+        case(
+            Shell::PowerShell,
+            r"C:\Users\it's\sdk",
+            r"$env:ANDROID_SDK_ROOT = 'C:\Users\it''s\sdk'"
+        ),
+        case(Shell::Cmd, r"C:\sdk", r#"setx ANDROID_SDK_ROOT "C:\sdk""#)
+    )]
+    fn quotes_export_snippets(shell: Shell, value: &str, expected: &str) {
+        assert_eq!(shell.export_snippet("ANDROID_SDK_ROOT", value), expected);
+    }
+
+    #[rstest(
+        name,
+        expected,
+        case("/bin/zsh", Shell::Zsh),
+        case("-zsh", Shell::Zsh),
+        case("fish", Shell::Fish),
+        case("pwsh.exe", Shell::PowerShell),
+        case("cargo", Shell::Posix)
+    )]
+    fn recognizes_shells(name: &str, expected: Shell) {
+        assert_eq!(Shell::from_name(name), expected);
+    }
+
+    #[rstest(
+        output,
+        expected,
+        case(" 4123 -zsh\n", Some((4123, "-zsh"))),
+        case("  812 /usr/local/bin/fish\n", Some((812, "/usr/local/bin/fish"))),
+        case("", None),
+        case("zsh\n", None)
+    )]
+    fn parses_ps_output(output: &str, expected: Option<(u32, &str)>) {
+        assert_eq!(parse_ps(output), expected);
+    }
+
+    #[test]
+    fn formats_command_line() {
+        assert_eq!(
+            posix_command_line(&["code", "--reuse-window", "/home/me/My App"]),
+            "code --reuse-window '/home/me/My App'"
+        );
+    }
+}
@@ -0,0 +1,221 @@
+use super::command_path;
+use crate::bossy;
+use std::{
+    env,
+    ffi::OsString,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+
+/// How to tell a terminal which directory to start in.
+#[derive(Clone, Copy, Debug)]
+enum Cwd {
+    /// It starts wherever it was launched from.
+    #[cfg_attr(target_os = "macos", allow(dead_code))]
+    Inherit,
+    /// It takes a flag followed by the dir.
+    #[cfg_attr(target_os = "macos", allow(dead_code))]
+    Flag(&'static str),
+    /// The dir is the last arg.
+    #[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+    Arg,
+}
+
+#[derive(Debug)]
+pub struct TerminalInfo {
+    pub name: &'static str,
+    command: &'static str,
+    /// Arguments that always go first.
+    args: &'static [&'static str],
+    cwd: Cwd,
+}
+
+#[cfg(target_os = "linux")]
+static KNOWN_TERMINALS: &[TerminalInfo] = &[
+    // Debian's alternatives system points this at whichever terminal the user
+    // picked, but we can't know which, so all we can do is inherit.
+    TerminalInfo {
+        name: "default terminal",
+        command: "x-terminal-emulator",
+        args: &[],
+        cwd: Cwd::Inherit,
+    },
+    TerminalInfo {
+        name: "GNOME Terminal",
+        command: "gnome-terminal",
+        args: &[],
+        cwd: Cwd::Flag("--working-directory"),
+    },
+    TerminalInfo {
+        name: "Konsole",
+        command: "konsole",
+        args: &[],
+        cwd: Cwd::Flag("--workdir"),
+    },
+    TerminalInfo {
+        name: "Xfce Terminal",
+        command: "xfce4-terminal",
+        args: &[],
+        cwd: Cwd::Flag("--working-directory"),
+    },
+    TerminalInfo {
+        name: "Alacritty",
+        command: "alacritty",
+        args: &[],
+        cwd: Cwd::Flag("--working-directory"),
+    },
+    TerminalInfo {
+        name: "kitty",
+        command: "kitty",
+        args: &[],
+        cwd: Cwd::Flag("--directory"),
+    },
+    TerminalInfo {
+        name: "WezTerm",
+        command: "wezterm",
+        args: &["start"],
+        cwd: Cwd::Flag("--cwd"),
+    },
+    TerminalInfo {
+        name: "foot",
+        command: "foot",
+        args: &[],
+        cwd: Cwd::Flag("--working-directory"),
+    },
+    TerminalInfo {
+        name: "xterm",
+        command: "xterm",
+        args: &[],
+        cwd: Cwd::Inherit,
+    },
+];
+
+#[cfg(target_os = "macos")]
+static KNOWN_TERMINALS: &[TerminalInfo] = &[
+    TerminalInfo {
+        name: "iTerm",
+        command: "open",
+        args: &["-a", "iTerm"],
+        cwd: Cwd::Arg,
+    },
+    TerminalInfo {
+        name: "Terminal",
+        command: "open",
+        args: &["-a", "Terminal"],
+        cwd: Cwd::Arg,
+    },
+];
+
+#[cfg(windows)]
+static KNOWN_TERMINALS: &[TerminalInfo] = &[
+    TerminalInfo {
+        name: "Windows Terminal",
+        command: "wt.exe",
+        args: &[],
+        cwd: Cwd::Flag("-d"),
+    },
+    TerminalInfo {
+        name: "Command Prompt",
+        command: "cmd.exe",
+        args: &["/c", "start", "cmd.exe"],
+        cwd: Cwd::Inherit,
+    },
+];
+
+fn identify(program: &Path) -> Option<&'static TerminalInfo> {
+    let name = program.file_stem()?.to_str()?;
+    KNOWN_TERMINALS
+        .iter()
+        .find(|info| info.command != "open" && info.command.trim_end_matches(".exe") == name)
+}
+
+fn available(info: &TerminalInfo) -> bool {
+    if cfg!(target_os = "macos") {
+        // `open` is always there, so check for the app instead.
+        let app = info.args.last().copied().unwrap_or_default();
+        return bossy::Command::impure("open")
+            .with_args(["-Ra", app])
+            .run_and_wait_for_output()
+            .is_ok();
+    }
+    command_path(info.command).is_ok()
+}
+
+#[derive(Debug)]
+pub struct Terminal {
+    program: OsString,
+    info: Option<&'static TerminalInfo>,
+}
+
+impl Terminal {
+    pub fn name(&self) -> String {
+        self.info
+            .map(|info| info.name.to_owned())
+            .unwrap_or_else(|| self.program.to_string_lossy().into_owned())
+    }
+
+    fn command(&self, dir: &Path) -> bossy::Command {
+        let mut command = bossy::Command::impure(&self.program);
+        command.set_current_dir(dir);
+        if let Some(info) = self.info {
+            command.add_args(info.args);
+            match info.cwd {
+                Cwd::Inherit => (),
+                Cwd::Flag(flag) => {
+                    command.add_arg(flag).add_arg(dir);
+                }
+                Cwd::Arg => {
+                    command.add_arg(dir);
+                }
+            }
+        }
+        command
+    }
+}
+
+/// The terminal the user would want opened. `TERMINAL` takes precedence,
+/// since that's the closest thing to a convention for picking one; otherwise,
+/// it's the first known terminal that's installed.
+pub fn default_terminal() -> Option<Terminal> {
+    if let Some(program) = env::var_os("TERMINAL").filter(|program| !program.is_empty()) {
+        let info = identify(Path::new(&program));
+        return Some(Terminal { program, info });
+    }
+    KNOWN_TERMINALS
+        .iter()
+        .find(|info| available(info))
+        .map(|info| Terminal {
+            program: info.command.into(),
+            info: Some(info),
+        })
+}
+
+#[derive(Debug, Error)]
+pub enum OpenTerminalError {
+    #[error("{0:?} isn't a directory.")]
+    DirMissing(PathBuf),
+    #[error("Couldn't find a terminal to open; set `TERMINAL` to the one you'd like to use.")]
+    NoTerminal,
+    #[error("Failed to launch {name}: {cause}")]
+    LaunchFailed {
+        name: String,
+        cause: Box<bossy::Error>,
+    },
+}
+
+/// Opens the default terminal with `dir` as its working directory.
+pub fn open_terminal_at(dir: impl AsRef<Path>) -> Result<(), OpenTerminalError> {
+    let dir = dir.as_ref();
+    if !dir.is_dir() {
+        return Err(OpenTerminalError::DirMissing(dir.to_owned()));
+    }
+    let terminal = default_terminal().ok_or(OpenTerminalError::NoTerminal)?;
+    log::info!("opening {} at {:?}", terminal.name(), dir);
+    terminal
+        .command(dir)
+        .run_and_detach()
+        .map_err(|cause| OpenTerminalError::LaunchFailed {
+            name: terminal.name(),
+            cause: Box::new(cause),
+        })
+}
@@ -0,0 +1,64 @@
+use std::path::PathBuf;
+
+/// The home dir of `user`, from the password database, so it works for
+/// users from LDAP and the like too.
+#[cfg(unix)]
+pub fn user_home_dir(user: &str) -> Option<PathBuf> {
+    use std::{
+        ffi::{CStr, CString, OsStr},
+        io,
+        mem::MaybeUninit,
+        os::unix::ffi::OsStrExt as _,
+        ptr,
+    };
+
+    let user = CString::new(user).ok()?;
+    let mut buf = vec![0; 1024];
+    loop {
+        let mut passwd = MaybeUninit::<libc::passwd>::uninit();
+        let mut found = ptr::null_mut();
+        let code = unsafe {
+            libc::getpwnam_r(
+                user.as_ptr(),
+                passwd.as_mut_ptr(),
+                buf.as_mut_ptr(),
+                buf.len(),
+                &mut found,
+            )
+        };
+        match code {
+            0 if found.is_null() => return None,
+            0 => {
+                let dir = unsafe { CStr::from_ptr((*found).pw_dir) };
+                return Some(OsStr::from_bytes(dir.to_bytes()).into());
+            }
+            // The entry didn't fit, so we try again with more room.
+            libc::ERANGE if buf.len() < 1 << 20 => buf.resize(buf.len() * 2, 0),
+            _ => {
+                log::warn!(
+                    "failed to look up home dir of {:?}: {}",
+                    user,
+                    io::Error::from_raw_os_error(code)
+                );
+                return None;
+            }
+        }
+    }
+}
+
+#[cfg(not(unix))]
+pub fn user_home_dir(_user: &str) -> Option<PathBuf> {
+    None
+}
+
+#[cfg(all(test, unix))]
+mod test {
+    use super::*;
+
+    #[test]
+    fn looks_up_home_dirs() {
+        assert!(user_home_dir("root").is_some());
+        assert_eq!(user_home_dir("definitely-not-a-real-user"), None);
+        assert_eq!(user_home_dir("nul\0byte"), None);
+    }
+}
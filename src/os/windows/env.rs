@@ -1,10 +1,11 @@
 use std::{collections::HashMap, env, ffi::OsString, path::Path};
 
-use crate::env::{Error, ExplicitEnv};
+use crate::env::{Error, ExplicitEnv, Suppression};
 
 #[derive(Debug, Clone)]
 pub struct Env {
     vars: HashMap<String, OsString>,
+    suppressions: Vec<Suppression>,
 }
 
 impl Env {
@@ -34,7 +35,10 @@ impl Env {
             vars.insert("TERM".into(), term);
         }
 
-        Ok(Self { vars })
+        Ok(Self {
+            vars,
+            suppressions: Vec::new(),
+        })
     }
 
     pub fn path(&self) -> &OsString {
@@ -48,10 +52,32 @@ impl Env {
         self.vars.insert("PATH".into(), path);
         self
     }
+
+    /// Sets `suppressions` on every tool spawned with this env, replacing any
+    /// that were set before. They're logged as they're applied, so `-v` shows
+    /// what we told the tools.
+    pub fn suppress_telemetry(mut self, suppressions: Vec<Suppression>) -> Self {
+        for suppression in &suppressions {
+            log::info!(
+                "setting {}={:?} for spawned tools, since {}",
+                suppression.var,
+                suppression.value,
+                suppression.reason
+            );
+        }
+        self.suppressions = suppressions;
+        self
+    }
 }
 
 impl ExplicitEnv for Env {
     fn explicit_env(&self) -> HashMap<String, OsString> {
-        self.vars.clone()
+        let mut vars = self.vars.clone();
+        vars.extend(
+            self.suppressions
+                .iter()
+                .map(|suppression| (suppression.var.clone(), suppression.value.clone())),
+        );
+        vars
     }
 }
@@ -2,6 +2,7 @@ mod env;
 pub(super) mod info;
 pub mod ln;
 
+use super::LaunchOptions;
 use crate::bossy;
 use std::{
     ffi::{OsStr, OsString},
@@ -33,6 +34,13 @@ pub enum DetectEditorError {
     IOError(#[source] std::io::Error),
 }
 
+impl DetectEditorError {
+    /// Whether this just means the user hasn't picked an editor.
+    pub fn no_default_editor(&self) -> bool {
+        matches!(self, Self::NoDefaultEditorSet)
+    }
+}
+
 impl From<core::Error> for DetectEditorError {
     fn from(err: core::Error) -> Self {
         Self::IOError(err.into())
@@ -45,6 +53,8 @@ pub enum OpenFileError {
     LaunchFailed(#[source] bossy::Error),
     #[error("An error occured while calling OS API: {0}")]
     IOError(#[source] std::io::Error),
+    #[error("Program {program:?} wasn't found")]
+    ProgramNotFound { program: OsString },
 }
 
 pub struct Application {
@@ -55,7 +65,9 @@ const RUST_EXT: &HSTRING = w!(".rs");
 const TEXT_EXT: &HSTRING = w!(".txt");
 
 impl Application {
-    pub fn detect_editor() -> Result<Self, DetectEditorError> {
+    // Only Linux can end up with a pager as its default editor, so there's
+    // nothing to allow here.
+    pub fn detect_editor(_allowed_pagers: &[String]) -> Result<Self, DetectEditorError> {
         let editor_command =
             Self::detect_associated_command(RUST_EXT).or_else(|err| match err {
                 DetectEditorError::NoDefaultEditorSet => Self::detect_associated_command(TEXT_EXT),
@@ -65,17 +77,148 @@ impl Application {
         Ok(Self { argv })
     }
 
-    pub fn open_file(&self, path: impl AsRef<Path>) -> Result<(), OpenFileError> {
-        let args = self.argv[1..]
+    pub fn name(&self) -> Option<String> {
+        Path::new(&self.argv[0])
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+    }
+
+    // The association registry doesn't give us a description, so these are
+    // only here to keep parity with the other platforms.
+    pub fn generic_name(&self) -> Option<&str> {
+        None
+    }
+
+    pub fn comment(&self) -> Option<&str> {
+        None
+    }
+
+    pub fn location(&self) -> Option<&Path> {
+        Some(Path::new(&self.argv[0]))
+    }
+
+    pub fn exec_string(&self) -> String {
+        self.argv
             .iter()
-            .map(|arg| Self::replace_command_arg(arg, &path.as_ref().as_os_str()))
-            .collect::<Vec<_>>();
-        bossy::Command::impure(&self.argv[0])
-            .with_args(&args)
+            .map(|arg| arg.to_string_lossy())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Checks that the associated program exists, without launching it.
+    pub fn validate(&self) -> Result<(), OpenFileError> {
+        let program = &self.argv[0];
+        let found = if Path::new(program).is_absolute() {
+            Path::new(program).is_file()
+        } else {
+            command_path(&program.to_string_lossy()).is_ok()
+        };
+        if found {
+            Ok(())
+        } else {
+            Err(OpenFileError::ProgramNotFound {
+                program: program.clone(),
+            })
+        }
+    }
+
+    pub fn open_file(&self, path: impl AsRef<Path>) -> Result<(), OpenFileError> {
+        self.open_file_with_options(path, LaunchOptions::default())
+    }
+
+    pub fn open_file_with_options(
+        &self,
+        path: impl AsRef<Path>,
+        options: LaunchOptions,
+    ) -> Result<(), OpenFileError> {
+        if options.nice.is_some() || options.ionice.is_some() || options.copy_from_hidden_dirs {
+            log::warn!("launch priority options aren't supported on Windows; ignoring them");
+        }
+        let argv = self.resolve_open_command(path)?;
+        bossy::Command::impure(&argv[0])
+            .with_args(&argv[1..])
+            .with_env_vars(&options.env)
             .run_and_detach()
             .map_err(OpenFileError::LaunchFailed)
     }
 
+    /// The command that opening `path` would run, without running it.
+    pub fn resolve_open_command(
+        &self,
+        path: impl AsRef<Path>,
+    ) -> Result<Vec<OsString>, OpenFileError> {
+        let path = path.as_ref().as_os_str();
+        Ok(std::iter::once(self.argv[0].clone())
+            .chain(
+                self.argv[1..]
+                    .iter()
+                    .map(|arg| Self::replace_command_arg(arg, path)),
+            )
+            .collect())
+    }
+
+    pub fn open_diff(
+        &self,
+        left: impl AsRef<Path>,
+        right: impl AsRef<Path>,
+    ) -> Result<(), OpenFileError> {
+        let (left, right) = (left.as_ref(), right.as_ref());
+        let diffed = self
+            .editor_info()
+            .and_then(|info| super::editor::open_diff(info, &self.argv[0], left, right));
+        match diffed {
+            Some(result) => result.map_err(OpenFileError::LaunchFailed),
+            None => {
+                log::info!(
+                    "editor doesn't support diffs, so opening {:?} and {:?} separately",
+                    left,
+                    right
+                );
+                self.open_file(left)?;
+                self.open_file(right)
+            }
+        }
+    }
+
+    /// Opens `path` in `profile` and/or the most recent window (per
+    /// `options`), as far as the editor supports them; otherwise, this is the
+    /// same as [`Self::open_file_with_options`].
+    pub fn open_file_in_profile(
+        &self,
+        path: impl AsRef<Path>,
+        profile: Option<&str>,
+        options: LaunchOptions,
+    ) -> Result<(), OpenFileError> {
+        let path = path.as_ref();
+        if profile.is_none() && !options.reuse_window {
+            return self.open_file_with_options(path, options);
+        }
+        let opened = self.editor_info().and_then(|info| {
+            super::editor::open_with_flags(
+                info,
+                &self.argv[0],
+                path,
+                profile,
+                options.reuse_window,
+                &options.env,
+            )
+        });
+        match opened {
+            Some(result) => result.map_err(OpenFileError::LaunchFailed),
+            None => {
+                log::debug!(
+                    "editor doesn't support profiles or reusing windows, so opening {:?} normally",
+                    path
+                );
+                self.open_file_with_options(path, options)
+            }
+        }
+    }
+
+    pub fn editor_info(&self) -> Option<&'static super::editor::EditorInfo> {
+        super::editor::identify(&self.argv[0])
+    }
+
     fn detect_associated_command(ext: &HSTRING) -> Result<Vec<u16>, DetectEditorError> {
         let mut len: u32 = 0;
         if let Err(e) = unsafe {
@@ -144,7 +287,7 @@ impl Application {
     }
 }
 
-pub fn open_file_with(
+pub fn launch_file_with(
     application: impl AsRef<OsStr>,
     path: impl AsRef<OsStr>,
 ) -> Result<(), OpenFileError> {
@@ -195,6 +338,16 @@ fn open_file_with_android_studio(path: impl AsRef<OsStr>) -> Result<(), OpenFile
     Ok(())
 }
 
+pub fn launch_file_manager(path: impl AsRef<Path>) -> Result<(), OpenFileError> {
+    let mut arg = OsString::from("/select,");
+    arg.push(dunce::simplified(path.as_ref()));
+    // `explorer.exe` exits with 1 even when it succeeds, so we can't wait on it.
+    bossy::Command::impure("explorer.exe")
+        .with_arg(arg)
+        .run_and_detach()
+        .map_err(OpenFileError::LaunchFailed)
+}
+
 pub fn command_path(name: &str) -> bossy::Result<bossy::Output> {
     bossy::Command::impure("where.exe")
         .with_arg(name)
@@ -280,7 +433,12 @@ pub fn replace_path_separator(path: OsString) -> OsString {
 pub mod consts {
     pub const CLANG: &str = "clang.cmd";
     pub const CLANGXX: &str = "clang++.cmd";
+    pub const CLANG_BIN: &str = "clang.exe";
     pub const LD: &str = "ld.exe";
     pub const READELF: &str = "readelf.exe";
     pub const NDK_STACK: &str = "ndk-stack.cmd";
+    pub const AVDMANAGER: &str = "avdmanager.bat";
+    pub const SDKMANAGER: &str = "sdkmanager.bat";
+    pub const AAPT2: &str = "aapt2.exe";
+    pub const APKSIGNER: &str = "apksigner.bat";
 }
@@ -1,18 +1,25 @@
 use crate::{
-    bicycle, bossy,
+    bicycle::{self, Action},
+    bossy,
     config::Config,
-    templating::{self, FancyPackResolveError},
+    os,
+    templating::{self, FancyPackResolveError, Partials, TemplateError, PARTIALS_DIR},
     util::{
-        cli::{Report, Reportable},
-        Git,
+        self,
+        cli::{Report, Reportable, TextWrapper},
+        prompt, Git,
     },
 };
-use std::path::PathBuf;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
 
 #[derive(Debug)]
 pub enum Error {
     GitInitFailed(bossy::Error),
     TemplatePackResolveFailed(FancyPackResolveError),
+    TemplateInvalid(TemplateError),
     ProcessingFailed {
         src: PathBuf,
         dest: PathBuf,
@@ -27,6 +34,7 @@ impl Reportable for Error {
             Self::TemplatePackResolveFailed(err) => {
                 Report::error("Failed to resolve template pack", err)
             }
+            Self::TemplateInvalid(err) => Report::error("Template pack is invalid", err),
             Self::ProcessingFailed { src, dest, cause } => Report::error(
                 format!(
                     "Base project template processing from src {:?} to dest {:?} failed",
@@ -40,9 +48,11 @@ impl Reportable for Error {
 
 pub fn gen(
     config: &Config,
-    bike: &bicycle::Bicycle,
+    bike: &mut bicycle::Bicycle,
     filter: &templating::Filter,
     submodule_commit: Option<String>,
+    wrapper: &TextWrapper,
+    non_interactive: bool,
 ) -> Result<(), Error> {
     println!("Generating base project...");
     let root = config.app().root_dir();
@@ -54,14 +64,154 @@ pub fn gen(
         .resolve(git, submodule_commit.as_deref())
         .map_err(Error::TemplatePackResolveFailed)?;
     log::info!("template pack chain: {:#?}", pack_chain);
+    let partials = Partials::load(&pack_chain).map_err(Error::TemplateInvalid)?;
+    partials
+        .validate(&pack_chain)
+        .map_err(Error::TemplateInvalid)?;
+    partials.register(bike).map_err(Error::TemplateInvalid)?;
+    let partials_dir = root.join(PARTIALS_DIR);
+    // Templates for files that are checked in, which the filter protects.
+    let mut protected = Vec::new();
     for pack in pack_chain {
         log::info!("traversing template pack {:#?}", pack);
-        bike.filter_and_process(&pack, &root, |_| (), filter.fun())
-            .map_err(|cause| Error::ProcessingFailed {
-                src: pack.to_owned(),
-                dest: root.to_owned(),
-                cause,
-            })?;
+        let mut filter_fn = filter.fun();
+        bike.filter_and_process(
+            pack,
+            root,
+            |_| (),
+            |action| {
+                if action.dest().starts_with(&partials_dir) {
+                    return false;
+                }
+                let allowed = filter_fn(action);
+                if let Action::WriteTemplate { src, dest } = action {
+                    if !allowed && dest.is_file() {
+                        protected.push((src.clone(), dest.clone()));
+                    }
+                }
+                allowed
+            },
+        )
+        .map_err(|cause| Error::ProcessingFailed {
+            src: pack.to_owned(),
+            dest: root.to_owned(),
+            cause,
+        })?;
+    }
+    let outdated = outdated(bike, &protected);
+    if !outdated.is_empty() {
+        Report::action_request(
+            "Some checked-in files differ from what their templates generate now",
+            format!(
+                "They were left alone, since they're checked in, so you might want to update them yourself:\n{}",
+                outdated
+                    .iter()
+                    .map(|(dest, _)| format!("{:?}", dest))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            ),
+        )
+        .print(wrapper);
+        if non_interactive || os::opening_disabled() || !os::can_prompt() {
+            log::info!("not offering to diff outdated files, since we can't prompt or open them");
+        } else {
+            offer_diffs(config, root, &outdated);
+        }
     }
     Ok(())
 }
+
+/// The files in `protected` (template, destination pairs) that don't match
+/// what their templates render to, along with what they render to. Failing
+/// to check just gets logged, since the files are left alone either way.
+fn outdated(bike: &bicycle::Bicycle, protected: &[(PathBuf, PathBuf)]) -> Vec<(PathBuf, String)> {
+    protected
+        .iter()
+        .filter_map(|(src, dest)| {
+            let rendered = fs::read_to_string(src)
+                .map_err(|err| err.to_string())
+                .and_then(|template| {
+                    bike.render(&template, |_| ())
+                        .map_err(|err| err.to_string())
+                });
+            match (rendered, fs::read_to_string(dest)) {
+                (Ok(rendered), Ok(current)) => {
+                    (rendered != current).then(|| (dest.clone(), rendered))
+                }
+                (Err(err), _) => {
+                    log::warn!("failed to render template {:?}: {}", src, err);
+                    None
+                }
+                (_, Err(err)) => {
+                    log::warn!("failed to read {:?}: {}", dest, err);
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+/// Offers to open each of `outdated` next to what its template generates
+/// now, in the user's editor. This is only a convenience, so nothing here
+/// fails the init.
+fn offer_diffs(config: &Config, root: &Path, outdated: &[(PathBuf, String)]) {
+    let generated_dir = util::temp_dir().join("generated");
+    for (dest, rendered) in outdated {
+        let relative = dest.strip_prefix(root).unwrap_or(dest);
+        match prompt::yes_no(
+            format!("Compare {:?} with what would be generated?", relative),
+            Some(false),
+        ) {
+            Ok(Some(true)) => (),
+            Ok(_) => continue,
+            Err(err) => {
+                log::warn!("failed to prompt for diffing {:?}: {}", dest, err);
+                return;
+            }
+        }
+        let generated = generated_dir.join(relative);
+        let written = generated
+            .parent()
+            .map(fs::create_dir_all)
+            .unwrap_or(Ok(()))
+            .and_then(|()| fs::write(&generated, rendered));
+        if let Err(err) = written {
+            log::warn!("failed to write {:?}: {}", generated, err);
+            continue;
+        }
+        match os::try_open_diff(dest, &generated, &config.editor().launch_options()) {
+            os::OpenOutcome::Opened(name) => println!("Opened diff in {}", name),
+            os::OpenOutcome::Skipped(reason) => {
+                println!("Not opening diff, since {}.", reason);
+                return;
+            }
+            os::OpenOutcome::Failed(err) => log::warn!("failed to open diff: {}", err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn finds_outdated_files() {
+        let dir = util::temp_dir().join("project-outdated-test");
+        fs::create_dir_all(&dir).unwrap();
+        let template = dir.join("lib.rs.hbs");
+        fs::write(&template, "// {{#if true}}new{{/if}}\n").unwrap();
+        let (current, outdated_file) = (dir.join("current.rs"), dir.join("outdated.rs"));
+        fs::write(&current, "// new\n").unwrap();
+        fs::write(&outdated_file, "// old\n").unwrap();
+        let found = outdated(
+            &bicycle::Bicycle::default(),
+            &[
+                (template.clone(), current),
+                (template.clone(), outdated_file.clone()),
+                (dir.join("missing.hbs"), outdated_file.clone()),
+            ],
+        );
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(found, vec![(outdated_file, "// new\n".to_owned())]);
+    }
+}
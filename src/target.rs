@@ -1,10 +1,32 @@
-use crate::{bossy, util};
+use crate::{
+    bossy,
+    util::{self, toolchain::Toolchain},
+};
 use once_cell_regex::exports::once_cell::sync::OnceCell;
 use std::{
     collections::BTreeMap,
     fmt::{self, Debug, Display},
+    num::NonZeroUsize,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
 };
 
+/// The most targets we'll build at once unless told otherwise, since a
+/// single optimized build can easily use a few GB of memory.
+pub const MAX_DEFAULT_BUILD_JOBS: usize = 4;
+
+/// How many targets to build at once by default: one per CPU, up to
+/// [`MAX_DEFAULT_BUILD_JOBS`].
+pub fn default_build_jobs() -> usize {
+    thread::available_parallelism()
+        .map(NonZeroUsize::get)
+        .unwrap_or(1)
+        .min(MAX_DEFAULT_BUILD_JOBS)
+}
+
 pub trait TargetTrait<'a>: Debug + Sized {
     const DEFAULT_KEY: &'static str;
 
@@ -36,16 +58,25 @@ pub trait TargetTrait<'a>: Debug + Sized {
 
     fn arch(&'a self) -> &'a str;
 
-    fn install(&'a self) -> bossy::Result<bossy::ExitStatus> {
-        util::rustup_add(self.triple())
+    fn install(&'a self, toolchain: Option<&Toolchain>) -> bossy::Result<bossy::ExitStatus> {
+        util::rustup_add(self.triple(), toolchain)
     }
 
-    fn install_all() -> bossy::Result<()>
+    fn install_all(toolchain: Option<&Toolchain>) -> bossy::Result<()>
     where
         Self: 'a,
     {
+        // rustup can't add targets to custom toolchains, so those have to come
+        // with whatever targets they need.
+        if let Some(toolchain) = toolchain.filter(|toolchain| toolchain.is_path()) {
+            println!(
+                "Not installing targets, since the pinned toolchain {} is a custom toolchain",
+                toolchain
+            );
+            return Ok(());
+        }
         for target in Self::all().values() {
-            target.install()?;
+            target.install(toolchain)?;
         }
         Ok(())
     }
@@ -135,3 +166,120 @@ where
         Ok(())
     })
 }
+
+/// Each target that failed, along with why.
+pub type TargetFailures<'a, T, E> = Vec<(&'a T, E)>;
+
+fn call_in_parallel<'a, T, E, F>(targets: &[&'a T], jobs: usize, f: &F) -> TargetFailures<'a, T, E>
+where
+    T: Sync,
+    E: Send,
+    F: Fn(&T, bool) -> Result<(), E> + Sync,
+{
+    let total = targets.len();
+    let next = AtomicUsize::new(0);
+    let failures = Mutex::new(Vec::new());
+    thread::scope(|scope| {
+        for _ in 0..jobs.min(total) {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::SeqCst);
+                let target = match targets.get(index) {
+                    Some(target) => *target,
+                    None => break,
+                };
+                // `f` reports its own progress as build events.
+                if let Err(err) = f(target, true) {
+                    failures
+                        .lock()
+                        .expect("developer error: a build worker panicked")
+                        .push((index, target, err));
+                }
+            });
+        }
+    });
+    let mut failures = failures
+        .into_inner()
+        .expect("developer error: a build worker panicked");
+    failures.sort_by_key(|(index, _, _)| *index);
+    failures
+        .into_iter()
+        .map(|(_, target, err)| (target, err))
+        .collect()
+}
+
+/// Like [`call_for_targets_with_fallback`], but calls `f` for up to `jobs`
+/// targets at once, and keeps going when one fails so that every failure is
+/// returned together. With `jobs` at `1`, targets are done one at a time, in
+/// order, stopping at the first failure, which is easier to debug.
+///
+/// `f` is also told whether other targets might be building alongside it, in
+/// which case it shouldn't share a cargo target dir with them (cargo locks the
+/// whole thing for as long as it's building, so they'd just wait in line), and
+/// should label its output so it can be told apart.
+pub fn call_for_targets_in_parallel_with_fallback<'a, Iter, I, T, U, E, F>(
+    targets: Iter,
+    fallback: &'a dyn Fn(U) -> Option<&'a T>,
+    arg: U,
+    jobs: usize,
+    f: F,
+) -> Result<Result<(), TargetFailures<'a, T, E>>, TargetInvalid>
+where
+    Iter: ExactSizeIterator<Item = &'a I>,
+    I: AsRef<str> + 'a,
+    T: TargetTrait<'a> + Sync,
+    E: Send,
+    F: Fn(&T, bool) -> Result<(), E> + Sync,
+{
+    get_targets(targets, Some((fallback, arg))).map(|targets| {
+        if jobs <= 1 || targets.len() <= 1 {
+            for target in targets {
+                f(target, false).map_err(|err| vec![(target, err)])?;
+            }
+            Ok(())
+        } else {
+            let failures = call_in_parallel(&targets, jobs, &f);
+            if failures.is_empty() {
+                Ok(())
+            } else {
+                Err(failures)
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn parallel_calls_overlap_and_report_every_failure() {
+        let targets = [&1, &2, &3, &4];
+        let running = AtomicUsize::new(0);
+        let overlapped = AtomicUsize::new(0);
+        let failures = call_in_parallel(&targets, 2, &|target: &i32, parallel| {
+            assert!(parallel);
+            running.fetch_add(1, Ordering::SeqCst);
+            // Wait a while for another call to start, which it only can if
+            // we aren't going one at a time.
+            let started = Instant::now();
+            while started.elapsed() < Duration::from_secs(5) {
+                if running.load(Ordering::SeqCst) > 1 {
+                    overlapped.fetch_add(1, Ordering::SeqCst);
+                    break;
+                }
+                thread::sleep(Duration::from_millis(1));
+            }
+            // The last call has nothing left to overlap with.
+            thread::sleep(Duration::from_millis(20));
+            running.fetch_sub(1, Ordering::SeqCst);
+            if target % 2 == 0 {
+                Err(*target)
+            } else {
+                Ok(())
+            }
+        });
+        assert!(overlapped.load(Ordering::SeqCst) >= 2);
+        assert_eq!(failures, vec![(&2, 2), (&4, 4)]);
+    }
+}
@@ -1,8 +1,9 @@
 mod fancy;
 mod filter;
 mod init;
+mod validate;
 
-pub use self::{fancy::*, filter::*, init::*};
+pub use self::{fancy::*, filter::*, init::*, validate::*};
 
 use crate::util::{self, Git};
 use std::{
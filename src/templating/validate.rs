@@ -0,0 +1,305 @@
+use crate::bicycle::{self, Bicycle};
+use once_cell_regex::regex;
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+
+/// Templates in this dir at the root of a pack can be included from any other
+/// template via `{{> name}}`, where `name` is the path relative to this dir
+/// without the `.hbs` extension. They're never generated on their own.
+pub static PARTIALS_DIR: &str = ".partials";
+
+#[derive(Debug, Error)]
+pub enum TemplateError {
+    #[error("Failed to read template {path:?}: {cause}")]
+    ReadFailed { path: PathBuf, cause: io::Error },
+    #[error("Template {template:?} includes `{include}`, but there's no {PARTIALS_DIR}/{include}.hbs in the template pack.")]
+    MissingInclude { template: PathBuf, include: String },
+    #[error("Template {template:?} includes itself: {}", .cycle.join(" -> "))]
+    IncludeCycle {
+        template: PathBuf,
+        cycle: Vec<String>,
+    },
+    #[error("Partial {path:?} is invalid: {cause}")]
+    PartialInvalid {
+        path: PathBuf,
+        cause: Box<bicycle::handlebars::TemplateError>,
+    },
+}
+
+#[derive(Debug)]
+struct Partial {
+    path: PathBuf,
+    source: String,
+}
+
+/// The partials from every pack in a chain. Later packs take precedence, the
+/// same way their templates overwrite those from earlier packs.
+#[derive(Debug, Default)]
+pub struct Partials(BTreeMap<String, Partial>);
+
+fn inline_partials(source: &str) -> Vec<&str> {
+    regex!(r#"\{\{~?#\*inline\s+"(?P<name>[^"]+)""#)
+        .captures_iter(source)
+        .map(|caps| caps.name("name").unwrap().as_str())
+        .collect()
+}
+
+/// The partials `source` includes by name. Dynamic partials (i.e.
+/// `{{> (lookup . "name")}}`) can only be resolved while rendering, and
+/// `@partial-block` and inline partials aren't in the partials dir, so none
+/// of those are included.
+fn includes(source: &str) -> impl Iterator<Item = &str> {
+    let inline = inline_partials(source);
+    regex!(r"\{\{~?#?>\s*(?P<name>[^\s}~]+)")
+        .captures_iter(source)
+        .map(|caps| caps.name("name").unwrap().as_str())
+        .filter(|name| !name.starts_with('(') && !name.starts_with('@'))
+        .map(|name| name.trim_matches(|c| c == '"' || c == '\''))
+        .filter(move |name| !inline.contains(name))
+}
+
+fn read(path: &Path) -> Result<String, TemplateError> {
+    fs::read_to_string(path).map_err(|cause| TemplateError::ReadFailed {
+        path: path.to_owned(),
+        cause,
+    })
+}
+
+fn walk(
+    dir: &Path,
+    f: &mut impl FnMut(&Path) -> Result<(), TemplateError>,
+) -> Result<(), TemplateError> {
+    let entries = fs::read_dir(dir).map_err(|cause| TemplateError::ReadFailed {
+        path: dir.to_owned(),
+        cause,
+    })?;
+    for entry in entries {
+        let path = entry
+            .map_err(|cause| TemplateError::ReadFailed {
+                path: dir.to_owned(),
+                cause,
+            })?
+            .path();
+        if path.is_dir() {
+            walk(&path, f)?;
+        } else if path.extension().and_then(|ext| ext.to_str()) == bicycle::DEFAULT_TEMPLATE_EXT {
+            f(&path)?;
+        }
+    }
+    Ok(())
+}
+
+impl Partials {
+    pub fn load(pack_chain: &[&Path]) -> Result<Self, TemplateError> {
+        let mut partials = BTreeMap::new();
+        for pack in pack_chain {
+            let dir = pack.join(PARTIALS_DIR);
+            if !dir.is_dir() {
+                continue;
+            }
+            walk(&dir, &mut |path| {
+                let name = path
+                    .strip_prefix(&dir)
+                    .expect("developer error: partial wasn't in partials dir")
+                    .with_extension("")
+                    .to_string_lossy()
+                    .replace('\\', "/");
+                let source = read(path)?;
+                partials.insert(
+                    name,
+                    Partial {
+                        path: path.to_owned(),
+                        source,
+                    },
+                );
+                Ok(())
+            })?;
+        }
+        Ok(Self(partials))
+    }
+
+    fn check_cycles<'a>(
+        &'a self,
+        name: &'a str,
+        stack: &mut Vec<&'a str>,
+        done: &mut Vec<&'a str>,
+    ) -> Result<(), TemplateError> {
+        if done.contains(&name) {
+            return Ok(());
+        }
+        if let Some(start) = stack.iter().position(|entered| *entered == name) {
+            let mut cycle: Vec<String> = stack[start..].iter().map(|s| s.to_string()).collect();
+            cycle.push(name.to_owned());
+            return Err(TemplateError::IncludeCycle {
+                template: self.0[name].path.clone(),
+                cycle,
+            });
+        }
+        // Missing includes are reported separately, with the including
+        // template's path, so there's nothing to follow here.
+        let partial = match self.0.get(name) {
+            Some(partial) => partial,
+            None => return Ok(()),
+        };
+        stack.push(name);
+        for include in includes(&partial.source) {
+            self.check_cycles(include, stack, done)?;
+        }
+        stack.pop();
+        done.push(name);
+        Ok(())
+    }
+
+    /// Checks every template in `pack_chain` (partials included) for includes
+    /// that don't exist or that include themselves, so that these fail with
+    /// a useful error rather than partway through rendering.
+    pub fn validate(&self, pack_chain: &[&Path]) -> Result<(), TemplateError> {
+        for pack in pack_chain {
+            walk(pack, &mut |path| {
+                let source = read(path)?;
+                for include in includes(&source) {
+                    if !self.0.contains_key(include) {
+                        return Err(TemplateError::MissingInclude {
+                            template: path.to_owned(),
+                            include: include.to_owned(),
+                        });
+                    }
+                }
+                Ok(())
+            })?;
+        }
+        let mut done = Vec::new();
+        for name in self.0.keys() {
+            self.check_cycles(name, &mut Vec::new(), &mut done)?;
+        }
+        Ok(())
+    }
+
+    pub fn register(&self, bike: &mut Bicycle) -> Result<(), TemplateError> {
+        for (name, partial) in &self.0 {
+            bike.register_partial(name, &partial.source)
+                .map_err(|cause| TemplateError::PartialInvalid {
+                    path: partial.path.clone(),
+                    cause,
+                })?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest(
+        source,
+        expected,
+        case("{{> header}}", &["header"]),
+        case("{{~> android/signing ~}}", &["android/signing"]),
+        case("{{#> layout}}body{{/layout}}", &["layout"]),
+        case(r#"{{> "quoted"}}"#, &["quoted"]),
+        case(r#"{{> (lookup . "partial")}}"#, &[]),
+        case("{{> (concat platform \"-manifest\")}}", &[]),
+        case("{{#> layout}}{{> @partial-block}}{{/layout}}", &["layout"]),
+        case(r#"{{#*inline "row"}}{{name}}{{/inline}}{{> row}}{{> footer}}"#, &["footer"]),
+        case("{{name}} and {{#if x}}y{{/if}}", &[])
+    )]
+    fn finds_static_includes(source: &str, expected: &[&str]) {
+        assert_eq!(includes(source).collect::<Vec<_>>(), expected);
+    }
+
+    fn pack(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = crate::util::temp_dir()
+            .join("template-validate-test")
+            .join(name);
+        if dir.exists() {
+            fs::remove_dir_all(&dir).unwrap();
+        }
+        for (path, contents) in files {
+            let path = dir.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+        dir
+    }
+
+    fn validate(packs: &[&Path]) -> Result<(), TemplateError> {
+        Partials::load(packs)?.validate(packs)
+    }
+
+    #[test]
+    fn accepts_valid_packs() {
+        let base = pack(
+            "valid-base",
+            &[
+                (".partials/header.hbs", "// {{app.name}}"),
+                (".partials/android/signing.hbs", "{{> header}}"),
+                ("build.gradle.hbs", "{{> header}}\n{{> android/signing}}"),
+                (
+                    "Info.plist.hbs",
+                    r#"{{> (lookup . "plist-partial")}}{{#> header}}{{> @partial-block}}{{/header}}"#,
+                ),
+                ("README.md", "{{> not-a-template}}"),
+            ],
+        );
+        // A later pack can provide a partial for an earlier one.
+        let overlay = pack(
+            "valid-overlay",
+            &[
+                (".partials/footer.hbs", "fin"),
+                ("lib.rs.hbs", "{{> footer}}"),
+            ],
+        );
+        let result = validate(&[&base, &overlay]);
+        fs::remove_dir_all(&base).unwrap();
+        fs::remove_dir_all(&overlay).unwrap();
+        result.unwrap();
+    }
+
+    #[test]
+    fn reports_missing_includes() {
+        let dir = pack(
+            "missing",
+            &[
+                (".partials/header.hbs", "// header"),
+                ("app/build.gradle.hbs", "{{> header}}\n{{> footer}}"),
+            ],
+        );
+        let result = validate(&[&dir]);
+        fs::remove_dir_all(&dir).unwrap();
+        match result {
+            Err(TemplateError::MissingInclude { template, include }) => {
+                assert_eq!(template, dir.join("app/build.gradle.hbs"));
+                assert_eq!(include, "footer");
+            }
+            other => panic!("expected a missing include, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn reports_cycles() {
+        let dir = pack(
+            "cycle",
+            &[
+                (".partials/a.hbs", "{{> b}}"),
+                (".partials/b.hbs", "{{#if x}}{{> c}}{{/if}}"),
+                (".partials/c.hbs", "{{> a}}"),
+                ("main.hbs", "{{> a}}"),
+            ],
+        );
+        let result = validate(&[&dir]);
+        fs::remove_dir_all(&dir).unwrap();
+        match result {
+            Err(TemplateError::IncludeCycle { template, cycle }) => {
+                assert_eq!(template, dir.join(".partials/a.hbs"));
+                assert_eq!(cycle, vec!["a", "b", "c", "a"]);
+            }
+            other => panic!("expected a cycle, got {:?}", other),
+        }
+    }
+}
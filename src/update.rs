@@ -1,5 +1,5 @@
 use crate::{
-    bossy,
+    bossy, os,
     util::{
         self,
         cli::{Report, TextWrapper},
@@ -22,6 +22,7 @@ static ENABLED_FEATURES: &[&str] = &[
 pub enum Error {
     NoHomeDir(util::NoHomeDir),
     StatusFailed(repo::Error),
+    ReadOnly { dir: PathBuf, cause: io::Error },
     MarkerCreateFailed { path: PathBuf, cause: io::Error },
     UpdateFailed(repo::Error),
     InstallFailed(bossy::Error),
@@ -35,6 +36,11 @@ impl Display for Error {
             Self::StatusFailed(err) => {
                 write!(f, "Failed to check status of `cargo-mobile` repo: {}", err)
             }
+            Self::ReadOnly { dir, cause } => write!(
+                f,
+                "Can't write to {:?}, so `cargo-mobile` can't be updated: {}",
+                dir, cause
+            ),
             Self::MarkerCreateFailed { path, cause } => {
                 write!(f, "Failed to create marker file at {:?}: {}", path, cause)
             }
@@ -74,6 +80,15 @@ pub fn update(wrapper: &TextWrapper) -> Result<(), Error> {
         log::info!("no marker file present at {:?}", marker);
     }
     let msg = if marker_exists || repo.status().map_err(Error::StatusFailed)?.stale() {
+        // Bailing out before the marker exists means a read-only checkouts
+        // dir doesn't leave us thinking an update is perpetually in progress.
+        let dir = marker
+            .parent()
+            .expect("developer error: marker path had no parent");
+        os::check_writable(dir).map_err(|cause| Error::ReadOnly {
+            dir: dir.to_owned(),
+            cause,
+        })?;
         File::create(&marker).map_err(|cause| Error::MarkerCreateFailed {
             path: marker.to_owned(),
             cause,
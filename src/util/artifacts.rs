@@ -0,0 +1,81 @@
+use super::cli::{Report, Reportable};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum CopyArtifactsError {
+    #[error("Failed to create output directory {path:?}: {cause}")]
+    DirCreationFailed { path: PathBuf, cause: io::Error },
+    #[error("Artifact {path:?} has no file name")]
+    NoFileName { path: PathBuf },
+    #[error("Failed to copy artifact {src:?} to {dest:?}: {cause}")]
+    CopyFailed {
+        src: PathBuf,
+        dest: PathBuf,
+        cause: io::Error,
+    },
+    #[error("Failed to canonicalize output path {path:?}: {cause}")]
+    CanonicalizationFailed { path: PathBuf, cause: io::Error },
+}
+
+impl Reportable for CopyArtifactsError {
+    fn report(&self) -> Report {
+        Report::error("Failed to copy artifacts to output directory", self)
+    }
+}
+
+fn copy_recursive(src: &Path, dest: &Path) -> io::Result<()> {
+    if src.is_dir() {
+        fs::create_dir_all(dest)?;
+        for entry in fs::read_dir(src)? {
+            let entry = entry?;
+            copy_recursive(&entry.path(), &dest.join(entry.file_name()))?;
+        }
+        Ok(())
+    } else {
+        fs::copy(src, dest).map(|_| ())
+    }
+}
+
+/// Copies each artifact into `out_dir`, creating it if needed. Artifacts can
+/// be files (i.e. APKs) or directories (i.e. `.xcarchive`s). The originals are
+/// left in place, so the in-tree build cache stays intact.
+///
+/// Returns the absolute paths of the copies.
+pub fn copy_artifacts(
+    out_dir: &Path,
+    artifacts: impl IntoIterator<Item = impl AsRef<Path>>,
+) -> Result<Vec<PathBuf>, CopyArtifactsError> {
+    fs::create_dir_all(out_dir).map_err(|cause| CopyArtifactsError::DirCreationFailed {
+        path: out_dir.to_owned(),
+        cause,
+    })?;
+    artifacts
+        .into_iter()
+        .map(|src| {
+            let src = src.as_ref();
+            let file_name = src
+                .file_name()
+                .ok_or_else(|| CopyArtifactsError::NoFileName {
+                    path: src.to_owned(),
+                })?;
+            let dest = out_dir.join(file_name);
+            if dest.is_dir() {
+                fs::remove_dir_all(&dest)
+            } else {
+                Ok(())
+            }
+            .and_then(|()| copy_recursive(src, &dest))
+            .map_err(|cause| CopyArtifactsError::CopyFailed {
+                src: src.to_owned(),
+                dest: dest.clone(),
+                cause,
+            })?;
+            dunce::canonicalize(&dest)
+                .map_err(|cause| CopyArtifactsError::CanonicalizationFailed { path: dest, cause })
+        })
+        .collect()
+}
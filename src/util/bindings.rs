@@ -0,0 +1,151 @@
+use crate::{config::app::App, opts::NoiseLevel, os, util::prompt};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+fn collect(path: &Path, mtimes: &mut BTreeMap<PathBuf, Option<SystemTime>>) {
+    match fs::metadata(path) {
+        Ok(metadata) if metadata.is_dir() => {
+            if let Ok(entries) = fs::read_dir(path) {
+                for entry in entries.filter_map(Result::ok) {
+                    collect(&entry.path(), mtimes);
+                }
+            }
+        }
+        Ok(metadata) => {
+            mtimes.insert(path.to_owned(), metadata.modified().ok());
+        }
+        // It's normal for bindings not to exist until codegen first runs.
+        Err(_) => {}
+    }
+}
+
+/// The modification times of the files listed in `app.open-bindings`, taken
+/// before codegen runs so we can tell afterwards which ones it touched.
+#[derive(Debug, Default)]
+pub struct Snapshot {
+    mtimes: BTreeMap<PathBuf, Option<SystemTime>>,
+}
+
+impl Snapshot {
+    fn of(paths: &[PathBuf]) -> Self {
+        let mut mtimes = BTreeMap::new();
+        for path in paths {
+            collect(path, &mut mtimes);
+        }
+        Self { mtimes }
+    }
+
+    pub fn take(app: &App) -> Self {
+        Self::of(&app.open_bindings())
+    }
+
+    fn changed_in(&self, paths: &[PathBuf]) -> Vec<PathBuf> {
+        Self::of(paths)
+            .mtimes
+            .into_iter()
+            .filter(|(path, mtime)| match self.mtimes.get(path) {
+                Some(before) => before != mtime,
+                None => true,
+            })
+            .map(|(path, _)| path)
+            .collect()
+    }
+
+    /// The files that were created or modified since the snapshot was taken.
+    pub fn changed(&self, app: &App) -> Vec<PathBuf> {
+        self.changed_in(&app.open_bindings())
+    }
+}
+
+/// Offers to open the bindings changed since `snapshot` in the user's editor.
+/// Opening them is only a convenience, so nothing here fails the build; when
+/// we can't ask, or there's no editor to open them in, we just say so.
+pub fn offer_to_open(app: &App, snapshot: &Snapshot, noise_level: NoiseLevel) {
+    let changed = snapshot.changed(app);
+    if changed.is_empty() {
+        return;
+    }
+    if os::opening_disabled() {
+        log::info!(
+            "not offering to open changed bindings {:?}, since opening is disabled",
+            changed
+        );
+        return;
+    }
+    if !os::can_prompt() {
+        log::info!(
+            "not offering to open changed bindings {:?}, since we can't prompt",
+            changed
+        );
+        return;
+    }
+    if !noise_level.polite() {
+        for path in &changed {
+            println!("Updated binding: {:?}", path);
+        }
+    }
+    let msg = format!(
+        "Codegen updated {} binding file{}. Open in your editor?",
+        changed.len(),
+        if changed.len() == 1 { "" } else { "s" }
+    );
+    match prompt::yes_no(msg, Some(true)) {
+        Ok(Some(true)) => (),
+        Ok(_) => return,
+        Err(err) => {
+            log::warn!("failed to prompt for opening bindings: {}", err);
+            return;
+        }
+    }
+    match os::try_open_files(&changed, None, &Default::default()) {
+        os::OpenOutcome::Opened(name) => println!("Opened bindings in {}", name),
+        os::OpenOutcome::Skipped(reason) => {
+            println!("Not opening bindings, since {}.", reason)
+        }
+        os::OpenOutcome::Failed(err) => log::warn!("failed to open bindings: {}", err),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::{fs::File, time::Duration};
+
+    #[test]
+    fn finds_changed_bindings() {
+        let dir = crate::util::temp_dir().join("bindings-snapshot-test");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("generated")).unwrap();
+        let header = dir.join("bindings.h");
+        let untouched = dir.join("generated/untouched.rs");
+        let modified = dir.join("generated/modified.rs");
+        let created = dir.join("generated/created.rs");
+        let removed = dir.join("generated/removed.rs");
+        for path in [&untouched, &modified, &removed] {
+            fs::write(path, "// generated").unwrap();
+        }
+        let paths = [header.clone(), dir.join("generated")];
+
+        let snapshot = Snapshot::of(&paths);
+        assert!(snapshot.changed_in(&paths).is_empty());
+
+        // Setting the time explicitly keeps this from depending on how
+        // coarse the filesystem's timestamps are.
+        File::options()
+            .write(true)
+            .open(&modified)
+            .unwrap()
+            .set_modified(SystemTime::now() + Duration::from_secs(60))
+            .unwrap();
+        fs::write(&header, "// generated").unwrap();
+        fs::write(&created, "// generated").unwrap();
+        fs::remove_file(&removed).unwrap();
+        assert_eq!(snapshot.changed_in(&paths), vec![header, created, modified]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,70 @@
+use super::{
+    bindings, check_filesystems, check_free_space,
+    hook::{self, Hook, HookError},
+};
+use crate::{
+    build_events::{Emitter, Phase},
+    config::app::App,
+    env::ExplicitEnv,
+    opts::NoiseLevel,
+};
+use std::env;
+
+/// Set for the native build system while a session's running, since Gradle
+/// calls back into `cargo android build` for each target, and those builds
+/// shouldn't run the hooks all over again.
+pub static SESSION_VAR: &str = "CARGO_MOBILE_BUILD_SESSION";
+
+/// The work that's done once around a build, however many targets it's for:
+/// checking the disk, running the `app.pre-build` and `app.post-build` hooks,
+/// and offering to open any bindings that codegen touched in between.
+#[derive(Debug)]
+pub struct BuildSession<'a> {
+    app: &'a App,
+    noise_level: NoiseLevel,
+    /// `None` if this session is nested in another one, in which case the
+    /// outer one does all the work.
+    bindings: Option<bindings::Snapshot>,
+}
+
+impl<'a> BuildSession<'a> {
+    pub fn begin(
+        app: &'a App,
+        env: &impl ExplicitEnv,
+        noise_level: NoiseLevel,
+        events: &Emitter,
+    ) -> Result<Self, HookError> {
+        if env::var_os(SESSION_VAR).is_some() {
+            log::info!("skipping pre-build work, since it was already done by the command that started this build");
+            return Ok(Self {
+                app,
+                noise_level,
+                bindings: None,
+            });
+        }
+        check_free_space(app, events);
+        check_filesystems(app, events);
+        events.phase_started(Phase::PreBuild, None);
+        let bindings = bindings::Snapshot::take(app);
+        hook::run(Hook::PreBuild, app, env, noise_level)?;
+        Ok(Self {
+            app,
+            noise_level,
+            bindings: Some(bindings),
+        })
+    }
+
+    /// Only call this once every target has been built; if any failed, just
+    /// drop the session, so that `app.post-build` doesn't see a half-built
+    /// project.
+    pub fn finish(self, env: &impl ExplicitEnv, events: &Emitter) -> Result<(), HookError> {
+        let bindings = match self.bindings {
+            Some(bindings) => bindings,
+            None => return Ok(()),
+        };
+        events.phase_started(Phase::PostBuild, None);
+        hook::run(Hook::PostBuild, self.app, env, self.noise_level)?;
+        bindings::offer_to_open(self.app, &bindings, self.noise_level);
+        Ok(())
+    }
+}
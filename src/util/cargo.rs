@@ -1,36 +1,84 @@
-use crate::{bossy, env::ExplicitEnv};
+use super::VersionTriple;
+use crate::{bossy, env::ExplicitEnv, opts::NoiseLevel, os};
+use once_cell_regex::exports::once_cell::sync::OnceCell;
 use std::path::PathBuf;
 
+/// `--config KEY=VALUE` was stabilized in cargo 1.63.
+const CONFIG_FLAG_MIN_VERSION: VersionTriple = VersionTriple::new(1, 63, 0);
+
+fn supports_config_flag() -> bool {
+    static SUPPORTED: OnceCell<bool> = OnceCell::new();
+    *SUPPORTED.get_or_init(|| match os::cargo_version() {
+        Ok(version) => version >= CONFIG_FLAG_MIN_VERSION,
+        Err(err) => {
+            // If we can't find cargo, the build will fail with a better error
+            // than we'd give here.
+            log::warn!("failed to check cargo version: {}", err);
+            true
+        }
+    })
+}
+
+/// Sets `key` for this invocation only. `value` is TOML, for `--config`;
+/// older cargo only takes overrides from the environment, so `env_value` is
+/// the same thing in env var form. Those replace array values from
+/// `.cargo/config.toml` rather than merging with them, but that beats the
+/// build failing outright.
+fn add_config(command: &mut bossy::Command, key: &str, value: &str, env_value: &str) {
+    if supports_config_flag() {
+        command
+            .add_arg("--config")
+            .add_arg(format!("{}={}", key, value));
+    } else {
+        let var = format!("CARGO_{}", key.replace(['.', '-'], "_").to_uppercase());
+        log::warn!(
+            "cargo is older than {}, so setting `{}` with `{}` instead of `--config`",
+            CONFIG_FLAG_MIN_VERSION,
+            key,
+            var
+        );
+        command.add_env_var(var, env_value);
+    }
+}
+
 #[derive(Debug)]
 pub struct CargoCommand<'a> {
     subcommand: &'a str,
-    verbose: bool,
+    noise_level: NoiseLevel,
     package: Option<&'a str>,
     manifest_path: Option<PathBuf>,
+    target_dir: Option<PathBuf>,
     target: Option<&'a str>,
     no_default_features: bool,
     features: Option<&'a [String]>,
     args: Option<&'a [String]>,
     release: bool,
+    panic_abort: bool,
+    build_std: bool,
+    rustflags: &'a [String],
 }
 
 impl<'a> CargoCommand<'a> {
     pub fn new(subcommand: &'a str) -> Self {
         Self {
             subcommand,
-            verbose: Default::default(),
+            noise_level: Default::default(),
             package: Default::default(),
             manifest_path: Default::default(),
+            target_dir: Default::default(),
             target: Default::default(),
             no_default_features: Default::default(),
             features: Default::default(),
             args: Default::default(),
             release: Default::default(),
+            panic_abort: Default::default(),
+            build_std: Default::default(),
+            rustflags: Default::default(),
         }
     }
 
-    pub fn with_verbose(mut self, verbose: bool) -> Self {
-        self.verbose = verbose;
+    pub fn with_noise_level(mut self, noise_level: NoiseLevel) -> Self {
+        self.noise_level = noise_level;
         self
     }
 
@@ -46,6 +94,11 @@ impl<'a> CargoCommand<'a> {
         self
     }
 
+    pub fn with_target_dir(mut self, target_dir: Option<PathBuf>) -> Self {
+        self.target_dir = target_dir;
+        self
+    }
+
     pub fn with_target(mut self, target: Option<&'a str>) -> Self {
         self.target = target;
         self
@@ -71,10 +124,36 @@ impl<'a> CargoCommand<'a> {
         self
     }
 
+    pub fn with_panic_abort(mut self, panic_abort: bool) -> Self {
+        self.panic_abort = panic_abort;
+        self
+    }
+
+    /// Rebuild std (with `panic_abort`) rather than using the prebuilt one.
+    /// This is nightly-only, so check with [`super::check_build_std`] first.
+    pub fn with_build_std(mut self, build_std: bool) -> Self {
+        self.build_std = build_std;
+        self
+    }
+
+    /// Extra rustflags for the target. These are passed via `--config` so that
+    /// cargo merges them with `target.<triple>.rustflags` from
+    /// `.cargo/config.toml`, which `RUSTFLAGS` would replace.
+    pub fn with_target_rustflags(mut self, rustflags: &'a [String]) -> Self {
+        self.rustflags = rustflags;
+        self
+    }
+
     fn into_command_inner(self, mut command: bossy::Command) -> bossy::Command {
         command.add_arg(self.subcommand);
-        if self.verbose {
-            command.add_arg("-vv");
+        if let Some(flag) = self.noise_level.cargo_flag() {
+            command.add_arg(flag);
+        }
+        if self.build_std {
+            if self.target.is_none() {
+                log::warn!("`-Z build-std` needs an explicit `--target`, but none was given");
+            }
+            command.add_args(["-Z", "build-std=std,panic_abort"]);
         }
         if let Some(package) = self.package {
             command.add_args(&["--package", package]);
@@ -85,6 +164,15 @@ impl<'a> CargoCommand<'a> {
             }
             command.add_arg("--manifest-path").add_arg(manifest_path);
         }
+        if let Some(target_dir) = self.target_dir {
+            if !target_dir.is_dir() {
+                if let Err(err) = std::fs::create_dir_all(&target_dir) {
+                    log::error!("failed to create target dir {:?}: {}", target_dir, err);
+                }
+            }
+            log::info!("using target dir {:?}", target_dir);
+            command.add_arg("--target-dir").add_arg(target_dir);
+        }
         if let Some(target) = self.target {
             // We used to use `util::host_target_triple` to avoid explicitly
             // specifying the default target triple here, since specifying it
@@ -96,18 +184,64 @@ impl<'a> CargoCommand<'a> {
             // but it wouldn't accomplish anything either.
             command.add_args(&["--target", target]);
         }
+        if !self.rustflags.is_empty() {
+            match self.target {
+                Some(target) => {
+                    let rustflags = toml::Value::Array(
+                        self.rustflags
+                            .iter()
+                            .cloned()
+                            .map(toml::Value::String)
+                            .collect(),
+                    );
+                    add_config(
+                        &mut command,
+                        &format!("target.{}.rustflags", target),
+                        &rustflags.to_string(),
+                        &self.rustflags.join(" "),
+                    );
+                }
+                None => log::warn!(
+                    "ignoring rustflags {:?}, since no `--target` was given",
+                    self.rustflags
+                ),
+            }
+        }
         if self.no_default_features {
             command.add_arg("--no-default-features");
         }
         if let Some(features) = self.features {
             command.add_args(&["--features", &features.join(" ")]);
         }
+        let uses_build_std = self.build_std
+            || self
+                .args
+                .unwrap_or_default()
+                .iter()
+                .any(|arg| arg.contains("build-std"));
         if let Some(args) = self.args {
             command.add_args(args);
         }
         if self.release {
             command.add_arg("--release");
         }
+        if self.panic_abort {
+            // Setting this on the profile rather than via `RUSTFLAGS`, since
+            // that env var would replace the `target.<triple>.rustflags` we
+            // write to `.cargo/config.toml` rather than merging with them.
+            let profile = if self.release { "release" } else { "dev" };
+            add_config(
+                &mut command,
+                &format!("profile.{}.panic", profile),
+                "\"abort\"",
+                "abort",
+            );
+            if !uses_build_std {
+                log::warn!(
+                    "`panic = \"abort\"` only applies to your own crates, since the prebuilt std is compiled with unwinding; set `app.build-std` (on nightly) to rebuild std as well"
+                );
+            }
+        }
         command
     }
 
@@ -1,7 +1,10 @@
-use crate::{opts, util};
+use crate::{build_log, opts, os, util};
 use colored::Colorize as _;
 use once_cell_regex::exports::once_cell::sync::Lazy;
-use std::fmt::{Debug, Display};
+use std::{
+    fmt::{Debug, Display},
+    path::PathBuf,
+};
 use structopt::{
     clap::{self, AppSettings},
     StructOpt,
@@ -48,6 +51,18 @@ pub struct GlobalFlags {
         global = true
     )]
     pub non_interactive: bool,
+    #[structopt(
+        long = "no-open",
+        help = "Never open editors or other tools, even if config or `--open` asks to",
+        global = true
+    )]
+    pub no_open: bool,
+    #[structopt(
+        long = "build-log",
+        help = "Log everything builds run and print to a file, whose path is printed if the build fails",
+        global = true
+    )]
+    pub build_log: bool,
 }
 
 #[derive(Clone, Copy, Debug, StructOpt)]
@@ -70,9 +85,52 @@ pub struct Profile {
     #[structopt(
         long = "release",
         help = "Build with release optimizations",
-        parse(from_flag = opts::Profile::from_flag),
+        conflicts_with = "debug"
+    )]
+    pub release: bool,
+    #[structopt(
+        long = "debug",
+        help = "Build without release optimizations, even if `release` is set in cargo-mobile.toml"
     )]
-    pub profile: opts::Profile,
+    pub debug: bool,
+}
+
+impl Profile {
+    /// The profile asked for on the command line, if either flag was given.
+    pub fn requested(self) -> Option<opts::Profile> {
+        if self.release {
+            Some(opts::Profile::Release)
+        } else if self.debug {
+            Some(opts::Profile::Debug)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Clone, Debug, StructOpt)]
+pub struct OutDir {
+    #[structopt(
+        long = "out-dir",
+        help = "Copy built artifacts into this directory (relative to the project root)",
+        parse(from_os_str)
+    )]
+    pub out_dir: Option<PathBuf>,
+}
+
+/// Gradle and Xcode call back into us to build the Rust lib, and this is how
+/// those builds find out about a `--target-dir` the outer one was given.
+pub static TARGET_DIR_VAR: &str = "CARGO_MOBILE_TARGET_DIR";
+
+#[derive(Clone, Debug, StructOpt)]
+pub struct TargetDir {
+    #[structopt(
+        long = "target-dir",
+        env = TARGET_DIR_VAR,
+        help = "Directory for cargo build output, overriding `app.target-dir` (relative to the project root)",
+        parse(from_os_str)
+    )]
+    pub target_dir: Option<PathBuf>,
 }
 
 #[derive(Clone, Copy, Debug, StructOpt)]
@@ -87,6 +145,15 @@ pub struct Filter {
     pub filter: Option<opts::FilterLevel>,
 }
 
+#[derive(Clone, Debug, StructOpt)]
+pub struct DeviceId {
+    #[structopt(
+        long = "device",
+        help = "ID of the device to use (as shown by `list`), instead of picking one"
+    )]
+    pub device: Option<String>,
+}
+
 pub type TextWrapper = textwrap::Wrapper<'static, textwrap::NoHyphenation>;
 
 pub mod colors {
@@ -98,6 +165,17 @@ pub mod colors {
     pub const VICTORY: Color = BrightGreen;
 }
 
+pub fn stdout_is_tty() -> bool {
+    use std::io::IsTerminal as _;
+    std::io::stdout().is_terminal()
+}
+
+/// Whether it's worth colorizing what we write to stdout, which isn't the case
+/// if it's being piped somewhere or the user opted out of color.
+pub fn should_colorize_stdout() -> bool {
+    stdout_is_tty() && colored::control::SHOULD_COLORIZE.should_colorize()
+}
+
 #[derive(Clone, Copy, Debug)]
 pub enum Label {
     Error,
@@ -264,6 +342,12 @@ pub fn exec<E: Exec>(name: &str) {
         let args = get_args(name);
         let input = E::from_iter_safe(&args).map_err(Exit::Clap)?;
         init_logging(input.global_flags().noise_level);
+        if input.global_flags().no_open {
+            os::disable_opening();
+        }
+        if input.global_flags().build_log {
+            build_log::request();
+        }
         log::debug!("raw args: {:#?}", args);
         input.exec(wrapper).map_err(Exit::report)
     })
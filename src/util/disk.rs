@@ -0,0 +1,70 @@
+use crate::{build_events::Emitter, config::app::App, os};
+use std::path::Path;
+
+const BYTES_PER_GB: u64 = 1_000_000_000;
+
+/// Warns if the filesystem the target dir lives on has less free space than
+/// `app.min-free-space-gb`, since running out partway through a build tends
+/// to fail in confusing ways. This is only advisory, so failing to check is
+/// logged rather than returned.
+pub fn check_free_space(app: &App, events: &Emitter) {
+    let threshold = app.min_free_space_gb();
+    if threshold == 0 {
+        return;
+    }
+    let target_dir = app.target_dir();
+    // The target dir might not exist yet before the first build, so we check
+    // whichever ancestor does.
+    let existing = target_dir
+        .ancestors()
+        .find(|path| path.exists())
+        .unwrap_or_else(|| Path::new("."));
+    match os::available_disk_space(existing) {
+        Ok(available) if available < threshold * BYTES_PER_GB => {
+            let message = format!(
+                "Only {:.1} GB free on the filesystem containing {:?}, which is below the {} GB set by `app.min-free-space-gb`; the build might run out of space.",
+                available as f64 / BYTES_PER_GB as f64,
+                target_dir,
+                threshold,
+            );
+            log::warn!("{}", message);
+            events.warning(message);
+        }
+        Ok(_) => (),
+        Err(err) => log::info!("failed to check free disk space at {:?}: {}", existing, err),
+    }
+}
+
+/// Messages about the app root or target dir being on a network filesystem,
+/// which makes builds much slower, and which `ln` and file permissions don't
+/// always behave on.
+pub fn network_fs_warnings(app: &App) -> Vec<String> {
+    let root_dir = app.root_dir();
+    let target_dir = app.target_dir();
+    let mut warnings = Vec::new();
+    if os::filesystem_kind(root_dir) == os::FsKind::Network {
+        warnings.push(format!(
+            "The project at {:?} is on a network filesystem, so builds will be slow, and symlinks and permissions might not work as expected. Consider moving it to a local disk.",
+            root_dir
+        ));
+        if target_dir.starts_with(root_dir) {
+            return warnings;
+        }
+    }
+    if os::filesystem_kind(&target_dir) == os::FsKind::Network {
+        warnings.push(format!(
+            "The target dir {:?} is on a network filesystem, so builds will be slow. Consider setting `app.target-dir` to somewhere on a local disk.",
+            target_dir
+        ));
+    }
+    warnings
+}
+
+/// Warns about anything [`network_fs_warnings`] finds. Like
+/// [`check_free_space`], this is only advisory.
+pub fn check_filesystems(app: &App, events: &Emitter) {
+    for message in network_fs_warnings(app) {
+        log::warn!("{}", message);
+        events.warning(message);
+    }
+}
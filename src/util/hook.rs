@@ -0,0 +1,125 @@
+use crate::{
+    bossy, build_log,
+    config::app::App,
+    env::ExplicitEnv,
+    opts::NoiseLevel,
+    util::cli::{Report, Reportable},
+};
+use thiserror::Error;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Hook {
+    PreBuild,
+    PostBuild,
+}
+
+impl Hook {
+    pub fn key(self) -> &'static str {
+        match self {
+            Self::PreBuild => "pre-build",
+            Self::PostBuild => "post-build",
+        }
+    }
+
+    fn command(self, app: &App) -> Option<&str> {
+        match self {
+            Self::PreBuild => app.pre_build(),
+            Self::PostBuild => app.post_build(),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum HookError {
+    #[error("Failed to run `app.{}` hook `{command}`: {cause}", .hook.key())]
+    RunFailed {
+        hook: Hook,
+        command: String,
+        cause: Box<bossy::Error>,
+    },
+    #[error("`app.{}` hook `{command}` exited with {status}{}", .hook.key(), output_suffix(.output))]
+    HookFailed {
+        hook: Hook,
+        command: String,
+        status: bossy::ExitStatus,
+        output: String,
+    },
+}
+
+fn output_suffix(output: &str) -> String {
+    if output.is_empty() {
+        ", and didn't print anything.".to_owned()
+    } else {
+        format!(":\n{}", output)
+    }
+}
+
+impl Reportable for HookError {
+    fn report(&self) -> Report {
+        match self {
+            Self::RunFailed { hook, .. } | Self::HookFailed { hook, .. } => {
+                Report::error(format!("`app.{}` hook failed", hook.key()), self)
+            }
+        }
+    }
+}
+
+#[cfg(not(windows))]
+fn shell_command(command: &str) -> bossy::Command {
+    bossy::Command::impure("sh").with_args(["-c", command])
+}
+
+#[cfg(windows)]
+fn shell_command(command: &str) -> bossy::Command {
+    bossy::Command::impure("cmd").with_args(["/C", command])
+}
+
+/// Runs the configured command for `hook`, if there is one, through the
+/// shell from the app root. The hook's output is only echoed when we're being
+/// noisy, but it's always included in the error if the hook fails.
+pub fn run(
+    hook: Hook,
+    app: &App,
+    env: &impl ExplicitEnv,
+    noise_level: NoiseLevel,
+) -> Result<(), HookError> {
+    let command = match hook.command(app) {
+        Some(command) => command,
+        None => return Ok(()),
+    };
+    if !noise_level.polite() {
+        println!("Running `app.{}` hook: {}", hook.key(), command);
+    }
+    build_log::line(&format!("$ {}", command));
+    let mut output = String::new();
+    let result = shell_command(command)
+        .with_current_dir(app.root_dir())
+        .with_env_vars(env.explicit_env())
+        .run_streaming(|stream, line| {
+            build_log::line(line);
+            if !noise_level.polite() {
+                match stream {
+                    bossy::OutputStream::Out => println!("{}", line),
+                    bossy::OutputStream::Err => eprintln!("{}", line),
+                }
+            }
+            output.push_str(line);
+            output.push('\n');
+        });
+    match result {
+        Ok(_) => Ok(()),
+        Err(cause) => Err(match cause.status() {
+            Some(status) => HookError::HookFailed {
+                hook,
+                command: command.to_owned(),
+                status,
+                output: output.trim_end().to_owned(),
+            },
+            None => HookError::RunFailed {
+                hook,
+                command: command.to_owned(),
+                cause: Box::new(cause),
+            },
+        }),
+    }
+}
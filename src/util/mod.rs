@@ -1,11 +1,17 @@
+mod artifacts;
+pub mod bindings;
+mod build_session;
 mod cargo;
 pub mod cli;
+mod disk;
 mod git;
+pub mod hook;
 pub mod ln;
 mod path;
 pub mod prompt;
+pub mod toolchain;
 
-pub use self::{cargo::*, git::*, path::*};
+pub use self::{artifacts::*, build_session::*, cargo::*, disk::*, git::*, path::*};
 
 use self::cli::{Report, Reportable};
 use crate::{
@@ -47,10 +53,17 @@ pub fn reverse_domain(domain: &str) -> String {
     domain.split('.').rev().collect::<Vec<_>>().join(".")
 }
 
-pub fn rustup_add(triple: &str) -> bossy::Result<bossy::ExitStatus> {
-    bossy::Command::impure("rustup")
-        .with_args(&["target", "add", triple])
-        .run_and_wait()
+/// Installs `triple` for `toolchain`, or for the default toolchain if none is
+/// pinned.
+pub fn rustup_add(
+    triple: &str,
+    toolchain: Option<&toolchain::Toolchain>,
+) -> bossy::Result<bossy::ExitStatus> {
+    let mut command = bossy::Command::impure("rustup").with_args(["target", "add", triple]);
+    if let Some(toolchain) = toolchain {
+        command.add_args(["--toolchain", toolchain.name()]);
+    }
+    command.run_and_wait()
 }
 
 #[derive(Debug, Error)]
@@ -436,6 +449,12 @@ impl RustVersion {
         )?
     }
 
+    pub fn nightly(&self) -> bool {
+        self.flavor
+            .as_ref()
+            .is_some_and(|flavor| flavor.flavor == "nightly" || flavor.flavor == "dev")
+    }
+
     pub fn valid(&self) -> bool {
         if cfg!(target_os = "macos") {
             const LAST_GOOD_STABLE: VersionTriple = VersionTriple::new(1, 45, 2);
@@ -460,6 +479,41 @@ impl RustVersion {
     }
 }
 
+#[derive(Debug, Error)]
+pub enum BuildStdError {
+    #[error(transparent)]
+    VersionCheckFailed(RustVersionError),
+    #[error("`app.build-std` requires a nightly toolchain, but rustc is v{version}.")]
+    NotNightly { version: String },
+}
+
+impl Reportable for BuildStdError {
+    fn report(&self) -> Report {
+        match self {
+            Self::VersionCheckFailed(err) => err.report(),
+            Self::NotNightly { .. } => Report::action_request(
+                "Can't build std on a stable toolchain",
+                format!(
+                    "{} Run `rustup override set nightly` and `rustup component add rust-src`, or disable `app.build-std`.",
+                    self
+                ),
+            ),
+        }
+    }
+}
+
+/// Checks that the active toolchain can build std, which is nightly-only.
+pub fn check_build_std() -> Result<RustVersion, BuildStdError> {
+    let version = RustVersion::check().map_err(BuildStdError::VersionCheckFailed)?;
+    if version.nightly() {
+        Ok(version)
+    } else {
+        Err(BuildStdError::NotNightly {
+            version: version.to_string(),
+        })
+    }
+}
+
 pub fn prepend_to_path(path: impl Display, base_path: impl Display) -> String {
     format!("{}:{}", path, base_path)
 }
@@ -574,11 +628,27 @@ pub enum OpenInEditorError {
     DetectFailed(os::DetectEditorError),
     #[error("Failed to open path in editor: {0}")]
     OpenFailed(os::OpenFileError),
+    #[error("Failed to open path in remote editor: {0}")]
+    RemoteOpenFailed(bossy::Error),
 }
 
-pub fn open_in_editor(path: impl AsRef<Path>) -> Result<(), OpenInEditorError> {
+/// `allowed_pagers` is from `editor.allowed-pagers`.
+pub fn open_in_editor(
+    path: impl AsRef<Path>,
+    allowed_pagers: &[String],
+) -> Result<(), OpenInEditorError> {
     let path = path.as_ref();
-    os::Application::detect_editor()
+    if os::opening_disabled() {
+        log::info!("not opening {:?}, since opening is disabled", path);
+        return Ok(());
+    }
+    if let Some(remote) = os::RemoteEditor::detect() {
+        log::info!("opening {:?} through remote editor {:?}", path, remote);
+        return remote
+            .open_file(path)
+            .map_err(OpenInEditorError::RemoteOpenFailed);
+    }
+    os::Application::detect_editor(allowed_pagers)
         .map_err(OpenInEditorError::DetectFailed)?
         .open_file(path)
         .map_err(OpenInEditorError::OpenFailed)
@@ -688,5 +758,12 @@ pub fn gradlew(
     config: &crate::android::config::Config,
     env: &crate::android::env::Env,
 ) -> bossy::Command {
-    os::gradlew_command(&config.project_dir()).with_env_vars(env.explicit_env())
+    os::gradlew_command(&config.project_dir())
+        .with_env_vars(env.explicit_env())
+        .with_env_vars(
+            config
+                .app()
+                .custom_target_dir()
+                .map(|target_dir| (cli::TARGET_DIR_VAR, target_dir)),
+        )
 }
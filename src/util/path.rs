@@ -14,14 +14,44 @@ pub fn home_dir() -> Result<PathBuf, NoHomeDir> {
     home::home_dir().ok_or(NoHomeDir)
 }
 
+/// [`expand_tilde`] against the user's home dir, for paths that are
+/// expected to have one. Paths that aren't valid UTF-8 are left as they are.
 pub fn expand_home(path: impl AsRef<Path>) -> Result<PathBuf, NoHomeDir> {
     let home = home_dir()?;
     let path = path.as_ref();
-    if let Ok(path) = path.strip_prefix("~") {
-        Ok(home.join(path))
+    Ok(match path.to_str() {
+        Some(path) => expand_tilde(path, &home).into(),
+        None => path.to_owned(),
+    })
+}
+
+/// Expands a leading `~` or `~user` in a word from a configured command or
+/// path, the way a shell would. Tildes anywhere else are left alone, as is
+/// `~user` if we can't find that user.
+pub fn expand_tilde(word: &str, home: &Path) -> String {
+    let rest = match word.strip_prefix('~') {
+        Some(rest) => rest,
+        None => return word.to_owned(),
+    };
+    let (user, rest) = match rest.find(std::path::is_separator) {
+        Some(index) => rest.split_at(index),
+        None => (rest, ""),
+    };
+    let home = if user.is_empty() {
+        home.to_owned()
     } else {
-        Ok(path.to_owned())
-    }
+        match crate::os::user_home_dir(user) {
+            Some(home) => home,
+            None => return word.to_owned(),
+        }
+    };
+    format!("{}{}", home.display(), rest)
+}
+
+/// [`expand_home`] for paths from the config, which are left as they are if
+/// there's no home dir.
+pub fn expand_config_path(path: &str) -> PathBuf {
+    expand_home(path).unwrap_or_else(|_| path.into())
 }
 
 #[derive(Debug, Error)]
@@ -256,4 +286,28 @@ mod test {
     fn test_prefix_path(root: impl AsRef<Path>, path: impl AsRef<Path>, result: &str) {
         assert_eq!(prefix_path(root, path), PathBuf::from(result));
     }
+
+    #[rstest(
+        word,
+        result,
+        case("~", "/home/user"),
+        case("~/bin/my-editor", "/home/user/bin/my-editor"),
+        case("my~editor", "my~editor"),
+        case("--dir=~/sub", "--dir=~/sub"),
+        case("/opt/~/editor", "/opt/~/editor"),
+        case("~definitely-not-a-real-user/sub", "~definitely-not-a-real-user/sub")
+    )]
+    fn test_expand_tilde(word: &str, result: &str) {
+        assert_eq!(expand_tilde(word, Path::new("/home/user")), result);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn expands_other_users() {
+        let root = crate::os::user_home_dir("root").expect("root has no home dir");
+        assert_eq!(
+            expand_tilde("~root/sub", Path::new("/home/user")),
+            format!("{}/sub", root.display())
+        );
+    }
 }
@@ -13,6 +13,39 @@ pub fn minimal(msg: impl Display) -> io::Result<String> {
     Ok(input)
 }
 
+#[cfg(unix)]
+fn set_echo(echo: bool) {
+    let arg = if echo { "echo" } else { "-echo" };
+    if let Err(err) = crate::bossy::Command::impure("stty")
+        .with_arg(arg)
+        .run_and_wait()
+    {
+        log::warn!("failed to run `stty {}`: {}", arg, err);
+    }
+}
+
+#[cfg(not(unix))]
+fn set_echo(_echo: bool) {}
+
+/// Like [`minimal`], but the response isn't echoed (on platforms where we know
+/// how to turn that off) and is returned untrimmed, aside from the newline.
+/// The caller is responsible for not letting the response linger.
+pub fn hidden(msg: impl Display) -> io::Result<String> {
+    let mut input = String::new();
+    print!("{}: ", msg);
+    io::stdout().flush()?;
+    set_echo(false);
+    let result = io::stdin().read_line(&mut input);
+    set_echo(true);
+    // The user's newline wasn't echoed either.
+    println!();
+    result?;
+    // Truncating in place, since trimming would leave a copy behind.
+    let len = input.trim_end_matches(&['\r', '\n'][..]).len();
+    input.truncate(len);
+    Ok(input)
+}
+
 pub fn default(
     msg: impl Display,
     default: Option<&str>,
@@ -0,0 +1,286 @@
+use super::cli::{Report, Reportable};
+use crate::bossy;
+use serde::Deserialize;
+use std::{
+    fmt::{self, Display},
+    fs, io,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+
+pub static OVERRIDE_VAR: &str = "RUSTUP_TOOLCHAIN";
+
+// When both are present, rustup uses the legacy file, so we check it first.
+static FILE_NAMES: [&str; 2] = ["rust-toolchain", "rust-toolchain.toml"];
+
+#[derive(Debug, Error)]
+pub enum ToolchainError {
+    #[error("Failed to read toolchain file at {path:?}: {cause}")]
+    ReadFailed { path: PathBuf, cause: io::Error },
+    #[error("Failed to parse toolchain file at {path:?}: {cause}")]
+    ParseFailed {
+        path: PathBuf,
+        cause: toml::de::Error,
+    },
+    #[error("Toolchain file at {path:?} doesn't specify a `channel` or `path`.")]
+    ChannelMissing { path: PathBuf },
+    #[error("Failed to list installed targets for toolchain {toolchain}: {cause}")]
+    TargetListFailed {
+        toolchain: String,
+        cause: Box<bossy::Error>,
+    },
+    #[error("The pinned toolchain {toolchain} doesn't have the {triple} target installed.")]
+    TargetMissing { toolchain: String, triple: String },
+}
+
+impl Reportable for ToolchainError {
+    fn report(&self) -> Report {
+        match self {
+            Self::TargetMissing {
+                toolchain, triple, ..
+            } => Report::action_request(
+                "Pinned toolchain is missing a target",
+                format!(
+                    "{} Run `rustup target add --toolchain {} {}`, or add it to `toolchain.targets` in your toolchain file.",
+                    self, toolchain, triple
+                ),
+            ),
+            _ => Report::error("Failed to check pinned toolchain", self),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct File {
+    toolchain: Section,
+}
+
+#[derive(Debug, Deserialize)]
+struct Section {
+    channel: Option<String>,
+    path: Option<String>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Source {
+    /// From the `RUSTUP_TOOLCHAIN` env var.
+    EnvVar,
+    File(PathBuf),
+}
+
+/// A toolchain pinned via `RUSTUP_TOOLCHAIN` or a `rust-toolchain(.toml)`
+/// file, which builds should use instead of the default toolchain.
+#[derive(Clone, Debug)]
+pub struct Toolchain {
+    name: String,
+    source: Source,
+    /// Whether this is a custom toolchain at a path rather than one rustup
+    /// installed, in which case rustup can't add targets to it.
+    is_path: bool,
+}
+
+impl Display for Toolchain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.source {
+            Source::EnvVar => write!(f, "{} (from `{}`)", self.name, OVERRIDE_VAR),
+            Source::File(path) => write!(f, "{} (from {:?})", self.name, path),
+        }
+    }
+}
+
+// Channels never contain path separators, so rustup takes anything that does
+// to be a path.
+fn is_path(name: &str) -> bool {
+    name.contains('/') || name.contains('\\')
+}
+
+impl Toolchain {
+    fn parse(path: &Path, contents: &str) -> Result<Self, ToolchainError> {
+        let toolchain = |name: String, is_path| Self {
+            name,
+            source: Source::File(path.to_owned()),
+            is_path,
+        };
+        let contents = contents.trim();
+        // The legacy format is just the toolchain name (or path) on its own.
+        if path.extension().is_none() && !contents.contains('\n') && !contents.contains('[') {
+            return if contents.is_empty() {
+                Err(ToolchainError::ChannelMissing {
+                    path: path.to_owned(),
+                })
+            } else {
+                Ok(toolchain(contents.to_owned(), is_path(contents)))
+            };
+        }
+        let file =
+            toml::from_str::<File>(contents).map_err(|cause| ToolchainError::ParseFailed {
+                path: path.to_owned(),
+                cause,
+            })?;
+        match (file.toolchain.channel, file.toolchain.path) {
+            (Some(channel), _) => Ok(toolchain(channel, false)),
+            (None, Some(toolchain_path)) => Ok(toolchain(toolchain_path, true)),
+            (None, None) => Err(ToolchainError::ChannelMissing {
+                path: path.to_owned(),
+            }),
+        }
+    }
+
+    /// Finds the toolchain rustup would use for `project_dir`, if one's been
+    /// pinned. Like rustup, `RUSTUP_TOOLCHAIN` takes precedence over
+    /// toolchain files, which are searched for in `project_dir` and its
+    /// ancestors.
+    pub fn detect(project_dir: impl AsRef<Path>) -> Result<Option<Self>, ToolchainError> {
+        if let Some(name) = std::env::var(OVERRIDE_VAR)
+            .ok()
+            .filter(|name| !name.is_empty())
+        {
+            return Ok(Some(Self {
+                is_path: is_path(&name),
+                name,
+                source: Source::EnvVar,
+            }));
+        }
+        for dir in project_dir.as_ref().ancestors() {
+            for file_name in FILE_NAMES {
+                let path = dir.join(file_name);
+                if !path.is_file() {
+                    continue;
+                }
+                let contents =
+                    fs::read_to_string(&path).map_err(|cause| ToolchainError::ReadFailed {
+                        path: path.clone(),
+                        cause,
+                    })?;
+                let toolchain = Self::parse(&path, &contents)?;
+                log::info!("found pinned toolchain {:?} in {:?}", toolchain.name, path);
+                return Ok(Some(toolchain));
+            }
+        }
+        Ok(None)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn source(&self) -> &Source {
+        &self.source
+    }
+
+    pub fn is_path(&self) -> bool {
+        self.is_path
+    }
+
+    pub fn installed_targets(&self) -> Result<Vec<String>, ToolchainError> {
+        bossy::Command::impure("rustup")
+            .with_args(["target", "list", "--installed", "--toolchain"])
+            .with_arg(&self.name)
+            .run_and_wait_for_string()
+            .map(|output| output.lines().map(|line| line.trim().to_owned()).collect())
+            .map_err(|cause| ToolchainError::TargetListFailed {
+                toolchain: self.name.clone(),
+                cause: Box::new(cause),
+            })
+    }
+
+    /// Checks that this toolchain can build for `triple`.
+    pub fn check_target(&self, triple: &str) -> Result<(), ToolchainError> {
+        if self
+            .installed_targets()?
+            .iter()
+            .any(|target| target == triple)
+        {
+            Ok(())
+        } else {
+            Err(ToolchainError::TargetMissing {
+                toolchain: self.name.clone(),
+                triple: triple.to_owned(),
+            })
+        }
+    }
+}
+
+/// The pinned toolchain to build `triple` with, if there is one, after
+/// checking that it actually has `triple` installed. If `rustup` can't list
+/// targets, which it never can for toolchains at a path, we let cargo find
+/// out the hard way rather than failing here.
+pub fn for_target(
+    project_dir: impl AsRef<Path>,
+    triple: &str,
+) -> Result<Option<Toolchain>, ToolchainError> {
+    let toolchain = Toolchain::detect(project_dir)?;
+    if let Some(toolchain) = toolchain.as_ref().filter(|toolchain| !toolchain.is_path()) {
+        match toolchain.check_target(triple) {
+            Err(ToolchainError::TargetListFailed { cause, .. }) => log::warn!(
+                "couldn't check if toolchain {} has {} installed: {}",
+                toolchain.name(),
+                triple,
+                cause
+            ),
+            result => result?,
+        }
+    }
+    Ok(toolchain)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest(
+        file_name,
+        contents,
+        name,
+        is_path,
+        case("rust-toolchain", "nightly-2024-05-01\n", "nightly-2024-05-01", false),
+        case("rust-toolchain", "/opt/rust/custom\n", "/opt/rust/custom", true),
+        case(
+            "rust-toolchain",
+            "[toolchain]\nchannel = \"1.77.0\"\n",
+            "1.77.0",
+            false
+        ),
+        case(
+            "rust-toolchain.toml",
+            "[toolchain]\nchannel = \"stable\"\ntargets = [\"aarch64-linux-android\"]\n",
+            "stable",
+            false
+        ),
+        case(
+            "rust-toolchain.toml",
+            "[toolchain]\npath = \"/opt/rust/custom\"\n",
+            "/opt/rust/custom",
+            true
+        )
+    )]
+    fn parses_toolchain_files(file_name: &str, contents: &str, name: &str, is_path: bool) {
+        let path = Path::new("/project").join(file_name);
+        let toolchain = Toolchain::parse(&path, contents).unwrap();
+        assert_eq!(toolchain.name(), name);
+        assert_eq!(toolchain.is_path(), is_path);
+        assert_eq!(toolchain.source(), &Source::File(path));
+    }
+
+    #[rstest(
+        file_name,
+        contents,
+        case("rust-toolchain", ""),
+        case("rust-toolchain.toml", "[toolchain]\ncomponents = [\"rustfmt\"]\n")
+    )]
+    fn requires_channel_or_path(file_name: &str, contents: &str) {
+        assert!(matches!(
+            Toolchain::parse(&Path::new("/project").join(file_name), contents),
+            Err(ToolchainError::ChannelMissing { .. })
+        ));
+    }
+
+    #[test]
+    fn rejects_invalid_toml() {
+        assert!(matches!(
+            Toolchain::parse(Path::new("/project/rust-toolchain.toml"), "[toolchain"),
+            Err(ToolchainError::ParseFailed { .. })
+        ));
+    }
+}